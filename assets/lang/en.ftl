@@ -0,0 +1,227 @@
+# English, the language every other one falls back to.
+#
+# Translations are files next to this one named after their language, like de.ftl, and only
+# need the lines they translate. Indented lines continue the text of the line above on a new
+# line, and { $name } is replaced by the value it names.
+
+language-name = English
+
+## Main menu
+
+menu-title = Shitty rhythm game
+menu-tutorial = Tutorial
+menu-setup = Setup
+menu-levels = Levels
+menu-recent = Recent
+menu-no-recent = No levels played yet
+menu-favorites = Favorites
+menu-no-favorites = Star levels with * to find them here
+menu-favorite = Star this level, right click the level to manage it
+menu-manage = Manage level
+menu-manage-title = Title
+menu-manage-rename = Rename
+menu-manage-renamed = Renamed, leave the title empty to show the folder name again
+menu-manage-reveal = Open folder
+menu-manage-validate = Check
+menu-manage-valid = The sheet is fine, { $projectiles } projectiles
+menu-manage-warnings = { $warnings } warnings, which don't stop it from being played:
+menu-manage-invalid = The sheet doesn't load: { $error }
+menu-manage-no-song = The sheet is fine, but there's no song.wav
+menu-manage-delete = Delete
+menu-manage-delete-confirm = Delete this level's folder and everything in it? This can't be undone.
+menu-manage-failed = That didn't work: { $error }
+menu-level-best = { $level }  (best { $score })
+menu-pack = { $pack }  (cleared { $cleared }/{ $levels })
+menu-level-full-combo = { $level }  (best { $score }, full combo)
+menu-replays = Replays
+menu-replay = { $player }  { $score }
+menu-replay-outdated = (chart changed since)
+menu-spectate = Spectate
+menu-leaderboard = Top runs
+menu-leaderboard-aggro = Top aggro runs
+menu-no-runs = No runs yet
+menu-density = Projectiles a second, peaking at { $peak }
+menu-stats = { $notes } projectiles over { $duration }s, at most { $nps } in a second, longest gap { $gap }s
+menu-stats-directions = Up { $up }, down { $down }, left { $left }, right { $right }
+menu-run = #{ $rank }  { $score }
+menu-run-date = Played { $date } UTC
+menu-run-modifiers = Modifiers: { $modifiers }
+menu-run-accuracy = Accuracy: { $accuracy }%
+menu-run-full-combo = Full combo
+menu-watch-replay = Watch replay
+menu-level-modified = { $level }  (best { $score }, chart changed since)
+menu-profile = Profile
+menu-new-profile = New profile
+menu-lifetime = { $runs } runs: { $perfect } perfect, { $great } great, { $late } late, { $miss } missed
+menu-settings = Settings
+menu-modifiers = Modifiers
+menu-play = Play
+menu-back = Back
+menu-demo = Press any key
+menu-crash-title = The game crashed
+menu-crash = Sorry, the game crashed the last time it ran. A log of what happened was saved to { $path }, please include it when reporting the problem.
+menu-crash-open = Open log
+menu-crash-dismiss = Dismiss
+menu-load-failed-title = The level didn't load
+menu-load-failed = It can't be played: { $error }
+menu-load-failed-dismiss = Dismiss
+menu-courses = Courses
+menu-no-courses = No courses, they're read from the courses folder
+menu-course = Course
+menu-course-run = #{ $rank }  { $score }  { $date } UTC
+menu-course-missing = A level of this course is missing or doesn't load
+menu-playlist = Playlist
+menu-playlist-empty = Add levels with "Add to playlist" to play them back to back
+menu-playlist-add = Add to playlist
+menu-playlist-play = Play playlist
+menu-playlist-clear = Clear
+
+## Setup
+
+setup-title = Welcome
+setup-step = Step { $step } of { $steps }
+setup-songs = Levels are read from a folder, each level in a folder of its own. Packs of levels can go in folders of their own in there too.
+setup-songs-dir = Songs folder
+setup-songs-found = { $levels } levels found
+setup-songs-create = Create folder
+setup-songs-open = Open folder
+setup-songs-rescan = Look again
+setup-audio = How loud should the songs and sounds be?
+setup-audio-test = Play a sound
+setup-calibration = Sounds can reach your ears a little late. Press Space on each tick so the game can make up for it, or set the offset yourself.
+setup-calibration-start = Start ticking
+setup-calibration-taps = { $taps } of { $needed } taps
+setup-controls =
+    WASD or the arrow keys raise the shield towards the projectiles, Escape pauses.
+    Holding the restart key restarts the level.
+setup-tutorial = That's everything. The tutorial shows how blocking works, it's worth playing before the levels.
+setup-tutorial-play = Play the tutorial
+setup-tutorial-skip = Go to the menu
+setup-back = Back
+setup-next = Next
+setup-skip = Skip setup
+
+## Settings
+
+settings-language = Language
+settings-crt = CRT filter
+settings-bloom = Bloom
+settings-fullscreen = Fullscreen
+settings-ui-scale = UI scale
+settings-shake = Camera shake
+settings-flashes = Screen flashes
+settings-background-dim = Background dim
+settings-playfield-opacity = Playfield opacity
+settings-death-warp = Death slow-down
+settings-high-contrast = High contrast projectiles
+settings-gpu-particles = GPU particles
+settings-frame-cap = Frame cap, 0 for none
+settings-battery-saver = Battery saver, slower menus
+settings-palette = Direction colors
+settings-glyphs = Direction shapes
+settings-drift-indicator = Show audio drift
+settings-streamer-mode = Streamer overlay (F7)
+settings-hitch = Hitch threshold
+settings-practice-ramp = Ramp up practice rate
+settings-restart-key = Restart key
+settings-restart-hold = Restart hold time
+settings-clip-buffer = Record clips (F9 to save)
+settings-clip-length = Clip length
+settings-music-volume = Music volume
+settings-sfx-volume = SFX volume
+settings-kick-volume = Block sound
+settings-death-volume = Death sound
+settings-audio-offset = Audio offset
+settings-hitsounds = Hitsounds
+settings-sync-url = Sync to (http:// url, empty to not sync)
+
+## Modifiers
+
+modifiers-rate = Rate
+modifiers-mirror = Mirror
+modifiers-random = Random
+modifiers-hidden = Hidden
+modifiers-no-fail = No fail
+modifiers-humanize = Humanize
+modifiers-aggro = Aggro
+modifiers-checkpoints = Checkpoint bars
+modifiers-autoplay = Autoplay
+modifiers-multiplier = Score multiplier: { $multiplier }x
+modifiers-unranked = Humanized runs aren't kept with the scores
+modifiers-aggro-scores = Aggro runs are kept on leaderboards of their own
+
+## Pause menu
+
+pause-title = Paused
+pause-resume = Resume
+pause-restart = Restart
+pause-practice = Practice section
+pause-stop-practice = Stop practicing
+pause-quit = Quit to menu
+pause-song-offset = Song offset { $offset }ms, +/- to nudge
+
+## Results
+
+results-died = You died
+results-cleared = Cleared
+results-score = Score: { $score }
+results-best-streak = Best streak: { $streak }
+results-judgments = Perfect { $perfect }  Great { $great }  Late { $late }  Miss { $miss }
+results-grade = Grade: { $grade }
+results-full-combo = Full combo!
+results-retry = Retry
+results-retry-checkpoint = Retry from checkpoint (C)
+results-practice = Practice this section
+results-replay = Watch replay (V)
+results-quit = Quit to menu
+
+marathon-cleared = Playlist cleared
+marathon-died = You died on { $level }
+marathon-level = { $level }  { $score }  (best streak { $streak })
+marathon-total = Total: { $score }, { $played } of { $count } levels
+marathon-retry = Play again
+
+## Hud
+
+hud-score = Score: { $score }
+hud-judgments = Perfect { $perfect }
+    Great { $great }
+    Late { $late }
+    Miss { $miss }
+hud-practice = Practice, loop { $loop } at { $rate }x
+hud-practice-marked = Practice start marked, ] to loop
+hud-review = Review
+hud-replay = Replay
+hud-spectate = Watching { $player }
+hud-phase-spiral = Spiral!
+hud-phase-wall = Wall!
+hud-phase-feint = Feint?
+hud-boss-defeated = Boss defeated!
+hud-full-combo = FULL COMBO
+hud-song-offset = Song offset { $offset }ms
+hud-aggro = Aggro { $multiplier }x ({ $progress }%)
+streamer-perfect = PERFECT
+streamer-great = GREAT
+streamer-late = LATE
+streamer-miss = MISS
+streamer-combo = { $combo } combo
+hud-pickup-slow = Slow { $seconds }s
+hud-pickup-wide = Wide { $seconds }s
+hud-pickup-double = Double { $seconds }s
+
+## Tutorial
+
+tutorial-up = Point the shield up with W or the up arrow
+tutorial-down = Down with S or the down arrow
+tutorial-left = Left with A or the left arrow
+tutorial-right = And right with D or the right arrow
+tutorial-start = Projectiles fly at the heart from every side.
+    Point the shield at them to block them.
+    Press space to start
+tutorial-judgments = Blocking right as a projectile reaches the
+    shield is a Perfect, a bit late is a Great.
+    Press space to go on
+tutorial-buffer = You can press a moment early, so quick
+    changes of direction still count.
+    Press space to go on
+tutorial-done = That's it! Press space to go back to the menu
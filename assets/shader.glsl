@@ -0,0 +1,31 @@
+#version 450
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 color_out;
+
+uniform sampler2D iSpectrum;
+uniform float iTime;
+uniform vec2 iResolution;
+uniform float iFlash;
+uniform float iBeat;
+
+void main() {
+    vec2 pos = uv * vec2(iResolution.x / iResolution.y, 1.0);
+    float radius = length(pos);
+
+    // low notes at the top, mirrored left and right
+    float band = abs(atan(pos.x, pos.y)) / 3.14159;
+    float level = texture(iSpectrum, vec2(band, 0.5)).r;
+    float bar = step(0.25, radius) * smoothstep(0.02, 0.0, radius - 0.25 - level * 0.5);
+
+    float pulse = pow(1.0 - iBeat, 3.0);
+    float rings = 0.5 + 0.5 * sin(radius * 30.0 - iTime * 4.0 - pulse * 2.0);
+
+    vec3 color = vec3(0.02, 0.02, 0.06);
+    color += vec3(0.05, 0.03, 0.12) * rings * (0.3 + 0.7 * pulse) * max(0.0, 1.0 - radius * 0.7);
+    color += vec3(0.15, 0.25, 0.6) * bar * (0.4 + 0.6 * level);
+    color += vec3(0.2) * iFlash;
+
+    color_out = vec4(color, 1.0);
+}
@@ -0,0 +1,18 @@
+#version 450
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 color_out;
+
+uniform sampler2D noise_texture;
+uniform sampler2D iChannel0;
+uniform float iTime;
+uniform vec2 iResolution;
+
+void main() {
+    // a dark blue glow pulsing slowly around the heart
+    float pulse = 0.5 + 0.5 * sin(iTime * 3.14159);
+    float glow = 1.0 - length(uv * vec2(iResolution.x / iResolution.y, 1.0)) * 0.6;
+
+    color_out = vec4(vec3(0.02, 0.03, 0.08) + vec3(0.03, 0.05, 0.12) * glow * (0.8 + 0.2 * pulse), 1.0);
+}
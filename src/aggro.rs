@@ -0,0 +1,57 @@
+//! The aggro modifier, where the shield drops back down a moment after each press and keeping it
+//! down between projectiles builds up a multiplier on the points of blocks. Raising it early or
+//! mashing wears the multiplier back down, so blocking at the last moment is worth the most.
+//!
+//! Aggro runs are kept on leaderboards of their own, see [`scores_key`].
+
+/// Seconds the shield stays up after a press before dropping back down.
+pub const GUARD: f32 = 0.15;
+
+/// Seconds of the shield being down it takes to build up another step of the multiplier.
+const BUILD: f32 = 1.0;
+
+/// Seconds of the shield being up it takes to lose a step of the multiplier.
+const DECAY: f32 = 0.25;
+
+/// Highest the multiplier goes.
+pub const MAX_MULTIPLIER: u32 = 4;
+
+#[derive(Clone, Debug, Default)]
+pub struct Aggro {
+    /// Steps of the multiplier built up, the whole ones counting.
+    charge: f32,
+}
+
+impl Aggro {
+    /// Builds the multiplier up or wears it down over `delta` seconds, depending on whether any
+    /// shield is `raised`.
+    pub fn update(&mut self, raised: bool, delta: f32) {
+        let change = if raised {
+            -delta / DECAY
+        } else {
+            delta / BUILD
+        };
+
+        self.charge = (self.charge + change).clamp(0.0, (MAX_MULTIPLIER - 1) as f32);
+    }
+
+    /// What the points of a block are multiplied by right now.
+    pub fn multiplier(&self) -> u32 {
+        1 + self.charge as u32
+    }
+
+    /// How far the multiplier is towards its next step, for drawing.
+    pub fn progress(&self) -> f32 {
+        self.charge.fract()
+    }
+}
+
+/// Whether a shield raised at `raised` has dropped back down by `time`.
+pub fn lowered(raised: f32, time: f32) -> bool {
+    time - raised >= GUARD
+}
+
+/// What aggro runs of the level or course kept under `key` are kept under, apart from the others.
+pub fn scores_key(key: &str) -> String {
+    format!("aggro:{}", key)
+}
@@ -0,0 +1,233 @@
+//! Where levels and their files are read from.
+//!
+//! Everything goes through macroquad's file loading, which reads from disk on desktop and
+//! fetches over http on the web, so the game doesn't touch the filesystem directly. The core
+//! assets fall back to the copies [built into the game](crate::embedded) when they're missing.
+
+use crate::audio_engine::Song;
+use crate::embedded;
+use crate::script::Script;
+use crate::sheet::{sheet_paths, Sheet};
+use crate::{log_debug, log_warn};
+use macroquad::audio::{load_sound, Sound};
+use macroquad::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Where levels are unless [`Settings::songs_dir`] says otherwise, each in a folder of its own.
+/// Folders without a sheet are packs, with levels or more packs in them.
+///
+/// [`Settings::songs_dir`]: crate::settings::Settings::songs_dir
+pub const LEVELS_DIR: &str = "songs";
+
+/// Where course files are, see [`course`](crate::course).
+pub const COURSES_DIR: &str = "courses";
+
+/// Lists the levels of a songs folder on the web, one directory name per line with the packs it's
+/// in, like `Pack/Level`, since there's no folder to scan.
+#[cfg(target_arch = "wasm32")]
+pub const LEVEL_INDEX: &str = "index.txt";
+
+/// Lists the course files on the web, one file name per line.
+#[cfg(target_arch = "wasm32")]
+pub const COURSE_INDEX: &str = "courses/index.txt";
+
+pub async fn load_text(path: &std::path::Path) -> Result<String, FileError> {
+    match load_string(path.to_str().unwrap()).await {
+        Ok(text) => Ok(text),
+        Err(err) => embedded::get(path)
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+            .map(str::to_string)
+            .ok_or(err),
+    }
+}
+
+/// Loads the file at `path`, or its built in copy if it's a core asset that's missing.
+pub async fn load_bytes(path: &std::path::Path) -> Result<Vec<u8>, FileError> {
+    match load_file(path.to_str().unwrap()).await {
+        Ok(bytes) => Ok(bytes),
+        Err(err) => embedded::get(path).map(<[u8]>::to_vec).ok_or(err),
+    }
+}
+
+/// Loads the sheet of the level in `level` from the first of its [`sheet_paths`] there is, the
+/// error naming the file.
+pub async fn load_sheet(level: &Path, difficulty: Option<&str>) -> Result<Sheet, String> {
+    let [path, json] = sheet_paths(level, difficulty);
+
+    let (path, source) = match load_text(&path).await {
+        Ok(source) => (path, source),
+        Err(err) => match load_text(&json).await {
+            Ok(source) => (json, source),
+            Err(_) => return Err(format!("{}: {}", path.display(), err)),
+        },
+    };
+
+    Sheet::parse_file(&path, &source).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// Loads the `script.txt` of the level in `level`, an empty script if there isn't one, the error
+/// naming the file.
+pub async fn load_script(level: &Path) -> Result<Script, String> {
+    let path = level.join("script.txt");
+
+    match load_text(&path).await {
+        Ok(source) => Script::parse(&source).map_err(|err| format!("{}: {}", path.display(), err)),
+        Err(_) => Ok(Script::default()),
+    }
+}
+
+/// Loads the `song.wav` of the level in `level`, the error naming the file.
+pub async fn load_song(level: &Path) -> Result<Song, String> {
+    let path = level.join("song.wav");
+
+    Song::load(&path.to_string_lossy())
+        .await
+        .map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// Loads a sound, the built in copy if it's a core asset that's missing. Only desktop builds
+/// have the fallback, the web serves the assets along with the game anyway.
+pub async fn load_asset_sound(path: &str) -> Result<Sound, FileError> {
+    let err = match load_sound(path).await {
+        Ok(sound) => return Ok(sound),
+        Err(err) => err,
+    };
+
+    // macroquad only loads sounds from files, so the built in copy is written out to load it
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(bytes) = embedded::get(path.as_ref()) {
+        let name = Path::new(path)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let copy = std::env::temp_dir().join(format!("srg-{}", name));
+
+        if std::fs::write(&copy, bytes).is_ok() {
+            return load_sound(copy.to_str().unwrap()).await;
+        }
+    }
+
+    Err(err)
+}
+
+/// Loads an image, the built in copy if it's a core asset that's missing.
+pub async fn load_asset_image(path: &str) -> Result<Image, FileError> {
+    let bytes = load_bytes(path.as_ref()).await?;
+
+    Ok(Image::from_file_with_format(&bytes, None))
+}
+
+/// Loads a texture if the file exists.
+pub async fn load_optional_texture(path: &std::path::Path) -> Option<Texture2D> {
+    load_texture(path.to_str().unwrap()).await.ok()
+}
+
+/// Loads a ttf font if the file exists.
+pub async fn load_optional_font(path: &std::path::Path) -> Option<Font> {
+    let bytes = load_file(path.to_str().unwrap()).await.ok()?;
+
+    Some(load_ttf_font_from_bytes(&bytes))
+}
+
+/// The pack `level` is in, the folders between the songs folder `dir` and it like `Pack/Sub`,
+/// `None` if it's right in `dir`.
+pub fn pack_of(level: &Path, dir: &Path) -> Option<String> {
+    let pack = level.parent()?.strip_prefix(dir).ok()?;
+
+    if pack.as_os_str().is_empty() {
+        None
+    } else {
+        Some(pack.to_string_lossy().replace('\\', "/"))
+    }
+}
+
+/// Directories of the levels installed in the songs folder `dir`, in the packs too.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn levels(dir: &Path) -> Vec<PathBuf> {
+    let mut levels = Vec::new();
+
+    if let Err(err) = find_levels(dir, &mut levels) {
+        log_warn!("failed to read {}: {}", dir.display(), err);
+    }
+
+    levels.sort();
+    log_debug!("found {} levels", levels.len());
+
+    levels
+}
+
+/// Adds the levels in `dir` to `levels`, going into the folders without a sheet as packs.
+#[cfg(not(target_arch = "wasm32"))]
+fn find_levels(dir: &Path, levels: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+
+        if !path.is_dir() {
+            continue;
+        }
+
+        let has_sheet = std::fs::read_dir(&path).is_ok_and(|files| {
+            files.flatten().any(|file| {
+                file.path()
+                    .extension()
+                    .is_some_and(|extension| extension == "sht" || extension == "json")
+            })
+        });
+
+        if has_sheet {
+            levels.push(path);
+        } else if let Err(err) = find_levels(&path, levels) {
+            log_warn!("failed to read {}: {}", path.display(), err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Directories of the levels bundled with the page in the songs folder `dir`, from its
+/// [`LEVEL_INDEX`].
+#[cfg(target_arch = "wasm32")]
+pub async fn levels(dir: &Path) -> Vec<PathBuf> {
+    let index = load_text(&dir.join(LEVEL_INDEX)).await.unwrap_or_default();
+
+    index
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| dir.join(line))
+        .collect()
+}
+
+/// Paths of the course files, none if there's no folder for them.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn courses() -> Vec<PathBuf> {
+    let mut courses: Vec<PathBuf> = match std::fs::read_dir(COURSES_DIR) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "toml")
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    courses.sort();
+    log_debug!("found {} courses", courses.len());
+
+    courses
+}
+
+/// Paths of the course files bundled with the page, from [`COURSE_INDEX`].
+#[cfg(target_arch = "wasm32")]
+pub async fn courses() -> Vec<PathBuf> {
+    let index = load_string(COURSE_INDEX).await.unwrap_or_default();
+
+    index
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| PathBuf::from(COURSES_DIR).join(line))
+        .collect()
+}
@@ -0,0 +1,105 @@
+//! Packing gameplay sprites into one texture, so drawing them back to back batches into a
+//! single draw call.
+
+use crate::asset_source::load_asset_image;
+use macroquad::prelude::*;
+
+/// Transparent pixels kept between sprites, so neighbours don't bleed into each other, even
+/// through the outlines drawn around them.
+pub const PADDING: u16 = 3;
+
+/// A part of an atlas texture.
+#[derive(Clone, Copy, Debug)]
+pub struct Sprite {
+    texture: Texture2D,
+    source: Rect,
+}
+
+impl Sprite {
+    pub fn width(&self) -> f32 {
+        self.source.w
+    }
+
+    pub fn height(&self) -> f32 {
+        self.source.h
+    }
+
+    /// The atlas the sprite is in.
+    pub fn texture(&self) -> Texture2D {
+        self.texture
+    }
+
+    /// Where the sprite is in its atlas, in pixels.
+    pub fn source(&self) -> Rect {
+        self.source
+    }
+
+    /// Size of the whole atlas the sprite is in, in pixels.
+    pub fn texture_size(&self) -> Vec2 {
+        vec2(self.texture.width(), self.texture.height())
+    }
+
+    /// Like [`draw_texture_ex`], `params.source` being relative to the sprite.
+    pub fn draw(&self, x: f32, y: f32, color: Color, params: DrawTextureParams) {
+        let source = match params.source {
+            Some(source) => Rect::new(
+                self.source.x + source.x,
+                self.source.y + source.y,
+                source.w,
+                source.h,
+            ),
+            None => self.source,
+        };
+
+        let params = DrawTextureParams {
+            source: Some(source),
+            dest_size: Some(params.dest_size.unwrap_or_else(|| source.size())),
+            ..params
+        };
+
+        draw_texture_ex(self.texture, x, y, color, params);
+    }
+}
+
+/// Loads the images at `paths` and packs them side by side into one texture.
+pub async fn load_sprites<const N: usize>(paths: [&str; N]) -> [Sprite; N] {
+    let mut images = Vec::with_capacity(N);
+
+    for path in paths {
+        images.push(load_asset_image(path).await.unwrap());
+    }
+
+    let width = images
+        .iter()
+        .map(|image| image.width + PADDING)
+        .sum::<u16>()
+        + PADDING;
+    let height = images.iter().map(|image| image.height).max().unwrap_or(0) + PADDING * 2;
+
+    let mut atlas = Image::gen_image_color(width, height, BLANK);
+    let mut sources = [Rect::new(0.0, 0.0, 0.0, 0.0); N];
+    let mut x = PADDING;
+
+    for (image, source) in images.iter().zip(&mut sources) {
+        for row in 0..image.height {
+            let from = row as usize * image.width as usize * 4;
+            let to = ((row + PADDING) as usize * width as usize + x as usize) * 4;
+            let length = image.width as usize * 4;
+
+            atlas.bytes[to..to + length].copy_from_slice(&image.bytes[from..from + length]);
+        }
+
+        *source = Rect::new(
+            x as f32,
+            PADDING as f32,
+            image.width as f32,
+            image.height as f32,
+        );
+        x += image.width + PADDING;
+    }
+
+    let texture = Texture2D::from_image(&atlas);
+    texture.set_filter(FilterMode::Nearest);
+
+    sources.map(|source| Sprite { texture, source })
+}
@@ -0,0 +1,144 @@
+use crate::audio_engine::{AudioEngine, Song};
+use crate::settings::Settings;
+use macroquad::audio::*;
+
+/// Music volume multiplier while ducked.
+const DUCK_VOLUME: f32 = 0.2;
+
+/// Plays music and sound effects at the volumes from the settings.
+pub struct Audio {
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    music: Option<Box<dyn AudioEngine>>,
+    /// The engine of the music last stopped, kept so playing it again doesn't reopen the device.
+    stopped: Option<Box<dyn AudioEngine>>,
+    /// Seconds left of the music being ducked.
+    duck: f32,
+    /// Stop the music once the duck is over.
+    stop_after_duck: bool,
+}
+
+impl Audio {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            music_volume: settings.music_volume,
+            sfx_volume: settings.sfx_volume,
+            music: None,
+            stopped: None,
+            duck: 0.0,
+            stop_after_duck: false,
+        }
+    }
+
+    fn current_music_volume(&self) -> f32 {
+        if self.duck > 0.0 {
+            self.music_volume * DUCK_VOLUME
+        } else {
+            self.music_volume
+        }
+    }
+
+    /// Plays `song` from `position` seconds in at `rate` times normal speed.
+    pub fn play_music(&mut self, song: &Song, position: f32, rate: f32) {
+        self.stop_music();
+
+        let mut music = song.engine();
+        music.set_volume(self.current_music_volume());
+        music.set_rate(rate);
+        music.play(position);
+
+        self.music = Some(music);
+    }
+
+    /// Plays the music again from `position`, on the engine it was last played on if there's
+    /// one, which is quicker than [`Audio::play_music`] opening a new one.
+    pub fn replay_music(&mut self, song: &Song, position: f32, rate: f32) {
+        self.stop_music();
+
+        let mut music = match self.stopped.take() {
+            Some(music) => music,
+            None => song.engine(),
+        };
+        music.set_volume(self.current_music_volume());
+        music.set_rate(rate);
+        music.play(position);
+
+        self.music = Some(music);
+    }
+
+    pub fn stop_music(&mut self) {
+        if let Some(mut music) = self.music.take() {
+            music.stop();
+            self.stopped = Some(music);
+        }
+
+        self.duck = 0.0;
+        self.stop_after_duck = false;
+    }
+
+    pub fn pause_music(&mut self) {
+        if let Some(music) = &mut self.music {
+            music.pause();
+        }
+    }
+
+    pub fn resume_music(&mut self) {
+        if let Some(music) = &mut self.music {
+            music.resume();
+        }
+    }
+
+    /// How far into the music playback is, if it's playing.
+    pub fn music_position(&self) -> Option<f32> {
+        self.music
+            .as_ref()
+            .filter(|music| music.is_playing())
+            .map(|music| music.position())
+    }
+
+    /// Plays a sound effect, `gain` is the per sound volume on top of the sfx volume.
+    pub fn play_sfx(&self, sound: Sound, gain: f32) {
+        play_sound(
+            sound,
+            PlaySoundParams {
+                looped: false,
+                volume: self.sfx_volume * gain,
+            },
+        );
+    }
+
+    /// Lowers the music for `duration` seconds, e.g. to let a sound effect through.
+    pub fn duck(&mut self, duration: f32) {
+        self.duck = self.duck.max(duration);
+
+        let volume = self.current_music_volume();
+
+        if let Some(music) = &mut self.music {
+            music.set_volume(volume);
+        }
+    }
+
+    /// Ducks the music and stops it once the duck is over.
+    pub fn duck_and_stop(&mut self, duration: f32) {
+        self.duck(duration);
+        self.stop_after_duck = true;
+    }
+
+    pub fn update(&mut self, frame_time: f32) {
+        if self.duck <= 0.0 {
+            return;
+        }
+
+        self.duck -= frame_time;
+
+        if self.duck > 0.0 {
+            return;
+        }
+
+        if self.stop_after_duck {
+            self.stop_music();
+        } else if let Some(music) = &mut self.music {
+            music.set_volume(self.music_volume);
+        }
+    }
+}
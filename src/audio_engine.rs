@@ -0,0 +1,638 @@
+//! Playback of the song behind a common interface.
+//!
+//! On desktop the song is played with rodio directly so it can be paused, seeked, played at a
+//! different rate and report exactly how far into the song playback is. Platforms rodio doesn't
+//! support fall back to macroquad's audio, which can only play and stop.
+//!
+//! Songs bigger than [`STREAM_SIZE`] aren't read into memory on desktop, they're decoded from the
+//! file a chunk at a time on a thread of their own, only a few chunks ahead of where the song is
+//! playing. The audio callback never waits on that thread, if it falls behind the gap is played
+//! as silence and skipped over once it catches up, so the song stays where the clock says.
+
+use macroquad::prelude::*;
+
+/// Bytes a song file has to be over to be streamed from disk rather than read into memory, about
+/// a minute and a half of CD quality wav.
+pub const STREAM_SIZE: u64 = 16 * 1024 * 1024;
+
+/// A song that can be played, paused and seeked.
+///
+/// Positions are in seconds of song time, independent of the playback rate.
+#[allow(dead_code)]
+pub trait AudioEngine {
+    /// Starts playing from `position`.
+    fn play(&mut self, position: f32);
+
+    fn pause(&mut self);
+
+    fn resume(&mut self);
+
+    fn stop(&mut self);
+
+    fn seek(&mut self, position: f32);
+
+    fn position(&self) -> f32;
+
+    /// Playback speed, 1 is normal speed.
+    fn set_rate(&mut self, rate: f32);
+
+    fn set_volume(&mut self, volume: f32);
+
+    fn is_playing(&self) -> bool;
+}
+
+/// The song of a level, loaded in whatever form the platform's engine needs.
+pub struct Song {
+    #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+    data: std::sync::Arc<rodio_engine::SongData>,
+    #[cfg(any(target_arch = "wasm32", target_os = "android"))]
+    sound: macroquad::audio::Sound,
+}
+
+impl Song {
+    /// Loads the song at `path`, failing if it can't be read or isn't in a format it plays in.
+    #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+    pub async fn load(path: &str) -> Result<Self, String> {
+        Self::open(path.as_ref())
+    }
+
+    /// Loads the song at `path` right away, macroquad reads files from disk as it's asked to on
+    /// desktop anyway.
+    #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+    pub fn open(path: &std::path::Path) -> Result<Self, String> {
+        let streamed = std::fs::metadata(path).is_ok_and(|file| file.len() > STREAM_SIZE);
+
+        let data = if streamed {
+            rodio_engine::SongData::File(path.into())
+        } else {
+            let bytes = std::fs::read(path).map_err(|err| err.to_string())?;
+
+            rodio_engine::SongData::Memory(std::sync::Arc::new(bytes))
+        };
+        rodio_engine::check(&data)?;
+
+        Ok(Self {
+            data: std::sync::Arc::new(data),
+        })
+    }
+
+    #[cfg(any(target_arch = "wasm32", target_os = "android"))]
+    pub async fn load(path: &str) -> Result<Self, String> {
+        Ok(Self {
+            sound: macroquad::audio::load_sound(path)
+                .await
+                .map_err(|err| err.to_string())?,
+        })
+    }
+
+    #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+    pub async fn from_bytes(data: Vec<u8>) -> Self {
+        Self {
+            data: std::sync::Arc::new(rodio_engine::SongData::Memory(std::sync::Arc::new(data))),
+        }
+    }
+
+    #[cfg(any(target_arch = "wasm32", target_os = "android"))]
+    pub async fn from_bytes(data: Vec<u8>) -> Self {
+        Self {
+            sound: macroquad::audio::load_sound_from_bytes(&data)
+                .await
+                .unwrap(),
+        }
+    }
+
+    #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+    pub fn engine(&self) -> Box<dyn AudioEngine> {
+        Box::new(rodio_engine::RodioEngine::new(self.data.clone()))
+    }
+
+    #[cfg(any(target_arch = "wasm32", target_os = "android"))]
+    pub fn engine(&self) -> Box<dyn AudioEngine> {
+        Box::new(MacroquadEngine::new(self.sound))
+    }
+
+    /// The song mixed down to mono with at least `min_rate` samples a second, along with the
+    /// rate it ended up at. `None` if it doesn't decode.
+    #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+    pub fn mono_samples(&self, min_rate: u32) -> Option<(Vec<f32>, u32)> {
+        rodio_engine::mono_samples(&self.data, min_rate)
+    }
+
+    /// Macroquad never hands out the samples of its sounds.
+    #[cfg(any(target_arch = "wasm32", target_os = "android"))]
+    pub fn mono_samples(&self, _min_rate: u32) -> Option<(Vec<f32>, u32)> {
+        None
+    }
+}
+
+/// Plays through macroquad, which can't pause or seek, so both restart the song and the
+/// position is estimated from the wall clock.
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+pub struct MacroquadEngine {
+    sound: macroquad::audio::Sound,
+    started: Option<f64>,
+    paused_at: f32,
+    volume: f32,
+}
+
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+impl MacroquadEngine {
+    pub fn new(sound: macroquad::audio::Sound) -> Self {
+        Self {
+            sound,
+            started: None,
+            paused_at: 0.0,
+            volume: 1.0,
+        }
+    }
+}
+
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+impl AudioEngine for MacroquadEngine {
+    fn play(&mut self, _position: f32) {
+        macroquad::audio::play_sound(
+            self.sound,
+            macroquad::audio::PlaySoundParams {
+                looped: false,
+                volume: self.volume,
+            },
+        );
+
+        self.started = Some(get_time());
+    }
+
+    fn pause(&mut self) {
+        self.paused_at = self.position();
+        self.stop();
+    }
+
+    fn resume(&mut self) {
+        self.play(self.paused_at);
+    }
+
+    fn stop(&mut self) {
+        macroquad::audio::stop_sound(self.sound);
+        self.started = None;
+    }
+
+    fn seek(&mut self, position: f32) {
+        if self.is_playing() {
+            self.play(position);
+        }
+    }
+
+    fn position(&self) -> f32 {
+        match self.started {
+            Some(started) => (get_time() - started) as f32,
+            None => self.paused_at,
+        }
+    }
+
+    fn set_rate(&mut self, _rate: f32) {}
+
+    fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+        macroquad::audio::set_sound_volume(self.sound, volume);
+    }
+
+    fn is_playing(&self) -> bool {
+        self.started.is_some()
+    }
+}
+
+#[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+mod rodio_engine {
+    use super::AudioEngine;
+    use crate::log_error;
+    use hound::{SampleFormat, WavReader};
+    use rodio::{Decoder, OutputStream, OutputStreamHandle, Sample, Sink, Source};
+    use std::fs::File;
+    use std::io::{BufReader, Cursor};
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::mpsc::{sync_channel, Receiver, TryRecvError};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Samples, over all channels, decoded at a time when streaming.
+    const CHUNK_SAMPLES: usize = 16384;
+
+    /// Chunks decoded ahead of the one playing when streaming.
+    const CHUNKS_AHEAD: usize = 8;
+
+    /// Where a song is played from.
+    pub enum SongData {
+        Memory(Arc<Vec<u8>>),
+        /// Streamed from the file at the path.
+        File(PathBuf),
+    }
+
+    #[derive(Clone)]
+    struct SharedBytes(Arc<Vec<u8>>);
+
+    impl AsRef<[u8]> for SharedBytes {
+        fn as_ref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    /// Counts the samples pulled through it.
+    struct Tracked<S> {
+        inner: S,
+        samples: Arc<AtomicU64>,
+    }
+
+    impl<S: Source> Iterator for Tracked<S>
+    where
+        S::Item: Sample,
+    {
+        type Item = S::Item;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let sample = self.inner.next();
+
+            if sample.is_some() {
+                self.samples.fetch_add(1, Ordering::Relaxed);
+            }
+
+            sample
+        }
+    }
+
+    impl<S: Source> Source for Tracked<S>
+    where
+        S::Item: Sample,
+    {
+        fn current_frame_len(&self) -> Option<usize> {
+            self.inner.current_frame_len()
+        }
+
+        fn channels(&self) -> u16 {
+            self.inner.channels()
+        }
+
+        fn sample_rate(&self) -> u32 {
+            self.inner.sample_rate()
+        }
+
+        fn total_duration(&self) -> Option<Duration> {
+            self.inner.total_duration()
+        }
+    }
+
+    type Samples = Box<dyn Iterator<Item = i16> + Send>;
+
+    /// Decodes a song on a thread of its own, [`CHUNKS_AHEAD`] chunks ahead of where it's
+    /// played, so only those chunks are ever in memory.
+    struct Streamed {
+        chunks: Receiver<Vec<i16>>,
+        chunk: std::vec::IntoIter<i16>,
+        /// Samples played as silence while the decoder was behind, skipped once it catches up.
+        owed: usize,
+        channels: u16,
+        sample_rate: u32,
+    }
+
+    impl Streamed {
+        /// Starts decoding the file at `path` from `start`, returning once the first chunk is
+        /// decoded. Wav files are seeked to `start`, anything else is decoded up to it.
+        fn open(path: &PathBuf, start: Duration) -> Option<Self> {
+            let (samples, channels, sample_rate) = match WavReader::open(path) {
+                Ok(reader) => {
+                    let spec = reader.spec();
+
+                    (seek_wav(reader, start)?, spec.channels, spec.sample_rate)
+                }
+                Err(_) => {
+                    let file = File::open(path)
+                        .map_err(|err| log_error!("failed to open {}: {}", path.display(), err))
+                        .ok()?;
+                    let decoder = Decoder::new(BufReader::new(file))
+                        .map_err(|err| log_error!("failed to decode {}: {}", path.display(), err))
+                        .ok()?;
+                    let channels = decoder.channels();
+                    let sample_rate = decoder.sample_rate();
+                    let samples: Samples = Box::new(decoder.skip_duration(start));
+
+                    (samples, channels, sample_rate)
+                }
+            };
+            let (sender, chunks) = sync_channel(CHUNKS_AHEAD);
+
+            // blocks on the channel once it's full, and stops once the song is dropped
+            std::thread::spawn(move || {
+                let mut samples = samples;
+
+                loop {
+                    let chunk: Vec<i16> = samples.by_ref().take(CHUNK_SAMPLES).collect();
+
+                    if chunk.is_empty() || sender.send(chunk).is_err() {
+                        break;
+                    }
+                }
+            });
+
+            // a song starting past its end has no chunks at all
+            let chunk = chunks.recv().unwrap_or_default().into_iter();
+
+            Some(Self {
+                chunks,
+                chunk,
+                owed: 0,
+                channels,
+                sample_rate,
+            })
+        }
+    }
+
+    /// The samples of `reader` from `start` on, converted like rodio's wav decoder does.
+    fn seek_wav(mut reader: WavReader<BufReader<File>>, start: Duration) -> Option<Samples> {
+        let spec = reader.spec();
+        let frame = (start.as_secs_f64() * spec.sample_rate as f64) as u32;
+
+        reader
+            .seek(frame.min(reader.duration()))
+            .map_err(|err| log_error!("failed to seek the song: {}", err))
+            .ok()?;
+
+        // a read error ends the song, like the end of the file would
+        let samples: Samples = match (spec.sample_format, spec.bits_per_sample) {
+            (SampleFormat::Int, 16) => Box::new(reader.into_samples::<i16>().map_while(Result::ok)),
+            (SampleFormat::Int, 24) => Box::new(
+                reader
+                    .into_samples::<i32>()
+                    .map_while(Result::ok)
+                    .map(|sample| (sample >> 8) as i16),
+            ),
+            (SampleFormat::Int, 8) => Box::new(
+                reader
+                    .into_samples::<i8>()
+                    .map_while(Result::ok)
+                    .map(|sample| (sample as i16) << 8),
+            ),
+            (SampleFormat::Float, 32) => Box::new(
+                reader
+                    .into_samples::<f32>()
+                    .map_while(Result::ok)
+                    .map(|sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16),
+            ),
+            (format, bits) => {
+                log_error!("unsupported wav format: {:?}, {} bits", format, bits);
+
+                return None;
+            }
+        };
+
+        Some(samples)
+    }
+
+    impl Iterator for Streamed {
+        type Item = i16;
+
+        fn next(&mut self) -> Option<i16> {
+            loop {
+                if let Some(sample) = self.chunk.next() {
+                    if self.owed > 0 {
+                        self.owed -= 1;
+                        continue;
+                    }
+
+                    return Some(sample);
+                }
+
+                // this runs on the audio callback, so it can't wait for the decoder to catch up
+                match self.chunks.try_recv() {
+                    Ok(chunk) => self.chunk = chunk.into_iter(),
+                    Err(TryRecvError::Empty) => {
+                        self.owed += 1;
+
+                        return Some(0);
+                    }
+                    Err(TryRecvError::Disconnected) => return None,
+                }
+            }
+        }
+    }
+
+    impl Source for Streamed {
+        fn current_frame_len(&self) -> Option<usize> {
+            None
+        }
+
+        fn channels(&self) -> u16 {
+            self.channels
+        }
+
+        fn sample_rate(&self) -> u32 {
+            self.sample_rate
+        }
+
+        fn total_duration(&self) -> Option<Duration> {
+            None
+        }
+    }
+
+    /// Fails if `data` isn't in a format rodio decodes, reading no more than its header.
+    pub fn check(data: &SongData) -> Result<(), String> {
+        let decoder = match data {
+            SongData::Memory(data) => {
+                Decoder::new(Cursor::new(SharedBytes(data.clone()))).map(drop)
+            }
+            SongData::File(path) => {
+                let file = File::open(path).map_err(|err| err.to_string())?;
+
+                Decoder::new(BufReader::new(file)).map(drop)
+            }
+        };
+
+        decoder.map_err(|err| err.to_string())
+    }
+
+    pub fn mono_samples(data: &SongData, min_rate: u32) -> Option<(Vec<f32>, u32)> {
+        match data {
+            SongData::Memory(data) => mix_down(
+                Decoder::new(Cursor::new(SharedBytes(data.clone()))).ok()?,
+                min_rate,
+            ),
+            SongData::File(path) => mix_down(
+                Decoder::new(BufReader::new(File::open(path).ok()?)).ok()?,
+                min_rate,
+            ),
+        }
+    }
+
+    /// Averages the samples of `decoder` down to mono, at least `min_rate` samples a second.
+    fn mix_down<S>(decoder: S, min_rate: u32) -> Option<(Vec<f32>, u32)>
+    where
+        S: Source<Item = i16>,
+    {
+        let channels = decoder.channels().max(1) as usize;
+        let rate = decoder.sample_rate();
+        // averaging neighbouring samples keeps the memory down on long songs
+        let every = (rate / min_rate.max(1)).max(1) as usize;
+
+        let per_sample = channels * every;
+
+        let mut mono = Vec::new();
+        let mut sum = 0.0;
+        let mut count = 0;
+
+        for sample in decoder {
+            sum += sample as f32;
+            count += 1;
+
+            if count == per_sample {
+                mono.push(sum / per_sample as f32 / i16::MAX as f32);
+                sum = 0.0;
+                count = 0;
+            }
+        }
+
+        Some((mono, rate / every as u32))
+    }
+
+    pub struct RodioEngine {
+        data: Arc<SongData>,
+        // the stream has to be kept alive for the sink to play
+        stream: Option<(OutputStream, OutputStreamHandle)>,
+        sink: Option<Sink>,
+        /// Song time the current source started at.
+        start: f32,
+        /// Samples played since `start`, over all channels.
+        samples: Arc<AtomicU64>,
+        samples_per_second: f32,
+        rate: f32,
+        volume: f32,
+        paused: bool,
+    }
+
+    impl RodioEngine {
+        pub fn new(data: Arc<SongData>) -> Self {
+            Self {
+                data,
+                stream: OutputStream::try_default().ok(),
+                sink: None,
+                start: 0.0,
+                samples: Arc::new(AtomicU64::new(0)),
+                samples_per_second: 1.0,
+                rate: 1.0,
+                volume: 1.0,
+                paused: false,
+            }
+        }
+
+        /// Replaces the sink with one playing from `position`.
+        fn start_at(&mut self, position: f32, paused: bool) {
+            self.stop();
+
+            let handle = match &self.stream {
+                Some((_, handle)) => handle,
+                None => return,
+            };
+
+            let sink = match Sink::try_new(handle) {
+                Ok(sink) => sink,
+                Err(_) => return,
+            };
+
+            let start = Duration::from_secs_f32(position.max(0.0));
+
+            let inner: Box<dyn Source<Item = i16> + Send> = match &*self.data {
+                SongData::Memory(data) => {
+                    match Decoder::new(Cursor::new(SharedBytes(data.clone()))) {
+                        Ok(decoder) => Box::new(decoder.skip_duration(start)),
+                        Err(_) => return,
+                    }
+                }
+                SongData::File(path) => match Streamed::open(path, start) {
+                    Some(streamed) => Box::new(streamed),
+                    None => return,
+                },
+            };
+
+            self.samples_per_second = inner.sample_rate() as f32 * inner.channels() as f32;
+            self.start = position.max(0.0);
+            self.samples = Arc::new(AtomicU64::new(0));
+            self.paused = paused;
+
+            let source = Tracked {
+                inner,
+                samples: self.samples.clone(),
+            };
+
+            sink.set_volume(self.volume);
+
+            if paused {
+                sink.pause();
+            }
+
+            sink.append(source.speed(self.rate));
+
+            self.sink = Some(sink);
+        }
+    }
+
+    impl AudioEngine for RodioEngine {
+        fn play(&mut self, position: f32) {
+            self.start_at(position, false);
+        }
+
+        fn pause(&mut self) {
+            if let Some(sink) = &self.sink {
+                sink.pause();
+            }
+
+            self.paused = true;
+        }
+
+        fn resume(&mut self) {
+            if let Some(sink) = &self.sink {
+                sink.play();
+            }
+
+            self.paused = false;
+        }
+
+        fn stop(&mut self) {
+            if let Some(sink) = self.sink.take() {
+                sink.stop();
+            }
+        }
+
+        fn seek(&mut self, position: f32) {
+            if self.sink.is_some() {
+                self.start_at(position, self.paused);
+            } else {
+                self.start = position;
+            }
+        }
+
+        fn position(&self) -> f32 {
+            self.start + self.samples.load(Ordering::Relaxed) as f32 / self.samples_per_second
+        }
+
+        fn set_rate(&mut self, rate: f32) {
+            self.rate = rate;
+
+            // speed can't be changed on a playing source, so restart at the current position
+            if self.sink.is_some() {
+                self.start_at(self.position(), self.paused);
+            }
+        }
+
+        fn set_volume(&mut self, volume: f32) {
+            self.volume = volume;
+
+            if let Some(sink) = &self.sink {
+                sink.set_volume(volume);
+            }
+        }
+
+        fn is_playing(&self) -> bool {
+            match &self.sink {
+                Some(sink) => !sink.empty() && !self.paused,
+                None => false,
+            }
+        }
+    }
+}
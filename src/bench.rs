@@ -0,0 +1,186 @@
+//! A stress test for measuring performance work, started with the hidden `--bench` argument.
+//!
+//! It autoplays a generated level that keeps hundreds of projectiles live and thousands of
+//! particles on screen for [`BENCH_SECONDS`]. It then appends the frame time percentiles of the
+//! run as a row of [`BENCH_PATH`], so runs before and after a change can be compared.
+
+use crate::audio_engine::Song;
+use crate::game::{Assets, GameState};
+use crate::modifiers::Modifiers;
+use crate::particles::{AreaSpawner, Emitter};
+use crate::post::PostProcessing;
+use crate::script::Script;
+use crate::settings::Settings;
+use crate::sheet::Sheet;
+use crate::{canvas, log_info, tutorial};
+use macroquad::prelude::*;
+use std::io::Write;
+
+/// Seconds of gameplay measured.
+pub const BENCH_SECONDS: f32 = 30.0;
+
+pub const BENCH_PATH: &str = "bench.csv";
+
+/// Bars of the generated sheet, a bar is a second at its 240 bpm.
+const BARS: u32 = BENCH_SECONDS as u32 + 2;
+
+/// Bars before arrival projectiles become live, so most of them are off screen.
+const PRESPAWN_BARS: u32 = 8;
+
+const PERCENTILES: [f32; 4] = [50.0, 90.0, 99.0, 100.0];
+
+/// A sheet with two projectiles every fourth of a beat from the second bar on.
+fn sheet_source() -> String {
+    let mut source = format!(
+        "#bpm 240.0\n#offset 0.0 0;0|0\n#prespawn 0;0|{}\n",
+        PRESPAWN_BARS
+    );
+    let sides = ["U", "R", "D", "L"];
+
+    for bar in 1..BARS {
+        for fourth in 0..16 {
+            let side = (bar * 16 + fourth) as usize;
+
+            for side in [side % 4, (side + 2) % 4] {
+                source.push_str(&format!("norm {} {};0|{}\n", sides[side], fourth, bar));
+            }
+        }
+    }
+
+    source
+}
+
+/// Thousands of textured particles drifting over the whole canvas.
+fn particle_storm(assets: &Assets) -> Emitter {
+    let (width, height) = (canvas::WIDTH, canvas::HEIGHT);
+
+    let storm = AreaSpawner {
+        texture: Some(assets.particle),
+        amount: 20,
+        color: WHITE,
+        area: Rect::new(-width / 2.0, -height / 2.0, width, height),
+        velocity: -32.0..32.0,
+        life_time: 4.0,
+        size: 2.0,
+        sub_emitter: None,
+    };
+
+    Emitter::new(storm, 60.0)
+}
+
+/// Seconds at `percentile` of the sorted `frame_times`.
+fn percentile(frame_times: &[f32], percentile: f32) -> f32 {
+    let index = (percentile / 100.0 * (frame_times.len() - 1) as f32).round() as usize;
+
+    frame_times[index]
+}
+
+/// Runs the benchmark, returning once the results are written.
+pub async fn run(settings: &Settings, post: &PostProcessing) {
+    let sheet = Sheet::parse(&sheet_source()).unwrap();
+    let song = Song::from_bytes(tutorial::click_track(sheet.bpm, sheet.bar_time(BARS))).await;
+    let assets = Assets::from_parts(
+        song,
+        tutorial::SHADER,
+        sheet,
+        Script::default(),
+        Vec::new(),
+        None,
+        None,
+        settings,
+    )
+    .await
+    .unwrap();
+
+    let modifiers = Modifiers {
+        autoplay: true,
+        ..Modifiers::default()
+    };
+    let mut state = GameState::new(&assets, settings, &modifiers).await;
+    state.particles.add_emitter(particle_storm(&assets));
+    state.start(&assets);
+
+    // how long each frame took to update and draw, apart from waiting on vsync
+    let mut work_times = Vec::new();
+    let mut frame_times = Vec::new();
+    let mut most_projectiles = 0;
+    let mut most_particles = 0;
+
+    log_info!("benchmarking for {}s", BENCH_SECONDS);
+
+    while state.run.env.time < BENCH_SECONDS {
+        let started = macroquad::miniquad::date::now();
+
+        state.update(&assets, settings).await;
+        state.draw(&assets, settings, post);
+
+        work_times.push((macroquad::miniquad::date::now() - started) as f32);
+        frame_times.push(get_frame_time());
+        most_projectiles = most_projectiles.max(state.run.active_projectiles.len());
+        most_particles = most_particles.max(state.particles.particles.len());
+
+        next_frame().await;
+    }
+
+    state.stop();
+
+    // the first frames load and warm caches, they'd only add noise
+    let skip = frame_times.len().min(30);
+    let mut work_times = work_times.split_off(skip);
+    let mut frame_times = frame_times.split_off(skip);
+
+    if frame_times.is_empty() {
+        return;
+    }
+
+    work_times.sort_by(f32::total_cmp);
+    frame_times.sort_by(f32::total_cmp);
+
+    let mut row = format!(
+        "{},{},{},{},{}",
+        macroquad::miniquad::date::now() as u64,
+        env!("CARGO_PKG_VERSION"),
+        frame_times.len(),
+        most_projectiles,
+        most_particles
+    );
+
+    for times in [&frame_times, &work_times] {
+        for p in PERCENTILES {
+            row.push_str(&format!(",{:.3}", percentile(times, p) * 1000.0));
+        }
+    }
+
+    log_info!(
+        "{} frames, frame p50 {:.2}ms p99 {:.2}ms, work p50 {:.2}ms p99 {:.2}ms",
+        frame_times.len(),
+        percentile(&frame_times, 50.0) * 1000.0,
+        percentile(&frame_times, 99.0) * 1000.0,
+        percentile(&work_times, 50.0) * 1000.0,
+        percentile(&work_times, 99.0) * 1000.0
+    );
+
+    match append_row(&row) {
+        Ok(()) => log_info!("results appended to {}", BENCH_PATH),
+        Err(err) => crate::log_error!("failed to write {}: {}", BENCH_PATH, err),
+    }
+}
+
+fn append_row(row: &str) -> std::io::Result<()> {
+    let new = !std::path::Path::new(BENCH_PATH).exists();
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(BENCH_PATH)?;
+
+    if new {
+        writeln!(
+            file,
+            "date,version,frames,projectiles,particles,\
+             frame_p50_ms,frame_p90_ms,frame_p99_ms,frame_max_ms,\
+             work_p50_ms,work_p90_ms,work_p99_ms,work_max_ms"
+        )?;
+    }
+
+    writeln!(file, "{}", row)
+}
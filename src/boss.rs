@@ -0,0 +1,347 @@
+//! Boss battles, a boss drawn over the level that fires the sheet's `#phase` patterns and loses
+//! health as the player blocks, turned on with `#boss`:
+//!
+//! ```text
+//! #boss 40.0
+//! // eight projectiles a beat apart, turning clockwise from above
+//! #phase 0;0|2 pattern=spiral count=8 every=0;1|0 side=U turn=cw
+//! // four volleys from every side at once
+//! #phase 0;0|4 pattern=wall count=4 every=0;2|0
+//! // winds up towards the left, then fires from the right
+//! #phase 0;0|6 pattern=feint count=3 every=0;1|0 side=L
+//! ```
+//!
+//! The boss is the level's `boss.png`, and winds up in the beat before the projectiles of each
+//! phase come on screen. Phases work without a boss too, firing their patterns with nobody to
+//! fire them.
+
+use crate::game::{Assets, Direction, Projectile};
+use crate::generators::{self, Timing, MAX_COUNT};
+use crate::sheet::{parse_jitter_value, TimeOffset, Token};
+use macroquad::prelude::*;
+
+/// Health the boss loses for every projectile blocked.
+pub const BOSS_DAMAGE: f32 = 1.0;
+
+/// Where the boss is in the arena, above the heart.
+pub const BOSS_POSITION: Vec2 = const_vec2!([0.0, -124.0]);
+
+/// Canvas pixels tall the boss is drawn.
+const BOSS_HEIGHT: f32 = 72.0;
+
+/// Seconds the boss flashes for after being hit.
+const HIT_FLASH: f32 = 0.15;
+
+/// Seconds the boss takes to fade out once defeated.
+const DEFEAT_FADE: f32 = 1.0;
+
+/// Seconds the defeat is announced on the hud for, in place of the health bar.
+pub const DEFEAT_BANNER: f32 = 2.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PatternKind {
+    /// One projectile at a time, each from the next side around.
+    Spiral,
+    /// Volleys from every side at once.
+    Wall,
+    /// Telegraphed from one side, fired from the opposite one.
+    Feint,
+}
+
+impl PatternKind {
+    const ALL: [(Self, &'static str); 3] = [
+        (Self::Spiral, "spiral"),
+        (Self::Wall, "wall"),
+        (Self::Feint, "feint"),
+    ];
+
+    pub fn name(self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(kind, _)| *kind == self)
+            .map_or("", |(_, name)| name)
+    }
+
+    /// The locale id announcing the pattern as it winds up.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Spiral => "hud-phase-spiral",
+            Self::Wall => "hud-phase-wall",
+            Self::Feint => "hud-phase-feint",
+        }
+    }
+}
+
+/// Projectiles fired by a phase, expanded into the sheet when it's parsed.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    /// Projectiles fired, or volleys for walls.
+    pub count: u32,
+    /// Time between projectiles or volleys.
+    pub every: TimeOffset,
+    /// The side spirals start from and feints pretend to fire from.
+    pub side: Direction,
+    /// Whether spirals turn clockwise.
+    pub clockwise: bool,
+    /// The lane fired at on stage sheets.
+    pub lane: usize,
+    /// Most seconds the humanize modifier moves each projectile by.
+    pub jitter: f32,
+}
+
+impl Pattern {
+    /// Parses the `key=value` pairs of a `#phase`, returning the first invalid pair on errors.
+    pub fn parse<'a>(
+        pairs: impl IntoIterator<Item = (&'a str, &'a str)>,
+        lanes: usize,
+    ) -> Result<Self, String> {
+        let mut kind = None;
+        let mut pattern = Self {
+            kind: PatternKind::Spiral,
+            count: 8,
+            every: TimeOffset {
+                fourths: 0,
+                beats: 1,
+                bars: 0,
+            },
+            side: Direction::Up,
+            clockwise: true,
+            lane: 0,
+            jitter: 0.0,
+        };
+
+        for (key, value) in pairs {
+            let invalid = || format!("{}={}", key, value);
+
+            match key {
+                "pattern" => {
+                    kind = PatternKind::ALL
+                        .iter()
+                        .find(|(_, name)| *name == value)
+                        .map(|(kind, _)| *kind);
+
+                    if kind.is_none() {
+                        return Err(invalid());
+                    }
+                }
+                "count" => {
+                    pattern.count = value
+                        .parse()
+                        .ok()
+                        .filter(|count| (1..=MAX_COUNT).contains(count))
+                        .ok_or_else(invalid)?
+                }
+                "every" => pattern.every = TimeOffset::parse(value).map_err(|_| invalid())?,
+                "side" => {
+                    pattern.side = match Token::parse(value) {
+                        Ok(Token::Direction(direction)) => direction,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "turn" => {
+                    pattern.clockwise = match value {
+                        "cw" => true,
+                        "ccw" => false,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "lane" => {
+                    pattern.lane = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|lane| (1..=lanes).contains(lane))
+                        .ok_or_else(invalid)?
+                        - 1
+                }
+                "jitter" => pattern.jitter = parse_jitter_value(value).ok_or_else(invalid)?,
+                _ => return Err(invalid()),
+            }
+        }
+
+        pattern.kind = kind.ok_or_else(|| String::from("pattern="))?;
+
+        Ok(pattern)
+    }
+
+    /// The projectiles of the pattern starting at `time`.
+    pub fn projectiles(&self, time: f32, bpm: f32) -> Vec<Projectile> {
+        let timing = Timing {
+            start: time,
+            every: self.every.time(bpm),
+            count: self.count,
+            lane: self.lane,
+            jitter: self.jitter,
+        };
+
+        // a wave between a side and itself keeps coming from that side
+        let mut projectiles = match self.kind {
+            PatternKind::Spiral => generators::spiral(&timing, &self.side, self.clockwise),
+            PatternKind::Wall => Direction::ALL
+                .iter()
+                .flat_map(|side| generators::wave(&timing, side, side))
+                .collect(),
+            PatternKind::Feint => {
+                let side = self.side.opposite();
+
+                generators::wave(&timing, &side, &side)
+            }
+        };
+
+        for projectile in &mut projectiles {
+            projectile.generated = true;
+        }
+
+        projectiles
+    }
+
+    /// The pattern as the `key=value` pairs of a `#phase`.
+    pub fn to_source(&self, lanes: usize) -> String {
+        let side = match self.side {
+            Direction::Up => "U",
+            Direction::Down => "D",
+            Direction::Left => "L",
+            Direction::Right => "R",
+        };
+
+        let mut source = format!(
+            "pattern={} count={} every={} side={} turn={}",
+            self.kind.name(),
+            self.count,
+            self.every,
+            side,
+            if self.clockwise { "cw" } else { "ccw" }
+        );
+
+        if lanes > 1 {
+            source.push_str(&format!(" lane={}", self.lane + 1));
+        }
+
+        if self.jitter > 0.0 {
+            source.push_str(&format!(" jitter={}s", self.jitter));
+        }
+
+        source
+    }
+}
+
+/// A `#phase` of the sheet.
+#[derive(Clone, Debug)]
+pub struct Phase {
+    /// When the first projectile of the pattern arrives.
+    pub time: f32,
+    pub pattern: Pattern,
+}
+
+/// The phase of `phases` winding up at `time`, with how far the windup has got from 0 to 1. Phases
+/// wind up for a beat at `bpm` until their projectiles become active, `lookahead` seconds before
+/// they arrive.
+pub fn windup(phases: &[Phase], time: f32, lookahead: f32, bpm: f32) -> Option<(&Phase, f32)> {
+    let length = 60.0 / bpm;
+
+    phases.iter().find_map(|phase| {
+        let progress = 1.0 - (phase.time - lookahead - time) / length;
+
+        Some((phase, progress)).filter(|_| (0.0..1.0).contains(&progress))
+    })
+}
+
+/// The boss of a level with `#boss`, while it's being played.
+pub struct Boss {
+    pub health: f32,
+    pub max: f32,
+    /// Seconds since the boss was last hit.
+    hit: f32,
+    /// Seconds since the boss was defeated.
+    pub defeated: Option<f32>,
+}
+
+impl Boss {
+    pub fn new(health: f32) -> Self {
+        Self {
+            health,
+            max: health,
+            hit: HIT_FLASH,
+            defeated: None,
+        }
+    }
+
+    /// Takes `amount` of health, returning whether that defeated the boss.
+    pub fn damage(&mut self, amount: f32) -> bool {
+        if self.defeated.is_some() {
+            return false;
+        }
+
+        self.health = (self.health - amount).max(0.0);
+        self.hit = 0.0;
+
+        if self.health <= 0.0 {
+            self.defeated = Some(0.0);
+        }
+
+        self.defeated.is_some()
+    }
+
+    pub fn update(&mut self, frame_time: f32) {
+        self.hit += frame_time;
+
+        if let Some(defeated) = &mut self.defeated {
+            *defeated += frame_time;
+        }
+    }
+
+    /// Draws the boss in the arena, winding up while `windup` goes from 0 to 1 before a phase.
+    pub fn draw(&self, assets: &Assets, windup: Option<f32>, time: f32) {
+        let texture = match assets.boss {
+            Some(texture) => texture,
+            None => return,
+        };
+
+        let fade = self
+            .defeated
+            .map_or(1.0, |defeated| 1.0 - defeated / DEFEAT_FADE);
+
+        if fade <= 0.0 {
+            return;
+        }
+
+        // shaking harder and swelling as the phase gets closer
+        let windup = windup.unwrap_or(0.0);
+        let shake = vec2((time * 60.0).sin(), (time * 47.0).cos()) * windup * 2.0;
+        let height = BOSS_HEIGHT * (1.0 + windup * 0.1);
+        let size = vec2(texture.width() / texture.height() * height, height);
+        let position = BOSS_POSITION + shake - size / 2.0;
+
+        let color = if self.hit < HIT_FLASH {
+            Color::new(1.0, 0.5, 0.5, fade)
+        } else {
+            Color::new(1.0, 1.0, 1.0, fade)
+        };
+
+        draw_texture_ex(
+            texture,
+            position.x,
+            position.y,
+            color,
+            DrawTextureParams {
+                dest_size: Some(size),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Draws the health bar with its top left corner at `corner`, in screen pixels.
+    pub fn draw_health(&self, corner: Vec2, size: Vec2) {
+        let fraction = self.health / self.max;
+
+        draw_rectangle(
+            corner.x,
+            corner.y,
+            size.x,
+            size.y,
+            Color::new(0.0, 0.0, 0.0, 0.5),
+        );
+        draw_rectangle(corner.x, corner.y, size.x * fraction, size.y, RED);
+        draw_rectangle_lines(corner.x, corner.y, size.x, size.y, 1.0, WHITE);
+    }
+}
@@ -0,0 +1,201 @@
+//! The gameplay camera, moved by `#camera` lines in sheets and `camera` actions in scripts.
+//!
+//! Both take `key=value` pairs, any left out keep their current value:
+//!
+//! ```text
+//! // zoom in and slowly turn a quarter around over two bars, starting at bar 16
+//! #camera 0;0|16 zoom=1.5 rotation=90 over=0;0|2
+//! ```
+//!
+//! `x` and `y` move the point the camera looks at, in canvas pixels from the heart.
+
+use crate::sheet::TimeOffset;
+use macroquad::prelude::*;
+
+#[derive(Clone, Debug)]
+pub struct CameraState {
+    pub zoom: f32,
+    /// In degrees.
+    pub rotation: f32,
+    pub target: Vec2,
+}
+
+impl Default for CameraState {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            rotation: 0.0,
+            target: Vec2::ZERO,
+        }
+    }
+}
+
+/// A change to the camera, eased into over `over`.
+#[derive(Clone, Debug)]
+pub struct CameraMove {
+    pub zoom: Option<f32>,
+    pub rotation: Option<f32>,
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+    pub over: Option<TimeOffset>,
+}
+
+impl CameraMove {
+    /// Parses the `key=value` pairs of a move, returning the first invalid pair on errors.
+    pub fn parse<'a>(pairs: impl IntoIterator<Item = (&'a str, &'a str)>) -> Result<Self, String> {
+        let mut movement = Self {
+            zoom: None,
+            rotation: None,
+            x: None,
+            y: None,
+            over: None,
+        };
+
+        for (key, value) in pairs {
+            let invalid = || format!("{}={}", key, value);
+            let number = || value.parse::<f32>().map_err(|_| invalid());
+
+            match key {
+                "zoom" => movement.zoom = Some(number()?).filter(|zoom| *zoom > 0.0),
+                "rotation" => movement.rotation = Some(number()?),
+                "x" => movement.x = Some(number()?),
+                "y" => movement.y = Some(number()?),
+                "over" => movement.over = Some(TimeOffset::parse(value).map_err(|_| invalid())?),
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(movement)
+    }
+}
+
+/// A move from the sheet made at `time`.
+#[derive(Debug)]
+pub struct CameraEvent {
+    pub time: f32,
+    pub movement: CameraMove,
+}
+
+pub struct Camera {
+    from: CameraState,
+    to: CameraState,
+    start: f32,
+    duration: f32,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self {
+            from: CameraState::default(),
+            to: CameraState::default(),
+            start: 0.0,
+            duration: 0.0,
+        }
+    }
+
+    /// The camera at `time`, part way through the last move.
+    pub fn state(&self, time: f32) -> CameraState {
+        let t = if self.duration > 0.0 {
+            ((time - self.start) / self.duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        // ease in and out so ramps don't start or stop abruptly
+        let t = t * t * (3.0 - 2.0 * t);
+
+        CameraState {
+            zoom: self.from.zoom + (self.to.zoom - self.from.zoom) * t,
+            rotation: self.from.rotation + (self.to.rotation - self.from.rotation) * t,
+            target: self.from.target.lerp(self.to.target, t),
+        }
+    }
+
+    /// Starts `movement` at `time`, from wherever the camera is at that point.
+    pub fn apply(&mut self, movement: &CameraMove, time: f32, bpm: f32) {
+        let from = self.state(time);
+
+        self.to = CameraState {
+            zoom: movement.zoom.unwrap_or(from.zoom),
+            rotation: movement.rotation.unwrap_or(from.rotation),
+            target: vec2(
+                movement.x.unwrap_or(from.target.x),
+                movement.y.unwrap_or(from.target.y),
+            ),
+        };
+        self.from = from;
+        self.start = time;
+        self.duration = movement.over.as_ref().map_or(0.0, |over| over.time(bpm));
+    }
+}
+
+/// Canvas pixels the camera moves at full trauma.
+const SHAKE_OFFSET: f32 = 8.0;
+
+/// Degrees the camera turns at full trauma.
+const SHAKE_ROTATION: f32 = 2.0;
+
+/// How fast trauma wears off, the fraction left after a second is `exp(-SHAKE_DECAY)`.
+const SHAKE_DECAY: f32 = 3.0;
+
+/// How many times a second the shake changes direction, roughly.
+const SHAKE_FREQUENCY: f32 = 20.0;
+
+/// Trauma based camera shake: hits add trauma which decays over time, and the camera moves by
+/// the square of it so small bumps stay subtle while big ones still land.
+pub struct Shake {
+    /// From 0 to 1.
+    pub trauma: f32,
+    time: f32,
+}
+
+impl Shake {
+    pub fn new() -> Self {
+        Self {
+            trauma: 0.0,
+            time: 0.0,
+        }
+    }
+
+    pub fn add(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).min(1.0);
+    }
+
+    pub fn update(&mut self, frame_time: f32) {
+        self.trauma *= (-SHAKE_DECAY * frame_time).exp();
+        self.time += frame_time;
+    }
+
+    /// Offset in canvas pixels and rotation in degrees to shake the camera by, scaled by
+    /// `intensity`.
+    pub fn offset(&self, intensity: f32) -> (Vec2, f32) {
+        let shake = self.trauma * self.trauma * intensity;
+        let x = self.time * SHAKE_FREQUENCY;
+
+        let offset = vec2(noise(0, x), noise(1, x)) * SHAKE_OFFSET * shake;
+        let rotation = noise(2, x) * SHAKE_ROTATION * shake;
+
+        (offset, rotation)
+    }
+}
+
+/// Smooth noise from -1 to 1, interpolating random values at whole numbers of `x`.
+fn noise(seed: u32, x: f32) -> f32 {
+    let lattice = |i: i32| {
+        let mut h = (i as u32).wrapping_mul(0x9e37_79b9) ^ seed.wrapping_mul(0x85eb_ca6b);
+
+        h ^= h >> 16;
+        h = h.wrapping_mul(0x7feb_352d);
+        h ^= h >> 15;
+
+        h as f32 / u32::MAX as f32 * 2.0 - 1.0
+    };
+
+    let i = x.floor();
+    let t = x - i;
+    let t = t * t * (3.0 - 2.0 * t);
+
+    let (a, b) = (lattice(i as i32), lattice(i as i32 + 1));
+
+    a + (b - a) * t
+}
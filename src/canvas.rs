@@ -0,0 +1,38 @@
+//! The fixed resolution virtual canvas gameplay is rendered to.
+//!
+//! Gameplay is always drawn at [`WIDTH`]x[`HEIGHT`] and scaled to fit the window with
+//! letterboxing, so every player sees the same arena regardless of their resolution.
+
+use macroquad::prelude::*;
+
+pub const WIDTH: f32 = 640.0;
+pub const HEIGHT: f32 = 360.0;
+
+pub fn size() -> Vec2 {
+    vec2(WIDTH, HEIGHT)
+}
+
+/// Scale from canvas pixels to screen pixels.
+pub fn scale() -> f32 {
+    (screen_width() / WIDTH).min(screen_height() / HEIGHT)
+}
+
+/// The letterboxed area of the screen the canvas is drawn to, in screen pixels.
+pub fn viewport() -> Rect {
+    let scale = scale();
+    let (width, height) = (WIDTH * scale, HEIGHT * scale);
+
+    Rect::new(
+        (screen_width() - width) / 2.0,
+        (screen_height() - height) / 2.0,
+        width,
+        height,
+    )
+}
+
+/// Converts a position in the arena, centered on the heart, to screen pixels.
+pub fn to_screen(position: Vec2) -> Vec2 {
+    let viewport = viewport();
+
+    vec2(viewport.x + viewport.w / 2.0, viewport.y + viewport.h / 2.0) + position * scale()
+}
@@ -0,0 +1,254 @@
+//! Screenshots and clips of the last few seconds of gameplay, saved to [`CAPTURES_DIR`].
+
+use macroquad::prelude::*;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+pub const CAPTURES_DIR: &str = "captures";
+
+/// Frames per second clips are recorded at.
+const CLIP_FPS: u32 = 30;
+
+/// Clips are recorded at the canvas resolution divided by this.
+const CLIP_DOWNSCALE: usize = 2;
+
+/// A path in the captures folder that doesn't exist yet, creating the folder if needed.
+fn capture_path(prefix: &str, extension: &str) -> io::Result<PathBuf> {
+    std::fs::create_dir_all(CAPTURES_DIR)?;
+
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    let mut index = 0;
+
+    loop {
+        let path = PathBuf::from(CAPTURES_DIR)
+            .join(format!("{}-{}-{}.{}", prefix, seconds, index, extension));
+
+        if !path.exists() {
+            return Ok(path);
+        }
+
+        index += 1;
+    }
+}
+
+/// Saves what's currently on screen as a png.
+pub fn screenshot() -> io::Result<PathBuf> {
+    let image = get_screen_data();
+    let path = capture_path("screenshot", "png")?;
+
+    let width = image.width as usize * 4;
+
+    // the screen is read bottom row first
+    let bytes: Vec<u8> = image.bytes.chunks(width).rev().flatten().copied().collect();
+
+    image::save_buffer(
+        &path,
+        &bytes,
+        image.width as u32,
+        image.height as u32,
+        image::ColorType::Rgba8,
+    )
+    .map_err(io::Error::other)?;
+
+    Ok(path)
+}
+
+/// Keeps the last few seconds of a texture, ready to be saved as a clip.
+pub struct ClipRecorder {
+    /// Frames as bottom-up BGR rows padded to 4 bytes, ready to be written to an avi.
+    frames: VecDeque<Vec<u8>>,
+    width: usize,
+    height: usize,
+    accumulator: f32,
+}
+
+impl ClipRecorder {
+    pub fn new() -> Self {
+        Self {
+            frames: VecDeque::new(),
+            width: 0,
+            height: 0,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Records `texture` if it's time for a new frame, keeping `length` seconds of frames.
+    pub fn record(&mut self, texture: Texture2D, length: f32) {
+        self.accumulator += get_frame_time();
+
+        if self.accumulator < 1.0 / CLIP_FPS as f32 {
+            return;
+        }
+
+        self.accumulator = 0.0;
+
+        let image = texture.get_texture_data();
+        let width = image.width as usize / CLIP_DOWNSCALE;
+        let height = image.height as usize / CLIP_DOWNSCALE;
+
+        if (width, height) != (self.width, self.height) {
+            self.frames.clear();
+            self.width = width;
+            self.height = height;
+        }
+
+        let stride = row_stride(width);
+        let mut frame = vec![0; stride * height];
+
+        for y in 0..height {
+            for x in 0..width {
+                let source = ((y * CLIP_DOWNSCALE) * image.width as usize + x * CLIP_DOWNSCALE) * 4;
+                let target = y * stride + x * 3;
+
+                frame[target] = image.bytes[source + 2];
+                frame[target + 1] = image.bytes[source + 1];
+                frame[target + 2] = image.bytes[source];
+            }
+        }
+
+        self.frames.push_back(frame);
+
+        let max_frames = (length * CLIP_FPS as f32) as usize;
+
+        while self.frames.len() > max_frames.max(1) {
+            self.frames.pop_front();
+        }
+    }
+
+    /// Saves the recorded frames as an uncompressed avi.
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let path = capture_path("clip", "avi")?;
+        let mut file = io::BufWriter::new(std::fs::File::create(&path)?);
+
+        write_avi(&mut file, self.width, self.height, &self.frames)?;
+        file.flush()?;
+
+        Ok(path)
+    }
+}
+
+/// Bytes in a row of a 24 bit bitmap, which are padded to 4 bytes.
+fn row_stride(width: usize) -> usize {
+    (width * 3 + 3) & !3
+}
+
+fn write_avi(
+    writer: &mut impl Write,
+    width: usize,
+    height: usize,
+    frames: &VecDeque<Vec<u8>>,
+) -> io::Result<()> {
+    let frame_size = (row_stride(width) * height) as u32;
+    let frame_count = frames.len() as u32;
+    let (width, height) = (width as u32, height as u32);
+
+    let u32_le = |value: u32| value.to_le_bytes();
+
+    // stream format, a BITMAPINFOHEADER
+    let mut strf = Vec::new();
+    for value in [40, width, height] {
+        strf.extend_from_slice(&u32_le(value));
+    }
+    strf.extend_from_slice(&1u16.to_le_bytes());
+    strf.extend_from_slice(&24u16.to_le_bytes());
+    for value in [0, frame_size, 0, 0, 0, 0] {
+        strf.extend_from_slice(&u32_le(value));
+    }
+
+    // stream header
+    let mut strh = Vec::new();
+    strh.extend_from_slice(b"vidsDIB ");
+    for value in [
+        0,           // flags
+        0,           // priority and language
+        0,           // initial frames
+        1,           // scale
+        CLIP_FPS,    // rate, frames per second is rate / scale
+        0,           // start
+        frame_count, // length
+        frame_size,  // suggested buffer size
+        u32::MAX,    // quality, default
+        0,           // sample size, varies
+    ] {
+        strh.extend_from_slice(&u32_le(value));
+    }
+    for value in [0, 0, width as u16, height as u16] {
+        strh.extend_from_slice(&value.to_le_bytes());
+    }
+
+    // main header
+    let mut avih = Vec::new();
+    for value in [
+        1_000_000 / CLIP_FPS,  // microseconds per frame
+        frame_size * CLIP_FPS, // max bytes per second
+        0,                     // padding granularity
+        0x10,                  // flags, has an index
+        frame_count,           // total frames
+        0,                     // initial frames
+        1,                     // streams
+        frame_size,            // suggested buffer size
+        width,
+        height,
+        0,
+        0,
+        0,
+        0,
+    ] {
+        avih.extend_from_slice(&u32_le(value));
+    }
+
+    let strl_size = 4 + (8 + strh.len()) + (8 + strf.len());
+    let hdrl_size = 4 + (8 + avih.len()) + (8 + strl_size);
+    let movi_size = 4 + frames.len() * (8 + frame_size as usize);
+    let idx1_size = frames.len() * 16;
+    let riff_size = 4 + (8 + hdrl_size) + (8 + movi_size) + (8 + idx1_size);
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&u32_le(riff_size as u32))?;
+    writer.write_all(b"AVI ")?;
+
+    writer.write_all(b"LIST")?;
+    writer.write_all(&u32_le(hdrl_size as u32))?;
+    writer.write_all(b"hdrl")?;
+    writer.write_all(b"avih")?;
+    writer.write_all(&u32_le(avih.len() as u32))?;
+    writer.write_all(&avih)?;
+
+    writer.write_all(b"LIST")?;
+    writer.write_all(&u32_le(strl_size as u32))?;
+    writer.write_all(b"strl")?;
+    writer.write_all(b"strh")?;
+    writer.write_all(&u32_le(strh.len() as u32))?;
+    writer.write_all(&strh)?;
+    writer.write_all(b"strf")?;
+    writer.write_all(&u32_le(strf.len() as u32))?;
+    writer.write_all(&strf)?;
+
+    writer.write_all(b"LIST")?;
+    writer.write_all(&u32_le(movi_size as u32))?;
+    writer.write_all(b"movi")?;
+
+    for frame in frames {
+        writer.write_all(b"00db")?;
+        writer.write_all(&u32_le(frame_size))?;
+        writer.write_all(frame)?;
+    }
+
+    // index offsets are relative to the 'movi' fourcc
+    writer.write_all(b"idx1")?;
+    writer.write_all(&u32_le(idx1_size as u32))?;
+
+    for index in 0..frame_count {
+        writer.write_all(b"00db")?;
+        writer.write_all(&u32_le(0x10))?;
+        writer.write_all(&u32_le(4 + index * (8 + frame_size)))?;
+        writer.write_all(&u32_le(frame_size))?;
+    }
+
+    Ok(())
+}
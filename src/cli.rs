@@ -0,0 +1,117 @@
+//! Command line arguments, for launching a level directly and for scripts.
+
+use crate::modifiers::Modifiers;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+usage: srg [LEVEL] [OPTIONS]
+
+  LEVEL                 level directory to play, skipping the menu
+
+options:
+  --difficulty NAME     play LEVEL/NAME.sht instead of LEVEL/sheet.sht, or .json
+  --autoplay            block every projectile automatically
+  --rate RATE           play the song at RATE times normal speed
+  --start-bar BAR       start playing at bar BAR
+  --check               check the sheet of LEVEL for errors and warnings and exit
+  --verify REPLAY       check the score REPLAY claims on LEVEL and exit
+  --help                print this message";
+
+#[derive(Debug)]
+pub enum ArgsError {
+    MissingValue(String),
+    InvalidValue(String, String),
+    UnknownArgument(String),
+    /// The argument needing a level.
+    MissingLevel(&'static str),
+}
+
+impl std::fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::MissingValue(arg) => write!(f, "{} needs a value", arg),
+            Self::InvalidValue(arg, value) => write!(f, "invalid value '{}' for {}", value, arg),
+            Self::UnknownArgument(arg) => write!(f, "unknown argument '{}'", arg),
+            Self::MissingLevel(arg) => write!(f, "{} needs a level", arg),
+        }
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub level: Option<PathBuf>,
+    pub difficulty: Option<String>,
+    pub autoplay: bool,
+    pub rate: Option<f32>,
+    pub start_bar: u32,
+    pub check: bool,
+    /// Replay file to verify.
+    pub verify: Option<PathBuf>,
+    /// Run the benchmark, left out of the usage since it's only for measuring the game.
+    pub bench: bool,
+    pub help: bool,
+}
+
+impl Args {
+    /// Parses the arguments, not including the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--difficulty" => parsed.difficulty = Some(value(&arg, args.next())?),
+                "--autoplay" => parsed.autoplay = true,
+                "--rate" => {
+                    let rate = value(&arg, args.next())?;
+
+                    parsed.rate = match rate.parse::<f32>() {
+                        Ok(rate) if rate > 0.0 => Some(rate),
+                        _ => return Err(ArgsError::InvalidValue(arg, rate)),
+                    };
+                }
+                "--start-bar" => {
+                    let bar = value(&arg, args.next())?;
+
+                    parsed.start_bar =
+                        bar.parse().map_err(|_| ArgsError::InvalidValue(arg, bar))?;
+                }
+                "--check" => parsed.check = true,
+                "--verify" => parsed.verify = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--bench" => parsed.bench = true,
+                "--help" | "-h" => parsed.help = true,
+                _ if arg.starts_with('-') || parsed.level.is_some() => {
+                    return Err(ArgsError::UnknownArgument(arg))
+                }
+                _ => parsed.level = Some(PathBuf::from(arg)),
+            }
+        }
+
+        if parsed.level.is_none() {
+            if parsed.check {
+                return Err(ArgsError::MissingLevel("--check"));
+            }
+
+            if parsed.verify.is_some() {
+                return Err(ArgsError::MissingLevel("--verify"));
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// `modifiers` with `--autoplay` and `--rate` on top, for every level the player launches.
+    pub fn modifiers(&self, modifiers: &Modifiers) -> Modifiers {
+        Modifiers {
+            autoplay: modifiers.autoplay || self.autoplay,
+            rate: self.rate.unwrap_or(modifiers.rate),
+            ..modifiers.clone()
+        }
+    }
+}
+
+fn value(arg: &str, value: Option<String>) -> Result<String, ArgsError> {
+    value.ok_or_else(|| ArgsError::MissingValue(arg.to_string()))
+}
@@ -0,0 +1,256 @@
+//! A small subset of toml, enough for settings and other hand written config files.
+//!
+//! Supports `[section]` headers, `key = value` pairs, `#` comments and booleans, numbers,
+//! strings and arrays as values. Keys inside a section are flattened to `section.key`.
+
+#[derive(Debug)]
+pub enum ConfigError {
+    InvalidLine(usize, String),
+    InvalidValue(String),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidLine(line, source) => write!(f, "invalid line {}: '{}'", line, source),
+            Self::InvalidValue(value) => write!(f, "invalid value '{}'", value),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+pub type Result<T> = std::result::Result<T, ConfigError>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Number(f32),
+    String(String),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    pub fn parse(source: &str) -> Result<Self> {
+        let source = source.trim();
+
+        if source == "true" {
+            return Ok(Self::Bool(true));
+        }
+
+        if source == "false" {
+            return Ok(Self::Bool(false));
+        }
+
+        if let Ok(number) = source.parse::<f32>() {
+            return Ok(Self::Number(number));
+        }
+
+        if source.len() >= 2 && source.starts_with('"') && source.ends_with('"') {
+            return Ok(Self::String(unescape(&source[1..source.len() - 1])));
+        }
+
+        if source.starts_with('[') && source.ends_with(']') {
+            let inner = source[1..source.len() - 1].trim();
+
+            if inner.is_empty() {
+                return Ok(Self::Array(Vec::new()));
+            }
+
+            return split_top_level(inner)
+                .into_iter()
+                .filter(|item| !item.trim().is_empty())
+                .map(Self::parse)
+                .collect::<Result<_>>()
+                .map(Self::Array);
+        }
+
+        Err(ConfigError::InvalidValue(source.to_string()))
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Self::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Number(value) => write!(f, "{:?}", value),
+            Self::String(value) => write!(f, "\"{}\"", escape(value)),
+            Self::Array(values) => {
+                write!(f, "[")?;
+
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{}", value)?;
+                }
+
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+fn escape(source: &str) -> String {
+    source.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn unescape(source: &str) -> String {
+    source.replace("\\\"", "\"").replace("\\\\", "\\")
+}
+
+/// Splits on commas that aren't inside strings or nested arrays.
+fn split_top_level(source: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut start = 0;
+    let mut prev = ' ';
+
+    for (i, c) in source.char_indices() {
+        match c {
+            '"' if prev != '\\' => in_string = !in_string,
+            '[' if !in_string => depth += 1,
+            ']' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                items.push(&source[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+
+        prev = c;
+    }
+
+    items.push(&source[start..]);
+
+    items
+}
+
+/// Strips a trailing `#` comment that isn't inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut prev = ' ';
+
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' if prev != '\\' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+
+        prev = c;
+    }
+
+    line
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub values: Vec<(String, Value)>,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut config = Self::new();
+        let mut section = String::new();
+
+        for (i, line) in source.lines().enumerate() {
+            let line = strip_comment(line).trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len() - 1].trim().to_string();
+                continue;
+            }
+
+            let index = line
+                .find('=')
+                .ok_or_else(|| ConfigError::InvalidLine(i + 1, line.to_string()))?;
+
+            let key = line[..index].trim();
+
+            if key.is_empty() {
+                return Err(ConfigError::InvalidLine(i + 1, line.to_string()));
+            }
+
+            let key = if section.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", section, key)
+            };
+
+            config.set(key, Value::parse(&line[index + 1..])?);
+        }
+
+        Ok(config)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.values
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn set(&mut self, key: impl Into<String>, value: Value) {
+        let key = key.into();
+
+        if let Some((_, v)) = self.values.iter_mut().find(|(k, _)| *k == key) {
+            *v = value;
+        } else {
+            self.values.push((key, value));
+        }
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key).and_then(Value::as_bool)
+    }
+
+    pub fn get_f32(&self, key: &str) -> Option<f32> {
+        self.get(key).and_then(Value::as_f32)
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(Value::as_str)
+    }
+}
+
+impl std::fmt::Display for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (key, value) in &self.values {
+            writeln!(f, "{} = {}", key, value)?;
+        }
+
+        Ok(())
+    }
+}
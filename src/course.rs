@@ -0,0 +1,119 @@
+//! Courses, marathons put together ahead of time in `courses/*.toml`, played with the modifiers
+//! they force whatever the player picked:
+//!
+//! ```toml
+//! name = "Encore"
+//! # level folders in songs, played in order
+//! levels = ["Undyne", "Undyne"]
+//!
+//! [modifiers]
+//! rate = 1.1
+//! hidden = true
+//! ```
+//!
+//! The modifiers take the same keys as the settings' `[modifiers]`, any left out are off. A course
+//! keeps a single record with the total score of each run, rather than one for each level.
+
+use crate::asset_source::load_text;
+use crate::config::Config;
+use crate::integrity::Digest;
+use crate::log_warn;
+use crate::modifiers::Modifiers;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug)]
+pub struct Course {
+    /// Name of the file without its extension, which the course's scores are kept under.
+    pub id: String,
+    /// Name shown in the menu, the id unless the file names it.
+    pub name: String,
+    /// Directories of the levels, in order.
+    pub levels: Vec<PathBuf>,
+    pub modifiers: Modifiers,
+    /// The hashes of the levels' charts together, `None` if one of them doesn't load.
+    pub chart: Option<u64>,
+}
+
+impl Course {
+    /// Parses the course file `source` of the course named `id`, its levels in the songs folder
+    /// `levels_dir`.
+    pub fn parse(id: &str, source: &str, levels_dir: &Path) -> Result<Self, String> {
+        let config = Config::parse(source).map_err(|err| err.to_string())?;
+
+        let levels = match config.get("levels") {
+            Some(crate::config::Value::Array(levels)) => levels
+                .iter()
+                .map(|level| {
+                    level
+                        .as_str()
+                        .map(|level| levels_dir.join(level))
+                        .ok_or_else(|| String::from("levels must be level folder names"))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => return Err(String::from("no levels")),
+        };
+
+        if levels.is_empty() {
+            return Err(String::from("no levels"));
+        }
+
+        Ok(Self {
+            id: id.to_string(),
+            name: config.get_str("name").unwrap_or(id).to_string(),
+            levels,
+            // a course played on autoplay isn't a course
+            modifiers: Modifiers {
+                autoplay: false,
+                ..Modifiers::from_config(&config)
+            },
+            chart: None,
+        })
+    }
+
+    /// What the course's scores are kept under, apart from the levels'.
+    pub fn scores_key(&self) -> String {
+        format!("course:{}", self.id)
+    }
+
+    /// Works out [`Course::chart`] from `chart`, the hash of a level's chart if it loads.
+    pub fn hash_charts(&mut self, chart: impl Fn(&PathBuf) -> Option<u64>) {
+        let mut digest = Digest::new();
+
+        for level in &self.levels {
+            match chart(level) {
+                Some(hash) => digest.bytes(&hash.to_le_bytes()),
+                None => {
+                    self.chart = None;
+
+                    return;
+                }
+            }
+        }
+
+        self.chart = Some(digest.finish());
+    }
+}
+
+/// Loads the courses in `paths`, leaving out the ones that don't parse.
+pub async fn load_courses(paths: Vec<PathBuf>, levels_dir: &Path) -> Vec<Course> {
+    let mut courses = Vec::new();
+
+    for path in paths {
+        let id = match path.file_stem() {
+            Some(id) => id.to_string_lossy().into_owned(),
+            None => continue,
+        };
+
+        let parsed = match load_text(&path).await {
+            Ok(source) => Course::parse(&id, &source, levels_dir),
+            Err(err) => Err(err.to_string()),
+        };
+
+        match parsed {
+            Ok(course) => courses.push(course),
+            Err(err) => log_warn!("{}: {}", path.display(), err),
+        }
+    }
+
+    courses
+}
@@ -0,0 +1,112 @@
+//! Crash logs, written to [`CRASHES_DIR`] when the game panics so the next launch can point the
+//! player at what happened instead of the game just vanishing.
+//!
+//! A log holds the panic with its backtrace, the level loaded last and the recent log lines.
+//! The path of the newest one is kept in [`PENDING_PATH`] until the player dismisses it.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const CRASHES_DIR: &str = "crashes";
+
+/// Path of the crash log the player hasn't been told about yet.
+pub const PENDING_PATH: &str = "crashes/pending";
+
+/// Log lines put in a crash log, the latest ones.
+const LOG_LINES: usize = 50;
+
+/// The level loaded last, named in crash logs.
+static LEVEL: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Remembers `level` as the one playing for crash logs.
+pub fn set_level(level: &Path) {
+    *LEVEL.lock().unwrap_or_else(|err| err.into_inner()) = Some(level.to_path_buf());
+}
+
+/// Writes a crash log whenever the game panics, before the panic is reported as usual.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn install() {
+    let report = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        crate::log_error!("{}", info);
+
+        match write_log(info) {
+            Ok(path) => eprintln!("crash log saved to {}", path.display()),
+            Err(err) => eprintln!("failed to save crash log: {}", err),
+        }
+
+        report(info);
+    }));
+}
+
+/// There's nowhere to write logs on the web, panics show up in the browser console already.
+#[cfg(target_arch = "wasm32")]
+pub fn install() {}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_log(info: &std::panic::PanicHookInfo) -> std::io::Result<PathBuf> {
+    use crate::log;
+    use std::fmt::Write;
+
+    let date = macroquad::miniquad::date::now() as u64;
+    let level = LEVEL.lock().unwrap_or_else(|err| err.into_inner()).clone();
+
+    let mut text = String::new();
+    let _ = writeln!(text, "srg {} crashed", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(
+        text,
+        "level: {}",
+        level.map_or(String::from("none"), |level| level.display().to_string())
+    );
+    let _ = writeln!(text, "{}", info);
+    let _ = writeln!(text);
+    let _ = writeln!(text, "{}", std::backtrace::Backtrace::force_capture());
+    let _ = writeln!(text, "last log lines:");
+
+    let recent = log::recent();
+
+    for line in &recent[recent.len().saturating_sub(LOG_LINES)..] {
+        let _ = writeln!(text, "{}", line);
+    }
+
+    std::fs::create_dir_all(CRASHES_DIR)?;
+
+    let path = PathBuf::from(CRASHES_DIR).join(format!("crash-{}.log", date));
+    std::fs::write(&path, text)?;
+    std::fs::write(PENDING_PATH, path.to_string_lossy().as_bytes())?;
+
+    Ok(path)
+}
+
+/// The crash log of the last crash, if the player hasn't dismissed it.
+pub fn pending() -> Option<PathBuf> {
+    let path = std::fs::read_to_string(PENDING_PATH).ok()?;
+    let path = PathBuf::from(path.trim());
+
+    Some(path).filter(|path| path.exists())
+}
+
+/// Stops bringing up the last crash.
+pub fn dismiss() {
+    let _ = std::fs::remove_file(PENDING_PATH);
+}
+
+/// Opens `path` with whatever the system opens files of its kind with.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn open(path: &Path) -> std::io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+
+    #[cfg(target_os = "macos")]
+    let mut command = std::process::Command::new("open");
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = std::process::Command::new("xdg-open");
+
+    command.arg(path).spawn().map(|_| ())
+}
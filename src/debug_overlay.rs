@@ -0,0 +1,180 @@
+//! Numbers for diagnosing stutter and timing problems, toggled with F3 and drawn in the top left
+//! over everything else.
+
+use crate::game::{Assets, GameState};
+use crate::log;
+use crate::settings::Settings;
+use macroquad::prelude::*;
+use std::collections::VecDeque;
+
+/// Frames the frame time graph shows.
+const FRAME_HISTORY: usize = 240;
+
+/// Frame time the graph is scaled to, anything longer is cut off at the top.
+const GRAPH_MAX: f32 = 1.0 / 30.0;
+
+/// Log lines shown under the numbers.
+const LOG_LINES: usize = 6;
+
+pub struct DebugOverlay {
+    pub visible: bool,
+    /// Seconds each of the last frames took, oldest first.
+    frame_times: VecDeque<f32>,
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            frame_times: VecDeque::with_capacity(FRAME_HISTORY),
+        }
+    }
+
+    /// Records the frame time and toggles the overlay on F3, every frame.
+    pub fn update(&mut self) {
+        if is_key_pressed(KeyCode::F3) {
+            self.visible = !self.visible;
+        }
+
+        if self.frame_times.len() >= FRAME_HISTORY {
+            self.frame_times.pop_front();
+        }
+
+        self.frame_times.push_back(get_frame_time());
+    }
+
+    pub fn draw(&self, settings: &Settings, game: Option<(&Assets, &GameState)>) {
+        if !self.visible {
+            return;
+        }
+
+        set_default_camera();
+
+        let scale = settings.ui_scale;
+        let font_size = 16.0 * scale;
+        let line_height = font_size * 1.1;
+        let padding = 8.0 * scale;
+
+        let average = self.frame_times.iter().sum::<f32>() / self.frame_times.len().max(1) as f32;
+        let worst = self.frame_times.iter().copied().fold(0.0, f32::max);
+
+        let mut lines = vec![format!(
+            "{} fps  {:.1}ms avg  {:.1}ms worst",
+            get_fps(),
+            average * 1000.0,
+            worst * 1000.0
+        )];
+
+        if let Some((assets, state)) = game {
+            let sheet = &assets.sheet;
+
+            lines.push(format!(
+                "particles {}  emitters {}",
+                state.particles.particles.len(),
+                state.particles.emitters.len()
+            ));
+            lines.push(format!(
+                "projectiles {} live  {}/{} spawned",
+                state.run.active_projectiles.len(),
+                state.run.next_projectile,
+                sheet.projectiles.len()
+            ));
+            lines.push(match state.drift {
+                Some(drift) => format!(
+                    "song {:.3}s  drift {:+.1}ms{}",
+                    state.run.env.time,
+                    drift * 1000.0,
+                    if state.is_resyncing() {
+                        "  resyncing"
+                    } else {
+                        ""
+                    }
+                ),
+                None => format!("song {:.3}s  no music", state.run.env.time),
+            });
+            lines.push(format!(
+                "windows perfect {:.0}ms  great {:.0}ms",
+                state.run.windows.perfect * 1000.0,
+                state.run.windows.great * 1000.0
+            ));
+
+            // the projectile about to arrive, and the judgment pressing now would give it
+            let next = state
+                .run
+                .active_projectiles
+                .iter()
+                .min_by(|a, b| a.arrival_time.total_cmp(&b.arrival_time));
+
+            if let Some(next) = next {
+                let offset = state.run.env.time - next.arrival_time;
+
+                lines.push(format!(
+                    "next {:?} in {:.0}ms, {:?} now",
+                    next.direction,
+                    -offset * 1000.0,
+                    state.run.windows.judge(offset)
+                ));
+            }
+        }
+
+        let recent = log::recent();
+        let log_lines = &recent[recent.len().saturating_sub(LOG_LINES)..];
+
+        let graph = vec2(FRAME_HISTORY as f32, 48.0) * scale;
+        let width = lines
+            .iter()
+            .chain(log_lines)
+            .map(|line| measure_text(line, None, font_size as u16, 1.0).width)
+            .fold(graph.x, f32::max);
+        let height = line_height * (lines.len() + log_lines.len()) as f32 + graph.y + padding;
+
+        draw_rectangle(
+            0.0,
+            0.0,
+            width + padding * 2.0,
+            height + padding * 2.0,
+            Color::new(0.0, 0.0, 0.0, 0.7),
+        );
+
+        let mut y = padding;
+
+        for line in &lines {
+            y += line_height;
+
+            draw_text(line, padding, y, font_size, WHITE);
+        }
+
+        y += padding;
+
+        // one bar a frame, red past a 60 fps frame
+        let bar = graph.x / FRAME_HISTORY as f32;
+
+        for (index, frame_time) in self.frame_times.iter().enumerate() {
+            let height = (frame_time / GRAPH_MAX).min(1.0) * graph.y;
+            let color = if *frame_time > 1.0 / 60.0 + 0.001 {
+                RED
+            } else {
+                GREEN
+            };
+
+            draw_rectangle(
+                padding + bar * index as f32,
+                y + graph.y - height,
+                bar,
+                height,
+                color,
+            );
+        }
+
+        let sixty = y + graph.y - (1.0 / 60.0 / GRAPH_MAX) * graph.y;
+        draw_line(padding, sixty, padding + graph.x, sixty, 1.0, GRAY);
+
+        y += graph.y;
+
+        for line in log_lines {
+            y += line_height;
+
+            draw_text(line, padding, y, font_size, LIGHTGRAY);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! How many projectiles a chart sends a second over its length, drawn as a bar graph with the
+//! level in the menu so the spikes can be seen before playing it.
+
+use crate::sheet::Sheet;
+use egui::{Color32, Rect, Sense, Ui, Vec2};
+
+/// Bars the length of a chart is split into.
+pub const BARS: usize = 48;
+
+/// Projectiles a second over a chart, from its first arrival to its last.
+#[derive(Clone, Debug)]
+pub struct Density {
+    /// Projectiles a second arriving during each bar of the graph.
+    pub bars: Vec<f32>,
+    /// The highest of [`Density::bars`].
+    pub peak: f32,
+}
+
+impl Density {
+    pub fn of(sheet: &Sheet) -> Self {
+        let (first, last) = match (sheet.projectiles.first(), sheet.projectiles.last()) {
+            (Some(first), Some(last)) => (first.arrival_time, last.arrival_time),
+            _ => {
+                return Self {
+                    bars: vec![0.0; BARS],
+                    peak: 0.0,
+                }
+            }
+        };
+
+        // a chart of projectiles all arriving together still gets a second to spread over
+        let length = (last - first).max(1.0);
+        let bar_length = length / BARS as f32;
+        let mut bars = vec![0.0; BARS];
+
+        for projectile in &sheet.projectiles {
+            let bar = ((projectile.arrival_time - first) / bar_length) as usize;
+
+            bars[bar.min(BARS - 1)] += 1.0 / bar_length;
+        }
+
+        let peak = bars.iter().copied().fold(0.0, f32::max);
+
+        Self { bars, peak }
+    }
+
+    /// Draws the graph `size` big, the bars scaled so the peak reaches the top.
+    pub fn show(&self, ui: &mut Ui, size: Vec2) {
+        let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+        let painter = ui.painter();
+        let bar_width = rect.width() / self.bars.len() as f32;
+
+        painter.rect_filled(rect, 0.0, Color32::from_black_alpha(96));
+
+        for (index, density) in self.bars.iter().enumerate() {
+            let fraction = match self.peak {
+                peak if peak > 0.0 => density / peak,
+                _ => 0.0,
+            };
+            let x = rect.left() + index as f32 * bar_width;
+            let bar = Rect::from_min_max(
+                egui::pos2(x, rect.bottom() - rect.height() * fraction),
+                egui::pos2(x + bar_width * 0.8, rect.bottom()),
+            );
+
+            // the spikes stand out in red
+            let color = Color32::from_rgb(
+                (96.0 + 159.0 * fraction) as u8,
+                (200.0 - 120.0 * fraction) as u8,
+                (255.0 - 175.0 * fraction) as u8,
+            );
+
+            painter.rect_filled(bar, 0.0, color);
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Effects data, read from [`EFFECTS_PATH`] so they can be changed without touching the code.
+
+use crate::asset_source::load_text;
+use crate::config::Config;
+use crate::hud::parse_color;
+use crate::judgment::Judgment;
+use crate::log_warn;
+use crate::outline::OutlineStyle;
+use macroquad::audio::*;
+use macroquad::prelude::*;
+
+pub const EFFECTS_PATH: &str = "assets/effects.toml";
+
+/// What happens when the combo reaches `combo`, from a `[milestone.<combo>]` section.
+pub struct Milestone {
+    pub combo: u32,
+    /// Sparks bursting out of the heart.
+    pub particles: usize,
+    pub sound: Option<Sound>,
+    /// Strength of the background flash.
+    pub flash: f32,
+}
+
+/// The particles a block sends off the shield, or a miss past it, from a `[hit.<judgment>]`
+/// section.
+#[derive(Clone, Debug)]
+pub struct HitEffect {
+    pub amount: usize,
+    pub color: Color,
+    /// Whether the particles are drawn with the particle sprite, or as circles `size` big.
+    pub sprite: bool,
+    pub size: f32,
+    /// Pixels a second.
+    pub speed: std::ops::Range<f32>,
+    /// Radians to either side of the way the particles fly.
+    pub spread: f32,
+    pub life_time: f32,
+    /// Whether the particles fly in towards the heart rather than out past the shield.
+    pub inward: bool,
+}
+
+/// The [`HitEffect`] of each judgment.
+#[derive(Clone, Debug)]
+pub struct HitEffects {
+    pub perfect: HitEffect,
+    pub great: HitEffect,
+    pub late: HitEffect,
+    pub miss: HitEffect,
+}
+
+impl Default for HitEffects {
+    fn default() -> Self {
+        Self {
+            perfect: HitEffect {
+                amount: 14,
+                color: Color::new(1.0, 0.83, 0.3, 1.0),
+                sprite: true,
+                size: 10.0,
+                speed: 160.0..380.0,
+                spread: 0.35,
+                life_time: 5.0,
+                inward: false,
+            },
+            great: HitEffect {
+                amount: 10,
+                color: WHITE,
+                sprite: true,
+                size: 10.0,
+                speed: 128.0..338.0,
+                spread: 0.2,
+                life_time: 5.0,
+                inward: false,
+            },
+            late: HitEffect {
+                amount: 5,
+                color: Color::new(0.6, 0.6, 0.6, 1.0),
+                sprite: false,
+                size: 2.0,
+                speed: 24.0..72.0,
+                spread: 0.8,
+                life_time: 0.6,
+                inward: false,
+            },
+            miss: HitEffect {
+                amount: 12,
+                color: Color::new(1.0, 0.2, 0.2, 1.0),
+                sprite: false,
+                size: 1.5,
+                speed: 96.0..256.0,
+                spread: 0.3,
+                life_time: 0.3,
+                inward: true,
+            },
+        }
+    }
+}
+
+impl HitEffects {
+    pub fn get(&self, judgment: Judgment) -> &HitEffect {
+        match judgment {
+            Judgment::Perfect => &self.perfect,
+            Judgment::Great => &self.great,
+            Judgment::Late => &self.late,
+            Judgment::Miss => &self.miss,
+        }
+    }
+
+    fn get_mut(&mut self, judgment: Judgment) -> &mut HitEffect {
+        match judgment {
+            Judgment::Perfect => &mut self.perfect,
+            Judgment::Great => &mut self.great,
+            Judgment::Late => &mut self.late,
+            Judgment::Miss => &mut self.miss,
+        }
+    }
+
+    /// The effects in the `[hit.<judgment>]` sections of `config`, the built in ones for what
+    /// they leave out.
+    fn from_config(config: &Config) -> Self {
+        let mut hits = Self::default();
+
+        let judgments = [
+            (Judgment::Perfect, "perfect"),
+            (Judgment::Great, "great"),
+            (Judgment::Late, "late"),
+            (Judgment::Miss, "miss"),
+        ];
+
+        for (judgment, name) in judgments {
+            let key = |field: &str| format!("hit.{}.{}", name, field);
+            let hit = hits.get_mut(judgment);
+
+            if let Some(amount) = config.get_f32(&key("amount")) {
+                hit.amount = amount.max(0.0) as usize;
+            }
+
+            if let Some(color) = config.get_str(&key("color")).and_then(parse_color) {
+                hit.color = color;
+            }
+
+            hit.sprite = config.get_bool(&key("sprite")).unwrap_or(hit.sprite);
+            hit.size = config.get_f32(&key("size")).unwrap_or(hit.size);
+            hit.speed.start = config.get_f32(&key("min_speed")).unwrap_or(hit.speed.start);
+            hit.speed.end = config.get_f32(&key("max_speed")).unwrap_or(hit.speed.end);
+            hit.spread = config.get_f32(&key("spread")).unwrap_or(hit.spread);
+            hit.life_time = config.get_f32(&key("life_time")).unwrap_or(hit.life_time);
+            hit.inward = config.get_bool(&key("inward")).unwrap_or(hit.inward);
+        }
+
+        hits
+    }
+}
+
+#[derive(Default)]
+pub struct Effects {
+    pub milestones: Vec<Milestone>,
+    pub outline: OutlineStyle,
+    pub hits: HitEffects,
+}
+
+impl Effects {
+    /// Loads the effects at `path`, without any if the file is missing or invalid.
+    pub async fn load(path: &str) -> Self {
+        let config = match load_text(path.as_ref()).await {
+            Ok(source) => Config::parse(&source).unwrap_or_else(|err| {
+                log_warn!("{}: {}", path, err);
+
+                Config::default()
+            }),
+            Err(_) => Config::default(),
+        };
+
+        let mut combos: Vec<u32> = config
+            .values
+            .iter()
+            .filter_map(|(key, _)| key.strip_prefix("milestone.")?.split('.').next())
+            .filter_map(|combo| combo.parse().ok())
+            .collect();
+
+        combos.sort_unstable();
+        combos.dedup();
+
+        let mut milestones = Vec::new();
+
+        for combo in combos {
+            let key = |field: &str| format!("milestone.{}.{}", combo, field);
+
+            let sound = match config.get_str(&key("sound")) {
+                Some(path) if !path.is_empty() => load_sound(path).await.ok(),
+                _ => None,
+            };
+
+            milestones.push(Milestone {
+                combo,
+                particles: config.get_f32(&key("particles")).unwrap_or(0.0) as usize,
+                sound,
+                flash: config.get_f32(&key("flash")).unwrap_or(0.0),
+            });
+        }
+
+        let default = OutlineStyle::default();
+        let outline = OutlineStyle {
+            color: config
+                .get_str("outline.color")
+                .and_then(parse_color)
+                .unwrap_or(default.color),
+            glow: config.get_f32("outline.glow").unwrap_or(default.glow),
+        };
+
+        Self {
+            milestones,
+            outline,
+            hits: HitEffects::from_config(&config),
+        }
+    }
+
+    /// The milestone reached at `combo`, if any.
+    pub fn milestone(&self, combo: u32) -> Option<&Milestone> {
+        self.milestones
+            .iter()
+            .find(|milestone| milestone.combo == combo)
+    }
+}
@@ -0,0 +1,41 @@
+//! The core assets built into the executable, so it runs without its `assets` folder. Files on
+//! disk are read first, so any of them can still be swapped out by putting a file in its place.
+
+/// The built in assets by the path they're read from.
+const FILES: &[(&str, &[u8])] = &[
+    ("assets/death.wav", include_bytes!("../assets/death.wav")),
+    ("assets/kick.wav", include_bytes!("../assets/kick.wav")),
+    (
+        "assets/full_combo.wav",
+        include_bytes!("../assets/full_combo.wav"),
+    ),
+    ("assets/shield.png", include_bytes!("../assets/shield.png")),
+    ("assets/heart.png", include_bytes!("../assets/heart.png")),
+    (
+        "assets/projectile.png",
+        include_bytes!("../assets/projectile.png"),
+    ),
+    (
+        "assets/particle.png",
+        include_bytes!("../assets/particle.png"),
+    ),
+    ("assets/pickup.png", include_bytes!("../assets/pickup.png")),
+    ("assets/noise.png", include_bytes!("../assets/noise.png")),
+    (
+        "assets/shader.glsl",
+        include_bytes!("../assets/shader.glsl"),
+    ),
+    ("assets/hud.toml", include_bytes!("../assets/hud.toml")),
+    (
+        "assets/effects.toml",
+        include_bytes!("../assets/effects.toml"),
+    ),
+];
+
+/// The built in copy of the asset at `path`, `None` if it isn't one of them.
+pub fn get(path: &std::path::Path) -> Option<&'static [u8]> {
+    FILES
+        .iter()
+        .find(|(file, _)| std::path::Path::new(file) == path)
+        .map(|(_, bytes)| *bytes)
+}
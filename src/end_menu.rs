@@ -0,0 +1,141 @@
+use crate::judgment::Grade;
+use crate::settings::Settings;
+use egui::*;
+use macroquad::prelude::*;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Ending {
+    Died,
+    Cleared,
+}
+
+/// How a run went, shown on the end menu.
+pub struct Summary {
+    pub ending: Ending,
+    pub score: u32,
+    pub best_combo: u32,
+    /// Whether the run can be watched again.
+    pub replay: bool,
+    /// Whether there's a checkpoint to retry from.
+    pub checkpoint: bool,
+    /// [`Sheet::level_hash`](crate::sheet::Sheet::level_hash) of the level played.
+    pub chart: u64,
+    /// [`Modifiers::tag`](crate::modifiers::Modifiers::tag) of the modifiers played with.
+    pub modifiers: String,
+    /// Fraction of projectiles blocked, `None` if none arrived.
+    pub accuracy: Option<f32>,
+    /// Whether the level was cleared without a miss.
+    pub full_combo: bool,
+    /// Perfects, greats, lates and misses.
+    pub judgments: [u32; 4],
+}
+
+impl Summary {
+    pub fn grade(&self) -> Option<Grade> {
+        Grade::of(self.judgments)
+    }
+}
+
+pub enum EndAction {
+    Retry,
+    RetryCheckpoint,
+    Practice,
+    Replay,
+    Quit,
+}
+
+/// Shown once the player dies or clears a level.
+pub struct EndMenu;
+
+impl EndMenu {
+    pub fn update(&self, settings: &Settings, summary: &Summary) -> Option<EndAction> {
+        let button_size = [200.0 * settings.ui_scale, 40.0 * settings.ui_scale];
+
+        let locale = &settings.locale;
+        let mut action = None;
+
+        let title = match summary.ending {
+            Ending::Died => locale.get("results-died"),
+            Ending::Cleared => locale.get("results-cleared"),
+        };
+
+        set_default_camera();
+
+        egui_macroquad::ui(|ctx| {
+            egui::Window::new(title)
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(locale.format("results-score", &[("score", &summary.score)]));
+                    ui.label(
+                        locale.format("results-best-streak", &[("streak", &summary.best_combo)]),
+                    );
+
+                    let [perfect, great, late, miss] = summary.judgments;
+
+                    ui.label(locale.format(
+                        "results-judgments",
+                        &[
+                            ("perfect", &perfect),
+                            ("great", &great),
+                            ("late", &late),
+                            ("miss", &miss),
+                        ],
+                    ));
+
+                    if let Some(grade) = summary.grade() {
+                        ui.label(locale.format("results-grade", &[("grade", &grade.name())]));
+                    }
+
+                    if summary.full_combo {
+                        ui.colored_label(Color32::GOLD, locale.get("results-full-combo"));
+                    }
+
+                    if ui
+                        .add_sized(button_size, Button::new(locale.get("results-retry")))
+                        .clicked()
+                    {
+                        action = Some(EndAction::Retry);
+                    }
+
+                    if summary.checkpoint
+                        && ui
+                            .add_sized(
+                                button_size,
+                                Button::new(locale.get("results-retry-checkpoint")),
+                            )
+                            .clicked()
+                    {
+                        action = Some(EndAction::RetryCheckpoint);
+                    }
+
+                    if ui
+                        .add_sized(button_size, Button::new(locale.get("results-practice")))
+                        .clicked()
+                    {
+                        action = Some(EndAction::Practice);
+                    }
+
+                    if summary.replay
+                        && ui
+                            .add_sized(button_size, Button::new(locale.get("results-replay")))
+                            .clicked()
+                    {
+                        action = Some(EndAction::Replay);
+                    }
+
+                    if ui
+                        .add_sized(button_size, Button::new(locale.get("results-quit")))
+                        .clicked()
+                    {
+                        action = Some(EndAction::Quit);
+                    }
+                });
+        });
+
+        egui_macroquad::draw();
+
+        action
+    }
+}
@@ -0,0 +1,60 @@
+//! What happens during a run, emitted by the simulation as it steps and handled afterwards by
+//! whatever needs to react, sounds, particles, the hud and the level's script.
+//!
+//! Events of the last frame stay in [`GameState::events`](crate::game::GameState::events) until
+//! the next one, for systems outside the game state to read.
+
+use crate::game::Direction;
+use crate::judgment::Judgment;
+use crate::pickups::PickupKind;
+use macroquad::prelude::Vec2;
+
+#[derive(Clone, Debug)]
+pub enum GameEvent {
+    NoteBlocked {
+        direction: Direction,
+        /// Where the projectile was when it was blocked, in the arena.
+        position: Vec2,
+        /// Seconds the press was after the projectile arrived.
+        offset: f32,
+        judgment: Judgment,
+        /// Points the block gave, with the modifiers' multiplier.
+        points: u32,
+        /// The combo including this block.
+        combo: u32,
+    },
+    /// A projectile got through, the run going on.
+    NoteMissed {
+        direction: Direction,
+        /// Where the projectile was when it got through, in the arena.
+        position: Vec2,
+    },
+    /// The combo reached a multiple of [`COMBO_MILESTONE`](crate::game::COMBO_MILESTONE) or a
+    /// milestone of the level's effects.
+    ComboMilestone(u32),
+    /// A projectile got through with no hearts left.
+    Death {
+        direction: Direction,
+        /// Where the projectile was when it got through, in the arena.
+        position: Vec2,
+    },
+    /// The shield of a lane was raised towards a new direction.
+    ShieldTurned { lane: usize, direction: Direction },
+    /// Every projectile has been dealt with and the song is over.
+    SongEnd,
+    /// The section with the given index in the sheet started.
+    SectionStart(usize),
+    /// The first projectile of the phase with the given index in the sheet arrived.
+    PhaseStart(usize),
+    /// The boss ran out of health.
+    BossDefeated,
+    /// A drop reached the heart, starting its effect.
+    PickupCollected(PickupKind),
+    /// The bomb meter was spent, clearing the projectiles on screen.
+    Bomb {
+        /// Projectiles cleared, pickups included.
+        cleared: usize,
+        /// Points the cleared projectiles gave, with the modifiers' multiplier.
+        points: u32,
+    },
+}
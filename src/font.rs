@@ -0,0 +1,69 @@
+//! The game's font, drawn with an outline in the hud and used by the menus, which fall back to
+//! egui's fonts for anything it doesn't cover. It only covers latin, greek and cyrillic, so
+//! titles and translations in other scripts show up as boxes in the hud, levels needing them can
+//! bring a `font.ttf` that covers them.
+
+use egui::{FontDefinitions, FontFamily};
+use macroquad::prelude::*;
+use std::borrow::Cow;
+use std::cell::Cell;
+
+/// Noto Sans Mono, covering latin, greek and cyrillic.
+const FONT: &[u8] = include_bytes!("../assets/font.ttf");
+
+thread_local! {
+    /// The default font once it's loaded, macroquad never frees a font's glyph atlas.
+    static DEFAULT_FONT: Cell<Option<Font>> = const { Cell::new(None) };
+}
+
+/// The font levels use unless they have a `font.ttf` of their own, loaded the first time it's
+/// needed and shared by every level after.
+pub fn default_font() -> Font {
+    DEFAULT_FONT.with(|cached| {
+        let font = cached
+            .get()
+            .unwrap_or_else(|| load_ttf_font_from_bytes(FONT));
+
+        cached.set(Some(font));
+
+        font
+    })
+}
+
+/// Egui fonts using the game's font first, sized for `ui_scale`.
+pub fn egui_fonts(ui_scale: f32) -> FontDefinitions {
+    let mut fonts = FontDefinitions::default();
+
+    fonts
+        .font_data
+        .insert("srg".to_string(), Cow::Borrowed(FONT));
+
+    for family in [FontFamily::Proportional, FontFamily::Monospace] {
+        fonts
+            .fonts_for_family
+            .entry(family)
+            .or_default()
+            .insert(0, "srg".to_string());
+    }
+
+    for (_, size) in fonts.family_and_size.values_mut() {
+        *size *= ui_scale;
+    }
+
+    fonts
+}
+
+/// Like [`draw_text_ex`], with a dark outline so the text reads over any background.
+pub fn draw_outlined_text(text: &str, x: f32, y: f32, params: TextParams) {
+    let width = (params.font_size as f32 / 16.0).max(1.0);
+    let outline = TextParams {
+        color: Color::new(0.0, 0.0, 0.0, params.color.a),
+        ..params
+    };
+
+    for (dx, dy) in [(-1.0, 0.0), (1.0, 0.0), (0.0, -1.0), (0.0, 1.0)] {
+        draw_text_ex(text, x + dx * width, y + dy * width, outline);
+    }
+
+    draw_text_ex(text, x, y, params);
+}
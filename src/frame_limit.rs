@@ -0,0 +1,38 @@
+//! Keeps frames from being drawn more often than the settings allow, sleeping off what's left of
+//! each one. Browsers pace frames themselves, so it does nothing on the web.
+
+use macroquad::prelude::*;
+
+pub struct FrameLimiter {
+    /// `get_time` the last frame was let through at.
+    last: f64,
+}
+
+impl FrameLimiter {
+    pub fn new() -> Self {
+        Self { last: get_time() }
+    }
+
+    /// Waits until a frame at `fps` frames a second is due, straight away for `None`.
+    pub fn wait(&mut self, fps: Option<f32>) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(fps) = fps {
+            let left = 1.0 / fps as f64 - (get_time() - self.last);
+
+            if left > 0.0 {
+                std::thread::sleep(std::time::Duration::from_secs_f64(left));
+            }
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        let _ = fps;
+
+        self.last = get_time();
+    }
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,93 @@
+//! Entry points for fuzzing, making sure sheets players write can only fail to load with an
+//! error and never panic the game. `fuzz/` runs them with `cargo fuzz`, the property tests in
+//! `tests/` run them on generated input.
+
+use crate::sheet::Sheet;
+
+/// Parses `data` as a sheet, writing parsed sheets back out and checking the result parses to
+/// the same things.
+pub fn sheet(data: &[u8]) {
+    let source = match std::str::from_utf8(data) {
+        Ok(source) => source,
+        Err(_) => return,
+    };
+
+    if let Ok(sheet) = Sheet::parse(source) {
+        round_trip(&sheet);
+    }
+}
+
+/// Parses `data` as a json sheet, checking parsed sheets the same way [`sheet`] does.
+pub fn json_sheet(data: &[u8]) {
+    let source = match std::str::from_utf8(data) {
+        Ok(source) => source,
+        Err(_) => return,
+    };
+
+    if let Ok(sheet) = Sheet::from_json(source) {
+        round_trip(&sheet);
+    }
+}
+
+/// Checks `sheet` written back out parses again, holding the same projectiles, sections, camera
+/// and uniform moves and phases. Times can move by rounding, so only what's there is compared.
+pub fn round_trip(sheet: &Sheet) -> Sheet {
+    let source = sheet.to_source();
+    let parsed = Sheet::parse(&source)
+        .unwrap_or_else(|err| panic!("written sheet doesn't parse: {}\n{}", err, source));
+
+    assert_eq!(parsed.bpm.to_bits(), sheet.bpm.to_bits(), "{}", source);
+    assert_eq!(parsed.stage, sheet.stage, "{}", source);
+    assert_eq!(parsed.swing, sheet.swing, "{}", source);
+    assert_eq!(parsed.meter, sheet.meter, "{}", source);
+    assert_eq!(parsed.momentum, sheet.momentum, "{}", source);
+    assert_eq!(
+        parsed.projectiles.len(),
+        sheet.projectiles.len(),
+        "{}",
+        source
+    );
+    assert_eq!(parsed.sections.len(), sheet.sections.len(), "{}", source);
+    assert_eq!(
+        parsed.camera_events.len(),
+        sheet.camera_events.len(),
+        "{}",
+        source
+    );
+    assert_eq!(
+        parsed.uniform_events.len(),
+        sheet.uniform_events.len(),
+        "{}",
+        source
+    );
+    assert_eq!(parsed.boss, sheet.boss, "{}", source);
+    assert_eq!(parsed.phases.len(), sheet.phases.len(), "{}", source);
+
+    // projectiles arriving together can swap places when rounding nudges their times, so
+    // they're compared sorted by the fourth of a beat they arrive on
+    let fourths = |sheet: &Sheet| {
+        let mut fourths: Vec<_> = sheet
+            .projectiles
+            .iter()
+            .map(|projectile| {
+                let time = sheet.unswung(projectile.arrival_time);
+                let fourth = (time - sheet.start_offset) * sheet.bpm / 15.0;
+
+                (
+                    fourth.round() as i64,
+                    projectile.direction.clone() as u32,
+                    projectile.lane,
+                    projectile.ty.name.clone(),
+                )
+            })
+            .collect();
+
+        fourths.sort();
+
+        fourths
+    };
+
+    assert_eq!(fourths(&parsed), fourths(sheet), "{}", source);
+
+    parsed
+}
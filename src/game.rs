@@ -1,10 +1,53 @@
+use crate::asset_source::{
+    load_asset_image, load_asset_sound, load_optional_font, load_optional_texture, load_script,
+    load_sheet, load_song, load_text,
+};
+use crate::atlas::{load_sprites, Sprite};
+use crate::audio::Audio;
+use crate::audio_engine::Song;
+use crate::boss::{self, PatternKind, BOSS_POSITION, DEFEAT_BANNER};
+use crate::camera::{CameraState, Shake};
+use crate::canvas;
+use crate::crash;
+use crate::effects::{Effects, HitEffect, EFFECTS_PATH};
+use crate::end_menu::{Ending, Summary};
+use crate::events::GameEvent;
+use crate::font::{default_font, draw_outlined_text};
+use crate::gpu_particles::GpuRenderer;
+use crate::health::{Health, PICKUP_OVERHEAL};
+use crate::hud::{Hud, HudValues, Placement, WidgetKind, HUD_PATH};
+use crate::input::{InputBuffer, Press, TouchControls};
+use crate::integrity::to_hex;
+use crate::judgment::{Judgment, TimingWindows};
+use crate::meter::BOMB_KEY;
+use crate::modifiers::Modifiers;
+use crate::momentum::Momentum;
+use crate::outline::{self, draw_outlined};
+use crate::palette::draw_direction_glyph;
 use crate::particles::*;
+use crate::pickups::PickupKind;
+use crate::post::{PostEffects, PostProcessing};
+use crate::practice::Practice;
+pub use crate::projectiles::ProjectileType;
+use crate::replay::{Replay, REPLAY_VERSION};
+use crate::review::{Review, REVIEW_LENGTH, REVIEW_RATE};
+use crate::run::Run;
+use crate::script::{Action, Script};
+use crate::settings::{HitsoundPaths, Settings, DEFAULT_HITSOUND};
 use crate::sheet::{ParseError, Sheet, Token, TokenStream};
+use crate::spectrum::{Spectrum, SPECTRUM_SHADER_PATH};
+use crate::stats::RunStats;
+use crate::streamer::StreamerOverlay;
+use crate::tutorial::Tutorial;
+use crate::uniforms::{self, ShaderUniform, UniformValue};
+use crate::video::{find_video, Video, VIDEO_SHADER};
+use crate::{log_debug, log_info};
 use macroquad::audio::*;
 use macroquad::prelude::*;
 use std::f32::consts::PI;
+use std::rc::Rc;
 
-const VERTEX: &str = r#"
+pub const VERTEX: &str = r#"
 #version 450
 
 layout(location = 0) in vec3 position;
@@ -18,92 +61,509 @@ void main() {
 }
 "#;
 
+/// Sounds played for each judgment and combo milestones.
+pub struct Hitsounds {
+    pub perfect: Sound,
+    pub great: Sound,
+    pub late: Sound,
+    pub miss: Option<Sound>,
+    pub milestone: Option<Sound>,
+}
+
+/// Loads the sound at `path`, `None` if the path is empty or the sound can't be loaded.
+async fn load_optional_sound(path: &str) -> Option<Sound> {
+    if path.is_empty() {
+        return None;
+    }
+
+    load_asset_sound(path).await.ok()
+}
+
+impl Hitsounds {
+    pub async fn load(paths: &HitsoundPaths) -> Self {
+        // blocks always make a sound, falling back to the default kick
+        let block = |sound: Option<Sound>, kick: Sound| sound.unwrap_or(kick);
+        let kick = load_asset_sound(DEFAULT_HITSOUND).await.unwrap();
+
+        Self {
+            perfect: block(load_optional_sound(&paths.perfect).await, kick),
+            great: block(load_optional_sound(&paths.great).await, kick),
+            late: block(load_optional_sound(&paths.late).await, kick),
+            miss: load_optional_sound(&paths.miss).await,
+            milestone: load_optional_sound(&paths.milestone).await,
+        }
+    }
+
+    pub fn get(&self, judgment: Judgment) -> Option<Sound> {
+        match judgment {
+            Judgment::Perfect => Some(self.perfect),
+            Judgment::Great => Some(self.great),
+            Judgment::Late => Some(self.late),
+            Judgment::Miss => self.miss,
+        }
+    }
+}
+
+/// Loads the noise texture shaders sample as `noise_texture`, repeating so shaders can scroll
+/// through it without running off its edges.
+async fn load_noise() -> Texture2D {
+    use macroquad::miniquad::{Texture, TextureFormat, TextureParams, TextureWrap};
+
+    let image = load_asset_image("assets/noise.png").await.unwrap();
+    let texture = Texture::from_data_and_format(
+        unsafe { get_internal_gl() }.quad_context,
+        &image.bytes,
+        TextureParams {
+            format: TextureFormat::RGBA8,
+            wrap: TextureWrap::Repeat,
+            filter: FilterMode::Linear,
+            width: image.width as u32,
+            height: image.height as u32,
+        },
+    );
+
+    Texture2D::from_miniquad_texture(texture)
+}
+
 pub struct Assets {
-    pub song: Sound,
+    pub song: Song,
     pub death: Sound,
-    pub kick: Sound,
-    pub shield: Texture2D,
-    pub heart: Texture2D,
-    pub projectile: Texture2D,
+    /// Played when a level is cleared without a miss.
+    pub full_combo: Sound,
+    pub hitsounds: Hitsounds,
+    pub shield: Sprite,
+    pub heart: Sprite,
+    pub projectile: Sprite,
     pub noise: Texture2D,
     pub ichannel0: Option<Texture2D>,
-    pub particle: Texture2D,
+    pub particle: Sprite,
+    /// Drawn for power-ups dropped by projectiles, tinted by their kind.
+    pub pickup: Sprite,
     pub background: Material,
+    /// Projectiles are drawn through it with high contrast on.
+    pub outline: Material,
+    /// Draws the particles of bursts moved by the GPU, `None` where it can't.
+    pub gpu_particles: Option<GpuRenderer>,
     pub sheet: Sheet,
+    pub script: Script,
+    /// [`Sheet::level_hash`] of the sheet with the script, what scores and replays are kept
+    /// under.
+    pub level_hash: u64,
+    pub effects: Effects,
+    pub hud: Hud,
+    /// The boss of levels with `#boss`, the level's `boss.png`.
+    pub boss: Option<Texture2D>,
+    /// The level's background video, see [`crate::video`].
+    pub video: Option<Video>,
+    /// The spectrum of levels without a shader or a video, see [`crate::spectrum`].
+    pub spectrum: Option<Spectrum>,
+    /// The custom uniforms of the background shader with the values they start at, see
+    /// [`crate::uniforms`].
+    pub uniforms: Vec<(String, UniformValue)>,
 }
 
 impl Assets {
-    pub async fn load(song_path: std::path::PathBuf) -> Self {
-        let ichannel0 = song_path.join("shader/iChannel0.png");
+    /// Loads the level in `song_path`, the error being why part of it doesn't load.
+    pub async fn load(
+        song_path: std::path::PathBuf,
+        difficulty: Option<&str>,
+        settings: &Settings,
+    ) -> Result<Self, String> {
+        AssetLoader::new(song_path, difficulty)
+            .finish(settings)
+            .await
+    }
 
-        let ichannel0 = if ichannel0.exists() {
-            Some(load_texture(ichannel0.to_str().unwrap())
-                .await
-                .unwrap())
-        } else {
-            None
-        };
+    /// Builds the assets of a level from its already loaded parts, `uniforms` being the ones
+    /// declared in its `level.toml`. Fails if `shader` doesn't compile.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn from_parts(
+        song: Song,
+        shader: &str,
+        sheet: Sheet,
+        script: Script,
+        mut uniforms: Vec<(String, UniformValue)>,
+        ichannel0: Option<Texture2D>,
+        font: Option<Font>,
+        settings: &Settings,
+    ) -> Result<Self, String> {
+        let font = font.unwrap_or_else(default_font);
+
+        // uniforms only the sheet or script set start at 0, typed by the first value they're set to
+        let set = sheet
+            .uniform_events
+            .iter()
+            .flat_map(|event| event.values.iter().cloned())
+            .chain(script.uniforms());
+
+        for (name, value) in set {
+            if !uniforms.iter().any(|(declared, _)| *declared == name) {
+                uniforms.push((name, value.zero()));
+            }
+        }
 
-        let assets = Self {
-            song: load_sound(song_path.join("song.wav").to_str().unwrap())
-                .await
-                .unwrap(),
-            death: load_sound("assets/death.wav").await.unwrap(),
-            kick: load_sound("assets/kick.wav").await.unwrap(),
-            shield: load_texture("assets/shield.png").await.unwrap(),
-            heart: load_texture("assets/heart.png").await.unwrap(),
-            projectile: load_texture("assets/projectile.png").await.unwrap(),
-            noise: load_texture("assets/noise.png").await.unwrap(),
+        let mut uniform_types = vec![
+            ("iTime".to_string(), UniformType::Float1),
+            ("iResolution".to_string(), UniformType::Float2),
+            ("iFlash".to_string(), UniformType::Float1),
+            ("iBeat".to_string(), UniformType::Float1),
+        ];
+
+        for (name, value) in &uniforms {
+            uniform_types.push((name.clone(), value.uniform_type()));
+        }
+
+        let [shield, heart, projectile, particle, pickup] = load_sprites([
+            "assets/shield.png",
+            "assets/heart.png",
+            "assets/projectile.png",
+            "assets/particle.png",
+            "assets/pickup.png",
+        ])
+        .await;
+        let background = load_material(
+            VERTEX,
+            shader,
+            MaterialParams {
+                textures: vec![
+                    "noise_texture".to_string(),
+                    "iChannel0".to_string(),
+                    "iVideo".to_string(),
+                    "iSpectrum".to_string(),
+                ],
+                uniforms: uniform_types,
+                ..Default::default()
+            },
+        )
+        .map_err(|err| format!("shader doesn't compile: {}", err))?;
+
+        Ok(Self {
+            song,
+            death: load_asset_sound("assets/death.wav").await.unwrap(),
+            full_combo: load_asset_sound("assets/full_combo.wav").await.unwrap(),
+            hitsounds: Hitsounds::load(&settings.hitsounds).await,
+            shield,
+            heart,
+            projectile,
+            noise: load_noise().await,
             ichannel0,
-            particle: load_texture("assets/particle.png").await.unwrap(),
-            background: load_material(
-                VERTEX,
-                &std::fs::read_to_string(song_path.join("shader/shader.glsl").to_str().unwrap())
-                    .unwrap(),
-                MaterialParams {
-                    textures: vec!["noise_texture".to_string(), "iChannel0".to_string()],
-                    uniforms: vec![
-                        ("iTime".to_string(), UniformType::Float1),
-                        ("iResolution".to_string(), UniformType::Float2),
-                    ],
-                    ..Default::default()
-                },
-            )
-            .unwrap(),
-            sheet: Sheet::parse(&std::fs::read_to_string(song_path.join("sheet.sht")).unwrap())
-                .unwrap(),
-        };
+            particle,
+            pickup,
+            background,
+            outline: outline::material(),
+            gpu_particles: GpuRenderer::shared(),
+            level_hash: sheet.level_hash(&script),
+            sheet,
+            script,
+            effects: Effects::load(EFFECTS_PATH).await,
+            hud: Hud::load(HUD_PATH, font).await,
+            boss: None,
+            video: None,
+            spectrum: None,
+            uniforms,
+        })
+    }
+}
+
+/// Loads the [`Assets`] of a level a step at a time, so the next level of a marathon can be
+/// loaded a step a frame while the one before plays without holding any frame up for long.
+pub struct AssetLoader {
+    pub song_path: std::path::PathBuf,
+    difficulty: Option<String>,
+    step: LoadStep,
+}
 
-        assets.shield.set_filter(FilterMode::Nearest);
-        assets.heart.set_filter(FilterMode::Nearest);
-        assets.projectile.set_filter(FilterMode::Nearest);
+/// How far an [`AssetLoader`] has got, each step with what the steps before it loaded.
+enum LoadStep {
+    Start,
+    /// The sheet, script and song, the parts a level can't do without.
+    Level(Box<(Sheet, Script, Song)>),
+    /// The level's own files.
+    Files(Box<LevelFiles>),
+    /// The backdrop worked out, only the assets built from the files left.
+    Backdrop(Box<(LevelFiles, String, Option<Spectrum>)>),
+    /// Every step taken, handed straight back by [`AssetLoader::step`].
+    Done(Box<Assets>),
+}
 
-        assets
+/// A level's sheet, script and song with the rest of the files in its folder.
+struct LevelFiles {
+    sheet: Sheet,
+    script: Script,
+    song: Song,
+    ichannel0: Option<Texture2D>,
+    font: Option<Font>,
+    video: Option<Video>,
+    uniforms: Vec<(String, UniformValue)>,
+    shader: Option<String>,
+    boss: Option<Texture2D>,
+}
+
+impl AssetLoader {
+    pub fn new(song_path: std::path::PathBuf, difficulty: Option<&str>) -> Self {
+        Self {
+            song_path,
+            difficulty: difficulty.map(str::to_string),
+            step: LoadStep::Start,
+        }
+    }
+
+    /// Takes the next step, the assets or why they don't load once it was the last.
+    pub async fn step(&mut self, settings: &Settings) -> Option<Result<Assets, String>> {
+        let step = std::mem::replace(&mut self.step, LoadStep::Start);
+
+        match self.advance(step, settings).await {
+            Ok(LoadStep::Done(assets)) => Some(Ok(*assets)),
+            Ok(step) => {
+                self.step = step;
+
+                None
+            }
+            Err(err) => Some(Err(err)),
+        }
+    }
+
+    /// Takes every step left at once.
+    pub async fn finish(mut self, settings: &Settings) -> Result<Assets, String> {
+        loop {
+            if let Some(loaded) = self.step(settings).await {
+                return loaded;
+            }
+        }
+    }
+
+    async fn advance(&self, step: LoadStep, settings: &Settings) -> Result<LoadStep, String> {
+        let song_path = &self.song_path;
+
+        Ok(match step {
+            LoadStep::Start => {
+                log_info!("loading {}", song_path.display());
+                crash::set_level(song_path);
+
+                let sheet = load_sheet(song_path, self.difficulty.as_deref()).await?;
+                let script = load_script(song_path).await?;
+                let song = load_song(song_path).await?;
+
+                log_info!(
+                    "{} projectiles at {} bpm, hash {}",
+                    sheet.projectiles.len(),
+                    sheet.bpm,
+                    to_hex(sheet.hash)
+                );
+                log_debug!("{} script hooks", script.hooks.len());
+
+                LoadStep::Level(Box::new((sheet, script, song)))
+            }
+            LoadStep::Level(level) => {
+                let (sheet, script, song) = *level;
+                let boss = if sheet.boss.is_some() {
+                    load_optional_texture(&song_path.join("boss.png")).await
+                } else {
+                    None
+                };
+
+                LoadStep::Files(Box::new(LevelFiles {
+                    ichannel0: load_optional_texture(&song_path.join("shader/iChannel0.png")).await,
+                    font: load_optional_font(&song_path.join("font.ttf")).await,
+                    video: find_video(song_path).and_then(Video::open),
+                    uniforms: uniforms::load_declared(song_path).await,
+                    shader: load_text(&song_path.join("shader/shader.glsl")).await.ok(),
+                    boss,
+                    sheet,
+                    script,
+                    song,
+                }))
+            }
+            LoadStep::Files(mut files) => {
+                // a video can be the whole background, folders with neither get the spectrum
+                let (shader, spectrum) = match (files.shader.take(), &files.video) {
+                    (Some(shader), _) => (shader, None),
+                    (None, Some(_)) => (VIDEO_SHADER.to_string(), None),
+                    (None, None) => (
+                        load_text(SPECTRUM_SHADER_PATH.as_ref())
+                            .await
+                            .map_err(|err| err.to_string())?,
+                        Some(Spectrum::new(&files.song)),
+                    ),
+                };
+
+                LoadStep::Backdrop(Box::new((*files, shader, spectrum)))
+            }
+            LoadStep::Backdrop(backdrop) => {
+                let (files, shader, spectrum) = *backdrop;
+                let LevelFiles {
+                    sheet,
+                    script,
+                    song,
+                    ichannel0,
+                    font,
+                    video,
+                    uniforms,
+                    boss,
+                    ..
+                } = files;
+                let mut assets = Assets::from_parts(
+                    song, &shader, sheet, script, uniforms, ichannel0, font, settings,
+                )
+                .await
+                .map_err(|err| {
+                    format!(
+                        "{}: {}",
+                        song_path.join("shader/shader.glsl").display(),
+                        err
+                    )
+                })?;
+
+                assets.boss = boss;
+                assets.video = video;
+                assets.spectrum = spectrum;
+
+                LoadStep::Done(Box::new(assets))
+            }
+            LoadStep::Done(assets) => LoadStep::Done(assets),
+        })
     }
 }
 
+/// Speed projectiles start a level at, on sheets that don't set a `min=` with `#momentum`.
+pub const START_SPEED: f32 = 128.0;
+
 pub struct Env {
     pub time: f32,
     pub speed: f32,
+    /// Lanes of the level, each with a heart at its [`lane_center`].
+    pub lanes: usize,
+    pub time_scale: TimeScale,
 }
 
 impl Env {
     pub fn new() -> Self {
         Self {
             time: 0.0,
-            speed: 128.0,
+            speed: START_SPEED,
+            lanes: 1,
+            time_scale: TimeScale::default(),
         }
     }
 }
 
+/// How fast the level's clock runs, 1 being the speed of the song. Each cause of slow motion
+/// sets its own factor, the song and sounds keep playing at their own speed.
 #[derive(Clone, Debug)]
-pub enum ProjectileType {
-    Normal,
+pub struct TimeScale {
+    /// Set by the script's `timescale` action.
+    pub script: f32,
+    /// Slows down to a stop as the player dies.
+    pub death: f32,
+    /// Set while a slow motion power-up is active.
+    pub pickup: f32,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self {
+            script: 1.0,
+            death: 1.0,
+            pickup: 1.0,
+        }
+    }
+}
+
+impl TimeScale {
+    pub fn get(&self) -> f32 {
+        self.script * self.death * self.pickup
+    }
+}
+
+/// Canvas pixels between the hearts of neighbouring lanes.
+const LANE_SPACING: f32 = canvas::WIDTH / 2.0;
+
+/// Where the heart of `lane` is out of `lanes`, the lanes spread evenly around the middle.
+pub fn lane_center(lane: usize, lanes: usize) -> Vec2 {
+    vec2((lane as f32 - (lanes - 1) as f32 / 2.0) * LANE_SPACING, 0.0)
+}
+
+/// What happened to a projectile judged by [`Projectile::judge`].
+pub enum Outcome {
+    /// Still on its way to the shield.
+    Flying,
+    /// A heart pickup was blocked.
+    Pickup,
+    /// Got through without counting, a heart pickup or a projectile let go over a hitch.
+    Passed,
+    Blocked {
+        /// Seconds the press was after the projectile arrived.
+        offset: f32,
+        judgment: Judgment,
+        points: u32,
+    },
+    /// Got through, leaving the player alive.
+    Missed,
+    /// Got through with no hearts left, ending the run.
+    Fatal,
+}
+
+/// The parts of a run judging projectiles reads and changes, borrowed from whatever is
+/// playing it.
+pub struct Judge<'a> {
+    pub sheet: &'a Sheet,
+    pub windows: &'a TimingWindows,
+    pub score: &'a mut u32,
+    pub combo: &'a mut u32,
+    pub best_combo: &'a mut u32,
+    pub health: &'a mut Health,
+    /// Whether misses are judged this step, projectiles getting through over a hitch are let go.
+    pub judge_misses: bool,
+    /// Whether running out of hearts doesn't end the run.
+    pub no_fail: bool,
+    /// What the points of blocks are multiplied by, 1 without the aggro modifier.
+    pub aggro: u32,
+}
+
+impl Judge<'_> {
+    /// Scores `projectile` blocked by a press at `pressed`.
+    pub fn block(&mut self, projectile: &Projectile, pressed: f32) -> Outcome {
+        *self.combo += 1;
+        *self.best_combo = (*self.best_combo).max(*self.combo);
+        self.health.block(*self.combo);
+
+        let offset = pressed - projectile.arrival_time;
+        let judgment = self.windows.judge(offset);
+        let points = self.sheet.weights.points(judgment) * self.aggro;
+
+        *self.score += points;
+
+        Outcome::Blocked {
+            offset,
+            judgment,
+            points,
+        }
+    }
+
+    /// Takes a heart for a projectile that got through and breaks the combo.
+    pub fn miss(&mut self) -> Outcome {
+        self.health.damage(HIT_DAMAGE);
+        *self.combo = 0;
+
+        if !self.health.is_dead() || self.no_fail {
+            Outcome::Missed
+        } else {
+            Outcome::Fatal
+        }
+    }
+
+    /// Gives the overheal of a blocked heart pickup.
+    pub fn pickup(&mut self) -> Outcome {
+        self.health.pickup(PICKUP_OVERHEAL);
+
+        Outcome::Pickup
+    }
 }
 
 pub enum ProjectileHit {
     None,
-    Blocked,
+    /// Blocked by a press at the given time.
+    Blocked(f32),
     Hit,
 }
 
@@ -112,17 +572,17 @@ pub struct Projectile {
     pub arrival_time: f32,
     pub direction: Direction,
     pub ty: ProjectileType,
+    /// The lane whose heart the projectile flies at, 0 on sheets without lanes.
+    pub lane: usize,
+    /// Whether the projectile was fired by a `#phase` rather than written in the sheet.
+    pub generated: bool,
+    /// Most seconds the humanize modifier moves the arrival by, set with `jitter=`.
+    pub jitter: f32,
+    /// The power-up dropped when it's blocked, set with `drop=`.
+    pub drop: Option<PickupKind>,
 }
 
 impl Projectile {
-    pub fn random(time: f32) -> Self {
-        Self {
-            arrival_time: time,
-            direction: Direction::random(),
-            ty: ProjectileType::Normal,
-        }
-    }
-
     pub fn distance(&self, env: &Env, bpm: f32) -> f32 {
         (self.arrival_time - env.time) * env.speed * (bpm / 60.0) + 48.0
     }
@@ -130,18 +590,25 @@ impl Projectile {
     pub fn position(&self, env: &Env, bpm: f32) -> Vec2 {
         let angle = self.direction.angle();
 
-        vec2(angle.cos(), angle.sin()) * self.distance(env, bpm)
+        lane_center(self.lane, env.lanes) + vec2(angle.cos(), angle.sin()) * self.distance(env, bpm)
     }
 
+    /// Parses a projectile of a sheet with `lanes` lanes, `norm @2 L 0;0|1` sending one at the
+    /// second lane's heart and `norm L 0;0|1` one at the first's.
     pub fn parse(
         tokens: &mut impl TokenStream,
         bpm: f32,
         offset: f32,
+        lanes: usize,
     ) -> crate::sheet::Result<Self> {
         let ty = tokens.next_token()?;
 
         if let Token::Projectile(ty) = ty {
-            let direction = tokens.next_token()?;
+            let (lane, direction) = match tokens.next_token()? {
+                Token::Lane(lane) if lane < lanes => (lane, tokens.next_token()?),
+                Token::Lane(lane) => return Err(ParseError::UnexpectedToken(Token::Lane(lane))),
+                direction => (0, direction),
+            };
 
             if let Token::Direction(direction) = direction {
                 let time_offset = tokens.next_token()?;
@@ -151,6 +618,10 @@ impl Projectile {
                         arrival_time: offset + time_offset.time(bpm),
                         direction,
                         ty,
+                        lane,
+                        generated: false,
+                        jitter: 0.0,
+                        drop: None,
                     })
                 } else {
                     Err(ParseError::UnexpectedToken(time_offset))
@@ -163,17 +634,24 @@ impl Projectile {
         }
     }
 
-    pub fn update(&self, env: &Env, shield: &Option<Direction>, bpm: f32) -> ProjectileHit {
-        let blocking = if let Some(shield) = shield {
-            *shield == self.direction
-        } else {
-            false
-        };
-
+    pub fn update(
+        &self,
+        env: &Env,
+        shield: &Option<Direction>,
+        input: &mut InputBuffer,
+        bpm: f32,
+    ) -> ProjectileHit {
         let distance = self.distance(env, bpm);
 
-        if blocking && distance < 48.0 {
-            ProjectileHit::Blocked
+        if distance >= 48.0 {
+            return ProjectileHit::None;
+        }
+
+        // a buffered press counts even if the shield has moved on since
+        if let Some(pressed) = input.take(self.lane, &self.direction, self.arrival_time) {
+            ProjectileHit::Blocked(pressed)
+        } else if shield.as_ref() == Some(&self.direction) {
+            ProjectileHit::Blocked(env.time)
         } else if distance <= 16.0 {
             ProjectileHit::Hit
         } else {
@@ -181,20 +659,63 @@ impl Projectile {
         }
     }
 
-    pub fn draw(&self, env: &Env, assets: &Assets) {
-        let angle = self.direction.angle();
+    /// Updates the projectile like [`Projectile::update`] and scores what happened to it the
+    /// way its type does, shared by the game and replay verification so both judge runs the same.
+    pub fn judge(
+        &self,
+        env: &Env,
+        shield: &Option<Direction>,
+        input: &mut InputBuffer,
+        judge: &mut Judge,
+    ) -> Outcome {
+        self.ty.behavior.update(self, env, shield, input, judge)
+    }
+
+    /// Draws the projectile the way its type does, with its colors faded by `alpha`.
+    pub fn draw(&self, env: &Env, assets: &Assets, settings: &Settings, alpha: f32) {
+        self.ty.behavior.draw(self, env, assets, settings, alpha)
+    }
+
+    /// Draws `texture` tinted `color` and turned by `angle` where the projectile is, with the
+    /// outline and glyph the settings ask for, for types to draw themselves with.
+    pub fn draw_sprite(
+        &self,
+        env: &Env,
+        assets: &Assets,
+        settings: &Settings,
+        (texture, color, angle): (Sprite, Color, f32),
+        alpha: f32,
+    ) {
         let offset = self.position(env, assets.sheet.bpm);
+        let fade = |color: Color| Color::new(color.r, color.g, color.b, color.a * alpha);
+
+        let x = offset.x - texture.width() / 2.0;
+        let y = offset.y - texture.height() / 2.0;
+
+        if settings.high_contrast {
+            draw_outlined(
+                assets.outline,
+                &assets.effects.outline,
+                texture,
+                (x, y),
+                angle,
+                fade(color),
+            );
+        } else {
+            texture.draw(
+                x,
+                y,
+                fade(color),
+                DrawTextureParams {
+                    rotation: angle,
+                    ..Default::default()
+                },
+            );
+        }
 
-        draw_texture_ex(
-            assets.projectile,
-            offset.x - assets.projectile.width() / 2.0,
-            offset.y - assets.projectile.height() / 2.0,
-            WHITE,
-            DrawTextureParams {
-                rotation: angle,
-                ..Default::default()
-            },
-        );
+        if settings.direction_glyphs {
+            draw_direction_glyph(&self.direction, offset, fade(BLACK));
+        }
     }
 }
 
@@ -207,15 +728,10 @@ pub enum Direction {
 }
 
 impl Direction {
-    pub fn random() -> Self {
-        match rand::gen_range(0u8, 4) {
-            0 => Self::Up,
-            1 => Self::Down,
-            2 => Self::Left,
-            3 => Self::Right,
-            _ => unreachable!(),
-        }
-    }
+    pub const ALL: [Self; 4] = [Self::Up, Self::Down, Self::Left, Self::Right];
+
+    /// The sides in clockwise order from the top.
+    pub const CLOCKWISE: [Self; 4] = [Self::Up, Self::Right, Self::Down, Self::Left];
 
     pub fn angle(&self) -> f32 {
         match self {
@@ -225,213 +741,2073 @@ impl Direction {
             Self::Down => PI / 2.0,
         }
     }
+
+    /// The side across the heart.
+    pub fn opposite(&self) -> Self {
+        match self {
+            Self::Up => Self::Down,
+            Self::Down => Self::Up,
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+        }
+    }
+
+    /// The side `quarters` quarter turns around from this one.
+    pub fn turned(&self, quarters: u32, clockwise: bool) -> Self {
+        let start = Self::CLOCKWISE
+            .iter()
+            .position(|side| side == self)
+            .unwrap_or(0);
+        let quarters = quarters as usize % 4;
+
+        let index = if clockwise {
+            start + quarters
+        } else {
+            start + 4 - quarters
+        };
+
+        Self::CLOCKWISE[index % 4].clone()
+    }
 }
 
-pub struct GameState {
-    pub shield: Option<Direction>,
-    pub env: Env,
-    pub projectiles: Vec<Projectile>,
-    pub camera_shake: f32,
-    pub score: u32,
-    pub death: Option<f32>,
-    pub particles: ParticleSystem,
+/// The on screen pause button for touch controls, top center of the canvas in screen pixels.
+fn pause_button(settings: &Settings) -> Rect {
+    let viewport = canvas::viewport();
+    let size = 32.0 * canvas::scale() * settings.ui_scale;
+
+    Rect::new(
+        viewport.x + (viewport.w - size) / 2.0,
+        viewport.y,
+        size,
+        size,
+    )
 }
 
-impl GameState {
-    pub async fn new(assets: &Assets) -> Self {
+/// The beat playing at `time`, counted from the start offset.
+pub fn beat_at(sheet: &Sheet, time: f32) -> i64 {
+    ((time - sheet.start_offset) * sheet.bpm / 60.0).floor() as i64
+}
+
+/// Slow dust drifting around the whole arena.
+fn ambient_dust() -> Emitter {
+    let (width, height) = (canvas::WIDTH, canvas::HEIGHT);
+
+    let dust = AreaSpawner {
+        texture: None,
+        amount: 1,
+        color: Color::new(0.6, 0.6, 0.6, 1.0),
+        area: Rect::new(-width / 2.0, -height / 2.0, width, height),
+        velocity: -8.0..8.0,
+        life_time: 8.0,
+        size: 1.0,
+        sub_emitter: None,
+    };
+
+    Emitter::new(dust, 6.0)
+}
+
+/// A ring around the heart spawned on every beat.
+fn heart_pulse(sheet: &Sheet) -> Emitter {
+    let bps = sheet.bpm / 60.0;
+
+    let ring = Ring {
+        texture: None,
+        amount: 32,
+        color: WHITE,
+        position: Vec2::ZERO,
+        radius: 16.0,
+        speed: 48.0,
+        life_time: 0.5,
+        size: 1.0,
+        sub_emitter: None,
+    };
+
+    let mut emitter = Emitter::new(ring, bps);
+
+    // line the spawns up with the beats of the song
+    emitter.accumulator = 1.0 - (sheet.start_offset * bps).fract();
+
+    emitter
+}
+
+/// The particles of `effect` for a projectile from `direction` judged at `position`.
+fn hit_particles(
+    effect: &HitEffect,
+    sprite: Sprite,
+    direction: &Direction,
+    position: Vec2,
+) -> DirectionalExplosion {
+    let angle = if effect.inward {
+        direction.opposite().angle()
+    } else {
+        direction.angle()
+    };
+
+    DirectionalExplosion {
+        texture: effect.sprite.then_some(sprite),
+        amount: effect.amount,
+        position,
+        direction: angle - effect.spread..angle + effect.spread,
+        speed: effect.speed.clone(),
+        size: effect.size,
+        life_time: effect.life_time,
+        color: effect.color,
+        rotation: 0.0..std::f32::consts::TAU,
+        angular_velocity: -std::f32::consts::PI..std::f32::consts::PI,
+        sub_emitter: None,
+    }
+}
+
+/// A short burst of sparks in front of the shield of the heart at `center` when it's raised,
+/// popping into smaller sparks.
+fn shield_burst(direction: &Direction, center: Vec2) -> Emitter {
+    let angle = direction.angle();
+
+    let pop = DirectionalExplosion {
+        texture: None,
+        amount: 3,
+        color: WHITE,
+        position: Vec2::ZERO,
+        speed: 8.0..24.0,
+        direction: 0.0..std::f32::consts::TAU,
+        rotation: 0.0..0.0,
+        angular_velocity: 0.0..0.0,
+        life_time: 0.2,
+        size: 0.5,
+        sub_emitter: None,
+    };
+
+    let sparks = DirectionalExplosion {
+        texture: None,
+        amount: 2,
+        color: WHITE,
+        position: Vec2::ZERO,
+        speed: 32.0..96.0,
+        direction: angle - 0.6..angle + 0.6,
+        rotation: 0.0..0.0,
+        angular_velocity: 0.0..0.0,
+        life_time: 0.3,
+        size: 1.0,
+        sub_emitter: Some(Rc::new(pop)),
+    };
+
+    Emitter::new(sparks, 60.0)
+        .with_position(center + vec2(angle.cos(), angle.sin()) * 32.0)
+        .with_life_time(0.1)
+}
+
+/// Keys that raise the shield in each direction. With lanes the first key of each raises the shield
+/// of the first lane and the second the second's.
+const DIRECTION_KEYS: [([KeyCode; 2], Direction); 4] = [
+    ([KeyCode::W, KeyCode::Up], Direction::Up),
+    ([KeyCode::S, KeyCode::Down], Direction::Down),
+    ([KeyCode::A, KeyCode::Left], Direction::Left),
+    ([KeyCode::D, KeyCode::Right], Direction::Right),
+];
+
+/// Health lost when a projectile reaches the heart.
+pub const HIT_DAMAGE: f32 = 1.0;
+
+/// The shield guarding the heart of a lane.
+pub struct Shield {
+    pub direction: Option<Direction>,
+    /// Angle the shield was at when last raised.
+    from: f32,
+    /// Progress of the shield snapping to its direction, 1 once it's there.
+    snap: f32,
+    /// Time the shield was last raised at, it drops back down after a moment with aggro.
+    pub raised: f32,
+}
+
+impl Shield {
+    pub fn new() -> Self {
         Self {
-            shield: None,
-            env: Env::new(),
-            projectiles: assets.sheet.projectiles.clone(),
-            camera_shake: 0.0,
-            score: 0,
-            death: None,
-            particles: ParticleSystem::new(),
+            direction: None,
+            from: 0.0,
+            snap: 1.0,
+            raised: 0.0,
         }
     }
 
-    pub fn start(&mut self, assets: &Assets) {
-        play_sound_once(assets.song);
-    }
+    /// Raises the shield towards `direction` at `time`, returning whether it turned.
+    pub fn raise(&mut self, direction: Direction, time: f32) -> bool {
+        let turned = self.direction.as_ref() != Some(&direction);
+
+        if turned {
+            // snap from wherever the shield is drawn right now
+            self.from = match &self.direction {
+                Some(_) => self.angle(),
+                None => direction.angle(),
+            };
+            self.snap = 0.0;
+        }
 
-    pub fn stop(&mut self, assets: &Assets) {
-        stop_sound(assets.song);
+        self.direction = Some(direction);
+        self.raised = time;
+
+        turned
     }
 
-    pub async fn restart(&mut self, assets: &Assets) {
-        *self = Self::new(assets).await;
-        self.start(assets);
+    /// Angle the shield is drawn at, easing out from where it was raised from.
+    fn angle(&self) -> f32 {
+        let to = match &self.direction {
+            Some(direction) => direction.angle(),
+            None => return self.from,
+        };
+
+        let t = 1.0 - (1.0 - self.snap).powi(3);
+        let difference = (to - self.from + PI).rem_euclid(std::f32::consts::TAU) - PI;
+
+        self.from + difference * t
     }
+}
 
-    pub async fn update(&mut self, assets: &Assets) {
-        let death_frame_time = get_frame_time() * (1.0 - self.death.unwrap_or(0.0)).max(0.0);
+pub struct GameState {
+    /// The run being played, everything that decides its score.
+    pub run: Run,
+    pub touch: TouchControls,
+    /// The song time, speed and momentum at each beat played, for reviews to start from.
+    speeds: Vec<(f32, f32, Momentum)>,
+    /// Seconds since the last bomb went off, for drawing its shockwave.
+    shockwave: Option<f32>,
+    /// Seconds since the level was cleared without a miss, for the full combo banner.
+    celebration: Option<f32>,
+    /// Whether [`GameEvent::SongEnd`] has been emitted.
+    song_ended: bool,
+    /// What happened during the last frame, see [`GameEvent`].
+    pub events: Vec<GameEvent>,
+    /// The background shader's custom uniforms, moved by the sheet and the script.
+    pub uniforms: Vec<ShaderUniform>,
+    /// Index of the next uniform event in the sheet.
+    next_uniform_event: usize,
+    pub shake: Shake,
+    pub aberration: f32,
+    /// Strength of the background flash from combo milestones.
+    pub flash: f32,
+    /// The score shown on the hud, rolling towards the real one.
+    displayed_score: f32,
+    popups: Vec<ScorePopup>,
+    pub stats: RunStats,
+    /// Draw the per-direction statistics over the level, toggled with tab.
+    pub show_stats: bool,
+    /// The last judgment shown by the streamer overlay.
+    pub streamer: StreamerOverlay,
+    /// Seconds since the player died.
+    pub death: Option<f32>,
+    /// Every press of the run, replayed when reviewing a death.
+    presses: Vec<Press>,
+    pub review: Option<Review>,
+    /// The last checkpoint reached, with the checkpoints modifier.
+    checkpoint: Option<Checkpoint>,
+    pub particles: ParticleSystem,
+    pub audio: Audio,
+    pub tutorial: Option<Tutorial>,
+    pub practice: Option<Practice>,
+    /// Start of the span marked for practice, set with `[` and looped from once `]` marks the end.
+    mark: Option<f32>,
+    /// Seconds the restart key has been held for, `None` until it's pressed during this run.
+    restart_held: Option<f32>,
+    /// Time the level starts at, restarting goes back here.
+    start_time: f32,
+    paused: bool,
+    /// Seconds the clock runs behind the song, from [`Settings::offset`].
+    pub offset: f32,
+    /// Seconds since the offset was last nudged, it's shown on the hud for a moment after.
+    offset_nudged: Option<f32>,
+    /// Seconds the clock is behind the song, measured every frame the music plays.
+    pub drift: Option<f32>,
+    /// Seconds spent slewing the clock back to the song since the drift passed
+    /// [`RESYNC_THRESHOLD`].
+    resync: Option<f32>,
+    /// Seconds the clock has fallen behind the song by running slower than it, which drift
+    /// correction leaves alone.
+    scaled_lag: f32,
+}
 
-        self.env.time += death_frame_time;
+/// Longest frame simulated, anything beyond is dropped and caught up by the drift correction.
+const MAX_FRAME_TIME: f32 = 0.25;
 
-        if let Some(death) = &mut self.death {
-            *death += get_frame_time();
-        } else {
-            if is_key_pressed(KeyCode::W) || is_key_pressed(KeyCode::Up) {
-                self.shield = Some(Direction::Up);
-            }
+/// Longest step the simulation advances by at once.
+pub const FIXED_STEP: f32 = 1.0 / 240.0;
 
-            if is_key_pressed(KeyCode::S) || is_key_pressed(KeyCode::Down) {
-                self.shield = Some(Direction::Down);
-            }
+/// Distance from the hearts of `lanes` lanes past which projectiles are off the screen seen by
+/// `camera`, with some margin for the size of the projectiles.
+fn view_distance(camera: &CameraState, lanes: usize) -> f32 {
+    let half = vec2(canvas::WIDTH, canvas::HEIGHT) / 2.0 / camera.zoom;
 
-            if is_key_pressed(KeyCode::A) || is_key_pressed(KeyCode::Left) {
-                self.shield = Some(Direction::Left);
-            }
+    half.length() + camera.target.length() + lane_center(0, lanes).length() + 16.0
+}
 
-            if is_key_pressed(KeyCode::D) || is_key_pressed(KeyCode::Right) {
-                self.shield = Some(Direction::Right);
-            }
+/// Seconds before arrival projectiles of `sheet` become active, with the camera at `camera` and
+/// projectiles moving at `speed`.
+pub fn activation_lookahead(sheet: &Sheet, camera: &CameraState, speed: f32) -> f32 {
+    // never later than the projectiles come on screen, so they can't pop in
+    let visible = (view_distance(camera, sheet.lanes()) - 48.0) / (speed * sheet.bpm / 60.0);
 
-            let env = &self.env;
-            let shield = &self.shield;
-            let camera_shake = &mut self.camera_shake;
-            let score = &mut self.score;
-            let death = &mut self.death;
-            let particles = &mut self.particles;
+    sheet.prespawn.unwrap_or(0.0).max(visible)
+}
 
-            self.projectiles.retain(|projectile| {
-                let hit = projectile.update(env, shield, assets.sheet.bpm);
+/// How long the music is ducked for when dying before it stops.
+const DEATH_DUCK: f32 = 0.6;
 
-                let retain = match hit {
-                    ProjectileHit::None => true,
-                    ProjectileHit::Blocked => false,
-                    ProjectileHit::Hit => true,
-                };
+/// How much of the drift between the game clock and the song is corrected each frame.
+const DRIFT_CORRECTION: f32 = 0.1;
 
-                if !retain {
-                    *camera_shake += 0.01;
-                    *score += 1;
-                    play_sound_once(assets.kick);
+/// Seconds of drift past which the clock stops easing towards the song and slews back to it, so
+/// projectiles don't jump after a hitch or an audio stall.
+pub const RESYNC_THRESHOLD: f32 = 0.03;
 
-                    let angle = projectile.direction.angle();
+/// Most the clock speeds up or slows down by while resyncing, as a fraction of the song's speed.
+const RESYNC_SLEW: f32 = 0.25;
 
-                    let explosion = DirectionalExplosion {
-                        texture: Some(assets.particle),
-                        amount: 10,
-                        position: projectile.position(env, assets.sheet.bpm),
-                        direction: angle - 0.2..angle + 0.2,
-                        speed: 128.0..338.0,
-                        size: 10.0,
-                        life_time: 5.0,
-                        color: WHITE,
-                        rotation: 0.0..std::f32::consts::TAU,
-                        angular_velocity: -std::f32::consts::PI..std::f32::consts::PI,
-                        ..Default::default()
-                    };
+/// Seconds of drift a resync ends at, easing takes over from there.
+const RESYNC_DONE: f32 = 0.002;
 
-                    particles.spawn(&explosion);
-                }
+/// Seconds the offset is shown on the hud for after being nudged.
+const OFFSET_SHOWN: f32 = 1.5;
 
-                if let ProjectileHit::Hit = hit {
-                    *death = Some(0.0);
-                    stop_sound(assets.song);
+/// A milestone sound plays every time the combo reaches a multiple of this.
+pub const COMBO_MILESTONE: u32 = 50;
 
-                    *camera_shake = 0.0;
+/// Seconds the shield takes to snap to a new direction.
+const SHIELD_SNAP: f32 = 0.06;
 
-                    play_sound_once(assets.death);
-                }
+/// How much bigger the heart is at the start of each beat.
+const HEART_PULSE: f32 = 0.15;
 
-                retain
-            });
+/// Seconds the shockwave of a bomb takes to reach the edge of the screen.
+const SHOCKWAVE_LIFE: f32 = 0.5;
 
-            self.camera_shake *= 0.9;
-    
-            // env
-            self.env.speed += get_frame_time() * 2.0;
-        }
+/// Shades of gold the confetti of a full combo comes in.
+const FULL_COMBO_COLORS: [Color; 4] = [
+    GOLD,
+    YELLOW,
+    Color::new(1.0, 0.62, 0.1, 1.0),
+    Color::new(1.0, 0.95, 0.7, 1.0),
+];
 
-        self.particles.update(death_frame_time);
+/// Seconds the full combo banner takes to settle after popping up.
+const FULL_COMBO_POP: f32 = 0.6;
 
-        if is_key_pressed(KeyCode::R) {
-            self.restart(assets).await;
-        }
-    }
+/// Seconds a score popup stays up for.
+const POPUP_LIFE: f32 = 0.6;
 
-    pub fn draw(&mut self, assets: &Assets) {
-        let offset = vec2(
-            rand::gen_range(-self.camera_shake, self.camera_shake),
-            rand::gen_range(-self.camera_shake, self.camera_shake),
-        );
+/// Points gained from a block, floating up from where it happened.
+struct ScorePopup {
+    points: u32,
+    /// Position in the arena.
+    position: Vec2,
+    age: f32,
+}
 
-        //let aspect = screen_width() / screen_height();
+/// Seconds after dying before the end menu comes up, long enough for the death slow-down.
+const DEATH_MENU_DELAY: f32 = 1.0;
 
-        set_camera(&Camera2D {
-            offset,
-            zoom: vec2(
-                1.0 / (screen_width() / 2.0).floor(),
-                -1.0 / (screen_height() / 2.0).floor(),
-            ),
-            ..Default::default()
-        });
+/// Fraction of the score lost every time a death is retried from a checkpoint.
+const CHECKPOINT_PENALTY: f32 = 0.25;
 
-        clear_background(BLACK);
+/// Where a death can be retried from.
+struct Checkpoint {
+    time: f32,
+    score: u32,
+    speed: f32,
+    momentum: Momentum,
+}
 
-        let resolution = vec2(screen_width(), screen_height());
+/// Distances from the heart the hidden modifier starts and finishes fading projectiles out at.
+const HIDDEN_FADE: (f32, f32) = (160.0, 96.0);
 
-        assets.background.set_texture("noise_texture", assets.noise);
+/// Canvas pixels from the screen edge telegraphs are drawn at.
+const TELEGRAPH_INSET: f32 = 10.0;
 
-        if let Some(ichannel0) = assets.ichannel0 {
-            assets.background.set_texture("iChannel0", ichannel0);
-        }
-        
-        assets.background.set_uniform("iTime", self.env.time);
-        assets.background.set_uniform("iResolution", resolution);
+/// Distance from the heart at `center` to the edge of the screen seen by `camera`, in the
+/// direction of `direction`, ignoring camera rotation.
+fn edge_distance(direction: &Direction, center: Vec2, camera: &CameraState) -> f32 {
+    let angle = direction.angle();
+    let ray = vec2(angle.cos(), angle.sin());
+    let half = vec2(canvas::WIDTH, canvas::HEIGHT) / 2.0 / camera.zoom;
 
-        gl_use_material(assets.background);
+    // directions are axis aligned, so only one of the terms is ever non zero
+    (half * ray.abs() + (camera.target - center) * ray).dot(Vec2::ONE)
+}
 
-        draw_rectangle(0.0, 0.0, 1.0, 1.0, WHITE);
+/// The background shader's custom uniforms as they are before the level starts.
+fn starting_uniforms(assets: &Assets) -> Vec<ShaderUniform> {
+    assets
+        .uniforms
+        .iter()
+        .map(|(name, value)| ShaderUniform::new(name, *value))
+        .collect()
+}
 
-        gl_use_default_material();
+/// The particles a run of `assets` starts with, bursts on the GPU when the settings and the
+/// graphics allow it.
+fn starting_particles(assets: &Assets, settings: &Settings) -> ParticleSystem {
+    let sheet = &assets.sheet;
+    let mut particles = match assets.gpu_particles {
+        Some(_) if settings.gpu_particles => ParticleSystem::with_gpu(),
+        _ => ParticleSystem::new(),
+    };
+    let lanes = sheet.lanes();
+
+    particles.add_emitter(ambient_dust());
 
-        self.particles.draw();
+    for lane in 0..lanes {
+        particles.add_emitter(heart_pulse(sheet).with_position(lane_center(lane, lanes)));
+    }
+
+    particles
+}
 
-        // projectiles
-        for projectile in &self.projectiles {
-            projectile.draw(&self.env, assets);
+impl GameState {
+    pub async fn new(assets: &Assets, settings: &Settings, modifiers: &Modifiers) -> Self {
+        Self {
+            run: Run::new(&assets.sheet, modifiers, rand::rand()),
+            touch: TouchControls::new(),
+            speeds: Vec::new(),
+            shockwave: None,
+            celebration: None,
+            song_ended: false,
+            events: Vec::new(),
+            uniforms: starting_uniforms(assets),
+            next_uniform_event: 0,
+            shake: Shake::new(),
+            aberration: 0.0,
+            flash: 0.0,
+            displayed_score: 0.0,
+            popups: Vec::new(),
+            streamer: StreamerOverlay::default(),
+            stats: RunStats::default(),
+            show_stats: false,
+            death: None,
+            presses: Vec::new(),
+            review: None,
+            checkpoint: None,
+            particles: starting_particles(assets, settings),
+            audio: Audio::new(settings),
+            tutorial: None,
+            practice: None,
+            mark: None,
+            restart_held: None,
+            start_time: 0.0,
+            paused: false,
+            offset: settings.offset(assets.sheet.hash),
+            offset_nudged: None,
+            drift: None,
+            resync: None,
+            scaled_lag: 0.0,
         }
+    }
 
-        // heart
-        draw_texture(
-            assets.heart,
-            -assets.heart.width() / 2.0,
-            -assets.heart.height() / 2.0,
-            WHITE,
-        );
+    /// Moves the start of the level to `time`, skipping the projectiles before it.
+    pub fn skip_to(&mut self, assets: &Assets, time: f32) {
+        self.start_time = time;
+        self.run.skip_to(&assets.sheet, time);
+        self.speeds.retain(|(at, ..)| *at <= time);
+        self.shockwave = None;
+        self.celebration = None;
+        self.uniforms = starting_uniforms(assets);
+        self.next_uniform_event = 0;
+        self.run_uniform_events(assets, time);
+        self.resync = None;
+        self.scaled_lag = 0.0;
+    }
 
-        // shield
-        if let Some(shield) = &self.shield {
-            let angle = shield.angle();
-            let offset = vec2(angle.cos(), angle.sin()) * 32.0;
+    pub fn start(&mut self, assets: &Assets) {
+        self.audio
+            .play_music(&assets.song, self.run.env.time + self.offset, self.rate());
+    }
 
-            draw_texture_ex(
-                assets.shield,
-                offset.x - assets.shield.width() / 2.0,
-                offset.y - assets.shield.height() / 2.0,
-                WHITE,
-                DrawTextureParams {
-                    rotation: angle,
-                    ..Default::default()
-                },
-            );
+    /// Moves the offset of the level by `amount` seconds and saves it, the clock easing over to
+    /// the new offset.
+    pub fn nudge_offset(&mut self, assets: &Assets, settings: &mut Settings, amount: f32) {
+        settings.nudge_song_offset(assets.sheet.hash, amount);
+        settings.save();
+
+        self.offset = settings.offset(assets.sheet.hash);
+        self.offset_nudged = Some(0.0);
+
+        log_info!(
+            "song offset {:+.0}ms, {:+.0}ms in total",
+            settings.song_offset(assets.sheet.hash) * 1000.0,
+            self.offset * 1000.0
+        );
+    }
+
+    /// The song speed, the practice loop's rate while practicing and slowed down in reviews.
+    fn rate(&self) -> f32 {
+        let rate = self
+            .practice
+            .as_ref()
+            .map_or(self.run.modifiers.rate, |practice| practice.rate);
+
+        if self
+            .review
+            .as_ref()
+            .is_some_and(|review| review.missed.is_some())
+        {
+            rate * REVIEW_RATE
+        } else {
+            rate
         }
+    }
+
+    /// Whether the player died past a checkpoint.
+    pub fn can_retry_checkpoint(&self) -> bool {
+        self.death.is_some() && self.checkpoint.is_some()
+    }
+
+    /// Goes back to the last checkpoint after a death, losing part of the score.
+    pub fn retry_checkpoint(&mut self, assets: &Assets) {
+        let (time, score, speed, momentum) = match (&self.checkpoint, self.death) {
+            (Some(checkpoint), Some(_)) => (
+                checkpoint.time,
+                checkpoint.score,
+                checkpoint.speed,
+                checkpoint.momentum.clone(),
+            ),
+            _ => return,
+        };
 
-        set_default_camera();
+        let start_time = self.start_time;
+
+        self.stop();
+        self.skip_to(assets, time);
+        self.start_time = start_time;
+        self.run.env.speed = speed;
+        self.run.momentum = momentum;
+        self.run.health = Health::new(self.run.health.max);
+        self.run.combo = 0;
+        self.death = None;
+        self.run.fatal = None;
+        self.review = None;
+        self.run.score = (score as f32 * (1.0 - CHECKPOINT_PENALTY)) as u32;
+        self.presses.retain(|press| press.time < time);
+        self.run.bombs.retain(|bomb| *bomb < time);
+        self.run.hitches.retain(|(from, _)| *from < time);
+        self.start(assets);
+    }
 
-        let bps = assets.sheet.bpm / 60.0;
-        let beat = (self.env.time * bps * 4.0).floor() as u32;
+    /// Whether the player died and the death can be reviewed.
+    pub fn can_review(&self) -> bool {
+        self.run.fatal.is_some() && self.tutorial.is_none()
+    }
 
-        draw_text(&format!("Score: {}", self.score), 15.0, 30.0, 50.0, WHITE);
-        draw_text(
-            &format!("{};{}|{}", beat % 4, (beat / 4) % 4, beat / 16),
-            500.0,
-            30.0,
-            50.0,
-            WHITE,
-        );
+    /// Whether every projectile of the sheet has been dealt with, only the rest of the song left.
+    pub fn projectiles_done(&self, assets: &Assets) -> bool {
+        self.run.next_projectile >= assets.sheet.projectiles.len()
+            && self.run.active_projectiles.is_empty()
+    }
+
+    /// Whether every projectile of the sheet has been dealt with and the song is over.
+    pub fn cleared(&self, assets: &Assets) -> bool {
+        self.death.is_none()
+            && self.practice.is_none()
+            && self.tutorial.is_none()
+            && self.projectiles_done(assets)
+            && self.run.env.time >= assets.sheet.end_time()
+    }
+
+    /// Whether every projectile that's arrived so far was blocked.
+    pub fn full_combo(&self) -> bool {
+        self.stats.hit_rate().is_some() && self.stats.judgments(Judgment::Miss) == 0
+    }
+
+    /// Plays the jingle, throws the confetti and puts up the banner of a full combo.
+    fn celebrate(&mut self, assets: &Assets) {
+        self.audio.play_sfx(assets.full_combo, 1.0);
+        self.flash += 1.0;
+        self.celebration = Some(0.0);
+
+        for lane in 0..self.run.env.lanes {
+            self.particles.burst(&Confetti {
+                texture: Some(assets.particle),
+                amount: 400,
+                colors: FULL_COMBO_COLORS.to_vec(),
+                position: lane_center(lane, self.run.env.lanes),
+                speed: 60.0..360.0,
+                life_time: 3.0,
+                size: 4.0,
+            });
+        }
+    }
+
+    /// How the run went once it's over, `None` while it's still going.
+    pub fn summary(&self, assets: &Assets) -> Option<Summary> {
+        let ending = match self.death {
+            Some(death) if death >= DEATH_MENU_DELAY => Ending::Died,
+            None if self.cleared(assets) => Ending::Cleared,
+            _ => return None,
+        };
+
+        Some(Summary {
+            replay: self.tutorial.is_none(),
+            checkpoint: self.can_retry_checkpoint(),
+            ending,
+            score: self.score(),
+            best_combo: self.run.best_combo,
+            chart: assets.level_hash,
+            modifiers: self.run.modifiers.tag(),
+            accuracy: self.stats.hit_rate(),
+            full_combo: matches!(ending, Ending::Cleared) && self.full_combo(),
+            judgments: self.stats.judgment_counts(),
+        })
+    }
+
+    /// Replays the seconds before the death in slow motion, from the presses of the run.
+    pub async fn review(&mut self, assets: &Assets, settings: &Settings) {
+        if let Some(missed) = self.run.fatal.take() {
+            let time = (missed.arrival_time - REVIEW_LENGTH).max(self.start_time);
+
+            self.replay(assets, settings, time, Some(missed)).await;
+        }
+    }
+
+    /// Replays the whole run once cleared.
+    pub async fn replay_run(&mut self, assets: &Assets, settings: &Settings) {
+        self.replay(assets, settings, self.start_time, None).await;
+    }
+
+    /// Plays `replay` back from its start, the level having been loaded for it.
+    pub async fn watch(&mut self, assets: &Assets, settings: &Settings, replay: &Replay) {
+        self.stop();
+
+        *self = Self::new(assets, settings, &replay.modifiers).await;
+        self.run.seed = replay.seed;
+        self.run.bombs = replay.bombs.clone();
+        self.run.hitches = replay.hitches.clone();
+        self.skip_to(assets, replay.start);
+        self.presses = replay.presses.clone();
+
+        let mut review = Review::new(replay.presses.clone(), None);
+        review.skip_to(replay.start);
+        review.player = Some(replay.player.clone());
+
+        self.review = Some(review);
+        self.start(assets);
+    }
+
+    /// Plays the presses of the run back from `time`, highlighting `missed`.
+    async fn replay(
+        &mut self,
+        assets: &Assets,
+        settings: &Settings,
+        time: f32,
+        missed: Option<Projectile>,
+    ) {
+        let presses = std::mem::take(&mut self.presses);
+        let bombs = std::mem::take(&mut self.run.bombs);
+        let hitches = std::mem::take(&mut self.run.hitches);
+        let checkpoint = self.checkpoint.take();
+        let start_time = self.start_time;
+        let seed = self.run.seed;
+        let speeds = std::mem::take(&mut self.speeds);
+        // from the last beat before `time`, where the speed is known
+        let (time, speed, momentum) = match speeds.iter().rposition(|(at, ..)| *at <= time) {
+            Some(index) => speeds[index].clone(),
+            None => (time, assets.sheet.momentum.min, Momentum::default()),
+        };
+
+        self.stop();
+
+        *self = Self::new(assets, settings, &self.run.modifiers).await;
+        self.run.seed = seed;
+        self.run.bombs = bombs;
+        self.run.hitches = hitches;
+        self.speeds = speeds;
+        self.skip_to(assets, time);
+        self.start_time = start_time;
+        self.run.env.speed = speed;
+        self.run.momentum = momentum;
+        self.presses = presses.clone();
+        self.checkpoint = checkpoint;
+
+        let mut review = Review::new(presses, missed);
+        review.skip_to(time);
+
+        self.review = Some(review);
+        self.start(assets);
+    }
+
+    /// Loops the level from `start` to `end`, collecting statistics for just that span.
+    pub fn practice(&mut self, assets: &Assets, settings: &Settings, start: f32, end: f32) {
+        self.practice = Some(Practice::new(
+            start,
+            end,
+            self.run.modifiers.rate,
+            settings.practice_ramp,
+            self.run.env.speed,
+        ));
+        self.stats = RunStats::default();
+        self.show_stats = true;
+
+        self.loop_practice(assets);
+    }
+
+    /// Loops the section of the sheet playing right now.
+    pub fn practice_section(&mut self, assets: &Assets, settings: &Settings) {
+        let (start, end) = assets.sheet.section_at(self.run.env.time);
+
+        self.practice(assets, settings, start, end);
+    }
+
+    /// Stops looping, carrying on through the rest of the level at the picked rate.
+    pub fn stop_practice(&mut self, assets: &Assets) {
+        self.practice = None;
+        self.show_stats = false;
+        self.start(assets);
+    }
+
+    /// Goes back to the start of the practice loop.
+    fn loop_practice(&mut self, assets: &Assets) {
+        let (start, speed) = match &self.practice {
+            Some(practice) => (practice.start, practice.speed),
+            None => return,
+        };
+
+        self.stop();
+        self.skip_to(assets, start);
+        self.run.env.speed = speed;
+        self.run.health = Health::new(self.run.health.max);
+        self.run.combo = 0;
+        self.death = None;
+        self.run.fatal = None;
+        self.start(assets);
+    }
+
+    pub fn stop(&mut self) {
+        self.audio.stop_music();
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.audio.pause_music();
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.audio.resume_music();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Whether the clock is slewing back to the song after drifting too far from it.
+    pub fn is_resyncing(&self) -> bool {
+        self.resync.is_some()
+    }
+
+    /// Moves the clock towards the song, `drift` seconds ahead of it. Small drift is eased away so
+    /// frame time jitter doesn't accumulate, anything past [`RESYNC_THRESHOLD`] is slewed back.
+    fn correct_drift(&mut self, drift: f32) {
+        if self.resync.is_none() && drift.abs() > RESYNC_THRESHOLD {
+            log_info!(
+                "clock {:+.0}ms off the song at {:.2}s, resyncing",
+                drift * 1000.0,
+                self.run.env.time
+            );
+
+            self.resync = Some(0.0);
+        }
+
+        let most = RESYNC_SLEW * get_frame_time() * self.rate();
+
+        match &mut self.resync {
+            Some(resync) => {
+                *resync += get_frame_time();
+                self.run.env.time += drift.clamp(-most, most);
+
+                if drift.abs() < RESYNC_DONE {
+                    log_info!("resynced in {:.2}s", resync);
+
+                    self.resync = None;
+                }
+            }
+            None => self.run.env.time += drift * DRIFT_CORRECTION,
+        }
+    }
+
+    /// The score with the modifiers' multiplier applied.
+    pub fn score(&self) -> u32 {
+        (self.run.score as f32 * self.run.modifiers.score_multiplier()).round() as u32
+    }
+
+    /// The run so far as a replay of `level` played by `player`.
+    pub fn to_replay(&self, assets: &Assets, player: &str, level: &str) -> Replay {
+        Replay {
+            version: REPLAY_VERSION,
+            chart: assets.level_hash,
+            level: level.to_string(),
+            player: player.to_string(),
+            modifiers: self.run.modifiers.clone(),
+            seed: self.run.seed,
+            start: self.start_time,
+            presses: self.presses.clone(),
+            bombs: self.run.bombs.clone(),
+            hitches: self.run.hitches.clone(),
+            score: self.score(),
+        }
+    }
+
+    pub fn tutorial_finished(&self) -> bool {
+        self.tutorial.as_ref().is_some_and(Tutorial::finished)
+    }
+
+    /// Plays the run again from where it started, resetting it in place rather than building a
+    /// new state, so it's over within the frame and the music carries on from the same engine.
+    pub fn restart(&mut self, assets: &Assets, settings: &Settings) {
+        if self.practice.is_some() {
+            self.loop_practice(assets);
+
+            return;
+        }
+
+        self.run = Run::new(&assets.sheet, &self.run.modifiers, rand::rand());
+        self.song_ended = false;
+        self.events.clear();
+
+        self.shake = Shake::new();
+        self.aberration = 0.0;
+        self.flash = 0.0;
+        self.displayed_score = 0.0;
+        self.popups.clear();
+        self.streamer = StreamerOverlay::default();
+        self.stats = RunStats::default();
+        self.death = None;
+        self.presses.clear();
+        self.speeds.clear();
+        self.review = None;
+        self.checkpoint = None;
+        self.particles = starting_particles(assets, settings);
+        self.audio.music_volume = settings.music_volume;
+        self.audio.sfx_volume = settings.sfx_volume;
+        self.tutorial = self.tutorial.take().map(|_| Tutorial::new());
+        self.mark = None;
+        self.restart_held = None;
+        self.paused = false;
+        self.offset = settings.offset(assets.sheet.hash);
+        self.offset_nudged = None;
+        self.drift = None;
+
+        self.skip_to(assets, self.start_time);
+        self.audio
+            .replay_music(&assets.song, self.run.env.time + self.offset, self.rate());
+    }
+
+    /// Starts the sheet's uniform moves up to `time`.
+    fn run_uniform_events(&mut self, assets: &Assets, time: f32) {
+        while let Some(event) = assets.sheet.uniform_events.get(self.next_uniform_event) {
+            if event.time > time {
+                break;
+            }
+
+            let duration = event
+                .over
+                .as_ref()
+                .map_or(0.0, |over| over.time(assets.sheet.bpm));
+
+            for (name, value) in &event.values {
+                if let Some(uniform) = self.uniforms.iter_mut().find(|u| u.name == *name) {
+                    uniform.move_to(*value, event.time, duration);
+                }
+            }
+
+            self.next_uniform_event += 1;
+        }
+    }
+
+    /// Runs the script's actions that change how the level looks for the events the run fired,
+    /// the run having played the rest.
+    fn run_script(&mut self, assets: &Assets) {
+        for event in std::mem::take(&mut self.run.fired) {
+            for action in assets.script.actions(&event) {
+                match action {
+                    Action::Uniform(name, value) => {
+                        if let Some(uniform) = self.uniforms.iter_mut().find(|u| u.name == *name) {
+                            uniform.move_to(*value, self.run.env.time, 0.0);
+                        }
+                    }
+                    Action::Shake(amount) => self.shake.add(*amount),
+                    Action::Flash(amount) => self.aberration += amount,
+                    Action::Spawn(..) | Action::Camera(_) | Action::TimeScale(_) => {}
+                }
+            }
+        }
+    }
+
+    pub async fn update(&mut self, assets: &Assets, settings: &Settings) {
+        self.events.clear();
+
+        let mut frame_time = get_frame_time().min(MAX_FRAME_TIME) * self.rate();
+        let touch = self
+            .touch
+            .update(pause_button(settings), self.run.env.lanes);
+        let tapped = !touch.presses.is_empty();
+
+        if touch.pause {
+            self.pause();
+        }
+
+        let mut pressed = Vec::new();
+
+        if self.death.is_none() && !self.run.modifiers.autoplay && self.review.is_none() {
+            for (keys, direction) in DIRECTION_KEYS {
+                if assets.sheet.stage {
+                    for (lane, key) in keys.iter().enumerate() {
+                        if is_key_pressed(*key) {
+                            pressed.push((lane, direction.clone()));
+                        }
+                    }
+                } else if keys.iter().any(|key| is_key_pressed(*key)) {
+                    pressed.push((0, direction));
+                }
+            }
+
+            pressed.extend(touch.presses);
+
+            if self.run.stunned() {
+                pressed.clear();
+            }
+
+            let time = self.run.env.time + frame_time * self.run.env.time_scale.get();
+            let presses: Vec<Press> = pressed
+                .iter()
+                .map(|(lane, direction)| Press {
+                    lane: *lane,
+                    direction: direction.clone(),
+                    time,
+                })
+                .collect();
+
+            self.run.press(&assets.sheet, &presses);
+            self.presses.extend(presses);
+
+            if is_key_pressed(BOMB_KEY) {
+                self.run.bomb(&assets.sheet, time);
+            }
+        }
+
+        if let Some(tutorial) = &mut self.tutorial {
+            let continued =
+                is_key_pressed(KeyCode::Space) || is_key_pressed(KeyCode::Enter) || tapped;
+
+            let directions: Vec<Direction> = pressed
+                .iter()
+                .map(|(_, direction)| direction.clone())
+                .collect();
+
+            tutorial.update(&assets.sheet, self.run.env.time, &directions, continued);
+
+            let waiting = tutorial.waiting(&assets.sheet, self.run.env.time);
+
+            // hold the song while the player reads the prompt
+            if waiting != tutorial.holding {
+                if waiting {
+                    self.audio.pause_music();
+                } else {
+                    self.audio.resume_music();
+                }
+
+                tutorial.holding = waiting;
+            }
+
+            if waiting {
+                frame_time = 0.0;
+            }
+        }
+
+        // a long frame is a hitch, not the player's fault
+        if get_frame_time() > settings.hitch_threshold {
+            log_debug!(
+                "{:.0}ms hitch at {:.2}s, not judging misses",
+                get_frame_time() * 1000.0,
+                self.run.env.time
+            );
+
+            self.run.hitch(frame_time * self.run.env.time_scale.get());
+        }
+
+        // catch up in fixed steps so long frames don't move projectiles past the shield
+        let steps = (frame_time / FIXED_STEP).ceil().max(1.0);
+
+        for _ in 0..steps as u32 {
+            self.step(assets, settings, frame_time / steps);
+        }
+
+        if self.death.is_none() {
+            self.drift = self
+                .audio
+                .music_position()
+                .map(|position| position - self.offset - self.scaled_lag - self.run.env.time);
+
+            if let Some(drift) = self.drift {
+                self.correct_drift(drift);
+            }
+
+            self.shake.update(frame_time);
+        }
+
+        self.aberration *= 0.9;
+        self.flash *= 0.9;
+
+        for shield in &mut self.run.shields {
+            shield.snap = (shield.snap + get_frame_time() / SHIELD_SNAP).min(1.0);
+        }
+
+        let score = self.score() as f32;
+        self.displayed_score += (score - self.displayed_score) * (get_frame_time() * 10.0).min(1.0);
+
+        // snap once close so the counter doesn't crawl through the last point
+        if (score - self.displayed_score).abs() < 0.5 {
+            self.displayed_score = score;
+        }
+
+        for popup in &mut self.popups {
+            popup.age += get_frame_time();
+        }
+
+        self.streamer.update(get_frame_time());
+
+        if let Some(nudged) = &mut self.offset_nudged {
+            *nudged += get_frame_time();
+        }
+
+        if let Some(shockwave) = &mut self.shockwave {
+            *shockwave += get_frame_time();
+        }
+
+        if let Some(celebration) = &mut self.celebration {
+            *celebration += get_frame_time();
+        }
+
+        self.popups.retain(|popup| popup.age < POPUP_LIFE);
+
+        self.audio.update(get_frame_time());
+
+        if let Some(practice) = &mut self.practice {
+            if self.run.env.time >= practice.end {
+                practice.next_pass();
+                self.loop_practice(assets);
+            }
+        }
+
+        if self.tutorial.is_none() {
+            if is_key_pressed(KeyCode::LeftBracket) {
+                self.mark = Some(self.run.env.time);
+            }
+
+            if is_key_pressed(KeyCode::RightBracket) {
+                if let Some(start) = self.mark.take().filter(|start| *start < self.run.env.time) {
+                    self.practice(assets, settings, start, self.run.env.time);
+                }
+            }
+        }
+
+        if is_key_pressed(KeyCode::Tab) {
+            self.show_stats = !self.show_stats;
+        }
+
+        if self.can_retry_checkpoint() && is_key_pressed(KeyCode::C) {
+            self.retry_checkpoint(assets);
+        }
+
+        if is_key_pressed(KeyCode::V) {
+            if self.can_review() {
+                self.review(assets, settings).await;
+            } else if self.cleared(assets) {
+                self.replay_run(assets, settings).await;
+            }
+        }
+
+        // held rather than pressed so a stray press doesn't throw away a run
+        if is_key_pressed(settings.restart_key) {
+            self.restart_held = Some(0.0);
+        } else if !is_key_down(settings.restart_key) {
+            self.restart_held = None;
+        }
+
+        if let Some(held) = &mut self.restart_held {
+            *held += get_frame_time();
+
+            if *held >= settings.restart_hold {
+                self.restart(assets, settings);
+            }
+        }
+    }
+
+    /// Reacts to the events from `from` on, with sounds, particles, the hud and the script.
+    fn handle_events(&mut self, assets: &Assets, settings: &Settings, from: usize) {
+        // taken while handled since handling needs the rest of the state
+        let events = std::mem::take(&mut self.events);
+
+        for event in events[from..].iter().cloned() {
+            let died = matches!(event, GameEvent::Death { .. });
+
+            match event {
+                GameEvent::NoteBlocked {
+                    direction,
+                    position,
+                    offset,
+                    judgment,
+                    points,
+                    ..
+                } => {
+                    self.shake.add(0.05);
+                    self.aberration += 0.3;
+
+                    self.popups.push(ScorePopup {
+                        points,
+                        position,
+                        age: 0.0,
+                    });
+
+                    self.stats.block(&direction, offset, judgment);
+                    self.streamer.judge(judgment);
+
+                    if let Some(sound) = assets.hitsounds.get(judgment) {
+                        self.audio.play_sfx(sound, settings.kick_volume);
+                    }
+
+                    let explosion = hit_particles(
+                        assets.effects.hits.get(judgment),
+                        assets.particle,
+                        &direction,
+                        position,
+                    );
+
+                    self.particles.spawn(&explosion);
+                }
+                GameEvent::ComboMilestone(combo) => {
+                    if combo.is_multiple_of(COMBO_MILESTONE) {
+                        if let Some(sound) = assets.hitsounds.milestone {
+                            self.audio.play_sfx(sound, 1.0);
+                        }
+                    }
+
+                    if let Some(milestone) = assets.effects.milestone(combo) {
+                        if let Some(sound) = milestone.sound {
+                            self.audio.play_sfx(sound, 1.0);
+                        }
+
+                        self.flash += milestone.flash;
+
+                        self.particles.burst(&Ring {
+                            texture: Some(assets.particle),
+                            amount: milestone.particles,
+                            color: WHITE,
+                            position: Vec2::ZERO,
+                            radius: 16.0,
+                            speed: 160.0,
+                            life_time: 1.0,
+                            size: 4.0,
+                            sub_emitter: None,
+                        });
+                    }
+                }
+                GameEvent::NoteMissed {
+                    direction,
+                    position,
+                }
+                | GameEvent::Death {
+                    direction,
+                    position,
+                } => {
+                    self.stats.miss(&direction);
+                    self.streamer.judge(Judgment::Miss);
+
+                    let flash = hit_particles(
+                        assets.effects.hits.get(Judgment::Miss),
+                        assets.particle,
+                        &direction,
+                        position,
+                    );
+
+                    self.particles.spawn(&flash);
+
+                    if let Some(practice) = &mut self.practice {
+                        practice.clean = false;
+                    }
+
+                    self.aberration = 2.0;
+
+                    if died {
+                        log_info!(
+                            "died at {:.2}s with {} points",
+                            self.run.env.time,
+                            self.run.score
+                        );
+
+                        self.audio.duck_and_stop(DEATH_DUCK);
+                        self.shake.trauma = 0.0;
+                        self.audio.play_sfx(assets.death, settings.death_volume);
+                    } else {
+                        self.shake.add(0.4);
+
+                        if let Some(sound) = assets.hitsounds.get(Judgment::Miss) {
+                            self.audio.play_sfx(sound, settings.kick_volume);
+                        }
+                    }
+                }
+                GameEvent::SectionStart(index) => {
+                    log_debug!("section {} at {:.2}s", index, self.run.env.time);
+                }
+                GameEvent::PhaseStart(index) => {
+                    log_debug!("phase {} at {:.2}s", index, self.run.env.time);
+                }
+                GameEvent::BossDefeated => {
+                    log_info!("boss defeated at {:.2}s", self.run.env.time);
+
+                    self.shake.add(0.6);
+                    self.flash += 1.0;
+
+                    self.particles.burst(&Ring {
+                        texture: Some(assets.particle),
+                        amount: 64,
+                        color: WHITE,
+                        position: BOSS_POSITION,
+                        radius: 16.0,
+                        speed: 200.0,
+                        life_time: 1.5,
+                        size: 6.0,
+                        sub_emitter: None,
+                    });
+                }
+                GameEvent::PickupCollected(kind) => {
+                    log_debug!("collected {} at {:.2}s", kind.name(), self.run.env.time);
+
+                    self.aberration += 0.5;
+
+                    for lane in 0..self.run.env.lanes {
+                        self.particles.spawn(&Ring {
+                            texture: Some(assets.particle),
+                            amount: 24,
+                            color: kind.color(),
+                            position: lane_center(lane, self.run.env.lanes),
+                            radius: 8.0,
+                            speed: 120.0,
+                            life_time: 0.6,
+                            size: 4.0,
+                            sub_emitter: None,
+                        });
+                    }
+                }
+                GameEvent::Bomb { cleared, points } => {
+                    log_info!(
+                        "bomb at {:.2}s cleared {} projectiles for {} points",
+                        self.run.env.time,
+                        cleared,
+                        points
+                    );
+
+                    self.shake.add(0.8);
+                    self.flash += 1.5;
+                    self.aberration += 1.0;
+                    self.shockwave = Some(0.0);
+
+                    for lane in 0..self.run.env.lanes {
+                        self.particles.burst(&Ring {
+                            texture: Some(assets.particle),
+                            amount: 64,
+                            color: GOLD,
+                            position: lane_center(lane, self.run.env.lanes),
+                            radius: 16.0,
+                            speed: 320.0,
+                            life_time: 1.0,
+                            size: 6.0,
+                            sub_emitter: None,
+                        });
+                    }
+
+                    if points > 0 {
+                        self.popups.push(ScorePopup {
+                            points,
+                            position: Vec2::ZERO,
+                            age: 0.0,
+                        });
+                    }
+                }
+                GameEvent::ShieldTurned { lane, direction } => {
+                    let center = lane_center(lane, self.run.env.lanes);
+
+                    self.particles.add_emitter(shield_burst(&direction, center));
+                }
+                GameEvent::SongEnd => {
+                    log_info!("cleared with {} points", self.run.score);
+
+                    if self.full_combo() {
+                        self.celebrate(assets);
+                    }
+                }
+            }
+        }
+
+        self.events = events;
+    }
+
+    /// Advances the simulation by `frame_time` seconds of the song, the clock moving by that
+    /// scaled by [`Env::time_scale`].
+    fn step(&mut self, assets: &Assets, settings: &Settings, frame_time: f32) {
+        self.run.env.time_scale.death = match self.death {
+            Some(_) if !settings.death_warp => 0.0,
+            death => (1.0 - death.unwrap_or(0.0)).max(0.0),
+        };
+        self.run.env.time_scale.pickup = self.run.pickups.time_scale();
+
+        let scaled_time = frame_time * self.run.env.time_scale.get();
+        let beat = self.run.beat;
+
+        self.scaled_lag += frame_time - scaled_time;
+
+        let alive = self.death.is_none();
+
+        if let Some(review) = self.review.as_mut().filter(|_| alive) {
+            let presses = review
+                .presses_until(self.run.env.time + scaled_time)
+                .to_vec();
+
+            self.run.press(&assets.sheet, &presses);
+        }
+
+        // practice loops keep going through misses
+        self.run.no_fail = self.run.modifiers.no_fail || self.practice.is_some();
+        self.run.step(&assets.sheet, &assets.script, scaled_time);
+        self.run_uniform_events(assets, self.run.env.time);
+
+        let from = self.events.len();
+
+        for event in std::mem::take(&mut self.run.events) {
+            let milestone = match &event {
+                GameEvent::NoteBlocked { combo, .. } => Some(*combo).filter(|combo| {
+                    combo.is_multiple_of(COMBO_MILESTONE)
+                        || assets.effects.milestone(*combo).is_some()
+                }),
+                _ => None,
+            };
+
+            self.events.push(event);
+            self.events.extend(milestone.map(GameEvent::ComboMilestone));
+        }
+
+        if let Some(death) = &mut self.death {
+            *death += frame_time;
+        } else if self.run.fatal.is_some() {
+            self.death = Some(0.0);
+        } else {
+            if !self.song_ended && self.cleared(assets) {
+                self.song_ended = true;
+                self.events.push(GameEvent::SongEnd);
+            }
+
+            if self.run.modifiers.checkpoints > 0 {
+                let time = assets
+                    .sheet
+                    .checkpoint_at(self.run.env.time, self.run.modifiers.checkpoints);
+
+                let reached = time > self.start_time
+                    && self
+                        .checkpoint
+                        .as_ref()
+                        .is_none_or(|checkpoint| checkpoint.time < time);
+
+                if reached {
+                    self.checkpoint = Some(Checkpoint {
+                        time,
+                        score: self.run.score,
+                        speed: self.run.env.speed,
+                        momentum: self.run.momentum.clone(),
+                    });
+                }
+            }
+
+            if self.run.beat > beat {
+                let sample = (
+                    self.run.env.time,
+                    self.run.env.speed,
+                    self.run.momentum.clone(),
+                );
+
+                self.speeds.push(sample);
+            }
+        }
+
+        self.handle_events(assets, settings, from);
+        self.run_script(assets);
+        self.particles.update(scaled_time);
+    }
+
+    /// Draws the hearts in the bottom left, partly filled for partial hearts and pink for
+    /// overheal.
+    fn draw_hearts(&self, assets: &Assets, settings: &Settings) {
+        let scale = canvas::scale() * settings.ui_scale;
+        let size = vec2(assets.heart.width(), assets.heart.height()) * scale;
+        let spacing = size.x + 4.0 * scale;
+
+        let hearts = self.run.health.max.ceil() as u32;
+        let overheal = self.run.health.overheal.ceil() as u32;
+        let count = hearts + overheal;
+
+        let row = vec2(spacing * count as f32 - 4.0 * scale, size.y);
+        let corner = assets.hud.hearts.place(row, settings.ui_scale);
+        let y = corner.y;
+
+        for index in 0..count {
+            let x = corner.x + spacing * index as f32;
+
+            let (fill, color) = if index < hearts {
+                (
+                    (self.run.health.hearts - index as f32).clamp(0.0, 1.0),
+                    WHITE,
+                )
+            } else {
+                let index = index - hearts;
+
+                (
+                    (self.run.health.overheal - index as f32).clamp(0.0, 1.0),
+                    PINK,
+                )
+            };
+
+            // an empty heart behind, the filled part drawn over it from the left
+            assets.heart.draw(
+                x,
+                y,
+                Color::new(0.3, 0.3, 0.3, 1.0),
+                DrawTextureParams {
+                    dest_size: Some(size),
+                    ..Default::default()
+                },
+            );
+
+            if fill > 0.0 {
+                assets.heart.draw(
+                    x,
+                    y,
+                    color,
+                    DrawTextureParams {
+                        dest_size: Some(vec2(size.x * fill, size.y)),
+                        source: Some(Rect::new(
+                            0.0,
+                            0.0,
+                            assets.heart.width() * fill,
+                            assets.heart.height(),
+                        )),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+    }
+
+    /// Draws a marker at the screen edge for each projectile about to come on screen.
+    fn draw_telegraphs(&self, assets: &Assets, settings: &Settings, camera: &CameraState) {
+        let lead = assets.sheet.telegraph_time();
+
+        if lead <= 0.0 {
+            return;
+        }
+
+        // seconds a projectile takes from the screen edge to its heart
+        let lanes = self.run.env.lanes;
+        let pixels_per_second = self.run.env.speed * assets.sheet.bpm / 60.0;
+        let travel = |direction: &Direction, lane: usize| {
+            let center = lane_center(lane, lanes);
+
+            (edge_distance(direction, center, camera) - 48.0).max(0.0) / pixels_per_second
+        };
+
+        let upcoming = assets.sheet.projectiles[self.run.next_projectile..]
+            .iter()
+            .enumerate()
+            .map(|(index, projectile)| {
+                let direction = self.run.modifiers.direction(
+                    &projectile.direction,
+                    self.run.seed,
+                    self.run.next_projectile + index,
+                );
+
+                (projectile.arrival_time, direction, projectile.lane)
+            });
+
+        let longest = (0..lanes)
+            .flat_map(|lane| {
+                Direction::ALL
+                    .iter()
+                    .map(move |direction| (direction, lane))
+            })
+            .map(|(direction, lane)| travel(direction, lane))
+            .fold(0.0, f32::max);
+
+        let projectiles = self
+            .run
+            .active_projectiles
+            .iter()
+            .map(|projectile| {
+                let direction = projectile.direction.clone();
+
+                (projectile.arrival_time, direction, projectile.lane)
+            })
+            .chain(upcoming)
+            .take_while(|(arrival_time, _, _)| *arrival_time <= self.run.env.time + longest + lead);
+
+        for (arrival_time, direction, lane) in projectiles {
+            let until = arrival_time - travel(&direction, lane) - self.run.env.time;
+
+            if until <= 0.0 || until > lead {
+                continue;
+            }
+
+            // fade in as the projectile gets closer
+            let alpha = (1.0 - until / lead) * 0.6 * settings.playfield_opacity;
+            let color = settings.palette.color(&direction);
+
+            let angle = direction.angle();
+            let ray = vec2(angle.cos(), angle.sin());
+            let center = lane_center(lane, lanes);
+            let edge = edge_distance(&direction, center, camera);
+            let tip = center + ray * (edge - TELEGRAPH_INSET - 6.0);
+            let base = center + ray * (edge - TELEGRAPH_INSET);
+            let side = vec2(-ray.y, ray.x) * 6.0;
+
+            draw_triangle(
+                tip,
+                base + side,
+                base - side,
+                Color::new(color.r, color.g, color.b, alpha),
+            );
+        }
+    }
+
+    /// Draws the boss winding up for the next phase, and the side a feint pretends to fire from.
+    fn draw_boss(&self, assets: &Assets, settings: &Settings, camera: &CameraState) {
+        let lookahead = activation_lookahead(&assets.sheet, camera, self.run.env.speed);
+        let windup = boss::windup(
+            &assets.sheet.phases,
+            self.run.env.time,
+            lookahead,
+            assets.sheet.bpm,
+        );
+
+        if let Some(boss) = &self.run.boss {
+            boss.draw(
+                assets,
+                windup.map(|(_, progress)| progress),
+                self.run.env.time,
+            );
+        }
+
+        let (phase, progress) = match windup {
+            Some((phase, progress)) if phase.pattern.kind == PatternKind::Feint => {
+                (phase, progress)
+            }
+            _ => return,
+        };
+
+        // drawn like a telegraph, pulsing so it reads as the real thing
+        let side = &phase.pattern.side;
+        let color = settings.palette.color(side);
+        let alpha = progress * (0.6 + (self.run.env.time * 20.0).sin() * 0.2);
+
+        let angle = side.angle();
+        let ray = vec2(angle.cos(), angle.sin());
+        let center = lane_center(phase.pattern.lane, self.run.env.lanes);
+        let edge = edge_distance(side, center, camera);
+        let tip = center + ray * (edge - TELEGRAPH_INSET - 6.0);
+        let base = center + ray * (edge - TELEGRAPH_INSET);
+        let normal = vec2(-ray.y, ray.x) * 6.0;
+
+        draw_triangle(
+            tip,
+            base + normal,
+            base - normal,
+            Color::new(color.r, color.g, color.b, alpha),
+        );
+    }
+
+    pub fn draw(&mut self, assets: &Assets, settings: &Settings, post: &PostProcessing) {
+        let (shake_offset, shake_rotation) = self.shake.offset(settings.shake);
+
+        let camera = self.run.camera.state(self.run.env.time);
+
+        set_camera(&Camera2D {
+            target: camera.target + shake_offset,
+            rotation: camera.rotation + shake_rotation,
+            offset: Vec2::ZERO,
+            zoom: vec2(2.0 / canvas::WIDTH, -2.0 / canvas::HEIGHT) * camera.zoom,
+            render_target: Some(post.target()),
+        });
+
+        clear_background(BLACK);
+
+        let resolution = canvas::size();
+
+        assets.background.set_texture("noise_texture", assets.noise);
+
+        if let Some(ichannel0) = assets.ichannel0 {
+            assets.background.set_texture("iChannel0", ichannel0);
+        }
+
+        if let Some(video) = &assets.video {
+            video.update(self.run.env.time);
+            assets.background.set_texture("iVideo", video.texture);
+        }
+
+        if let Some(spectrum) = &assets.spectrum {
+            spectrum.update(self.run.env.time + self.offset);
+            assets.background.set_texture("iSpectrum", spectrum.texture);
+        }
+
+        assets.background.set_uniform("iTime", self.run.env.time);
+        assets.background.set_uniform("iResolution", resolution);
+        assets.background.set_uniform(
+            "iBeat",
+            ((self.run.env.time - assets.sheet.start_offset).max(0.0) * assets.sheet.bpm / 60.0)
+                .fract(),
+        );
+        assets
+            .background
+            .set_uniform("iFlash", self.flash * settings.flashes);
+
+        for uniform in &self.uniforms {
+            uniform
+                .value(self.run.env.time)
+                .set_on(assets.background, &uniform.name);
+        }
+
+        gl_use_material(assets.background);
+
+        draw_rectangle(0.0, 0.0, 1.0, 1.0, WHITE);
+
+        gl_use_default_material();
+
+        if settings.background_dim > 0.0 {
+            draw_rectangle(
+                -canvas::WIDTH / 2.0,
+                -canvas::HEIGHT / 2.0,
+                canvas::WIDTH,
+                canvas::HEIGHT,
+                Color::new(0.0, 0.0, 0.0, settings.background_dim),
+            );
+        }
+
+        // flash shaders that don't use iFlash too
+        let flash = (self.flash * settings.flashes).min(1.0) * 0.3;
+
+        if flash > 0.01 {
+            draw_rectangle(
+                -canvas::WIDTH / 2.0,
+                -canvas::HEIGHT / 2.0,
+                canvas::WIDTH,
+                canvas::HEIGHT,
+                Color::new(1.0, 1.0, 1.0, flash),
+            );
+        }
+
+        self.particles.draw(assets.gpu_particles.as_ref());
+
+        if let Some(age) = self.shockwave.filter(|age| *age < SHOCKWAVE_LIFE) {
+            let progress = age / SHOCKWAVE_LIFE;
+            let radius = progress * view_distance(&camera, self.run.env.lanes);
+
+            draw_circle_lines(
+                0.0,
+                0.0,
+                radius,
+                8.0 * (1.0 - progress) + 1.0,
+                Color::new(1.0, 0.85, 0.2, 1.0 - progress),
+            );
+        }
+
+        self.run
+            .pickups
+            .draw(assets.pickup, self.run.env.time, self.run.env.lanes);
+        self.draw_boss(assets, settings, &camera);
+
+        // projectiles, skipping the ones still off screen
+        let view_distance = view_distance(&camera, self.run.env.lanes);
+
+        for projectile in &self.run.active_projectiles {
+            if projectile.distance(&self.run.env, assets.sheet.bpm) > view_distance {
+                continue;
+            }
+
+            let alpha = if self.run.modifiers.hidden {
+                let (start, end) = HIDDEN_FADE;
+                let distance = projectile.distance(&self.run.env, assets.sheet.bpm);
+
+                ((distance - end) / (start - end)).clamp(0.0, 1.0)
+            } else {
+                1.0
+            };
+
+            projectile.draw(
+                &self.run.env,
+                assets,
+                settings,
+                alpha * settings.playfield_opacity,
+            );
+
+            if self
+                .review
+                .as_ref()
+                .is_some_and(|review| review.is_missed(projectile))
+            {
+                let position = projectile.position(&self.run.env, assets.sheet.bpm);
+                // pulse so it stands out even among a crowd
+                let radius = 12.0 + (get_time() as f32 * 10.0).sin() * 2.0;
+
+                draw_circle_lines(position.x, position.y, radius, 2.0, RED);
+            }
+        }
+
+        self.draw_telegraphs(assets, settings, &camera);
+
+        // hearts, pulsing on every beat, with their shields
+        let since_start = (self.run.env.time - assets.sheet.start_offset).max(0.0);
+        let phase = (since_start * assets.sheet.bpm / 60.0).fract();
+        let heart_size = vec2(assets.heart.width(), assets.heart.height())
+            * (1.0 + HEART_PULSE * (1.0 - phase).powi(3));
+
+        let opacity = settings.playfield_opacity;
+
+        for (lane, shield) in self.run.shields.iter().enumerate() {
+            let center = lane_center(lane, self.run.env.lanes);
+
+            assets.heart.draw(
+                center.x - heart_size.x / 2.0,
+                center.y - heart_size.y / 2.0,
+                Color::new(1.0, 1.0, 1.0, opacity),
+                DrawTextureParams {
+                    dest_size: Some(heart_size),
+                    ..Default::default()
+                },
+            );
+
+            if let Some(direction) = &shield.direction {
+                let angle = shield.angle();
+                let offset = center + vec2(angle.cos(), angle.sin()) * 32.0;
+
+                assets.shield.draw(
+                    offset.x - assets.shield.width() / 2.0,
+                    offset.y - assets.shield.height() / 2.0,
+                    Color {
+                        a: opacity,
+                        ..if self.run.stunned() {
+                            GRAY
+                        } else {
+                            settings.palette.color(direction)
+                        }
+                    },
+                    DrawTextureParams {
+                        rotation: angle,
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+
+        post.draw(&PostEffects {
+            aberration: self.aberration * settings.flashes,
+            vignette: 1.0 - self.run.health.fraction(),
+            desaturate: self.death.unwrap_or(0.0),
+            crt: settings.crt,
+            bloom: settings.bloom,
+        });
+
+        // in fourths of a beat from the start offset, so it ticks over right on the beat
+        let beat = (since_start * assets.sheet.bpm / 60.0 * 4.0).floor() as u32;
+
+        // hud is drawn in screen space, positioned and scaled relative to the canvas
+        let viewport = canvas::viewport();
+        let scale = canvas::scale() * settings.ui_scale;
+        let font_size = 16.0 * scale;
+
+        let hud = &assets.hud;
+        let locale = &settings.locale;
+        let text_params = TextParams {
+            font: hud.font,
+            font_size: font_size as u16,
+            ..Default::default()
+        };
+        let draw_placed = |text: &str, placement: &Placement| {
+            let position = placement.place_text(text, hud.font, font_size, settings.ui_scale);
+
+            draw_outlined_text(text, position.x, position.y, text_params);
+        };
+
+        let progress = (self.run.env.time - assets.sheet.start_offset)
+            / (assets.sheet.end_time() - assets.sheet.start_offset);
+
+        hud.draw(
+            &HudValues {
+                score: self.displayed_score,
+                combo: self.run.combo,
+                accuracy: self.stats.hit_rate(),
+                progress,
+                judgments: self.stats.judgment_counts(),
+                beat: format!("{};{}|{}", beat % 4, (beat / 4) % 4, beat / 16),
+            },
+            // the streamer overlay has them in its own corner
+            if settings.streamer_mode {
+                &[WidgetKind::Combo, WidgetKind::Accuracy]
+            } else {
+                &[]
+            },
+            locale,
+            settings.ui_scale,
+        );
+
+        if settings.streamer_mode {
+            let held = Direction::ALL.map(|direction| {
+                DIRECTION_KEYS
+                    .iter()
+                    .filter(|(_, key_direction)| *key_direction == direction)
+                    .any(|(keys, _)| keys.iter().any(|key| is_key_down(*key)))
+            });
+
+            self.streamer.draw(
+                hud,
+                &assets.effects.hits,
+                (held, self.run.combo, self.stats.hit_rate()),
+                locale,
+                settings.ui_scale,
+            );
+        }
+
+        if let Some(practice) = &self.practice {
+            draw_placed(
+                &locale.format(
+                    "hud-practice",
+                    &[
+                        ("loop", &(practice.loops + 1)),
+                        ("rate", &format!("{:.2}", practice.rate)),
+                    ],
+                ),
+                &hud.practice,
+            );
+        } else if self.mark.is_some() {
+            draw_placed(locale.get("hud-practice-marked"), &hud.practice);
+        }
+
+        let review_text =
+            self.review
+                .as_ref()
+                .map(|review| match (&review.missed, &review.player) {
+                    (Some(_), _) => locale.get("hud-review").to_string(),
+                    (None, Some(player)) => locale.format("hud-spectate", &[("player", player)]),
+                    (None, None) => locale.get("hud-replay").to_string(),
+                });
+
+        if let Some(text) = review_text {
+            draw_placed(&text, &hud.review);
+        }
+
+        let effects: Vec<String> = self
+            .run
+            .pickups
+            .effects
+            .iter()
+            .map(|effect| {
+                let seconds = format!("{:.1}", effect.until - self.run.env.time);
+
+                locale.format(effect.kind.label(), &[("seconds", &seconds)])
+            })
+            .collect();
+
+        if !effects.is_empty() {
+            draw_placed(&effects.join("  "), &hud.pickups);
+        }
+
+        if self.run.modifiers.aggro {
+            let percent = format!("{:.0}", self.run.aggro.progress() * 100.0);
+
+            draw_placed(
+                &locale.format(
+                    "hud-aggro",
+                    &[
+                        ("multiplier", &self.run.aggro.multiplier()),
+                        ("progress", &percent),
+                    ],
+                ),
+                &hud.aggro,
+            );
+        }
+
+        if let Some(drift) = self.drift.filter(|_| settings.drift_indicator) {
+            let color = if self.is_resyncing() {
+                RED
+            } else if drift.abs() < RESYNC_THRESHOLD / 3.0 {
+                GREEN
+            } else {
+                YELLOW
+            };
+            let size = vec2(4.0, 4.0) * scale;
+            let corner = hud.drift.place(size, settings.ui_scale);
+
+            draw_rectangle(corner.x, corner.y, size.x, size.y, color);
+        }
+
+        if self
+            .offset_nudged
+            .is_some_and(|nudged| nudged < OFFSET_SHOWN)
+        {
+            let offset = settings.song_offset(assets.sheet.hash) * 1000.0;
+
+            draw_placed(
+                &locale.format("hud-song-offset", &[("offset", &format!("{:+.0}", offset))]),
+                &hud.prompt,
+            );
+        }
+
+        if let Some(celebration) = self.celebration {
+            let settle = (celebration / FULL_COMBO_POP).min(1.0);
+            // overshoots and springs back to its size
+            let pop = 1.0 + (1.0 - settle).powi(2) * (settle * 12.0).sin() * 0.6;
+            let text = locale.get("hud-full-combo");
+            let size = font_size * 2.0 * pop;
+            let position = hud
+                .banner
+                .place_text(text, hud.font, size, settings.ui_scale);
+
+            draw_outlined_text(
+                text,
+                position.x,
+                position.y,
+                TextParams {
+                    font_size: size as u16,
+                    color: Color {
+                        a: settle.powf(0.3),
+                        ..GOLD
+                    },
+                    ..text_params
+                },
+            );
+        }
+
+        if let Some(boss) = &self.run.boss {
+            match boss.defeated {
+                Some(defeated) if defeated < DEFEAT_BANNER => {
+                    draw_placed(locale.get("hud-boss-defeated"), &hud.boss)
+                }
+                Some(_) => {}
+                None => {
+                    let size = vec2(160.0, 4.0) * scale;
+
+                    boss.draw_health(hud.boss.place(size, settings.ui_scale), size);
+                }
+            }
+        }
+
+        if let Some(rules) = &assets.sheet.meter {
+            let size = vec2(80.0, 4.0) * scale;
+
+            self.run.meter.draw(
+                rules,
+                hud.meter.place(size, settings.ui_scale),
+                size,
+                self.run.env.time,
+            );
+        }
+
+        let lookahead = activation_lookahead(&assets.sheet, &camera, self.run.env.speed);
+
+        if let Some((phase, _)) = boss::windup(
+            &assets.sheet.phases,
+            self.run.env.time,
+            lookahead,
+            assets.sheet.bpm,
+        ) {
+            let text = locale.get(phase.pattern.kind.label());
+            let size = measure_text(text, Some(hud.font), font_size as u16, 1.0);
+            let position = canvas::to_screen(BOSS_POSITION + vec2(0.0, 48.0));
+
+            draw_outlined_text(text, position.x - size.width / 2.0, position.y, text_params);
+        }
+
+        if self.touch.used {
+            let button = pause_button(settings);
+            let bar = button.w / 5.0;
+
+            for x in [bar, bar * 3.0] {
+                draw_rectangle(
+                    button.x + x,
+                    button.y + bar,
+                    bar,
+                    button.h - bar * 2.0,
+                    WHITE,
+                );
+            }
+        }
+
+        if let Some(prompt) = self
+            .tutorial
+            .as_ref()
+            .and_then(|tutorial| tutorial.prompt(&assets.sheet, self.run.env.time))
+        {
+            let line_height = font_size * 1.2;
+            let lines: Vec<&str> = settings.locale.get(prompt).lines().collect();
+            let widths: Vec<f32> = lines
+                .iter()
+                .map(|line| measure_text(line, Some(hud.font), font_size as u16, 1.0).width)
+                .collect();
+
+            let block = vec2(
+                widths.iter().copied().fold(0.0, f32::max),
+                line_height * lines.len() as f32,
+            );
+            let corner = hud.prompt.place(block, settings.ui_scale);
+
+            // lines centered within the block
+            for (index, (line, width)) in lines.iter().zip(&widths).enumerate() {
+                draw_outlined_text(
+                    line,
+                    corner.x + (block.x - width) / 2.0,
+                    corner.y + line_height * (index as f32 + 1.0),
+                    text_params,
+                );
+            }
+        }
+
+        self.draw_hearts(assets, settings);
+
+        for popup in &self.popups {
+            let progress = popup.age / POPUP_LIFE;
+            let position = canvas::to_screen(popup.position - vec2(0.0, progress * 16.0));
+            let text = format!("+{}", popup.points);
+            let size = measure_text(&text, Some(hud.font), font_size as u16, 1.0);
+
+            draw_outlined_text(
+                &text,
+                position.x - size.width / 2.0,
+                position.y,
+                TextParams {
+                    color: Color::new(1.0, 1.0, 1.0, 1.0 - progress),
+                    ..text_params
+                },
+            );
+        }
+
+        if self.show_stats {
+            self.stats.draw_overlay(settings);
+        }
+
+        if let Some(held) = self.restart_held.filter(|_| settings.restart_hold > 0.0) {
+            let progress = (held / settings.restart_hold).min(1.0);
+            let center = vec2(viewport.x + viewport.w / 2.0, viewport.y + viewport.h / 2.0);
+            let radius = 24.0 * scale;
+            let segments = (32.0 * progress).ceil() as u32;
+
+            // an arc going clockwise from the top, complete once the restart happens
+            for segment in 0..segments {
+                let from = segment as f32 / 32.0 * std::f32::consts::TAU - PI / 2.0;
+                let to =
+                    ((segment + 1) as f32 / 32.0).min(progress) * std::f32::consts::TAU - PI / 2.0;
+
+                let a = center + vec2(from.cos(), from.sin()) * radius;
+                let b = center + vec2(to.cos(), to.sin()) * radius;
+
+                draw_line(a.x, a.y, b.x, b.y, 3.0 * scale, WHITE);
+            }
+        }
     }
 }
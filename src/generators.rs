@@ -0,0 +1,106 @@
+//! Pattern generators, sheet lines that expand into many projectiles when the sheet is parsed:
+//!
+//! ```text
+//! // 16 projectiles a beat apart, turning clockwise from above, starting on the second bar
+//! #spiral 16 0;1|0 cw 0;0|2
+//! // the same turning the other way from the left, at the second lane's heart
+//! #spiral 16 0;1|0 ccw L @2 0;0|2
+//! // 8 projectiles sweeping from the left over the top to the right and back, a beat apart
+//! #wave L->R 8 0;0|6
+//! // the same, a fourth of a beat apart
+//! #wave L->R 8 1;0|0 0;0|6
+//! // loosened by up to 4ms either way with the humanize modifier
+//! #spiral 16 1;0|0 cw 0;0|8 jitter=4ms
+//! ```
+//!
+//! Counts are whole numbers and every generated projectile is a `norm`. Sheets written back out
+//! list the projectiles instead of the generators.
+
+use crate::game::{Direction, Projectile};
+use crate::projectiles::ProjectileType;
+use crate::sheet::TimeOffset;
+
+/// The most projectiles a generator, or a volley of them for boss walls, makes.
+pub const MAX_COUNT: u32 = 256;
+
+/// The count written as `offset`, counts being plain whole numbers that parse as beats.
+pub fn count(offset: &TimeOffset) -> Option<u32> {
+    match offset {
+        TimeOffset {
+            fourths: 0,
+            beats,
+            bars: 0,
+        } if (1..=MAX_COUNT).contains(beats) => Some(*beats),
+        _ => None,
+    }
+}
+
+/// Where and how often a generator fires.
+#[derive(Clone, Debug)]
+pub struct Timing {
+    /// When the first projectile arrives.
+    pub start: f32,
+    /// Seconds between projectiles.
+    pub every: f32,
+    pub count: u32,
+    pub lane: usize,
+    /// Most seconds the humanize modifier moves each projectile by.
+    pub jitter: f32,
+}
+
+impl Timing {
+    fn projectiles(&self, directions: impl Iterator<Item = Direction>) -> Vec<Projectile> {
+        let ty = ProjectileType::parse("norm").unwrap().unwrap();
+
+        directions
+            .take(self.count as usize)
+            .enumerate()
+            .map(|(index, direction)| Projectile {
+                arrival_time: self.start + self.every * index as f32,
+                direction,
+                ty: ty.clone(),
+                lane: self.lane,
+                generated: false,
+                jitter: self.jitter,
+                drop: None,
+            })
+            .collect()
+    }
+}
+
+/// Projectiles from each side in turn, starting from `side`.
+pub fn spiral(timing: &Timing, side: &Direction, clockwise: bool) -> Vec<Projectile> {
+    let directions = (0..).map(|index| side.turned(index, clockwise));
+
+    timing.projectiles(directions)
+}
+
+/// Projectiles sweeping from `from` to `to` and back, the short way around, clockwise for
+/// opposite sides.
+pub fn wave(timing: &Timing, from: &Direction, to: &Direction) -> Vec<Projectile> {
+    let position = |side: &Direction| {
+        Direction::CLOCKWISE
+            .iter()
+            .position(|other| other == side)
+            .unwrap()
+    };
+    let quarters = (position(to) + 4 - position(from)) % 4;
+
+    // the sides passed from one end to the other
+    let path: Vec<Direction> = match quarters {
+        0 => vec![from.clone()],
+        3 => vec![from.clone(), to.clone()],
+        _ => (0..=quarters as u32)
+            .map(|index| from.turned(index, true))
+            .collect(),
+    };
+
+    let bounce = (path.len() - 1).max(1) * 2;
+    let directions = (0..).map(|index| {
+        let index = index % bounce;
+
+        path[index.min(bounce - index) % path.len()].clone()
+    });
+
+    timing.projectiles(directions)
+}
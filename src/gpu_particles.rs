@@ -0,0 +1,305 @@
+//! Particles moved by a shader instead of one by one on the CPU, for effects with so many of
+//! them that updating and batching each would cost frames. Bursts are uploaded once as they're
+//! spawned and drawn as instanced quads, the shader working out where each particle has got to
+//! from where it started, so frames only pay for the particles that are drawn.
+//!
+//! Only particles that move in a straight line can run on the GPU: textured ones without a sub
+//! emitter, all drawn from the same atlas. Anything else stays on the CPU, as does everything
+//! when [`Settings::gpu_particles`] is off or the shader doesn't compile.
+//!
+//! [`Settings::gpu_particles`]: crate::settings::Settings::gpu_particles
+
+use crate::log_warn;
+use crate::particles::Particle;
+use macroquad::miniquad::{
+    Bindings, BlendFactor, BlendState, BlendValue, Buffer, BufferLayout, BufferType, Equation,
+    PassAction, Pipeline, PipelineParams, Shader, ShaderMeta, UniformBlockLayout, UniformDesc,
+    UniformType, VertexAttribute, VertexFormat, VertexStep,
+};
+use macroquad::prelude::*;
+use std::cell::Cell;
+
+/// Most particles the GPU has at once, bursts past it are left to the CPU.
+pub const MAX_INSTANCES: usize = 16384;
+
+const VERTEX: &str = r#"
+#version 450
+
+layout(location = 0) in vec2 corner;
+layout(location = 1) in vec2 origin;
+layout(location = 2) in vec2 velocity;
+layout(location = 3) in vec2 spin;
+layout(location = 4) in vec2 life;
+layout(location = 5) in vec4 tint;
+layout(location = 6) in vec4 source;
+layout(location = 7) in vec2 size;
+
+layout(location = 0) out vec2 uv;
+layout(location = 1) out vec4 color;
+
+uniform mat4 Projection;
+uniform float time;
+
+void main() {
+    float age = time - life.x;
+    float rotation = spin.x + spin.y * age;
+    // particles past their life shrink to nothing until they're dropped
+    vec2 offset = (corner - 0.5) * size * step(age, life.y);
+    vec2 turned = vec2(
+        offset.x * cos(rotation) - offset.y * sin(rotation),
+        offset.x * sin(rotation) + offset.y * cos(rotation)
+    );
+
+    gl_Position = Projection * vec4(origin + velocity * age + turned, 0.0, 1.0);
+    uv = source.xy + corner * source.zw;
+    color = vec4(tint.rgb, 1.0 - age / life.y);
+}
+"#;
+
+const FRAGMENT: &str = r#"
+#version 450
+
+layout(location = 0) in vec2 uv;
+layout(location = 1) in vec4 color;
+
+layout(location = 0) out vec4 color_out;
+
+uniform sampler2D Texture;
+
+void main() {
+    color_out = texture(Texture, uv) * color;
+}
+"#;
+
+/// A particle as the shader gets it, where it was when it spawned and how it moves from there.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct Instance {
+    origin: Vec2,
+    velocity: Vec2,
+    /// Rotation it spawned with and its angular velocity.
+    spin: Vec2,
+    /// [`GpuParticles::time`] it spawned at and how long it lives.
+    life: Vec2,
+    tint: [f32; 4],
+    /// Part of the atlas it's drawn from, in uv.
+    source: [f32; 4],
+    size: Vec2,
+}
+
+#[repr(C)]
+struct Uniforms {
+    projection: Mat4,
+    time: f32,
+}
+
+/// The particles of a run handed to the GPU, plain data so runs can keep them without a
+/// graphics context.
+#[derive(Default)]
+pub struct GpuParticles {
+    instances: Vec<Instance>,
+    /// The atlas every particle is drawn from, set by the first burst.
+    texture: Option<Texture2D>,
+    /// Seconds the particles have been updated for.
+    time: f32,
+}
+
+impl GpuParticles {
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Takes `particles` if every one of them can run on the GPU, returning whether it did.
+    pub fn push(&mut self, particles: &[Particle]) -> bool {
+        if self.instances.len() + particles.len() > MAX_INSTANCES {
+            return false;
+        }
+
+        let mut instances = Vec::with_capacity(particles.len());
+
+        for particle in particles {
+            let sprite = match particle.texture {
+                Some(sprite) if particle.sub_emitter.is_none() => sprite,
+                _ => return false,
+            };
+
+            let texture = sprite.texture();
+
+            if self.texture.is_some_and(|atlas| atlas != texture) {
+                return false;
+            }
+
+            let source = sprite.source();
+            let atlas = sprite.texture_size();
+
+            self.texture = Some(texture);
+            instances.push(Instance {
+                origin: particle.position,
+                velocity: particle.velocity,
+                spin: vec2(particle.rotation, particle.angular_velocity),
+                life: vec2(self.time - particle.life, particle.life_time),
+                tint: [particle.color.r, particle.color.g, particle.color.b, 1.0],
+                source: [
+                    source.x / atlas.x,
+                    source.y / atlas.y,
+                    source.w / atlas.x,
+                    source.h / atlas.y,
+                ],
+                size: source.size(),
+            });
+        }
+
+        self.instances.append(&mut instances);
+
+        true
+    }
+
+    pub fn update(&mut self, frame_time: f32) {
+        self.time += frame_time;
+
+        let time = self.time;
+
+        self.instances
+            .retain(|instance| time - instance.life.x < instance.life.y);
+    }
+}
+
+/// What the GPU draws particles with, built once and shared by every level.
+#[derive(Clone, Copy)]
+pub struct GpuRenderer {
+    pipeline: Pipeline,
+    quad: Buffer,
+    indices: Buffer,
+    instances: Buffer,
+}
+
+thread_local! {
+    /// The renderer once it's been built, or `None` in it if building it failed. Miniquad never
+    /// frees pipelines or buffers, so building one a level would leak them.
+    static RENDERER: Cell<Option<Option<GpuRenderer>>> = const { Cell::new(None) };
+}
+
+impl GpuRenderer {
+    /// The renderer, built the first time it's needed. `None` where the shader doesn't compile,
+    /// particles stay on the CPU there.
+    pub fn shared() -> Option<Self> {
+        RENDERER.with(|cached| {
+            let renderer = cached.get().unwrap_or_else(Self::build);
+
+            cached.set(Some(renderer));
+
+            renderer
+        })
+    }
+
+    fn build() -> Option<Self> {
+        let ctx = unsafe { get_internal_gl() }.quad_context;
+
+        let shader = Shader::new(
+            ctx,
+            VERTEX,
+            FRAGMENT,
+            ShaderMeta {
+                images: vec!["Texture".to_string()],
+                uniforms: UniformBlockLayout {
+                    uniforms: vec![
+                        UniformDesc::new("Projection", UniformType::Mat4),
+                        UniformDesc::new("time", UniformType::Float1),
+                    ],
+                },
+            },
+        )
+        .map_err(|err| log_warn!("particles stay on the cpu, shader failed: {}", err))
+        .ok()?;
+
+        let pipeline = Pipeline::with_params(
+            ctx,
+            &[
+                BufferLayout::default(),
+                BufferLayout {
+                    step_func: VertexStep::PerInstance,
+                    ..Default::default()
+                },
+            ],
+            &[
+                VertexAttribute::with_buffer("corner", VertexFormat::Float2, 0),
+                VertexAttribute::with_buffer("origin", VertexFormat::Float2, 1),
+                VertexAttribute::with_buffer("velocity", VertexFormat::Float2, 1),
+                VertexAttribute::with_buffer("spin", VertexFormat::Float2, 1),
+                VertexAttribute::with_buffer("life", VertexFormat::Float2, 1),
+                VertexAttribute::with_buffer("tint", VertexFormat::Float4, 1),
+                VertexAttribute::with_buffer("source", VertexFormat::Float4, 1),
+                VertexAttribute::with_buffer("size", VertexFormat::Float2, 1),
+            ],
+            shader,
+            PipelineParams {
+                color_blend: Some(BlendState::new(
+                    Equation::Add,
+                    BlendFactor::Value(BlendValue::SourceAlpha),
+                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                )),
+                ..Default::default()
+            },
+        );
+
+        let corners: [Vec2; 4] = [
+            vec2(0.0, 0.0),
+            vec2(1.0, 0.0),
+            vec2(1.0, 1.0),
+            vec2(0.0, 1.0),
+        ];
+        let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+        Some(Self {
+            pipeline,
+            quad: Buffer::immutable(ctx, BufferType::VertexBuffer, &corners),
+            indices: Buffer::immutable(ctx, BufferType::IndexBuffer, &indices),
+            instances: Buffer::stream(
+                ctx,
+                BufferType::VertexBuffer,
+                MAX_INSTANCES * std::mem::size_of::<Instance>(),
+            ),
+        })
+    }
+
+    /// Draws `particles` with the camera and render target in use, over whatever's been drawn.
+    pub fn draw(&self, particles: &GpuParticles) {
+        let texture = match particles.texture {
+            Some(texture) if !particles.is_empty() => texture,
+            _ => return,
+        };
+
+        let mut gl = unsafe { get_internal_gl() };
+
+        // everything batched so far goes first, so the particles end up on top of it
+        gl.flush();
+
+        let projection = gl.quad_gl.get_projection_matrix();
+        let pass = gl.quad_gl.get_active_render_pass();
+        let ctx = gl.quad_context;
+
+        self.instances.update(ctx, &particles.instances);
+
+        match pass {
+            Some(pass) => ctx.begin_pass(pass, PassAction::Nothing),
+            None => ctx.begin_default_pass(PassAction::Nothing),
+        }
+
+        ctx.apply_pipeline(&self.pipeline);
+        ctx.apply_bindings(&Bindings {
+            vertex_buffers: vec![self.quad, self.instances],
+            index_buffer: self.indices,
+            images: vec![texture.raw_miniquad_texture_handle()],
+        });
+        ctx.apply_uniforms(&Uniforms {
+            projection,
+            time: particles.time,
+        });
+        ctx.draw(0, 6, particles.instances.len() as i32);
+        ctx.end_render_pass();
+    }
+}
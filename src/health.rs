@@ -0,0 +1,70 @@
+//! The player's hearts, regenerating on long clean streaks and topped up by heart pickups.
+
+/// Blocks in a row it takes to regenerate.
+const REGEN_STREAK: u32 = 25;
+
+/// Hearts regenerated after each streak of [`REGEN_STREAK`] blocks.
+const REGEN_AMOUNT: f32 = 0.25;
+
+/// Hearts a pickup gives on top of the maximum.
+pub const PICKUP_OVERHEAL: f32 = 1.0;
+
+/// Seconds overheal lasts before it fades.
+const OVERHEAL_DURATION: f32 = 10.0;
+
+pub struct Health {
+    /// Hearts left, the player dies at 0.
+    pub hearts: f32,
+    pub max: f32,
+    /// Temporary hearts on top of [`Health::hearts`], lost before them.
+    pub overheal: f32,
+    /// Seconds until the overheal fades.
+    overheal_time: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self {
+            hearts: max,
+            max,
+            overheal: 0.0,
+            overheal_time: 0.0,
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.hearts <= 0.0
+    }
+
+    /// Fraction of the maximum hearts left.
+    pub fn fraction(&self) -> f32 {
+        (self.hearts / self.max).clamp(0.0, 1.0)
+    }
+
+    pub fn damage(&mut self, amount: f32) {
+        let absorbed = amount.min(self.overheal);
+
+        self.overheal -= absorbed;
+        self.hearts -= amount - absorbed;
+    }
+
+    /// Regenerates after every streak of clean blocks, `combo` being the combo after the block.
+    pub fn block(&mut self, combo: u32) {
+        if combo.is_multiple_of(REGEN_STREAK) {
+            self.hearts = (self.hearts + REGEN_AMOUNT).min(self.max);
+        }
+    }
+
+    pub fn pickup(&mut self, amount: f32) {
+        self.overheal += amount;
+        self.overheal_time = OVERHEAL_DURATION;
+    }
+
+    pub fn update(&mut self, frame_time: f32) {
+        self.overheal_time -= frame_time;
+
+        if self.overheal_time <= 0.0 {
+            self.overheal = 0.0;
+        }
+    }
+}
@@ -0,0 +1,443 @@
+//! The hud, laid out by [`HUD_PATH`] and anchored to the edges of the canvas so it stays put at
+//! any resolution.
+//!
+//! Each widget section in the file adds that widget, any left out aren't drawn:
+//!
+//! ```toml
+//! [score]
+//! anchor = "top_left"
+//! x = 8
+//! y = 0
+//! size = 16
+//! color = "#ffffff"
+//! font = "assets/font.ttf"
+//! ```
+//!
+//! The widgets are `score`, `combo`, `accuracy`, `progress`, `judgments` and `beat`. `progress`
+//! is a bar, `size` being its height and `width` its width. `practice`, `review`, `prompt`,
+//! `hearts`, `boss`, `drift`, `pickups`, `meter`, `banner`, `aggro` and the
+//! [streamer overlay](crate::streamer)'s `streamer_judgment`, `streamer_keys` and `streamer_stats`
+//! sections only move those elements, they're always drawn when needed. Widgets without a font
+//! use the level's `font.ttf`, or the game's font if it has none.
+
+use crate::asset_source::{load_optional_font, load_text};
+use crate::canvas;
+use crate::config::Config;
+use crate::font::draw_outlined_text;
+use crate::locale::Locale;
+use crate::log_warn;
+use macroquad::prelude::*;
+
+pub const HUD_PATH: &str = "assets/hud.toml";
+
+/// The point of the canvas an element is attached to, which is also the point of the element
+/// that's attached, like the top right corner of an element anchored to the top right.
+#[derive(Clone, Copy, Debug)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "top_left" => Self::TopLeft,
+            "top" => Self::Top,
+            "top_right" => Self::TopRight,
+            "left" => Self::Left,
+            "center" => Self::Center,
+            "right" => Self::Right,
+            "bottom_left" => Self::BottomLeft,
+            "bottom" => Self::Bottom,
+            "bottom_right" => Self::BottomRight,
+            _ => return None,
+        })
+    }
+
+    /// How far along the width and height the anchor is, from 0 to 1.
+    pub fn factors(&self) -> Vec2 {
+        match self {
+            Self::TopLeft => vec2(0.0, 0.0),
+            Self::Top => vec2(0.5, 0.0),
+            Self::TopRight => vec2(1.0, 0.0),
+            Self::Left => vec2(0.0, 0.5),
+            Self::Center => vec2(0.5, 0.5),
+            Self::Right => vec2(1.0, 0.5),
+            Self::BottomLeft => vec2(0.0, 1.0),
+            Self::Bottom => vec2(0.5, 1.0),
+            Self::BottomRight => vec2(1.0, 1.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Placement {
+    pub anchor: Anchor,
+    /// Offset from the anchor in canvas pixels scaled by the ui scale, x right and y down.
+    pub offset: Vec2,
+}
+
+impl Placement {
+    pub fn new(anchor: Anchor, x: f32, y: f32) -> Self {
+        Self {
+            anchor,
+            offset: vec2(x, y),
+        }
+    }
+
+    /// Reads the placement in `[section]`, keeping the parts of `self` it doesn't set.
+    fn read(self, config: &Config, section: &str) -> Self {
+        let key = |field: &str| format!("{}.{}", section, field);
+
+        Self {
+            anchor: config
+                .get_str(&key("anchor"))
+                .and_then(Anchor::from_name)
+                .unwrap_or(self.anchor),
+            offset: vec2(
+                config.get_f32(&key("x")).unwrap_or(self.offset.x),
+                config.get_f32(&key("y")).unwrap_or(self.offset.y),
+            ),
+        }
+    }
+
+    /// The top left corner of an element `size` screen pixels big, in screen pixels.
+    pub fn place(&self, size: Vec2, ui_scale: f32) -> Vec2 {
+        let viewport = canvas::viewport();
+        let factors = self.anchor.factors();
+
+        vec2(viewport.x, viewport.y) + viewport.size() * factors - size * factors
+            + self.offset * canvas::scale() * ui_scale
+    }
+
+    /// The baseline to draw `text` at, treating it as `font_size` tall.
+    pub fn place_text(&self, text: &str, font: Font, font_size: f32, ui_scale: f32) -> Vec2 {
+        let width = measure_text(text, Some(font), font_size as u16, 1.0).width;
+
+        self.place(vec2(width, font_size), ui_scale) + vec2(0.0, font_size)
+    }
+}
+
+/// Parses a `#rrggbb` or `#rrggbbaa` color.
+pub fn parse_color(source: &str) -> Option<Color> {
+    let hex = source.strip_prefix('#')?;
+
+    if !matches!(hex.len(), 6 | 8) {
+        return None;
+    }
+
+    let channel = |index: usize| {
+        hex.get(index * 2..index * 2 + 2)
+            .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+    };
+
+    Some(Color::from_rgba(
+        channel(0)?,
+        channel(1)?,
+        channel(2)?,
+        if hex.len() == 8 { channel(3)? } else { 255 },
+    ))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WidgetKind {
+    Score,
+    Combo,
+    Accuracy,
+    Progress,
+    Judgments,
+    Beat,
+}
+
+impl WidgetKind {
+    const ALL: [(Self, &'static str); 6] = [
+        (Self::Score, "score"),
+        (Self::Combo, "combo"),
+        (Self::Accuracy, "accuracy"),
+        (Self::Progress, "progress"),
+        (Self::Judgments, "judgments"),
+        (Self::Beat, "beat"),
+    ];
+}
+
+pub struct Widget {
+    pub kind: WidgetKind,
+    pub placement: Placement,
+    /// Font size, or height for bars, in canvas pixels.
+    pub size: f32,
+    /// Width of bars in canvas pixels.
+    pub width: f32,
+    pub color: Color,
+    pub font: Font,
+}
+
+/// What the widgets show, gathered from the game every frame.
+pub struct HudValues {
+    pub score: f32,
+    pub combo: u32,
+    pub accuracy: Option<f32>,
+    /// How far through the level, from 0 to 1.
+    pub progress: f32,
+    /// Perfect, great, late and miss counts.
+    pub judgments: [u32; 4],
+    pub beat: String,
+}
+
+pub struct Hud {
+    pub widgets: Vec<Widget>,
+    /// Font of widgets that don't pick one, and of everything else on the hud.
+    pub font: Font,
+    /// The practice loop line.
+    pub practice: Placement,
+    /// The review and replay banner.
+    pub review: Placement,
+    /// The tutorial prompts, centered on this placement.
+    pub prompt: Placement,
+    pub hearts: Placement,
+    /// The boss health bar.
+    pub boss: Placement,
+    /// The audio drift indicator, drawn when it's turned on in the settings.
+    pub drift: Placement,
+    /// The power-up effects active.
+    pub pickups: Placement,
+    /// The bomb meter, on levels with `#meter`.
+    pub meter: Placement,
+    /// The full combo banner.
+    pub banner: Placement,
+    /// The multiplier of the aggro modifier.
+    pub aggro: Placement,
+    /// The last judgment on the streamer overlay.
+    pub streamer_judgment: Placement,
+    /// The keys held down on the streamer overlay.
+    pub streamer_keys: Placement,
+    /// The combo and accuracy on the streamer overlay, drawn instead of their widgets.
+    pub streamer_stats: Placement,
+}
+
+impl Hud {
+    /// Only the score and beat counter, used when the layout file is missing.
+    fn fallback(font: Font) -> Self {
+        let widget = |kind, placement| Widget {
+            kind,
+            placement,
+            size: 16.0,
+            width: 0.0,
+            color: WHITE,
+            font,
+        };
+
+        Self {
+            font,
+            widgets: vec![
+                widget(WidgetKind::Score, Placement::new(Anchor::TopLeft, 8.0, 0.0)),
+                widget(
+                    WidgetKind::Beat,
+                    Placement::new(Anchor::TopRight, -8.0, 0.0),
+                ),
+            ],
+            practice: Placement::new(Anchor::TopLeft, 8.0, 19.2),
+            review: Placement::new(Anchor::Bottom, 0.0, -54.0),
+            prompt: Placement::new(Anchor::Center, 0.0, 90.0),
+            hearts: Placement::new(Anchor::BottomLeft, 8.0, -8.0),
+            boss: Placement::new(Anchor::Top, 0.0, 10.0),
+            drift: Placement::new(Anchor::TopRight, -8.0, 34.0),
+            pickups: Placement::new(Anchor::BottomRight, -8.0, -28.0),
+            meter: Placement::new(Anchor::Bottom, 0.0, -8.0),
+            banner: Placement::new(Anchor::Top, 0.0, 40.0),
+            aggro: Placement::new(Anchor::TopLeft, 8.0, 38.4),
+            streamer_judgment: Placement::new(Anchor::Center, 0.0, 60.0),
+            streamer_keys: Placement::new(Anchor::BottomLeft, 8.0, -32.0),
+            streamer_stats: Placement::new(Anchor::TopLeft, 8.0, 58.0),
+        }
+    }
+
+    /// Loads the hud layout at `path`, falling back to the score and beat counter if the file is
+    /// missing or invalid. Text is drawn with `font` where the layout doesn't pick one.
+    pub async fn load(path: &str, font: Font) -> Self {
+        let config = match load_text(path.as_ref())
+            .await
+            .map(|source| Config::parse(&source))
+        {
+            Ok(Ok(config)) => config,
+            Ok(Err(err)) => {
+                log_warn!("{}: {}", path, err);
+
+                return Self::fallback(font);
+            }
+            Err(_) => return Self::fallback(font),
+        };
+
+        let fallback = Self::fallback(font);
+        let has_section = |section: &str| {
+            let prefix = format!("{}.", section);
+
+            config
+                .values
+                .iter()
+                .any(|(key, _)| key.starts_with(&prefix))
+        };
+
+        // fonts are loaded once, however many widgets use them
+        let mut fonts: Vec<(String, Font)> = Vec::new();
+        let mut widgets = Vec::new();
+
+        for (kind, section) in WidgetKind::ALL {
+            if !has_section(section) {
+                continue;
+            }
+
+            let key = |field: &str| format!("{}.{}", section, field);
+
+            let widget_font = match config.get_str(&key("font")) {
+                Some(path) if !path.is_empty() => {
+                    match fonts.iter().find(|(loaded, _)| loaded == path) {
+                        Some((_, font)) => Some(*font),
+                        None => {
+                            let loaded = load_optional_font(path.as_ref()).await;
+
+                            if let Some(loaded) = loaded {
+                                fonts.push((path.to_string(), loaded));
+                            }
+
+                            loaded
+                        }
+                    }
+                }
+                _ => None,
+            };
+
+            widgets.push(Widget {
+                kind,
+                placement: Placement::new(Anchor::TopLeft, 0.0, 0.0).read(&config, section),
+                size: config.get_f32(&key("size")).unwrap_or(16.0),
+                width: config.get_f32(&key("width")).unwrap_or(128.0),
+                color: config
+                    .get_str(&key("color"))
+                    .and_then(parse_color)
+                    .unwrap_or(WHITE),
+                font: widget_font.unwrap_or(font),
+            });
+        }
+
+        Self {
+            widgets,
+            font,
+            practice: fallback.practice.read(&config, "practice"),
+            review: fallback.review.read(&config, "review"),
+            prompt: fallback.prompt.read(&config, "prompt"),
+            hearts: fallback.hearts.read(&config, "hearts"),
+            boss: fallback.boss.read(&config, "boss"),
+            drift: fallback.drift.read(&config, "drift"),
+            pickups: fallback.pickups.read(&config, "pickups"),
+            meter: fallback.meter.read(&config, "meter"),
+            banner: fallback.banner.read(&config, "banner"),
+            aggro: fallback.aggro.read(&config, "aggro"),
+            streamer_judgment: fallback
+                .streamer_judgment
+                .read(&config, "streamer_judgment"),
+            streamer_keys: fallback.streamer_keys.read(&config, "streamer_keys"),
+            streamer_stats: fallback.streamer_stats.read(&config, "streamer_stats"),
+        }
+    }
+
+    /// Draws the widgets, but not those of the kinds in `hidden`.
+    pub fn draw(&self, values: &HudValues, hidden: &[WidgetKind], locale: &Locale, ui_scale: f32) {
+        let scale = canvas::scale() * ui_scale;
+
+        for widget in self
+            .widgets
+            .iter()
+            .filter(|widget| !hidden.contains(&widget.kind))
+        {
+            let size = widget.size * scale;
+
+            let text = match widget.kind {
+                WidgetKind::Score => {
+                    locale.format("hud-score", &[("score", &values.score.round())])
+                }
+                WidgetKind::Combo => format!("{}x", values.combo),
+                WidgetKind::Accuracy => match values.accuracy {
+                    Some(accuracy) => format!("{:.1}%", accuracy * 100.0),
+                    None => "-".to_string(),
+                },
+                WidgetKind::Judgments => {
+                    let [perfect, great, late, miss] = values.judgments;
+
+                    locale.format(
+                        "hud-judgments",
+                        &[
+                            ("perfect", &perfect),
+                            ("great", &great),
+                            ("late", &late),
+                            ("miss", &miss),
+                        ],
+                    )
+                }
+                WidgetKind::Beat => values.beat.clone(),
+                WidgetKind::Progress => {
+                    let bar = vec2(widget.width * scale, size);
+                    let corner = widget.placement.place(bar, ui_scale);
+                    let color = widget.color;
+
+                    draw_rectangle(
+                        corner.x,
+                        corner.y,
+                        bar.x,
+                        bar.y,
+                        Color::new(color.r, color.g, color.b, color.a * 0.3),
+                    );
+                    draw_rectangle(
+                        corner.x,
+                        corner.y,
+                        bar.x * values.progress.clamp(0.0, 1.0),
+                        bar.y,
+                        color,
+                    );
+
+                    continue;
+                }
+            };
+
+            draw_lines(&text, widget, size, ui_scale);
+        }
+    }
+}
+
+/// Draws the lines of `text` as one block placed by the widget, each line aligned to the side
+/// of the block the widget is anchored to.
+fn draw_lines(text: &str, widget: &Widget, font_size: f32, ui_scale: f32) {
+    let line_height = font_size * 1.2;
+    let lines: Vec<&str> = text.lines().collect();
+    let widths: Vec<f32> = lines
+        .iter()
+        .map(|line| measure_text(line, Some(widget.font), font_size as u16, 1.0).width)
+        .collect();
+
+    let block = vec2(
+        widths.iter().copied().fold(0.0, f32::max),
+        font_size + line_height * (lines.len() as f32 - 1.0),
+    );
+    let corner = widget.placement.place(block, ui_scale);
+    let align = widget.placement.anchor.factors().x;
+
+    for (index, (line, width)) in lines.iter().zip(&widths).enumerate() {
+        draw_outlined_text(
+            line,
+            corner.x + (block.x - width) * align,
+            corner.y + font_size + line_height * index as f32,
+            TextParams {
+                font: widget.font,
+                font_size: font_size as u16,
+                color: widget.color,
+                ..Default::default()
+            },
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use crate::canvas;
+use crate::game::Direction;
+use macroquad::prelude::*;
+
+/// How early a press can be, in seconds before a projectile reaches the shield, and still
+/// block it.
+pub const BUFFER_WINDOW: f32 = 0.1;
+
+/// Keys that can be bound to actions, with the names they're saved under.
+pub const BINDABLE_KEYS: &[(&str, KeyCode)] = &[
+    ("A", KeyCode::A),
+    ("B", KeyCode::B),
+    ("C", KeyCode::C),
+    ("D", KeyCode::D),
+    ("E", KeyCode::E),
+    ("F", KeyCode::F),
+    ("G", KeyCode::G),
+    ("H", KeyCode::H),
+    ("I", KeyCode::I),
+    ("J", KeyCode::J),
+    ("K", KeyCode::K),
+    ("L", KeyCode::L),
+    ("M", KeyCode::M),
+    ("N", KeyCode::N),
+    ("O", KeyCode::O),
+    ("P", KeyCode::P),
+    ("Q", KeyCode::Q),
+    ("R", KeyCode::R),
+    ("S", KeyCode::S),
+    ("T", KeyCode::T),
+    ("U", KeyCode::U),
+    ("V", KeyCode::V),
+    ("W", KeyCode::W),
+    ("X", KeyCode::X),
+    ("Y", KeyCode::Y),
+    ("Z", KeyCode::Z),
+    ("0", KeyCode::Key0),
+    ("1", KeyCode::Key1),
+    ("2", KeyCode::Key2),
+    ("3", KeyCode::Key3),
+    ("4", KeyCode::Key4),
+    ("5", KeyCode::Key5),
+    ("6", KeyCode::Key6),
+    ("7", KeyCode::Key7),
+    ("8", KeyCode::Key8),
+    ("9", KeyCode::Key9),
+    ("F1", KeyCode::F1),
+    ("F2", KeyCode::F2),
+    ("F3", KeyCode::F3),
+    ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5),
+    ("F6", KeyCode::F6),
+    ("F7", KeyCode::F7),
+    ("F8", KeyCode::F8),
+    ("F9", KeyCode::F9),
+    ("F10", KeyCode::F10),
+    ("F11", KeyCode::F11),
+    ("F12", KeyCode::F12),
+    ("Space", KeyCode::Space),
+    ("Enter", KeyCode::Enter),
+    ("Backspace", KeyCode::Backspace),
+    ("Tab", KeyCode::Tab),
+    ("Delete", KeyCode::Delete),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+];
+
+pub fn key_name(key: KeyCode) -> &'static str {
+    BINDABLE_KEYS
+        .iter()
+        .find(|(_, k)| *k == key)
+        .map_or("?", |(name, _)| name)
+}
+
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, key)| *key)
+}
+
+/// A press raising the shield of `lane` towards `direction` at `time`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Press {
+    pub lane: usize,
+    pub direction: Direction,
+    pub time: f32,
+}
+
+/// Recent shield presses with the time they happened, so blocks are judged by when the player
+/// pressed rather than where the shield happens to be pointing.
+pub struct InputBuffer {
+    presses: Vec<Press>,
+}
+
+impl InputBuffer {
+    pub fn new() -> Self {
+        Self {
+            presses: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, press: Press) {
+        self.presses.push(press);
+    }
+
+    /// Forgets presses too old to block anything at `time`.
+    pub fn expire(&mut self, time: f32) {
+        self.presses
+            .retain(|press| press.time >= time - BUFFER_WINDOW);
+    }
+
+    /// Consumes the earliest press of `direction` on `lane` that can block a projectile arriving
+    /// at `arrival_time`, returning when it was pressed.
+    pub fn take(&mut self, lane: usize, direction: &Direction, arrival_time: f32) -> Option<f32> {
+        let index = self.presses.iter().position(|press| {
+            press.lane == lane
+                && press.direction == *direction
+                && press.time >= arrival_time - BUFFER_WINDOW
+        })?;
+
+        Some(self.presses.remove(index).time)
+    }
+}
+
+/// Whether any key, mouse button or touch was pressed this frame.
+pub fn any_pressed() -> bool {
+    get_last_key_pressed().is_some()
+        || [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+            .iter()
+            .any(|button| is_mouse_button_pressed(*button))
+        || touches()
+            .iter()
+            .any(|touch| touch.phase == TouchPhase::Started)
+}
+
+/// Screen pixels, before the canvas scale, a touch has to move to count as a swipe.
+const SWIPE_DISTANCE: f32 = 24.0;
+
+/// What touches did this frame.
+#[derive(Default)]
+pub struct TouchInput {
+    /// Lanes and directions of the shields raised.
+    pub presses: Vec<(usize, Direction)>,
+    pub pause: bool,
+}
+
+/// Turns touches into shield presses. Tapping raises the shield towards the tapped side of the
+/// screen and swiping raises it in the direction of the swipe. With several lanes the screen is
+/// split between them, a touch raising the shield of the lane it starts in.
+pub struct TouchControls {
+    /// Touches being tracked with where they started and whether they've swiped yet.
+    touches: Vec<(u64, Vec2, bool)>,
+    /// Whether the screen has been touched, touch only controls are hidden until then.
+    pub used: bool,
+}
+
+impl TouchControls {
+    pub fn new() -> Self {
+        Self {
+            touches: Vec::new(),
+            used: false,
+        }
+    }
+
+    /// Reads this frame's touches on a level with `lanes` lanes, a tap inside `pause_button`
+    /// pauses instead of raising a shield.
+    pub fn update(&mut self, pause_button: Rect, lanes: usize) -> TouchInput {
+        let mut input = TouchInput::default();
+        let touches = touches();
+
+        self.touches
+            .retain(|(id, _, _)| touches.iter().any(|touch| touch.id == *id));
+
+        for touch in touches {
+            match touch.phase {
+                TouchPhase::Started => {
+                    self.used = true;
+
+                    if pause_button.contains(touch.position) {
+                        input.pause = true;
+                        self.touches.push((touch.id, touch.position, true));
+                    } else {
+                        let lane = lane_of(touch.position, lanes);
+                        let width = screen_width() / lanes as f32;
+                        let center = vec2(width * (lane as f32 + 0.5), screen_height() / 2.0);
+
+                        input
+                            .presses
+                            .push((lane, direction_of(touch.position - center)));
+                        self.touches.push((touch.id, touch.position, false));
+                    }
+                }
+                TouchPhase::Moved => {
+                    let tracked = self.touches.iter_mut().find(|(id, _, _)| *id == touch.id);
+
+                    if let Some((_, start, swiped)) = tracked {
+                        let offset = touch.position - *start;
+
+                        if !*swiped && offset.length() > SWIPE_DISTANCE * canvas::scale() {
+                            input
+                                .presses
+                                .push((lane_of(*start, lanes), direction_of(offset)));
+                            *swiped = true;
+                        }
+                    }
+                }
+                TouchPhase::Ended | TouchPhase::Cancelled => {
+                    self.touches.retain(|(id, _, _)| *id != touch.id);
+                }
+                TouchPhase::Stationary => {}
+            }
+        }
+
+        input
+    }
+}
+
+/// The lane of the part of the screen `position` is in, the screen split evenly between `lanes`.
+fn lane_of(position: Vec2, lanes: usize) -> usize {
+    let lane = (position.x / screen_width() * lanes as f32).max(0.0) as usize;
+
+    lane.min(lanes - 1)
+}
+
+/// The direction `offset` mostly points in, in screen space.
+fn direction_of(offset: Vec2) -> Direction {
+    if offset.x.abs() > offset.y.abs() {
+        if offset.x > 0.0 {
+            Direction::Right
+        } else {
+            Direction::Left
+        }
+    } else if offset.y > 0.0 {
+        Direction::Down
+    } else {
+        Direction::Up
+    }
+}
@@ -0,0 +1,63 @@
+//! Checksums tying score records to the chart and modifiers they were set with.
+//!
+//! They aren't secret, so they keep out edited charts and hand edited score files rather than a
+//! determined cheater.
+
+/// A 64 bit FNV-1a hash built up piece by piece, the same on every platform and run.
+pub struct Digest {
+    hash: u64,
+}
+
+impl Digest {
+    pub fn new() -> Self {
+        Self {
+            hash: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash = (self.hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    pub fn number(&mut self, number: f32) {
+        self.bytes(&number.to_bits().to_le_bytes());
+    }
+
+    pub fn integer(&mut self, integer: u32) {
+        self.bytes(&integer.to_le_bytes());
+    }
+
+    /// Adds `number` with a marker of whether it's set, so a missing value and a value of 0
+    /// hash differently.
+    pub fn optional(&mut self, number: Option<f32>) {
+        match number {
+            Some(number) => {
+                self.bytes(&[1]);
+                self.number(number);
+            }
+            None => self.bytes(&[0]),
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+pub fn checksum(bytes: &[u8]) -> u64 {
+    let mut digest = Digest::new();
+    digest.bytes(bytes);
+
+    digest.finish()
+}
+
+/// Writes a hash the way score files store them, since config numbers can't hold 64 bits.
+pub fn to_hex(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+pub fn from_hex(source: &str) -> Option<u64> {
+    u64::from_str_radix(source, 16).ok()
+}
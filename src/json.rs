@@ -0,0 +1,440 @@
+//! Enough json to trade sheets with tools written in other languages, see
+//! [`Sheet::to_json`](crate::sheet::Sheet::to_json).
+//!
+//! Numbers are kept as `f32` like everywhere else in the game, and objects keep their keys in the
+//! order they were written. Arrays and objects nest at most [`MAX_DEPTH`] deep, sheets don't need
+//! more and parsing deeper ones would run out of stack.
+
+/// Most arrays and objects nested in each other, counting the outermost.
+pub const MAX_DEPTH: usize = 64;
+
+#[derive(Debug)]
+pub enum JsonError {
+    /// A character that can't be where it is, with its byte offset.
+    UnexpectedChar(usize, char),
+    /// A number or escape that doesn't read as one, with its byte offset.
+    Invalid(usize, String),
+    UnexpectedEof,
+    /// An array or object nested more than [`MAX_DEPTH`] deep, with its byte offset.
+    TooDeep(usize),
+}
+
+impl std::fmt::Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedChar(offset, c) => {
+                write!(f, "unexpected '{}' at byte {}", c.escape_default(), offset)
+            }
+            Self::Invalid(offset, source) => write!(f, "invalid '{}' at byte {}", source, offset),
+            Self::UnexpectedEof => write!(f, "unexpected end of file"),
+            Self::TooDeep(offset) => {
+                write!(f, "nested more than {} deep at byte {}", MAX_DEPTH, offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+pub type Result<T> = std::result::Result<T, JsonError>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f32),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut parser = Parser {
+            source,
+            offset: 0,
+            depth: 0,
+        };
+
+        let json = parser.value()?;
+        parser.skip_whitespace();
+
+        match parser.peek() {
+            Some(c) => Err(JsonError::UnexpectedChar(parser.offset, c)),
+            None => Ok(json),
+        }
+    }
+
+    pub fn object<'a>(pairs: impl IntoIterator<Item = (&'a str, Json)>) -> Self {
+        Self::Object(
+            pairs
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    /// The value under `key` if this is an object that has it.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Self::Object(pairs) => pairs
+                .iter()
+                .find(|(other, _)| other == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Self::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Self::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Whether the value fits on one line, which only arrays and objects of numbers, strings
+    /// and the like do.
+    fn is_flat(&self) -> bool {
+        match self {
+            Self::Array(values) => values.iter().all(Self::is_scalar),
+            Self::Object(pairs) => pairs.iter().all(|(_, value)| value.is_scalar()),
+            _ => true,
+        }
+    }
+
+    fn is_scalar(&self) -> bool {
+        match self {
+            Self::Array(values) => values.is_empty(),
+            Self::Object(pairs) => pairs.is_empty(),
+            _ => true,
+        }
+    }
+
+    fn write(&self, f: &mut std::fmt::Formatter<'_>, indent: usize) -> std::fmt::Result {
+        // the other lines of a long array or object go in a level further
+        let inner = "  ".repeat(indent + 1);
+        let flat = self.is_flat();
+        let separator = if flat { ", " } else { ",\n" };
+
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(value) => write!(f, "{}", value),
+            // json has no infinities or nans
+            Self::Number(value) if !value.is_finite() => write!(f, "null"),
+            // whole numbers without the `.0`, so counts read as counts
+            Self::Number(value) if value.fract() == 0.0 && value.abs() < 1e9 => {
+                write!(f, "{}", *value as i64)
+            }
+            Self::Number(value) => write!(f, "{:?}", value),
+            Self::String(value) => write_string(f, value),
+            Self::Array(values) => {
+                write!(f, "[")?;
+
+                if !flat {
+                    write!(f, "\n{}", inner)?;
+                }
+
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "{}", separator)?;
+
+                        if !flat {
+                            write!(f, "{}", inner)?;
+                        }
+                    }
+
+                    value.write(f, indent + 1)?;
+                }
+
+                if !flat {
+                    write!(f, "\n{}", "  ".repeat(indent))?;
+                }
+
+                write!(f, "]")
+            }
+            Self::Object(pairs) => {
+                write!(f, "{{")?;
+
+                if !flat {
+                    write!(f, "\n{}", inner)?;
+                }
+
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "{}", separator)?;
+
+                        if !flat {
+                            write!(f, "{}", inner)?;
+                        }
+                    }
+
+                    write_string(f, key)?;
+                    write!(f, ": ")?;
+                    value.write(f, indent + 1)?;
+                }
+
+                if !flat {
+                    write!(f, "\n{}", "  ".repeat(indent))?;
+                }
+
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// Written with the arrays and objects that don't fit on a line spread over several.
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, value: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+
+    write!(f, "\"")
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    /// Byte offset of the next character.
+    offset: usize,
+    /// Arrays and objects the next value is in.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.source[self.offset..].chars().next()
+    }
+
+    fn next(&mut self) -> Result<char> {
+        let c = self.peek().ok_or(JsonError::UnexpectedEof)?;
+        self.offset += c.len_utf8();
+
+        Ok(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self
+            .peek()
+            .filter(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+        {
+            self.offset += c.len_utf8();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        let offset = self.offset;
+
+        match self.next()? {
+            c if c == expected => Ok(()),
+            c => Err(JsonError::UnexpectedChar(offset, c)),
+        }
+    }
+
+    /// Takes `word` if it's next, failing on its first character that isn't.
+    fn keyword(&mut self, word: &str) -> Result<()> {
+        word.chars().try_for_each(|c| self.expect(c))
+    }
+
+    fn value(&mut self) -> Result<Json> {
+        self.skip_whitespace();
+
+        match self.peek().ok_or(JsonError::UnexpectedEof)? {
+            'n' => self.keyword("null").map(|_| Json::Null),
+            't' => self.keyword("true").map(|_| Json::Bool(true)),
+            'f' => self.keyword("false").map(|_| Json::Bool(false)),
+            '"' => self.string().map(Json::String),
+            '[' => self.nested(Self::array),
+            '{' => self.nested(Self::object),
+            '-' | '0'..='9' => self.number(),
+            c => Err(JsonError::UnexpectedChar(self.offset, c)),
+        }
+    }
+
+    /// Parses an array or object with `parse`, one deeper than the value it's in.
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Json>) -> Result<Json> {
+        if self.depth == MAX_DEPTH {
+            return Err(JsonError::TooDeep(self.offset));
+        }
+
+        self.depth += 1;
+        let json = parse(self);
+        self.depth -= 1;
+
+        json
+    }
+
+    fn number(&mut self) -> Result<Json> {
+        let start = self.offset;
+
+        while let Some(c) = self
+            .peek()
+            .filter(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.offset += c.len_utf8();
+        }
+
+        let source = &self.source[start..self.offset];
+
+        source
+            .parse()
+            .map(Json::Number)
+            .map_err(|_| JsonError::Invalid(start, source.to_string()))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.expect('"')?;
+
+        let mut string = String::new();
+
+        loop {
+            match self.next()? {
+                '"' => return Ok(string),
+                '\\' => {
+                    let offset = self.offset;
+
+                    match self.next()? {
+                        '"' => string.push('"'),
+                        '\\' => string.push('\\'),
+                        '/' => string.push('/'),
+                        'b' => string.push('\u{8}'),
+                        'f' => string.push('\u{c}'),
+                        'n' => string.push('\n'),
+                        'r' => string.push('\r'),
+                        't' => string.push('\t'),
+                        'u' => string.push(self.unicode_escape(offset)?),
+                        c => return Err(JsonError::UnexpectedChar(offset, c)),
+                    }
+                }
+                c => string.push(c),
+            }
+        }
+    }
+
+    /// The character of a `\u` escape whose `u` is at `offset`, taking the second half of a
+    /// surrogate pair too if it's one.
+    fn unicode_escape(&mut self, offset: usize) -> Result<char> {
+        let first = self.hex()?;
+
+        let code = if (0xd800..0xdc00).contains(&first) {
+            self.keyword("\\u")?;
+            let second = self.hex()?;
+
+            if !(0xdc00..0xe000).contains(&second) {
+                return Err(JsonError::Invalid(
+                    offset,
+                    self.source[offset..self.offset].to_string(),
+                ));
+            }
+
+            0x10000 + ((first - 0xd800) << 10) + (second - 0xdc00)
+        } else {
+            first
+        };
+
+        char::from_u32(code)
+            .ok_or_else(|| JsonError::Invalid(offset, self.source[offset..self.offset].to_string()))
+    }
+
+    fn hex(&mut self) -> Result<u32> {
+        let start = self.offset;
+        let source = self
+            .source
+            .get(start..start + 4)
+            .ok_or(JsonError::UnexpectedEof)?;
+
+        self.offset += 4;
+
+        u32::from_str_radix(source, 16).map_err(|_| JsonError::Invalid(start, source.to_string()))
+    }
+
+    fn array(&mut self) -> Result<Json> {
+        self.expect('[')?;
+        self.skip_whitespace();
+
+        let mut values = Vec::new();
+
+        if self.peek() == Some(']') {
+            self.offset += 1;
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+
+            let offset = self.offset;
+
+            match self.next()? {
+                ',' => {}
+                ']' => return Ok(Json::Array(values)),
+                c => return Err(JsonError::UnexpectedChar(offset, c)),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json> {
+        self.expect('{')?;
+        self.skip_whitespace();
+
+        let mut pairs = Vec::new();
+
+        if self.peek() == Some('}') {
+            self.offset += 1;
+            return Ok(Json::Object(pairs));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            pairs.push((key, self.value()?));
+            self.skip_whitespace();
+
+            let offset = self.offset;
+
+            match self.next()? {
+                ',' => {}
+                '}' => return Ok(Json::Object(pairs)),
+                c => return Err(JsonError::UnexpectedChar(offset, c)),
+            }
+        }
+    }
+}
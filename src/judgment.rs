@@ -0,0 +1,114 @@
+/// How well a projectile was handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Judgment {
+    Perfect,
+    Great,
+    Late,
+    Miss,
+}
+
+/// How far off a block can be, in seconds from when the projectile reached the shield, for each
+/// judgment.
+#[derive(Clone, Debug)]
+pub struct TimingWindows {
+    pub perfect: f32,
+    pub great: f32,
+}
+
+impl Default for TimingWindows {
+    fn default() -> Self {
+        Self {
+            perfect: 0.035,
+            great: 0.08,
+        }
+    }
+}
+
+impl TimingWindows {
+    /// Judges a block `offset` seconds after the projectile reached the shield, negative when
+    /// early.
+    pub fn judge(&self, offset: f32) -> Judgment {
+        let offset = offset.abs();
+
+        if offset <= self.perfect {
+            Judgment::Perfect
+        } else if offset <= self.great {
+            Judgment::Great
+        } else {
+            Judgment::Late
+        }
+    }
+}
+
+/// Points a block is worth for each judgment.
+#[derive(Clone, Debug)]
+pub struct ScoreWeights {
+    pub perfect: u32,
+    pub great: u32,
+    pub late: u32,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        Self {
+            perfect: 1,
+            great: 1,
+            late: 1,
+        }
+    }
+}
+
+impl ScoreWeights {
+    pub fn points(&self, judgment: Judgment) -> u32 {
+        match judgment {
+            Judgment::Perfect => self.perfect,
+            Judgment::Great => self.great,
+            Judgment::Late => self.late,
+            Judgment::Miss => 0,
+        }
+    }
+}
+
+/// Letter grade of a run, from how well its projectiles were handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grade {
+    S,
+    A,
+    B,
+    C,
+    D,
+}
+
+impl Grade {
+    /// Grade of a run with `judgments` perfects, greats, lates and misses, `None` if nothing
+    /// arrived. Perfects are worth all of a projectile, greats and lates less, and only runs
+    /// without a miss get an S.
+    pub fn of(judgments: [u32; 4]) -> Option<Self> {
+        let [perfect, great, late, miss] = judgments;
+        let total = perfect + great + late + miss;
+
+        if total == 0 {
+            return None;
+        }
+
+        let worth = (perfect as f32 + great as f32 * 0.75 + late as f32 * 0.4) / total as f32;
+
+        Some(match worth {
+            _ if worth >= 0.95 && miss == 0 => Self::S,
+            _ if worth >= 0.9 => Self::A,
+            _ if worth >= 0.8 => Self::B,
+            _ if worth >= 0.65 => Self::C,
+            _ => Self::D,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::S => "S",
+            Self::A => "A",
+            Self::B => "B",
+            Self::C => "C",
+            Self::D => "D",
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! The game as a library, for tools and tests that want to parse sheets or drive the game
+//! without the window the binary opens.
+
+// most of the game state is built with `new` on starting a level, defaults would mean nothing
+#![allow(clippy::new_without_default)]
+
+pub mod aggro;
+pub mod asset_source;
+pub mod atlas;
+pub mod audio;
+pub mod audio_engine;
+pub mod bench;
+pub mod boss;
+pub mod camera;
+pub mod canvas;
+pub mod capture;
+pub mod cli;
+pub mod config;
+pub mod course;
+pub mod crash;
+pub mod debug_overlay;
+pub mod density;
+pub mod effects;
+pub mod embedded;
+pub mod end_menu;
+pub mod events;
+pub mod font;
+pub mod frame_limit;
+pub mod fuzz;
+pub mod game;
+pub mod generators;
+pub mod gpu_particles;
+pub mod health;
+pub mod hud;
+pub mod input;
+pub mod integrity;
+pub mod json;
+pub mod judgment;
+pub mod library;
+pub mod lint;
+pub mod locale;
+pub mod log;
+pub mod main_menu;
+pub mod manifest;
+pub mod meter;
+pub mod modifiers;
+pub mod momentum;
+pub mod outline;
+pub mod palette;
+pub mod particles;
+pub mod pause_menu;
+pub mod pickups;
+pub mod playlist;
+pub mod post;
+pub mod practice;
+pub mod profile;
+pub mod projectiles;
+pub mod replay;
+pub mod review;
+pub mod run;
+pub mod scores;
+pub mod script;
+pub mod settings;
+pub mod setup;
+pub mod sheet;
+pub mod spectrum;
+pub mod stats;
+pub mod streamer;
+pub mod sync;
+pub mod tutorial;
+pub mod uniforms;
+pub mod verify;
+pub mod video;
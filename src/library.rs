@@ -0,0 +1,99 @@
+//! What a profile has done with the levels beyond scoring them: when it last played each and
+//! which ones it starred, for the Recent and Favorites tabs of the menu. Kept in the profile's
+//! `library.toml`:
+//!
+//! ```toml
+//! played.Some level = "1619805900"
+//! favorites.Some level = true
+//! ```
+
+use crate::config::{Config, Value};
+use crate::log_error;
+use crate::profile::Profile;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Default)]
+pub struct Library {
+    /// Folder names of the starred levels.
+    favorites: Vec<String>,
+    /// Folder names of the levels played, with the date they were last played at in seconds since
+    /// the unix epoch.
+    played: Vec<(String, u64)>,
+}
+
+impl Library {
+    fn path(profile: &Profile) -> PathBuf {
+        profile.dir().join("library.toml")
+    }
+
+    /// Loads the library of `profile`, empty if it has no library file or it's invalid.
+    pub fn load(profile: &Profile) -> Self {
+        let config = profile
+            .read(&Self::path(profile))
+            .and_then(|source| Config::parse(&source).ok())
+            .unwrap_or_default();
+
+        let mut library = Self::default();
+
+        for (key, value) in &config.values {
+            // level names can have dots in them, so only the section is split off
+            if let Some(level) = key.strip_prefix("played.") {
+                if let Some(date) = value.as_str().and_then(|date| date.parse().ok()) {
+                    library.played.push((level.to_string(), date));
+                }
+            } else if let Some(level) = key.strip_prefix("favorites.") {
+                if value.as_bool() == Some(true) {
+                    library.favorites.push(level.to_string());
+                }
+            }
+        }
+
+        library
+    }
+
+    pub fn save(&self, profile: &Profile) {
+        let mut config = Config::new();
+
+        // dates don't fit in a number without losing the seconds
+        for (level, date) in &self.played {
+            config.set(format!("played.{}", level), Value::String(date.to_string()));
+        }
+
+        for level in &self.favorites {
+            config.set(format!("favorites.{}", level), Value::Bool(true));
+        }
+
+        if let Err(err) = profile.write(&Self::path(profile), &config.to_string()) {
+            log_error!("failed to save library: {}", err);
+        }
+    }
+
+    pub fn is_favorite(&self, level: &str) -> bool {
+        self.favorites.iter().any(|name| name == level)
+    }
+
+    /// Stars `level`, or unstars it if it already is.
+    pub fn toggle_favorite(&mut self, level: &str) {
+        match self.favorites.iter().position(|name| name == level) {
+            Some(index) => {
+                self.favorites.remove(index);
+            }
+            None => self.favorites.push(level.to_string()),
+        }
+    }
+
+    /// When `level` was last played, `None` if it never was.
+    pub fn last_played(&self, level: &str) -> Option<u64> {
+        self.played
+            .iter()
+            .find(|(name, _)| name == level)
+            .map(|(_, date)| *date)
+    }
+
+    pub fn played(&mut self, level: &str, date: u64) {
+        match self.played.iter_mut().find(|(name, _)| name == level) {
+            Some((_, last)) => *last = date,
+            None => self.played.push((level.to_string(), date)),
+        }
+    }
+}
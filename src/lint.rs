@@ -0,0 +1,233 @@
+//! Warnings about sheets that load fine but probably don't play the way they were meant to, shown
+//! by `--check` and by checking a level in the menu. None of them stop a sheet from being played.
+
+use crate::game::Direction;
+use crate::sheet::Sheet;
+
+/// Projectiles arriving within a second past which a burst is warned about.
+pub const BURST_NPS: usize = 12;
+
+/// Seconds apart projectiles from the same side can arrive and still be told apart.
+pub const OVERLAP: f32 = 0.01;
+
+/// Seconds the player needs to move the shield to another side, turns closer together than it
+/// can't be made.
+pub const MIN_TURN: f32 = 0.05;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Warning {
+    /// Projectiles from the same side arriving together, the later one hidden under the first.
+    Overlap {
+        time: f32,
+        direction: Direction,
+        /// The lane on stage sheets, `None` on the others.
+        lane: Option<usize>,
+    },
+    /// A projectile arriving before the point `#offset` starts the chart at, or before the song
+    /// has started at all.
+    BeforeOffset { time: f32, offset: f32 },
+    /// More than [`BURST_NPS`] projectiles arriving within a second, from the first projectile
+    /// of the burst to the last.
+    Burst { start: f32, end: f32, peak: usize },
+    /// The shield having to turn to another side faster than [`MIN_TURN`].
+    Turn {
+        time: f32,
+        from: Direction,
+        to: Direction,
+        /// Seconds between the two projectiles.
+        gap: f32,
+        /// Beats between the two projectiles at the sheet's bpm.
+        beats: f32,
+    },
+}
+
+impl Warning {
+    /// When in the song the warning is about.
+    pub fn time(&self) -> f32 {
+        match self {
+            Self::Overlap { time, .. } => *time,
+            Self::BeforeOffset { time, .. } => *time,
+            Self::Burst { start, .. } => *start,
+            Self::Turn { time, .. } => *time,
+        }
+    }
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Overlap {
+                time,
+                direction,
+                lane,
+            } => {
+                write!(f, "{:.2}s: projectiles from {:?}", time, direction)?;
+
+                if let Some(lane) = lane {
+                    write!(f, " on lane {}", lane + 1)?;
+                }
+
+                write!(f, " arrive on top of each other")
+            }
+            Self::BeforeOffset { time, offset } if *time < 0.0 => write!(
+                f,
+                "{:.2}s: projectile arrives before the song starts, the offset being {:.2}s",
+                time, offset
+            ),
+            Self::BeforeOffset { time, offset } => write!(
+                f,
+                "{:.2}s: projectile arrives before the offset at {:.2}s",
+                time, offset
+            ),
+            Self::Burst { start, end, peak } => write!(
+                f,
+                "{:.2}s to {:.2}s: burst of up to {} projectiles a second, more than {}",
+                start, end, peak, BURST_NPS
+            ),
+            Self::Turn {
+                time,
+                from,
+                to,
+                gap,
+                beats,
+            } => write!(
+                f,
+                "{:.2}s: turning from {:?} to {:?} in {:.0}ms, {:.3} beats, is too fast to make",
+                time,
+                from,
+                to,
+                gap * 1000.0,
+                beats
+            ),
+        }
+    }
+}
+
+/// The warnings about `sheet`, in the order they come up in the song.
+pub fn lint(sheet: &Sheet) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    before_offset(sheet, &mut warnings);
+    overlaps(sheet, &mut warnings);
+    bursts(sheet, &mut warnings);
+    turns(sheet, &mut warnings);
+
+    warnings.sort_by(|a, b| a.time().total_cmp(&b.time()));
+
+    warnings
+}
+
+fn before_offset(sheet: &Sheet, warnings: &mut Vec<Warning>) {
+    // a negative offset starts the chart before the song
+    let start = sheet.start_offset.max(0.0);
+
+    for projectile in &sheet.projectiles {
+        if projectile.arrival_time < start {
+            warnings.push(Warning::BeforeOffset {
+                time: projectile.arrival_time,
+                offset: sheet.start_offset,
+            });
+        }
+    }
+}
+
+fn overlaps(sheet: &Sheet, warnings: &mut Vec<Warning>) {
+    for lane in 0..sheet.lanes() {
+        for direction in Direction::ALL {
+            let mut last = None;
+
+            for projectile in sheet
+                .projectiles
+                .iter()
+                .filter(|projectile| projectile.lane == lane && projectile.direction == direction)
+            {
+                if last.is_some_and(|last| projectile.arrival_time - last < OVERLAP) {
+                    warnings.push(Warning::Overlap {
+                        time: projectile.arrival_time,
+                        direction: direction.clone(),
+                        lane: sheet.stage.then_some(lane),
+                    });
+                }
+
+                last = Some(projectile.arrival_time);
+            }
+        }
+    }
+}
+
+fn bursts(sheet: &Sheet, warnings: &mut Vec<Warning>) {
+    let projectiles = &sheet.projectiles;
+    let mut start = 0;
+    // the start, end and peak of the burst going on
+    let mut burst: Option<(f32, f32, usize)> = None;
+
+    for (end, projectile) in projectiles.iter().enumerate() {
+        while projectiles[start].arrival_time <= projectile.arrival_time - 1.0 {
+            start += 1;
+        }
+
+        let count = end + 1 - start;
+
+        if count <= BURST_NPS {
+            continue;
+        }
+
+        let first = projectiles[start].arrival_time;
+
+        match &mut burst {
+            // windows overlapping the burst carry it on
+            Some((_, until, peak)) if first <= *until => {
+                *until = projectile.arrival_time;
+                *peak = (*peak).max(count);
+            }
+            _ => {
+                if let Some((start, end, peak)) = burst.take() {
+                    warnings.push(Warning::Burst { start, end, peak });
+                }
+
+                burst = Some((first, projectile.arrival_time, count));
+            }
+        }
+    }
+
+    if let Some((start, end, peak)) = burst {
+        warnings.push(Warning::Burst { start, end, peak });
+    }
+}
+
+fn turns(sheet: &Sheet, warnings: &mut Vec<Warning>) {
+    for lane in 0..sheet.lanes() {
+        // pickups can be let through, so they don't have to be turned to
+        let mut projectiles = sheet
+            .projectiles
+            .iter()
+            .filter(|projectile| projectile.lane == lane && !projectile.ty.behavior.is_pickup());
+
+        let mut last = match projectiles.next() {
+            Some(projectile) => projectile,
+            None => continue,
+        };
+        // projectiles from every side at once are warned about once
+        let mut warned = f32::NEG_INFINITY;
+
+        for projectile in projectiles {
+            let gap = projectile.arrival_time - last.arrival_time;
+
+            if projectile.direction != last.direction
+                && gap < MIN_TURN
+                && projectile.arrival_time - warned >= OVERLAP
+            {
+                warned = projectile.arrival_time;
+                warnings.push(Warning::Turn {
+                    time: projectile.arrival_time,
+                    from: last.direction.clone(),
+                    to: projectile.direction.clone(),
+                    gap,
+                    beats: gap * sheet.bpm / 60.0,
+                });
+            }
+
+            last = projectile;
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Translations of the text shown to players, one file per language in [`LANG_DIR`].
+//!
+//! Files use a small subset of fluent: `id = text` lines, indented lines continuing the text
+//! of the line before on a new line, `#` comments and `{ $name }` placeholders:
+//!
+//! ```text
+//! # the score on the hud
+//! hud-score = Score: { $score }
+//! tutorial-start = Projectiles fly at the heart from every side.
+//!     Press space to start
+//! ```
+//!
+//! Anything a translation leaves out falls back to english, which is built into the binary.
+
+pub const LANG_DIR: &str = "assets/lang";
+
+const ENGLISH: &str = include_str!("../assets/lang/en.ftl");
+
+#[derive(Clone, Debug, Default)]
+pub struct Locale {
+    strings: Vec<(String, String)>,
+}
+
+impl Locale {
+    pub fn parse(source: &str) -> Self {
+        let mut strings: Vec<(String, String)> = Vec::new();
+
+        for line in source.lines() {
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with(char::is_whitespace) {
+                if let Some((_, text)) = strings.last_mut() {
+                    text.push('\n');
+                    text.push_str(line.trim());
+                }
+
+                continue;
+            }
+
+            if let Some((id, text)) = line.split_once('=') {
+                strings.push((id.trim().to_string(), text.trim().to_string()));
+            }
+        }
+
+        Self { strings }
+    }
+
+    /// Loads `language` from [`LANG_DIR`] over english, just english if there's no such file.
+    pub fn load(language: &str) -> Self {
+        let mut locale = Self::parse(ENGLISH);
+        let path = format!("{}/{}.ftl", LANG_DIR, language);
+
+        if let Ok(source) = std::fs::read_to_string(path) {
+            for (id, text) in Self::parse(&source).strings {
+                match locale.strings.iter_mut().find(|(other, _)| *other == id) {
+                    Some((_, existing)) => *existing = text,
+                    None => locale.strings.push((id, text)),
+                }
+            }
+        }
+
+        locale
+    }
+
+    /// The text of `id`, or `id` itself if no language has it.
+    pub fn get<'a>(&'a self, id: &'a str) -> &'a str {
+        self.strings
+            .iter()
+            .find(|(other, _)| other == id)
+            .map_or(id, |(_, text)| text)
+    }
+
+    /// The text of `id` with its placeholders filled in from `args`.
+    pub fn format(&self, id: &str, args: &[(&str, &dyn std::fmt::Display)]) -> String {
+        let mut text = self.get(id).to_string();
+
+        for (name, value) in args {
+            let value = value.to_string();
+
+            text = text
+                .replace(&format!("{{ ${} }}", name), &value)
+                .replace(&format!("{{${}}}", name), &value);
+        }
+
+        text
+    }
+}
+
+/// Codes and names of the languages in [`LANG_DIR`], english first. Only english where there's
+/// no folder to list, like on the web.
+pub fn languages() -> Vec<(String, String)> {
+    let mut codes: Vec<String> = std::fs::read_dir(LANG_DIR)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|extension| extension == "ftl"))
+                .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+                .filter(|code| code != "en")
+                .collect()
+        })
+        .unwrap_or_default();
+
+    codes.sort();
+    codes.insert(0, "en".to_string());
+
+    codes
+        .into_iter()
+        .map(|code| {
+            let name = Locale::load(&code).get("language-name").to_string();
+
+            (code, name)
+        })
+        .collect()
+}
@@ -0,0 +1,135 @@
+//! Logging for diagnosing problems players run into, printed to stderr and kept in memory for
+//! the debug overlay and crash logs.
+//!
+//! Messages are logged with [`log_error!`](crate::log_error), [`log_warn!`](crate::log_warn),
+//! [`log_info!`](crate::log_info) and [`log_debug!`](crate::log_debug). Only messages at or
+//! above the level in the `SRG_LOG` environment variable are logged, `info` if it isn't set.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Lines kept in memory, older ones are dropped.
+pub const RECENT_LINES: usize = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    /// Things happening every frame or every file, too many to log by default.
+    Debug,
+}
+
+impl Level {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "error" => Some(Self::Error),
+            "warn" => Some(Self::Warn),
+            "info" => Some(Self::Info),
+            "debug" => Some(Self::Debug),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Error => "ERROR",
+            Self::Warn => "WARN",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG",
+        }
+    }
+}
+
+static LEVEL: AtomicUsize = AtomicUsize::new(Level::Info as usize);
+
+/// Seconds since the epoch logging started at, as bits, lines are stamped relative to it.
+static START: AtomicU64 = AtomicU64::new(0);
+
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Starts the clock lines are stamped with and reads the level from `SRG_LOG`.
+pub fn init() {
+    START.store(
+        macroquad::miniquad::date::now().to_bits(),
+        Ordering::Relaxed,
+    );
+
+    if let Ok(name) = std::env::var("SRG_LOG") {
+        match Level::parse(&name) {
+            Some(level) => set_level(level),
+            None => crate::log_warn!("unknown log level '{}'", name),
+        }
+    }
+}
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as usize, Ordering::Relaxed);
+}
+
+pub fn enabled(level: Level) -> bool {
+    level as usize <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Logs a message from `target`, used through the macros.
+pub fn log(level: Level, target: &str, message: std::fmt::Arguments) {
+    if !enabled(level) {
+        return;
+    }
+
+    let start = f64::from_bits(START.load(Ordering::Relaxed));
+    let line = format!(
+        "{:9.3} {:5} {}: {}",
+        macroquad::miniquad::date::now() - start,
+        level.name(),
+        target,
+        message
+    );
+
+    eprintln!("{}", line);
+
+    // still usable after a panic while logging, the crash log needs it most then
+    let mut recent = RECENT.lock().unwrap_or_else(|err| err.into_inner());
+
+    if recent.len() >= RECENT_LINES {
+        recent.pop_front();
+    }
+
+    recent.push_back(line);
+}
+
+/// The last [`RECENT_LINES`] lines logged, oldest first.
+pub fn recent() -> Vec<String> {
+    let recent = RECENT.lock().unwrap_or_else(|err| err.into_inner());
+
+    recent.iter().cloned().collect()
+}
+
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::Level::Error, module_path!(), format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::Level::Warn, module_path!(), format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::Level::Info, module_path!(), format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::Level::Debug, module_path!(), format_args!($($arg)+))
+    };
+}
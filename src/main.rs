@@ -1,38 +1,674 @@
-mod game;
-mod main_menu;
-mod particles;
-mod sheet;
-
-use game::*;
 use macroquad::prelude::*;
-use main_menu::*;
+use srg::capture::ClipRecorder;
+use srg::cli::{Args, USAGE};
+use srg::debug_overlay::DebugOverlay;
+use srg::end_menu::{EndAction, EndMenu};
+use srg::frame_limit::FrameLimiter;
+use srg::game::*;
+use srg::main_menu::*;
+use srg::modifiers::Modifiers;
+use srg::pause_menu::{PauseAction, PauseMenu};
+use srg::playlist::{Marathon, MarathonAction, MarathonResults};
+use srg::post::PostProcessing;
+use srg::profile::Profile;
+use srg::replay::Replay;
+use srg::scores::Scores;
+use srg::script::Script;
+use srg::settings::{set_fullscreen, Settings, SONG_OFFSET_STEP};
+use srg::setup::{Setup, SetupAction};
+use srg::sheet::{find_sheet, Sheet};
+use srg::tutorial::Tutorial;
+use srg::verify::verify_replay;
+use srg::{aggro, asset_source, capture, course, input, sync, tutorial};
+use srg::{log_error, log_info, log_warn};
+use std::path::{Path, PathBuf};
+
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "SRG".to_string(),
+        fullscreen: Settings::load(&Profile::current()).fullscreen,
+        high_dpi: true,
+        window_resizable: true,
+        ..Default::default()
+    }
+}
+
+fn main() {
+    srg::log::init();
+    srg::crash::install();
+
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if args.help {
+        println!("{}", USAGE);
+        return;
+    }
+
+    if args.check {
+        std::process::exit(check(&args));
+    }
+
+    if let Some(replay) = &args.verify {
+        std::process::exit(verify(&args, replay));
+    }
+
+    macroquad::Window::from_config(window_conf(), run(args));
+}
+
+/// Parses the sheet of the level in `args`, returning the exit code.
+fn check(args: &Args) -> i32 {
+    let path = find_sheet(args.level.as_ref().unwrap(), args.difficulty.as_deref());
+
+    let source = match std::fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            return 1;
+        }
+    };
+
+    match Sheet::parse_file(&path, &source) {
+        Ok(sheet) => {
+            println!(
+                "{}: ok, {} projectiles at {} bpm, hash {}",
+                path.display(),
+                sheet.projectiles.len(),
+                sheet.bpm,
+                srg::integrity::to_hex(sheet.hash)
+            );
+
+            for warning in srg::lint::lint(&sheet) {
+                println!("{}: warning: {}", path.display(), warning);
+            }
+
+            0
+        }
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+
+            1
+        }
+    }
+}
+
+/// Plays `replay` back on the level in `args`, returning the exit code.
+fn verify(args: &Args, replay: &std::path::Path) -> i32 {
+    let level = args.level.as_ref().unwrap();
+    let path = find_sheet(level, args.difficulty.as_deref());
+
+    let sheet = match std::fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|source| Sheet::parse_file(&path, &source).map_err(|err| err.to_string()))
+    {
+        Ok(sheet) => sheet,
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            return 1;
+        }
+    };
+
+    let script = match std::fs::read_to_string(level.join("script.txt")) {
+        Ok(source) => match Script::parse(&source) {
+            Ok(script) => script,
+            Err(err) => {
+                eprintln!("{}: {}", level.join("script.txt").display(), err);
+                return 1;
+            }
+        },
+        Err(_) => Script::default(),
+    };
+
+    let replay_file = replay;
+    let replay = match Replay::load(replay_file) {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("{}: {}", replay_file.display(), err);
+            return 1;
+        }
+    };
+
+    let result = verify_replay(&sheet, &script, &replay);
+
+    println!(
+        "{}: score {} (claims {}), best combo {}, {:?}",
+        replay_file.display(),
+        result.score,
+        replay.score,
+        result.best_combo,
+        result.ending,
+    );
+
+    if result.verified {
+        println!("verified");
+
+        0
+    } else {
+        if replay.chart != sheet.level_hash(&script) {
+            println!("recorded on a different chart");
+        }
+
+        println!("not verified");
+
+        1
+    }
+}
+
+fn report_capture(result: std::io::Result<PathBuf>) {
+    match result {
+        Ok(path) => log_info!("saved {}", path.display()),
+        Err(err) => log_error!("failed to save capture: {}", err),
+    }
+}
+
+/// What scores of the level at `path` are kept under, its folder name and the difficulty if any.
+fn level_name(path: &std::path::Path, difficulty: Option<&str>) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy();
+
+    Some(match difficulty {
+        Some(difficulty) => format!("{} [{}]", name, difficulty),
+        None => name.into_owned(),
+    })
+}
+
+/// Loads the settings and scores of `profile`, syncing them first if it syncs.
+fn load_profile(profile: &Profile) -> (Settings, Scores) {
+    let settings = Settings::load(profile);
+
+    if settings.sync_url.is_empty() {
+        return (settings, Scores::load(profile));
+    }
+
+    match sync::sync(profile, &settings.sync_url) {
+        Ok(true) => (Settings::load(profile), Scores::load(profile)),
+        Ok(false) => (settings, Scores::load(profile)),
+        Err(err) => {
+            log_warn!("failed to sync profile: {}", err);
+
+            (settings, Scores::load(profile))
+        }
+    }
+}
+
+/// Seconds to move the song offset by, `+` moving it later and `-` earlier.
+fn offset_nudge() -> Option<f32> {
+    if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) {
+        Some(SONG_OFFSET_STEP)
+    } else if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract) {
+        Some(-SONG_OFFSET_STEP)
+    } else {
+        None
+    }
+}
+
+/// The menu of the levels and courses in the songs folder of `settings`.
+async fn load_menu(settings: &Settings) -> MainMenu {
+    let levels_dir = PathBuf::from(&settings.songs_dir);
+    let courses = course::load_courses(asset_source::courses().await, &levels_dir).await;
+    let levels = asset_source::levels(&levels_dir).await;
+
+    MainMenu::new(levels_dir, levels, courses).await
+}
+
+/// Starts the level in `level_path`, the error being why it didn't load.
+async fn launch(
+    level_path: PathBuf,
+    difficulty: Option<&str>,
+    start_bar: u32,
+    modifiers: &Modifiers,
+    settings: &Settings,
+) -> Result<(Assets, GameState), String> {
+    let assets = Assets::load(level_path, difficulty, settings).await?;
+    let mut state = GameState::new(&assets, settings, modifiers).await;
+
+    if start_bar > 0 {
+        state.skip_to(&assets, assets.sheet.bar_time(start_bar));
+    }
+
+    state.start(&assets);
+
+    Ok((assets, state))
+}
 
-#[macroquad::main("SRG")]
-async fn main() {
-    let mut main_menu = MainMenu::new();
+/// Starts the level of `marathon` that's up, on the assets preloaded for it if there are any.
+async fn launch_marathon(
+    marathon: &mut Marathon,
+    modifiers: &Modifiers,
+    settings: &Settings,
+) -> Result<(Assets, GameState), String> {
+    let assets = marathon.take_assets(settings).await?;
+    let mut state = GameState::new(&assets, settings, modifiers).await;
+
+    marathon.carry_into(&mut state);
+    state.start(&assets);
+
+    Ok((assets, state))
+}
+
+/// What was `loaded`, telling the player in the menu why if the level didn't load.
+fn loaded<T>(loaded: Result<T, String>, main_menu: &mut MainMenu) -> Option<T> {
+    loaded
+        .map_err(|err| {
+            log_error!("failed to load the level: {}", err);
+            main_menu.show_load_error(err);
+        })
+        .ok()
+}
+
+async fn launch_tutorial(settings: &mut Settings) -> (Assets, GameState) {
+    settings.tutorial_seen = true;
+    settings.save();
+
+    let assets = tutorial::load_assets(settings).await;
+    let mut state = GameState::new(&assets, settings, &Modifiers::default()).await;
+
+    state.tutorial = Some(Tutorial::new());
+    state.start(&assets);
+
+    (assets, state)
+}
+
+async fn run(mut args: Args) {
+    let (mut settings, mut scores) = load_profile(&Profile::current());
+    let post = PostProcessing::new();
+
+    if args.bench {
+        srg::bench::run(&settings, &post).await;
+        return;
+    }
+
+    let mut main_menu = load_menu(&settings).await;
+    // the setup being gone through, on the first launch or when picked in the menu
+    let mut setup = None;
+    let mut clips = ClipRecorder::new();
+    let mut overlay = DebugOverlay::new();
+    let mut limiter = FrameLimiter::new();
     let mut game: Option<(Assets, GameState)> = None;
+    // whether the level playing is an idle demo
+    let mut demo = false;
+    // folder name of the level playing, for its scores
+    let mut level: Option<String> = None;
+    // whether the run that just ended has been added to the scores
+    let mut scored = false;
+    // the playlist being played through, its levels aren't scored on their own
+    let mut marathon: Option<Marathon> = None;
+
+    rand::srand(miniquad::date::now() as u64);
+
+    if let Some(level_path) = args.level.take() {
+        level = level_name(&level_path, args.difficulty.as_deref());
+        game = loaded(
+            launch(
+                level_path,
+                args.difficulty.as_deref(),
+                args.start_bar,
+                &args.modifiers(&settings.modifiers),
+                &settings,
+            )
+            .await,
+            &mut main_menu,
+        );
+    } else if Setup::needed(&settings) {
+        setup = Some(Setup::new().await);
+    } else if !settings.tutorial_seen {
+        game = Some(launch_tutorial(&mut settings).await);
+    }
 
     loop {
+        settings.handle_fullscreen_toggle();
+        settings.handle_streamer_toggle();
+
         if let Some((assets, state)) = &mut game {
-            state.update(assets).await;
-            state.draw(assets);
+            if let Some(amount) = offset_nudge().filter(|_| !demo) {
+                state.nudge_offset(assets, &mut settings, amount);
+            }
+
+            if !state.is_paused() {
+                state.update(assets, &settings).await;
+            }
+
+            state.draw(assets, &settings, &post);
+
+            // loaded while the end of the song plays, so the next level starts without a wait
+            if let Some(marathon) = marathon.as_mut() {
+                if state.death.is_none() && state.projectiles_done(assets) {
+                    marathon.preload(&settings).await;
+                }
+            }
+
+            if settings.clip_buffer {
+                clips.record(post.target().texture, settings.clip_length);
+
+                if is_key_pressed(KeyCode::F9) {
+                    report_capture(clips.save());
+                }
+            }
+
+            if demo {
+                draw_demo_overlay(&settings);
+
+                if input::any_pressed() || state.cleared(assets) {
+                    state.stop();
+
+                    game = None;
+                }
+            } else if state.is_paused() {
+                let offset = settings.song_offset(assets.sheet.hash);
+
+                match PauseMenu.update(&settings, state.practice.is_some(), offset) {
+                    Some(PauseAction::Resume) => state.resume(),
+                    Some(PauseAction::Restart) => match &mut marathon {
+                        Some(current) => {
+                            let modifiers = state.run.modifiers.clone();
+
+                            state.stop();
+                            current.restart();
+                            game = loaded(
+                                launch_marathon(current, &modifiers, &settings).await,
+                                &mut main_menu,
+                            );
+
+                            if game.is_none() {
+                                marathon = None;
+                            }
+                        }
+                        None => {
+                            state.restart(assets, &settings);
+                            scored = false;
+                        }
+                    },
+                    Some(PauseAction::Practice) => {
+                        if state.practice.is_some() {
+                            state.stop_practice(assets);
+                        } else {
+                            state.practice_section(assets, &settings);
+                        }
+
+                        state.resume();
+                    }
+                    Some(PauseAction::Quit) => {
+                        state.stop();
 
-            if is_key_pressed(KeyCode::Escape) {
-                state.stop(assets);
+                        game = None;
+                        marathon = None;
+                    }
+                    None => {}
+                }
+            } else if is_key_pressed(KeyCode::Escape) {
+                state.pause();
+            } else if state.death.is_some()
+                && marathon.is_none()
+                && is_key_pressed(settings.restart_key)
+            {
+                // nothing left to lose, so no holding it down
+                state.restart(assets, &settings);
+                scored = false;
+            } else if state.tutorial_finished() {
+                state.stop();
 
                 game = None;
+            } else if let (Some(current), Some(summary)) = (&mut marathon, state.summary(assets)) {
+                if current.ending.is_none() {
+                    current.finish_level(state, &summary);
+                }
+
+                let modifiers = state.run.modifiers.clone();
+
+                if !scored && modifiers.ranked() {
+                    if let Some(summary) = current.course_summary(&modifiers) {
+                        let mut key = current.course.as_ref().unwrap().scores_key();
+
+                        if modifiers.aggro {
+                            key = aggro::scores_key(&key);
+                        }
+
+                        // courses keep no replays, there'd be one for every level
+                        scores.record(&key, &summary, miniquad::date::now() as u64);
+                        scores.save(&settings.profile);
+
+                        if !settings.sync_url.is_empty() {
+                            sync::sync_in_background(&settings.profile, &settings.sync_url);
+                        }
+
+                        scored = true;
+                    }
+                }
+
+                if current.ending.is_none() {
+                    state.stop();
+                    game = loaded(
+                        launch_marathon(current, &modifiers, &settings).await,
+                        &mut main_menu,
+                    );
+
+                    if game.is_none() {
+                        marathon = None;
+                    }
+                } else {
+                    match MarathonResults.update(&settings, current) {
+                        Some(MarathonAction::Retry) => {
+                            state.stop();
+                            current.restart();
+                            scored = false;
+                            game = loaded(
+                                launch_marathon(current, &modifiers, &settings).await,
+                                &mut main_menu,
+                            );
+
+                            if game.is_none() {
+                                marathon = None;
+                            }
+                        }
+                        Some(MarathonAction::Quit) => {
+                            state.stop();
+
+                            game = None;
+                            marathon = None;
+                        }
+                        None => {}
+                    }
+                }
+            } else if let Some(summary) = state.summary(assets) {
+                if !scored && !demo && state.run.modifiers.ranked() {
+                    if let Some(level) = &level {
+                        let date = miniquad::date::now() as u64;
+                        let key = if state.run.modifiers.aggro {
+                            aggro::scores_key(level)
+                        } else {
+                            level.clone()
+                        };
+                        let recorded = scores.record(&key, &summary, date);
+                        let profile = &settings.profile;
+
+                        if recorded.kept {
+                            let replay = state.to_replay(assets, &profile.name, level);
+
+                            if let Err(err) = replay.save(&Replay::path(profile, level, date)) {
+                                log_error!("failed to save replay: {}", err);
+                            }
+                        }
+
+                        for date in recorded.dropped {
+                            let _ = std::fs::remove_file(Replay::path(profile, level, date));
+                        }
+
+                        scores.save(&settings.profile);
+
+                        if !settings.sync_url.is_empty() {
+                            sync::sync_in_background(&settings.profile, &settings.sync_url);
+                        }
+                    }
+                }
+
+                scored = true;
+
+                match EndMenu.update(&settings, &summary) {
+                    Some(EndAction::Retry) => {
+                        state.restart(assets, &settings);
+                        scored = false;
+                    }
+                    Some(EndAction::RetryCheckpoint) => {
+                        state.retry_checkpoint(assets);
+                        scored = false;
+                    }
+                    Some(EndAction::Practice) => state.practice_section(assets, &settings),
+                    Some(EndAction::Replay) => {
+                        if state.can_review() {
+                            state.review(assets, &settings).await;
+                        } else {
+                            state.replay_run(assets, &settings).await;
+                        }
+                    }
+                    Some(EndAction::Quit) => {
+                        state.stop();
+
+                        game = None;
+                    }
+                    None => {}
+                }
+            }
+        } else if let Some(current) = &mut setup {
+            if let Some(SetupAction::Done { tutorial }) = current.update(&mut settings).await {
+                setup = None;
+                settings.tutorial_seen |= !tutorial;
+                settings.save();
+
+                if main_menu.levels_dir() != Path::new(&settings.songs_dir) {
+                    main_menu = load_menu(&settings).await;
+                }
+
+                if tutorial {
+                    game = Some(launch_tutorial(&mut settings).await);
+                    demo = false;
+                    level = None;
+                }
             }
         } else {
-            if let Some(level_path) = main_menu.update() {
-                let assets = Assets::load(level_path).await;
-                let mut state = GameState::new(&assets).await;
+            match main_menu.update(&mut settings, &scores) {
+                Some(MenuAction::Play(level_path)) => {
+                    level = level_name(&level_path, None);
+                    game = loaded(
+                        launch(
+                            level_path,
+                            None,
+                            0,
+                            &args.modifiers(&settings.modifiers),
+                            &settings,
+                        )
+                        .await,
+                        &mut main_menu,
+                    );
+                    demo = false;
+                    scored = false;
+                }
+                Some(MenuAction::Marathon(levels)) => {
+                    let mut current = Marathon::new(levels);
+
+                    log_info!("playing a playlist of {} levels", current.levels.len());
+
+                    game = loaded(
+                        launch_marathon(
+                            &mut current,
+                            &args.modifiers(&settings.modifiers),
+                            &settings,
+                        )
+                        .await,
+                        &mut main_menu,
+                    );
+                    marathon = game.as_ref().map(|_| current);
+                    demo = false;
+                    level = None;
+                }
+                Some(MenuAction::Course(course)) => {
+                    log_info!("playing the course {}", course.name);
+
+                    let modifiers = course.modifiers.clone();
+                    let mut current = Marathon::of_course(course);
+
+                    game = loaded(
+                        launch_marathon(&mut current, &modifiers, &settings).await,
+                        &mut main_menu,
+                    );
+                    marathon = game.as_ref().map(|_| current);
+                    demo = false;
+                    level = None;
+                    scored = false;
+                }
+                Some(MenuAction::Tutorial) => {
+                    game = Some(launch_tutorial(&mut settings).await);
+                    demo = false;
+                    level = None;
+                }
+                Some(MenuAction::Setup) => setup = Some(Setup::new().await),
+                Some(MenuAction::Demo(level_path)) => {
+                    level = None;
+                    let modifiers = Modifiers {
+                        autoplay: true,
+                        ..Modifiers::default()
+                    };
+
+                    // nobody asked for the demo, so a level that doesn't load is only logged
+                    game = launch(level_path, None, 0, &modifiers, &settings)
+                        .await
+                        .map_err(|err| log_warn!("failed to load the demo: {}", err))
+                        .ok();
+                    demo = true;
+                }
+                Some(MenuAction::Spectate(level_path, replay)) => {
+                    let assets = Assets::load(level_path, None, &settings).await;
 
-                state.start(&assets);
+                    if let Some(assets) = loaded(assets, &mut main_menu) {
+                        let mut state = GameState::new(&assets, &settings, &replay.modifiers).await;
 
-                game = Some((assets, state));
+                        state.watch(&assets, &settings, &replay).await;
+
+                        game = Some((assets, state));
+                    }
+                    demo = false;
+                    level = None;
+                    // someone else's run isn't the player's score
+                    scored = true;
+                }
+                Some(MenuAction::SwitchProfile(profile)) => {
+                    if let Err(err) = profile.select() {
+                        log_error!("failed to switch profile: {}", err);
+                    }
+
+                    let (loaded_settings, loaded_scores) = load_profile(&profile);
+                    settings = loaded_settings;
+                    scores = loaded_scores;
+                    set_fullscreen(settings.fullscreen);
+
+                    if main_menu.levels_dir() != Path::new(&settings.songs_dir) {
+                        main_menu = load_menu(&settings).await;
+                    }
+
+                    if Setup::needed(&settings) {
+                        setup = Some(Setup::new().await);
+                    }
+                }
+                None => {}
             }
         }
 
+        overlay.update();
+        overlay.draw(
+            &settings,
+            game.as_ref().map(|(assets, state)| (assets, state)),
+        );
+
+        if is_key_pressed(KeyCode::F12) {
+            report_capture(capture::screenshot());
+        }
+
+        let in_menu = game.as_ref().is_none_or(|(_, state)| state.is_paused());
+
+        limiter.wait(settings.frames_per_second(in_menu));
         next_frame().await;
     }
 }
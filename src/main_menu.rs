@@ -1,50 +1,1098 @@
+use crate::aggro;
+use crate::asset_source::{load_script, load_sheet, pack_of};
+use crate::canvas;
+use crate::course::Course;
+use crate::crash;
+use crate::density::Density;
+use crate::font;
+use crate::input;
+use crate::library::Library;
+use crate::lint;
+use crate::log_warn;
+use crate::manifest;
+use crate::playlist::Playlist;
+use crate::profile::{profiles, Profile};
+use crate::replay::{replays_of, Replay};
+use crate::scores::Scores;
+use crate::script::Script;
+use crate::settings::Settings;
+use crate::sheet::{find_sheet, ChartStats, Sheet};
 use egui::*;
 use macroquad::prelude::*;
-use std::fs;
-use std::io::prelude::*;
+use std::path::{Path, PathBuf};
 
-pub struct MainMenu {}
+/// Seconds the menu has to sit idle before a demo starts.
+const DEMO_IDLE: f32 = 30.0;
+
+/// Lint warnings listed when a level is checked from the menu.
+const MANAGE_WARNINGS: usize = 8;
+
+pub enum MenuAction {
+    Play(PathBuf),
+    Tutorial,
+    /// Go through the setup of the first launch again.
+    Setup,
+    /// Autoplay a level until the player presses something.
+    Demo(PathBuf),
+    /// Switch to another profile, creating it if it's new.
+    SwitchProfile(Profile),
+    /// Watch a replay of a level.
+    Spectate(PathBuf, Replay),
+    /// Play levels back to back, carrying the score and hearts over.
+    Marathon(Vec<PathBuf>),
+    /// Play the levels of a course as a marathon, with its modifiers.
+    Course(Course),
+}
+
+/// The level the manage dialog is open for, opened by right clicking it.
+#[cfg(not(target_arch = "wasm32"))]
+struct Managing {
+    /// Index of the level in [`MainMenu::levels`].
+    index: usize,
+    /// Title typed in to rename the level to.
+    title: String,
+    /// Whether the player has asked to delete the level and has yet to confirm it.
+    confirm_delete: bool,
+    /// How the last thing done to the level went.
+    status: Option<String>,
+}
+
+/// What the list in the side panel shows.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    Levels,
+    /// Levels played before, the last played first.
+    Recent,
+    /// Levels starred by the player.
+    Favorites,
+    Courses,
+}
+
+pub struct MainMenu {
+    /// The songs folder the levels were read from.
+    levels_dir: PathBuf,
+    levels: Vec<PathBuf>,
+    /// Level hash of each level's sheet and script, `None` if either doesn't parse.
+    charts: Vec<Option<u64>>,
+    /// Density of each level's sheet, `None` if it doesn't parse.
+    densities: Vec<Option<Density>>,
+    /// Statistics of each level's sheet, `None` if it doesn't parse.
+    stats: Vec<Option<ChartStats>>,
+    /// Title of each level from its `level.toml`, `None` to show its folder name.
+    titles: Vec<Option<String>>,
+    courses: Vec<Course>,
+    tab: Tab,
+    /// Index of the course the course dialog is open for.
+    selected_course: Option<usize>,
+    /// The ui scale the egui fonts were last built for.
+    ui_scale: Option<f32>,
+    /// Seconds since the player last did anything.
+    idle: f32,
+    last_mouse_position: (f32, f32),
+    #[cfg(not(target_arch = "wasm32"))]
+    managing: Option<Managing>,
+    /// Level the modifiers dialog is open for.
+    selected: Option<PathBuf>,
+    /// Replays of the selected level, loaded when it's selected.
+    replays: Vec<Replay>,
+    /// Name typed in for a new profile.
+    new_profile: String,
+    /// Log of the crash the last time the game ran, until the player dismisses it.
+    crash: Option<PathBuf>,
+    /// Why the last level picked didn't load, until the player dismisses it.
+    load_error: Option<String>,
+    playlist: Playlist,
+    library: Library,
+    /// Name of the profile the playlist and library were loaded for.
+    loaded_profile: Option<String>,
+}
 
 impl MainMenu {
-    pub fn new() -> Self {
-        Self {}
+    pub async fn new(levels_dir: PathBuf, levels: Vec<PathBuf>, mut courses: Vec<Course>) -> Self {
+        let mut charts = Vec::new();
+        let mut densities = Vec::new();
+        let mut stats = Vec::new();
+        let mut titles = Vec::new();
+
+        for level in &levels {
+            let sheet = load_sheet(level, None).await.ok();
+            let script = load_script(level).await.ok();
+
+            charts.push(match (&sheet, &script) {
+                (Some(sheet), Some(script)) => Some(sheet.level_hash(script)),
+                _ => None,
+            });
+            densities.push(sheet.as_ref().map(Density::of));
+            stats.push(sheet.as_ref().map(Sheet::stats));
+            titles.push(manifest::load_title(level).await);
+        }
+
+        for course in &mut courses {
+            course.hash_charts(|level| {
+                let index = levels.iter().position(|other| other == level)?;
+
+                charts[index]
+            });
+        }
+
+        Self {
+            levels_dir,
+            levels,
+            charts,
+            densities,
+            stats,
+            titles,
+            courses,
+            tab: Tab::Levels,
+            selected_course: None,
+            ui_scale: None,
+            idle: 0.0,
+            last_mouse_position: mouse_position(),
+            #[cfg(not(target_arch = "wasm32"))]
+            managing: None,
+            selected: None,
+            replays: Vec::new(),
+            new_profile: String::new(),
+            crash: crash::pending(),
+            load_error: None,
+            playlist: Playlist::default(),
+            library: Library::default(),
+            loaded_profile: None,
+        }
+    }
+
+    pub fn levels_dir(&self) -> &Path {
+        &self.levels_dir
+    }
+
+    /// Tells the player the level they picked didn't load, and why.
+    pub fn show_load_error(&mut self, error: String) {
+        self.load_error = Some(error);
+    }
+
+    fn apply_ui_scale(&mut self, ctx: &CtxRef, ui_scale: f32) {
+        if self.ui_scale == Some(ui_scale) {
+            return;
+        }
+
+        ctx.set_fonts(font::egui_fonts(ui_scale));
+
+        self.ui_scale = Some(ui_scale);
+    }
+
+    /// Starts a demo of a random level once the menu has been idle for long enough.
+    fn update_idle(&mut self) -> Option<MenuAction> {
+        let mouse_position = mouse_position();
+
+        if input::any_pressed() || mouse_position != self.last_mouse_position {
+            self.idle = 0.0;
+        } else {
+            self.idle += get_frame_time();
+        }
+
+        self.last_mouse_position = mouse_position;
+
+        if self.idle < DEMO_IDLE || self.levels.is_empty() {
+            return None;
+        }
+
+        self.idle = 0.0;
+
+        let level = rand::gen_range(0, self.levels.len());
+
+        Some(MenuAction::Demo(self.levels[level].clone()))
     }
 
-    pub fn update(&mut self) -> Option<std::path::PathBuf> {
-        let mut level = None;
+    pub fn update(&mut self, settings: &mut Settings, scores: &Scores) -> Option<MenuAction> {
+        let mut action = self.update_idle();
+
+        if self.loaded_profile.as_ref() != Some(&settings.profile.name) {
+            self.playlist = Playlist::load(&settings.profile);
+            self.library = Library::load(&settings.profile);
+            self.loaded_profile = Some(settings.profile.name.clone());
+        }
 
         clear_background(BLACK);
 
         set_default_camera();
 
         egui_macroquad::ui(|ctx| {
-            egui::SidePanel::left("side_panel", 200.0).show(ctx, |ui| {
-                ui.heading("Shitty rhythm game");
+            self.apply_ui_scale(ctx, settings.ui_scale);
 
-                ui.label("Levels");
+            egui::SidePanel::left("side_panel", 200.0 * settings.ui_scale).show(ctx, |ui| {
+                ui.heading(settings.locale.get("menu-title"));
+
+                ui.label(settings.locale.get("menu-profile"));
 
                 ui.group(|ui| {
+                    let current = settings.profile.clone();
+
+                    ComboBox::from_id_source("profile")
+                        .selected_text(&current.name)
+                        .show_ui(ui, |ui| {
+                            for name in profiles(&current) {
+                                if ui.selectable_label(name == current.name, &name).clicked() {
+                                    action = Some(MenuAction::SwitchProfile(Profile::new(&name)));
+                                }
+                            }
+                        });
+
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut self.new_profile);
+
+                        let profile = Profile::new(&self.new_profile);
+
+                        if ui.button(settings.locale.get("menu-new-profile")).clicked()
+                            && profile.is_valid()
+                        {
+                            action = Some(MenuAction::SwitchProfile(profile));
+                            self.new_profile.clear();
+                        }
+                    });
+
+                    let lifetime = &scores.lifetime;
+
+                    if lifetime.runs > 0 {
+                        let [perfect, great, late, miss] = lifetime.judgments;
+
+                        ui.label(settings.locale.format(
+                            "menu-lifetime",
+                            &[
+                                ("runs", &lifetime.runs),
+                                ("perfect", &perfect),
+                                ("great", &great),
+                                ("late", &late),
+                                ("miss", &miss),
+                            ],
+                        ));
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    if ui.button(settings.locale.get("menu-tutorial")).clicked() {
+                        action = Some(MenuAction::Tutorial);
+                    }
+
+                    if ui.button(settings.locale.get("menu-setup")).clicked() {
+                        action = Some(MenuAction::Setup);
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.selectable_value(
+                        &mut self.tab,
+                        Tab::Levels,
+                        settings.locale.get("menu-levels"),
+                    );
+                    ui.selectable_value(
+                        &mut self.tab,
+                        Tab::Recent,
+                        settings.locale.get("menu-recent"),
+                    );
+                    ui.selectable_value(
+                        &mut self.tab,
+                        Tab::Favorites,
+                        settings.locale.get("menu-favorites"),
+                    );
+                    ui.selectable_value(
+                        &mut self.tab,
+                        Tab::Courses,
+                        settings.locale.get("menu-courses"),
+                    );
+                });
+
+                ui.group(|ui| {
+                    if self.tab == Tab::Courses {
+                        if self.courses.is_empty() {
+                            ui.label(settings.locale.get("menu-no-courses"));
+                        }
+
+                        for (index, course) in self.courses.iter().enumerate() {
+                            let text = match scores.get(&course.scores_key()) {
+                                Some(record) => settings.locale.format(
+                                    "menu-level-best",
+                                    &[("level", &course.name), ("score", &record.best_score)],
+                                ),
+                                None => course.name.clone(),
+                            };
+
+                            if ui.button(text).clicked() {
+                                self.selected_course = Some(index);
+                                self.selected = None;
+                            }
+                        }
+
+                        return;
+                    }
+
+                    let names: Vec<String> = self
+                        .levels
+                        .iter()
+                        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+                        .collect();
+                    let mut shown: Vec<usize> = (0..self.levels.len()).collect();
+
+                    match self.tab {
+                        Tab::Recent => {
+                            shown
+                                .retain(|&index| self.library.last_played(&names[index]).is_some());
+                            shown.sort_by_key(|&index| {
+                                std::cmp::Reverse(self.library.last_played(&names[index]))
+                            });
+                        }
+                        Tab::Favorites => {
+                            shown.retain(|&index| self.library.is_favorite(&names[index]))
+                        }
+                        Tab::Levels | Tab::Courses => {}
+                    }
+
+                    if shown.is_empty() {
+                        match self.tab {
+                            Tab::Recent => {
+                                ui.label(settings.locale.get("menu-no-recent"));
+                            }
+                            Tab::Favorites => {
+                                ui.label(settings.locale.get("menu-no-favorites"));
+                            }
+                            Tab::Levels | Tab::Courses => {}
+                        }
+                    }
+
+                    // levels in packs are grouped by them, those right in the songs folder first
+                    let mut groups: Vec<(Option<String>, Vec<usize>)> = Vec::new();
+
+                    for index in shown {
+                        let pack = match self.tab {
+                            Tab::Levels => pack_of(&self.levels[index], &self.levels_dir),
+                            _ => None,
+                        };
+
+                        match groups.iter_mut().find(|(other, _)| *other == pack) {
+                            Some((_, levels)) => levels.push(index),
+                            None => groups.push((pack, vec![index])),
+                        }
+                    }
+
+                    groups.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+                    let mut starred = None;
+                    let mut clicked = None;
+                    #[cfg(not(target_arch = "wasm32"))]
+                    let mut managed = None;
+
+                    let mut level_row = |ui: &mut Ui, index: usize| {
+                        let name = self.titles[index].as_ref().unwrap_or(&names[index]);
+                        let text = match scores.get(&names[index]) {
+                            Some(record) if Some(record.chart) != self.charts[index] => {
+                                settings.locale.format(
+                                    "menu-level-modified",
+                                    &[("level", name), ("score", &record.best_score)],
+                                )
+                            }
+                            Some(record) if record.full_combo => settings.locale.format(
+                                "menu-level-full-combo",
+                                &[("level", name), ("score", &record.best_score)],
+                            ),
+                            Some(record) => settings.locale.format(
+                                "menu-level-best",
+                                &[("level", name), ("score", &record.best_score)],
+                            ),
+                            None => name.clone(),
+                        };
+                        let favorite = self.library.is_favorite(name);
+
+                        ui.horizontal(|ui| {
+                            if ui
+                                .selectable_label(favorite, "*")
+                                .on_hover_text(settings.locale.get("menu-favorite"))
+                                .clicked()
+                            {
+                                starred = Some(index);
+                            }
+
+                            let response = ui.button(text);
+
+                            if response.clicked() {
+                                clicked = Some(index);
+                            }
+
+                            #[cfg(not(target_arch = "wasm32"))]
+                            if response.secondary_clicked() {
+                                managed = Some(index);
+                            }
+                        });
+                    };
+
                     ScrollArea::auto_sized().show(ui, |ui| {
-                        for entry in fs::read_dir("songs").unwrap() {
-                            if let Ok(entry) = entry {
-                                if entry.path().is_dir() {
-                                    let response = ui.button(
-                                        entry.path().file_name().unwrap().to_str().unwrap(),
-                                    );
-
-                                    if response.clicked() {
-                                        level = Some(entry.path());
+                        for (pack, levels) in &groups {
+                            let pack = match pack {
+                                Some(pack) => pack,
+                                None => {
+                                    for &index in levels {
+                                        level_row(ui, index);
                                     }
+
+                                    continue;
                                 }
-                            }
+                            };
+
+                            let cleared = levels
+                                .iter()
+                                .filter(|&&index| {
+                                    scores
+                                        .get(&names[index])
+                                        .is_some_and(|record| record.clears > 0)
+                                })
+                                .count();
+                            let title = settings.locale.format(
+                                "menu-pack",
+                                &[
+                                    ("pack", pack),
+                                    ("cleared", &cleared),
+                                    ("levels", &levels.len()),
+                                ],
+                            );
+
+                            CollapsingHeader::new(title).id_source(pack).show(ui, |ui| {
+                                for &index in levels {
+                                    level_row(ui, index);
+                                }
+                            });
                         }
                     });
+
+                    if let Some(index) = starred {
+                        self.library.toggle_favorite(&names[index]);
+                        self.library.save(&settings.profile);
+                    }
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(index) = managed {
+                        self.managing = Some(Managing {
+                            index,
+                            title: self.titles[index].clone().unwrap_or_default(),
+                            confirm_delete: false,
+                            status: None,
+                        });
+                    }
+
+                    if let Some(index) = clicked {
+                        self.selected_course = None;
+                        self.selected = Some(self.levels[index].clone());
+                        self.replays = replays_of(&settings.profile, &names[index]);
+                    }
+                });
+
+                ui.label(settings.locale.get("menu-playlist"));
+
+                ui.group(|ui| {
+                    if self.playlist.levels.is_empty() {
+                        ui.label(settings.locale.get("menu-playlist-empty"));
+                    }
+
+                    let mut moved = None;
+                    let mut removed = None;
+
+                    for (index, level) in self.playlist.levels.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                level
+                                    .file_name()
+                                    .unwrap_or_default()
+                                    .to_string_lossy()
+                                    .as_ref(),
+                            );
+
+                            if ui.small_button("^").clicked() {
+                                moved = Some((index, -1));
+                            }
+
+                            if ui.small_button("v").clicked() {
+                                moved = Some((index, 1));
+                            }
+
+                            if ui.small_button("x").clicked() {
+                                removed = Some(index);
+                            }
+                        });
+                    }
+
+                    if let Some((index, by)) = moved {
+                        self.playlist.move_level(index, by);
+                        self.playlist.save(&settings.profile);
+                    }
+
+                    if let Some(index) = removed {
+                        self.playlist.levels.remove(index);
+                        self.playlist.save(&settings.profile);
+                    }
+
+                    if !self.playlist.levels.is_empty() {
+                        ui.horizontal(|ui| {
+                            if ui
+                                .button(settings.locale.get("menu-playlist-play"))
+                                .clicked()
+                            {
+                                action = Some(MenuAction::Marathon(self.playlist.levels.clone()));
+                            }
+
+                            if ui
+                                .button(settings.locale.get("menu-playlist-clear"))
+                                .clicked()
+                            {
+                                self.playlist.levels.clear();
+                                self.playlist.save(&settings.profile);
+                            }
+                        });
+                    }
+                });
+
+                ui.label(settings.locale.get("menu-settings"));
+
+                ui.group(|ui| {
+                    if settings.ui(ui) {
+                        settings.save();
+                    }
                 });
             });
+
+            if let Some(log) = self.crash.clone() {
+                egui::Window::new(settings.locale.get("menu-crash-title"))
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(ctx, |ui| {
+                        ui.label(
+                            settings
+                                .locale
+                                .format("menu-crash", &[("path", &log.display())]),
+                        );
+
+                        ui.horizontal(|ui| {
+                            #[cfg(not(target_arch = "wasm32"))]
+                            if ui.button(settings.locale.get("menu-crash-open")).clicked() {
+                                if let Err(err) = crash::open(&log) {
+                                    log_warn!("failed to open {}: {}", log.display(), err);
+                                }
+                            }
+
+                            if ui
+                                .button(settings.locale.get("menu-crash-dismiss"))
+                                .clicked()
+                            {
+                                crash::dismiss();
+                                self.crash = None;
+                            }
+                        });
+                    });
+            }
+
+            if let Some(error) = self.load_error.clone() {
+                egui::Window::new(settings.locale.get("menu-load-failed-title"))
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(ctx, |ui| {
+                        ui.label(
+                            settings
+                                .locale
+                                .format("menu-load-failed", &[("error", &error)]),
+                        );
+
+                        if ui
+                            .button(settings.locale.get("menu-load-failed-dismiss"))
+                            .clicked()
+                        {
+                            self.load_error = None;
+                        }
+                    });
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(managing) = &mut self.managing {
+                let index = managing.index;
+                let path = self.levels[index].clone();
+                let mut renamed = None;
+                let mut validate = false;
+                let mut delete = false;
+                let mut close = false;
+
+                egui::Window::new(settings.locale.get("menu-manage"))
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(ctx, |ui| {
+                        ui.label(path.display().to_string());
+
+                        ui.horizontal(|ui| {
+                            ui.label(settings.locale.get("menu-manage-title"));
+                            ui.text_edit_singleline(&mut managing.title);
+
+                            if ui
+                                .button(settings.locale.get("menu-manage-rename"))
+                                .clicked()
+                            {
+                                renamed = Some(managing.title.trim().to_string());
+                            }
+                        });
+
+                        if let Some(status) = &managing.status {
+                            ui.label(status.as_str());
+                        }
+
+                        if managing.confirm_delete {
+                            ui.label(settings.locale.get("menu-manage-delete-confirm"));
+
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .button(settings.locale.get("menu-manage-delete"))
+                                    .clicked()
+                                {
+                                    delete = true;
+                                }
+
+                                if ui.button(settings.locale.get("menu-back")).clicked() {
+                                    managing.confirm_delete = false;
+                                }
+                            });
+
+                            return;
+                        }
+
+                        ui.horizontal(|ui| {
+                            if ui
+                                .button(settings.locale.get("menu-manage-reveal"))
+                                .clicked()
+                            {
+                                if let Err(err) = crash::open(&path) {
+                                    log_warn!("failed to open {}: {}", path.display(), err);
+                                }
+                            }
+
+                            if ui
+                                .button(settings.locale.get("menu-manage-validate"))
+                                .clicked()
+                            {
+                                validate = true;
+                            }
+
+                            if ui
+                                .button(settings.locale.get("menu-manage-delete"))
+                                .clicked()
+                            {
+                                managing.confirm_delete = true;
+                            }
+
+                            if ui.button(settings.locale.get("menu-back")).clicked() {
+                                close = true;
+                            }
+                        });
+                    });
+
+                if let Some(title) = renamed {
+                    managing.status = Some(match manifest::save_title(&path, &title) {
+                        Ok(()) => {
+                            self.titles[index] = Some(title).filter(|title| !title.is_empty());
+
+                            settings.locale.get("menu-manage-renamed").to_string()
+                        }
+                        Err(err) => settings
+                            .locale
+                            .format("menu-manage-failed", &[("error", &err)]),
+                    });
+                }
+
+                if validate {
+                    let file = find_sheet(&path, None);
+                    let sheet = std::fs::read_to_string(&file)
+                        .map_err(|err| err.to_string())
+                        .and_then(|source| {
+                            Sheet::parse_file(&file, &source).map_err(|err| err.to_string())
+                        });
+
+                    let script = match std::fs::read_to_string(path.join("script.txt")) {
+                        Ok(source) => Script::parse(&source).ok(),
+                        Err(_) => Some(Script::default()),
+                    };
+
+                    self.charts[index] = sheet
+                        .as_ref()
+                        .ok()
+                        .zip(script.as_ref())
+                        .map(|(sheet, script)| sheet.level_hash(script));
+                    self.densities[index] = sheet.as_ref().ok().map(Density::of);
+                    self.stats[index] = sheet.as_ref().ok().map(Sheet::stats);
+                    managing.status = Some(match sheet {
+                        Ok(_) if !path.join("song.wav").exists() => {
+                            settings.locale.get("menu-manage-no-song").to_string()
+                        }
+                        Ok(sheet) => {
+                            let warnings = lint::lint(&sheet);
+                            let mut status = settings.locale.format(
+                                "menu-manage-valid",
+                                &[("projectiles", &sheet.projectiles.len())],
+                            );
+
+                            if !warnings.is_empty() {
+                                status.push('\n');
+                                status.push_str(&settings.locale.format(
+                                    "menu-manage-warnings",
+                                    &[("warnings", &warnings.len())],
+                                ));
+                            }
+
+                            // the rest can be seen with --check
+                            for warning in warnings.iter().take(MANAGE_WARNINGS) {
+                                status.push_str(&format!("\n{}", warning));
+                            }
+
+                            status
+                        }
+                        Err(err) => settings
+                            .locale
+                            .format("menu-manage-invalid", &[("error", &err)]),
+                    });
+                }
+
+                if delete {
+                    match std::fs::remove_dir_all(&path) {
+                        Ok(()) => {
+                            self.levels.remove(index);
+                            self.charts.remove(index);
+                            self.densities.remove(index);
+                            self.stats.remove(index);
+                            self.titles.remove(index);
+                            self.playlist.levels.retain(|level| *level != path);
+                            self.playlist.save(&settings.profile);
+
+                            if self.selected.as_ref() == Some(&path) {
+                                self.selected = None;
+                            }
+
+                            close = true;
+                        }
+                        Err(err) => {
+                            managing.confirm_delete = false;
+                            managing.status = Some(
+                                settings
+                                    .locale
+                                    .format("menu-manage-failed", &[("error", &err)]),
+                            );
+                        }
+                    }
+                }
+
+                if close {
+                    self.managing = None;
+                }
+            }
+
+            if let Some(course) = self.selected_course.map(|index| &self.courses[index]) {
+                let top = scores
+                    .get(&course.scores_key())
+                    .filter(|record| Some(record.chart) == course.chart)
+                    .map_or(&[][..], |record| &record.top);
+                let mut closed = false;
+
+                egui::Window::new(settings.locale.get("menu-course"))
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(ctx, |ui| {
+                        ui.heading(&course.name);
+
+                        for (number, level) in course.levels.iter().enumerate() {
+                            ui.label(format!(
+                                "{}. {}",
+                                number + 1,
+                                level.file_name().unwrap_or_default().to_string_lossy()
+                            ));
+                        }
+
+                        ui.label(settings.locale.format(
+                            "menu-run-modifiers",
+                            &[("modifiers", &course.modifiers.tag())],
+                        ));
+
+                        ui.separator();
+
+                        if top.is_empty() {
+                            ui.label(settings.locale.get("menu-no-runs"));
+                        }
+
+                        for (rank, run) in top.iter().enumerate() {
+                            ui.label(settings.locale.format(
+                                "menu-course-run",
+                                &[
+                                    ("rank", &(rank + 1)),
+                                    ("score", &run.score),
+                                    ("date", &format_date(run.date)),
+                                ],
+                            ));
+                        }
+
+                        ui.separator();
+
+                        if course.chart.is_none() {
+                            ui.label(settings.locale.get("menu-course-missing"));
+                        }
+
+                        ui.horizontal(|ui| {
+                            if course.chart.is_some()
+                                && ui.button(settings.locale.get("menu-play")).clicked()
+                            {
+                                action = Some(MenuAction::Course(course.clone()));
+                                closed = true;
+                            }
+
+                            if ui.button(settings.locale.get("menu-back")).clicked() {
+                                closed = true;
+                            }
+                        });
+                    });
+
+                if closed {
+                    self.selected_course = None;
+                }
+            }
+
+            if let Some(path) = self.selected.clone() {
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                // aggro runs are only shown while picking the aggro modifier
+                let (key, title) = if settings.modifiers.aggro {
+                    (aggro::scores_key(&name), "menu-leaderboard-aggro")
+                } else {
+                    (name.clone(), "menu-leaderboard")
+                };
+                let top = scores.get(&key).map_or(&[][..], |record| &record.top);
+
+                egui::Window::new(settings.locale.get(title))
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(Align2::RIGHT_TOP, [-8.0, 8.0])
+                    .show(ctx, |ui| {
+                        let index = self.levels.iter().position(|level| *level == path);
+                        let density = index.and_then(|index| self.densities[index].as_ref());
+                        let stats = index.and_then(|index| self.stats[index]);
+
+                        if let Some(stats) = stats {
+                            let [up, down, left, right] = stats.directions;
+
+                            ui.label(settings.locale.format(
+                                "menu-stats",
+                                &[
+                                    ("notes", &stats.notes),
+                                    ("duration", &format!("{:.0}", stats.duration)),
+                                    ("nps", &stats.max_nps),
+                                    ("gap", &format!("{:.1}", stats.longest_gap)),
+                                ],
+                            ));
+                            ui.label(settings.locale.format(
+                                "menu-stats-directions",
+                                &[
+                                    ("up", &up),
+                                    ("down", &down),
+                                    ("left", &left),
+                                    ("right", &right),
+                                ],
+                            ));
+                        }
+
+                        if let Some(density) = density {
+                            ui.label(settings.locale.format(
+                                "menu-density",
+                                &[("peak", &format!("{:.1}", density.peak))],
+                            ));
+                            density.show(ui, egui::vec2(240.0, 40.0) * settings.ui_scale);
+                            ui.separator();
+                        }
+
+                        if top.is_empty() {
+                            ui.label(settings.locale.get("menu-no-runs"));
+                        }
+
+                        let mut watched = None;
+
+                        for (rank, run) in top.iter().enumerate() {
+                            let header = settings.locale.format(
+                                "menu-run",
+                                &[("rank", &(rank + 1)), ("score", &run.score)],
+                            );
+
+                            CollapsingHeader::new(header)
+                                .id_source(("run", run.date))
+                                .show(ui, |ui| {
+                                    ui.label(settings.locale.format(
+                                        "menu-run-date",
+                                        &[("date", &format_date(run.date))],
+                                    ));
+
+                                    if !run.modifiers.is_empty() {
+                                        ui.label(settings.locale.format(
+                                            "menu-run-modifiers",
+                                            &[("modifiers", &run.modifiers)],
+                                        ));
+                                    }
+
+                                    if let Some(accuracy) = run.accuracy {
+                                        let percent = format!("{:.1}", accuracy * 100.0);
+
+                                        ui.label(settings.locale.format(
+                                            "menu-run-accuracy",
+                                            &[("accuracy", &percent)],
+                                        ));
+                                    }
+
+                                    if run.full_combo {
+                                        ui.colored_label(
+                                            Color32::GOLD,
+                                            settings.locale.get("menu-run-full-combo"),
+                                        );
+                                    }
+
+                                    let replay = Replay::path(&settings.profile, &name, run.date);
+
+                                    if replay.exists()
+                                        && ui
+                                            .button(settings.locale.get("menu-watch-replay"))
+                                            .clicked()
+                                    {
+                                        watched = Some(replay);
+                                    }
+                                });
+                        }
+
+                        match watched.map(|replay| Replay::load(&replay)) {
+                            Some(Ok(replay)) => {
+                                action = Some(MenuAction::Spectate(path.clone(), replay));
+                                self.selected = None;
+                            }
+                            Some(Err(err)) => log_warn!("failed to load replay: {}", err),
+                            None => {}
+                        }
+                    });
+
+                egui::Window::new(settings.locale.get("menu-modifiers"))
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(ctx, |ui| {
+                        ui.label(path.file_name().unwrap().to_string_lossy().as_ref());
+
+                        if settings.modifiers.ui(ui, &settings.locale) {
+                            settings.save();
+                        }
+
+                        ui.horizontal(|ui| {
+                            if ui.button(settings.locale.get("menu-play")).clicked() {
+                                let name = path.file_name().unwrap().to_string_lossy();
+
+                                self.library.played(&name, miniquad::date::now() as u64);
+                                self.library.save(&settings.profile);
+                                action = Some(MenuAction::Play(path.clone()));
+                                self.selected = None;
+                            }
+
+                            if ui
+                                .button(settings.locale.get("menu-playlist-add"))
+                                .clicked()
+                            {
+                                self.playlist.levels.push(path.clone());
+                                self.playlist.save(&settings.profile);
+                            }
+
+                            if ui.button(settings.locale.get("menu-back")).clicked() {
+                                self.selected = None;
+                            }
+                        });
+
+                        if !self.replays.is_empty() {
+                            let chart = self
+                                .levels
+                                .iter()
+                                .position(|level| *level == path)
+                                .and_then(|index| self.charts[index]);
+
+                            ui.separator();
+                            ui.label(settings.locale.get("menu-replays"));
+
+                            let mut watched = None;
+
+                            for replay in &self.replays {
+                                ui.horizontal(|ui| {
+                                    ui.label(settings.locale.format(
+                                        "menu-replay",
+                                        &[("player", &replay.player), ("score", &replay.score)],
+                                    ));
+
+                                    if Some(replay.chart) != chart {
+                                        ui.label(settings.locale.get("menu-replay-outdated"));
+                                    }
+
+                                    if ui.button(settings.locale.get("menu-spectate")).clicked() {
+                                        watched = Some(replay.clone());
+                                    }
+                                });
+                            }
+
+                            if let Some(replay) = watched {
+                                action = Some(MenuAction::Spectate(path, replay));
+                                self.selected = None;
+                            }
+                        }
+                    });
+            }
         });
 
         egui_macroquad::draw();
 
-        level
+        action
+    }
+}
+
+/// `date` in seconds since the unix epoch as a UTC date and time, `2021-04-30 18:05`.
+fn format_date(date: u64) -> String {
+    let days = (date / 86400) as i64;
+    let minutes = date % 86400 / 60;
+
+    // days to a civil date, from Howard Hinnant's `civil_from_days`
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+/// Drawn over a demo, inviting the player to take over.
+pub fn draw_demo_overlay(settings: &Settings) {
+    let viewport = canvas::viewport();
+    let font_size = 24.0 * canvas::scale() * settings.ui_scale;
+
+    // blink once a second
+    if get_time().fract() < 0.5 {
+        let text = settings.locale.get("menu-demo");
+        let size = measure_text(text, None, font_size as u16, 1.0);
+
+        draw_text(
+            text,
+            viewport.x + (viewport.w - size.width) / 2.0,
+            viewport.y + viewport.h * 0.8,
+            font_size,
+            WHITE,
+        );
     }
 }
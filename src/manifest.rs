@@ -0,0 +1,47 @@
+//! What a level's `level.toml` says about it beyond the [uniforms](crate::uniforms) of its
+//! shader: the title the menu shows it as, its folder name if it hasn't got one.
+//!
+//! ```toml
+//! title = "Spear of Justice"
+//! ```
+
+use crate::asset_source::load_text;
+use crate::config::{Config, Value};
+use std::path::{Path, PathBuf};
+
+fn path(level: &Path) -> PathBuf {
+    level.join("level.toml")
+}
+
+/// The title of the level in `level`, `None` if it has none or no `level.toml`.
+pub async fn load_title(level: &Path) -> Option<String> {
+    let config = Config::parse(&load_text(&path(level)).await.ok()?).ok()?;
+
+    config
+        .get_str("title")
+        .filter(|title| !title.is_empty())
+        .map(str::to_string)
+}
+
+/// Sets the title of the level in `level`, taking it back to its folder name if `title` is
+/// empty. The rest of `level.toml` is kept, though not its comments.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_title(level: &Path, title: &str) -> std::io::Result<()> {
+    let path = path(level);
+    let mut config = match std::fs::read_to_string(&path) {
+        Ok(source) => Config::parse(&source)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Config::new(),
+        Err(err) => return Err(err),
+    };
+
+    config.values.retain(|(key, _)| key != "title");
+
+    if !title.is_empty() {
+        config
+            .values
+            .insert(0, ("title".to_string(), Value::String(title.to_string())));
+    }
+
+    std::fs::write(path, config.to_string())
+}
@@ -0,0 +1,114 @@
+//! The bomb meter of sheets with `#meter`, filled by perfect blocks and spent with space to clear
+//! every projectile off the screen for less than blocking them would give:
+//!
+//! ```text
+//! // full after 30 perfects, cleared projectiles are worth half a perfect
+//! #meter perfects=30 score=50%
+//! ```
+//!
+//! Both settings are optional, the defaults being [`DEFAULT_PERFECTS`] and [`DEFAULT_SCORE`].
+//! Clearing doesn't count towards the combo or the judgments, and heart pickups cleared give
+//! nothing.
+
+use crate::game::Projectile;
+use crate::sheet::Sheet;
+use macroquad::prelude::*;
+
+pub const DEFAULT_PERFECTS: u32 = 30;
+
+/// Percent of a perfect's points each cleared projectile is worth.
+pub const DEFAULT_SCORE: f32 = 50.0;
+
+/// The most perfects a meter can take to fill.
+pub const MAX_PERFECTS: u32 = 1000;
+
+/// Key spending a full meter.
+pub const BOMB_KEY: KeyCode = KeyCode::Space;
+
+/// How a sheet's meter fills and what clearing is worth, set with `#meter`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeterRules {
+    /// Perfects that fill the meter.
+    pub perfects: u32,
+    /// Percent of a perfect's points each cleared projectile is worth.
+    pub score: f32,
+}
+
+impl Default for MeterRules {
+    fn default() -> Self {
+        Self {
+            perfects: DEFAULT_PERFECTS,
+            score: DEFAULT_SCORE,
+        }
+    }
+}
+
+/// The meter of a run.
+#[derive(Clone, Debug, Default)]
+pub struct Meter {
+    /// Perfects since the meter was last spent, up to the sheet's [`MeterRules::perfects`].
+    pub filled: u32,
+}
+
+impl Meter {
+    pub fn perfect(&mut self, rules: &MeterRules) {
+        self.filled = (self.filled + 1).min(rules.perfects);
+    }
+
+    pub fn is_full(&self, rules: &MeterRules) -> bool {
+        self.filled >= rules.perfects
+    }
+
+    pub fn fraction(&self, rules: &MeterRules) -> f32 {
+        self.filled as f32 / rules.perfects as f32
+    }
+
+    /// Spends a full meter clearing `active` off the screen, returning the points the cleared
+    /// projectiles are worth on `sheet`, `None` if the meter isn't full.
+    pub fn detonate(
+        &mut self,
+        rules: &MeterRules,
+        sheet: &Sheet,
+        active: &mut Vec<Projectile>,
+    ) -> Option<u32> {
+        if !self.is_full(rules) {
+            return None;
+        }
+
+        self.filled = 0;
+
+        let worth = (sheet.weights.perfect as f32 * rules.score / 100.0).round() as u32;
+        let cleared = active
+            .drain(..)
+            .filter(|projectile| !projectile.ty.behavior.is_pickup())
+            .count();
+
+        Some(worth * cleared as u32)
+    }
+
+    /// Draws the meter with its top left corner at `corner`, in screen pixels, pulsing at `time`
+    /// once full.
+    pub fn draw(&self, rules: &MeterRules, corner: Vec2, size: Vec2, time: f32) {
+        let color = if self.is_full(rules) {
+            Color::new(1.0, 0.85, 0.2, 0.75 + (time * 8.0).sin() * 0.25)
+        } else {
+            SKYBLUE
+        };
+
+        draw_rectangle(
+            corner.x,
+            corner.y,
+            size.x,
+            size.y,
+            Color::new(0.0, 0.0, 0.0, 0.5),
+        );
+        draw_rectangle(
+            corner.x,
+            corner.y,
+            size.x * self.fraction(rules),
+            size.y,
+            color,
+        );
+        draw_rectangle_lines(corner.x, corner.y, size.x, size.y, 1.0, WHITE);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::config::{Config, Value};
+use crate::game::{Direction, Projectile};
+use crate::locale::Locale;
+
+/// Changes to how a level plays, picked before starting it.
+#[derive(Clone, Debug)]
+pub struct Modifiers {
+    /// Block every projectile automatically.
+    pub autoplay: bool,
+    /// Song speed, 1 is normal speed.
+    pub rate: f32,
+    /// Swap projectiles coming from the left and right.
+    pub mirror: bool,
+    /// Send every projectile from a random side.
+    pub random: bool,
+    /// Fade projectiles out before they reach the shield.
+    pub hidden: bool,
+    /// Keep playing when out of health.
+    pub no_fail: bool,
+    /// Move projectiles with `jitter=` off the beat a little, differently every run.
+    pub humanize: bool,
+    /// Bars between checkpoints a death can be retried from, 0 for none. Sheets with sections
+    /// have a checkpoint at the start of each instead.
+    pub checkpoints: u32,
+    /// Drop the shield after each press, keeping it down building a multiplier on blocks, see
+    /// [`aggro`](crate::aggro).
+    pub aggro: bool,
+}
+
+impl Default for Modifiers {
+    fn default() -> Self {
+        Self {
+            autoplay: false,
+            rate: 1.0,
+            mirror: false,
+            random: false,
+            hidden: false,
+            no_fail: false,
+            humanize: false,
+            checkpoints: 0,
+            aggro: false,
+        }
+    }
+}
+
+impl Modifiers {
+    /// Reads the modifiers from the `modifiers.*` keys of `config`.
+    pub fn from_config(config: &Config) -> Self {
+        let default = Self::default();
+        let get_bool = |key: &str| config.get_bool(&format!("modifiers.{}", key));
+
+        Self {
+            autoplay: get_bool("autoplay").unwrap_or(default.autoplay),
+            rate: config
+                .get_f32("modifiers.rate")
+                .filter(|rate| *rate > 0.0)
+                .unwrap_or(default.rate),
+            mirror: get_bool("mirror").unwrap_or(default.mirror),
+            random: get_bool("random").unwrap_or(default.random),
+            hidden: get_bool("hidden").unwrap_or(default.hidden),
+            no_fail: get_bool("no_fail").unwrap_or(default.no_fail),
+            humanize: get_bool("humanize").unwrap_or(default.humanize),
+            checkpoints: config
+                .get_f32("modifiers.checkpoints")
+                .map_or(default.checkpoints, |bars| bars.max(0.0) as u32),
+            aggro: get_bool("aggro").unwrap_or(default.aggro),
+        }
+    }
+
+    pub fn write_config(&self, config: &mut Config) {
+        config.set("modifiers.autoplay", Value::Bool(self.autoplay));
+        config.set("modifiers.rate", Value::Number(self.rate));
+        config.set("modifiers.mirror", Value::Bool(self.mirror));
+        config.set("modifiers.random", Value::Bool(self.random));
+        config.set("modifiers.hidden", Value::Bool(self.hidden));
+        config.set("modifiers.no_fail", Value::Bool(self.no_fail));
+        config.set("modifiers.humanize", Value::Bool(self.humanize));
+        config.set(
+            "modifiers.checkpoints",
+            Value::Number(self.checkpoints as f32),
+        );
+        config.set("modifiers.aggro", Value::Bool(self.aggro));
+    }
+
+    /// Short text naming the modifiers, like `1.25x mirror hidden`, kept with the scores set
+    /// with them.
+    pub fn tag(&self) -> String {
+        let mut tag = format!("{}x", self.rate);
+
+        for (enabled, name) in [
+            (self.autoplay, "autoplay"),
+            (self.mirror, "mirror"),
+            (self.random, "random"),
+            (self.hidden, "hidden"),
+            (self.no_fail, "no_fail"),
+            (self.humanize, "humanize"),
+            (self.aggro, "aggro"),
+        ] {
+            if enabled {
+                tag += " ";
+                tag += name;
+            }
+        }
+
+        if self.checkpoints > 0 {
+            tag += &format!(" checkpoints={}", self.checkpoints);
+        }
+
+        tag
+    }
+
+    /// What the score is multiplied by, harder modifiers are worth more.
+    pub fn score_multiplier(&self) -> f32 {
+        if self.autoplay {
+            return 0.0;
+        }
+
+        let mut multiplier = self.rate;
+
+        if self.hidden {
+            multiplier *= 1.1;
+        }
+
+        if self.no_fail {
+            multiplier *= 0.5;
+        }
+
+        multiplier
+    }
+
+    /// Whether runs count for scores, not with autoplay, or with humanize since it makes each
+    /// run play a little differently.
+    pub fn ranked(&self) -> bool {
+        !self.autoplay && !self.humanize
+    }
+
+    /// When the `index`th projectile of the sheet arrives, moved by up to its jitter with
+    /// humanize. `seed` picks how far so it stays the same for the whole run.
+    pub fn arrival_time(&self, projectile: &Projectile, seed: u32, index: usize) -> f32 {
+        if !self.humanize || projectile.jitter <= 0.0 {
+            return projectile.arrival_time;
+        }
+
+        // a different seed than random sides use, so the two don't line up
+        let fraction = hash(seed.rotate_left(16), index) as f32 / u32::MAX as f32;
+
+        projectile.arrival_time + (fraction * 2.0 - 1.0) * projectile.jitter
+    }
+
+    /// The direction the `index`th projectile of the sheet comes from, `seed` picks the random
+    /// sides so they stay the same for the whole run.
+    pub fn direction(&self, direction: &Direction, seed: u32, index: usize) -> Direction {
+        let direction = if self.random {
+            match hash(seed, index) % 4 {
+                0 => Direction::Up,
+                1 => Direction::Down,
+                2 => Direction::Left,
+                _ => Direction::Right,
+            }
+        } else {
+            direction.clone()
+        };
+
+        match direction {
+            Direction::Left if self.mirror => Direction::Right,
+            Direction::Right if self.mirror => Direction::Left,
+            direction => direction,
+        }
+    }
+
+    /// Draws the modifier widgets, returns true if anything changed.
+    pub fn ui(&mut self, ui: &mut egui::Ui, locale: &Locale) -> bool {
+        let mut changed = false;
+
+        changed |= ui
+            .add(egui::Slider::new(&mut self.rate, 0.5..=2.0).text(locale.get("modifiers-rate")))
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.mirror, locale.get("modifiers-mirror"))
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.random, locale.get("modifiers-random"))
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.hidden, locale.get("modifiers-hidden"))
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.no_fail, locale.get("modifiers-no-fail"))
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.humanize, locale.get("modifiers-humanize"))
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.aggro, locale.get("modifiers-aggro"))
+            .changed();
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.checkpoints, 0..=32)
+                    .text(locale.get("modifiers-checkpoints")),
+            )
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.autoplay, locale.get("modifiers-autoplay"))
+            .changed();
+
+        ui.label(locale.format(
+            "modifiers-multiplier",
+            &[("multiplier", &format!("{:.2}", self.score_multiplier()))],
+        ));
+
+        if self.humanize {
+            ui.label(locale.get("modifiers-unranked"));
+        } else if self.aggro {
+            ui.label(locale.get("modifiers-aggro-scores"));
+        }
+
+        changed
+    }
+}
+
+/// Mixes `seed` and `index` into a well spread number.
+fn hash(seed: u32, index: usize) -> u32 {
+    let mut x = seed ^ (index as u32).wrapping_mul(0x9e37_79b9);
+
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+
+    x
+}
@@ -0,0 +1,75 @@
+//! How fast projectiles fly as a level goes on. They speed up steadily through every level, and
+//! sheets with `#momentum` can have them speed up with perfect blocks and slow down after misses
+//! too, so the level gets harder while the player keeps up and backs off when they don't:
+//!
+//! ```text
+//! // a little faster on every perfect, a lot slower after a miss, never past 320
+//! #momentum ramp=1 perfect=0.5 miss=24 max=320
+//! ```
+//!
+//! Every setting is optional, left out they're what levels without `#momentum` play at. Speed
+//! gained or lost by blocks eases in at [`EASE`] a second, so projectiles on screen don't jump.
+
+use crate::game::START_SPEED;
+use crate::judgment::Judgment;
+
+/// Speed gained every second on levels without `#momentum`.
+pub const DEFAULT_RAMP: f32 = 2.0;
+
+/// Most speed a second gained or lost from perfects and misses.
+pub const EASE: f32 = 16.0;
+
+/// How a sheet's projectiles speed up, set with `#momentum`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MomentumRules {
+    /// Speed gained every second.
+    pub ramp: f32,
+    /// Speed gained for each perfect block.
+    pub perfect: f32,
+    /// Speed lost for each miss.
+    pub miss: f32,
+    /// Slowest projectiles get, also the speed they start at.
+    pub min: f32,
+    /// Fastest projectiles get, infinite for no limit.
+    pub max: f32,
+}
+
+impl Default for MomentumRules {
+    fn default() -> Self {
+        Self {
+            ramp: DEFAULT_RAMP,
+            perfect: 0.0,
+            miss: 0.0,
+            min: START_SPEED,
+            max: f32::INFINITY,
+        }
+    }
+}
+
+/// The speed still to ease in over a run.
+#[derive(Clone, Debug, Default)]
+pub struct Momentum {
+    /// Speed from blocks not eased in yet, negative after misses.
+    pending: f32,
+}
+
+impl Momentum {
+    /// Speeds up for a block judged `judgment`.
+    pub fn block(&mut self, rules: &MomentumRules, judgment: Judgment) {
+        if judgment == Judgment::Perfect {
+            self.pending += rules.perfect;
+        }
+    }
+
+    pub fn miss(&mut self, rules: &MomentumRules) {
+        self.pending -= rules.miss;
+    }
+
+    /// Moves `speed` on by `delta` seconds of the ramp and of what's being eased in.
+    pub fn update(&mut self, rules: &MomentumRules, speed: &mut f32, delta: f32) {
+        let eased = self.pending.clamp(-EASE * delta, EASE * delta);
+
+        self.pending -= eased;
+        *speed = (*speed + delta * rules.ramp + eased).clamp(rules.min, rules.max);
+    }
+}
@@ -0,0 +1,176 @@
+//! The outline and glow projectiles are drawn with when [`Settings::high_contrast`] is on, so
+//! they stay readable over bright level shaders. Its color and the strength of the glow are set
+//! in the `[outline]` section of the effects file.
+//!
+//! [`Settings::high_contrast`]: crate::settings::Settings::high_contrast
+
+use crate::atlas::{Sprite, PADDING};
+use macroquad::prelude::*;
+use std::cell::Cell;
+
+/// Pixels around a sprite the outline and glow reach, as far as the shader samples. Never more
+/// than the atlas' padding, so they don't pick up the sprites next to it.
+pub const MARGIN: u16 = PADDING;
+
+const VERTEX: &str = r#"
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec2 texcoord;
+layout(location = 2) in vec4 color0;
+
+layout(location = 0) out vec2 uv;
+layout(location = 1) out vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1.0);
+    color = color0 / 255.0;
+    uv = texcoord;
+}
+"#;
+
+const FRAGMENT: &str = r#"
+#version 450
+
+layout(location = 0) in vec2 uv;
+layout(location = 1) in vec4 color;
+
+layout(location = 0) out vec4 color_out;
+
+uniform sampler2D Texture;
+// size of a pixel of the atlas in uv
+uniform vec2 texel;
+uniform vec4 outline_color;
+uniform float glow;
+
+// `top` drawn over `bottom`, neither premultiplied
+vec4 over(vec4 top, vec4 bottom) {
+    float alpha = top.a + bottom.a * (1.0 - top.a);
+
+    if (alpha <= 0.0) {
+        return vec4(0.0);
+    }
+
+    return vec4((top.rgb * top.a + bottom.rgb * bottom.a * (1.0 - top.a)) / alpha, alpha);
+}
+
+void main() {
+    vec4 sprite = texture(Texture, uv) * color;
+
+    float outline = 0.0;
+    float halo = 0.0;
+
+    for (int x = -3; x <= 3; x++) {
+        for (int y = -3; y <= 3; y++) {
+            float distance = length(vec2(x, y));
+            float alpha = texture(Texture, uv + vec2(x, y) * texel).a;
+
+            if (distance <= 1.5) {
+                outline = max(outline, alpha);
+            }
+
+            halo += alpha * max(0.0, 1.0 - distance / 3.5);
+        }
+    }
+
+    vec4 glowing = vec4(color.rgb, clamp(halo / 8.0 * glow, 0.0, 1.0) * color.a);
+    vec4 outlined = vec4(outline_color.rgb, outline * outline_color.a * color.a);
+
+    color_out = over(sprite, over(outlined, glowing));
+}
+"#;
+
+/// How outlines look, from the `[outline]` section of the effects file.
+#[derive(Clone, Debug)]
+pub struct OutlineStyle {
+    pub color: Color,
+    /// Strength of the glow in the sprite's own color, 0 for none.
+    pub glow: f32,
+}
+
+impl Default for OutlineStyle {
+    fn default() -> Self {
+        Self {
+            color: BLACK,
+            glow: 0.5,
+        }
+    }
+}
+
+thread_local! {
+    /// The outline material once it's loaded, macroquad never frees a material's pipeline.
+    static MATERIAL: Cell<Option<Material>> = const { Cell::new(None) };
+}
+
+/// The material outlines are drawn with, loaded the first time it's needed and shared by every
+/// level after.
+pub fn material() -> Material {
+    MATERIAL.with(|cached| {
+        let material = cached.get().unwrap_or_else(load_material);
+
+        cached.set(Some(material));
+
+        material
+    })
+}
+
+fn load_material() -> Material {
+    macroquad::material::load_material(
+        VERTEX,
+        FRAGMENT,
+        MaterialParams {
+            uniforms: vec![
+                ("texel".to_string(), UniformType::Float2),
+                ("outline_color".to_string(), UniformType::Float4),
+                ("glow".to_string(), UniformType::Float1),
+            ],
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+/// Draws `sprite` tinted `color` and turned by `rotation` with its top left corner at `x`, `y`,
+/// outlined through `material` the way `style` says.
+pub fn draw_outlined(
+    material: Material,
+    style: &OutlineStyle,
+    sprite: Sprite,
+    (x, y): (f32, f32),
+    rotation: f32,
+    color: Color,
+) {
+    let margin = MARGIN as f32;
+    let outline = style.color;
+
+    material.set_uniform("texel", Vec2::ONE / sprite.texture_size());
+    material.set_uniform(
+        "outline_color",
+        [outline.r, outline.g, outline.b, outline.a],
+    );
+    material.set_uniform("glow", style.glow);
+
+    gl_use_material(material);
+
+    // drawn bigger than the sprite so the outline has room around it
+    sprite.draw(
+        x - margin,
+        y - margin,
+        color,
+        DrawTextureParams {
+            source: Some(Rect::new(
+                -margin,
+                -margin,
+                sprite.width() + margin * 2.0,
+                sprite.height() + margin * 2.0,
+            )),
+            rotation,
+            ..Default::default()
+        },
+    );
+
+    gl_use_default_material();
+}
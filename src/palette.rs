@@ -0,0 +1,99 @@
+use crate::game::Direction;
+use macroquad::prelude::*;
+
+/// Colorblind safe palettes projectiles and the shield can be tinted with by direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Palette {
+    Off,
+    OkabeIto,
+    TolBright,
+    Ibm,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 4] = [Self::Off, Self::OkabeIto, Self::TolBright, Self::Ibm];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::OkabeIto => "okabe-ito",
+            Self::TolBright => "tol-bright",
+            Self::Ibm => "ibm",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|palette| palette.name() == name)
+    }
+
+    /// Colors for up, down, left and right.
+    fn colors(&self) -> Option<[u32; 4]> {
+        match self {
+            Self::Off => None,
+            Self::OkabeIto => Some([0xE69F00, 0x56B4E9, 0x009E73, 0xCC79A7]),
+            Self::TolBright => Some([0x4477AA, 0xCCBB44, 0x66CCEE, 0xAA3377]),
+            Self::Ibm => Some([0x648FFF, 0xFFB000, 0xDC267F, 0xFE6100]),
+        }
+    }
+
+    pub fn color(&self, direction: &Direction) -> Color {
+        let colors = match self.colors() {
+            Some(colors) => colors,
+            None => return WHITE,
+        };
+
+        let rgb = match direction {
+            Direction::Up => colors[0],
+            Direction::Down => colors[1],
+            Direction::Left => colors[2],
+            Direction::Right => colors[3],
+        };
+
+        Color::new(
+            ((rgb >> 16) & 0xff) as f32 / 255.0,
+            ((rgb >> 8) & 0xff) as f32 / 255.0,
+            (rgb & 0xff) as f32 / 255.0,
+            1.0,
+        )
+    }
+}
+
+/// Draws a small shape unique to `direction` centered on `position`, so directions can be told
+/// apart without relying on color.
+pub fn draw_direction_glyph(direction: &Direction, position: Vec2, color: Color) {
+    let size = 3.0;
+
+    match direction {
+        Direction::Up => draw_triangle(
+            position + vec2(0.0, -size),
+            position + vec2(-size, size),
+            position + vec2(size, size),
+            color,
+        ),
+        Direction::Down => draw_circle(position.x, position.y, size, color),
+        Direction::Left => draw_rectangle(
+            position.x - size,
+            position.y - size,
+            size * 2.0,
+            size * 2.0,
+            color,
+        ),
+        Direction::Right => {
+            draw_triangle(
+                position + vec2(0.0, -size),
+                position + vec2(-size, 0.0),
+                position + vec2(size, 0.0),
+                color,
+            );
+            draw_triangle(
+                position + vec2(0.0, size),
+                position + vec2(-size, 0.0),
+                position + vec2(size, 0.0),
+                color,
+            );
+        }
+    }
+}
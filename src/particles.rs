@@ -1,12 +1,26 @@
+use crate::atlas::Sprite;
+use crate::gpu_particles::{GpuParticles, GpuRenderer};
 use macroquad::prelude::*;
+use std::rc::Rc;
 
 pub trait ParticleSpawner {
     fn spawn_particles(&self) -> Vec<Particle>;
 }
 
+/// Spawns particles from `spawner` offset by `position`.
+pub fn spawn_particles_at(spawner: &dyn ParticleSpawner, position: Vec2) -> Vec<Particle> {
+    let mut particles = spawner.spawn_particles();
+
+    for particle in &mut particles {
+        particle.position += position;
+    }
+
+    particles
+}
+
 #[derive(Default)]
 pub struct DirectionalExplosion {
-    pub texture: Option<Texture2D>,
+    pub texture: Option<Sprite>,
     pub amount: usize,
     pub color: Color,
     pub position: Vec2,
@@ -16,12 +30,13 @@ pub struct DirectionalExplosion {
     pub angular_velocity: std::ops::Range<f32>,
     pub life_time: f32,
     pub size: f32,
+    /// Spawned where each particle dies.
+    pub sub_emitter: Option<Rc<dyn ParticleSpawner>>,
 }
 
 impl ParticleSpawner for DirectionalExplosion {
     fn spawn_particles(&self) -> Vec<Particle> {
         (0..self.amount)
-            .into_iter()
             .map(|_| {
                 let direction = rand::gen_range(self.direction.start, self.direction.end);
                 let speed = rand::gen_range(self.speed.start, self.speed.end);
@@ -30,7 +45,7 @@ impl ParticleSpawner for DirectionalExplosion {
                     rand::gen_range(self.angular_velocity.start, self.angular_velocity.end);
 
                 Particle {
-                    texture: self.texture.clone(),
+                    texture: self.texture,
                     position: self.position,
                     rotation: rand::gen_range(self.rotation.start, self.rotation.end),
                     velocity,
@@ -39,22 +54,205 @@ impl ParticleSpawner for DirectionalExplosion {
                     color: self.color,
                     life: 0.0,
                     life_time: self.life_time,
+                    sub_emitter: self.sub_emitter.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Spawns particles at random positions inside a rectangle, drifting slowly.
+#[derive(Default)]
+pub struct AreaSpawner {
+    pub texture: Option<Sprite>,
+    pub amount: usize,
+    pub color: Color,
+    pub area: Rect,
+    pub velocity: std::ops::Range<f32>,
+    pub life_time: f32,
+    pub size: f32,
+    /// Spawned where each particle dies.
+    pub sub_emitter: Option<Rc<dyn ParticleSpawner>>,
+}
+
+impl ParticleSpawner for AreaSpawner {
+    fn spawn_particles(&self) -> Vec<Particle> {
+        (0..self.amount)
+            .map(|_| {
+                let position = vec2(
+                    rand::gen_range(self.area.x, self.area.x + self.area.w),
+                    rand::gen_range(self.area.y, self.area.y + self.area.h),
+                );
+                let velocity = vec2(
+                    rand::gen_range(self.velocity.start, self.velocity.end),
+                    rand::gen_range(self.velocity.start, self.velocity.end),
+                );
+
+                Particle {
+                    texture: self.texture,
+                    position,
+                    rotation: 0.0,
+                    velocity,
+                    angular_velocity: 0.0,
+                    size: self.size,
+                    color: self.color,
+                    life: 0.0,
+                    life_time: self.life_time,
+                    sub_emitter: self.sub_emitter.clone(),
                 }
             })
             .collect()
     }
 }
 
+/// Spawns particles evenly spaced on a circle, moving outwards.
+#[derive(Default)]
+pub struct Ring {
+    pub texture: Option<Sprite>,
+    pub amount: usize,
+    pub color: Color,
+    pub position: Vec2,
+    pub radius: f32,
+    pub speed: f32,
+    pub life_time: f32,
+    pub size: f32,
+    /// Spawned where each particle dies.
+    pub sub_emitter: Option<Rc<dyn ParticleSpawner>>,
+}
+
+impl ParticleSpawner for Ring {
+    fn spawn_particles(&self) -> Vec<Particle> {
+        (0..self.amount)
+            .map(|i| {
+                let angle = i as f32 / self.amount as f32 * std::f32::consts::TAU;
+                let direction = vec2(angle.cos(), angle.sin());
+
+                Particle {
+                    texture: self.texture,
+                    position: self.position + direction * self.radius,
+                    rotation: angle,
+                    velocity: direction * self.speed,
+                    angular_velocity: 0.0,
+                    size: self.size,
+                    color: self.color,
+                    life: 0.0,
+                    life_time: self.life_time,
+                    sub_emitter: self.sub_emitter.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Spawns particles flung out from a point in every direction, each a random one of `colors`,
+/// tumbling as they go.
+#[derive(Default)]
+pub struct Confetti {
+    pub texture: Option<Sprite>,
+    pub amount: usize,
+    pub colors: Vec<Color>,
+    pub position: Vec2,
+    pub speed: std::ops::Range<f32>,
+    pub life_time: f32,
+    pub size: f32,
+}
+
+impl ParticleSpawner for Confetti {
+    fn spawn_particles(&self) -> Vec<Particle> {
+        (0..self.amount)
+            .map(|_| {
+                let direction = rand::gen_range(0.0, std::f32::consts::TAU);
+                let speed = rand::gen_range(self.speed.start, self.speed.end);
+                let color = match self.colors.len() {
+                    0 => WHITE,
+                    count => self.colors[rand::gen_range(0, count)],
+                };
+
+                Particle {
+                    texture: self.texture,
+                    position: self.position,
+                    rotation: rand::gen_range(0.0, std::f32::consts::TAU),
+                    velocity: vec2(direction.cos(), direction.sin()) * speed,
+                    angular_velocity: rand::gen_range(-8.0, 8.0),
+                    size: self.size,
+                    color,
+                    life: 0.0,
+                    // some fade out sooner, so the burst thins out instead of vanishing at once
+                    life_time: self.life_time * rand::gen_range(0.5, 1.0),
+                    sub_emitter: None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// A long-lived particle source that spawns from a [`ParticleSpawner`] at a fixed rate.
+///
+/// Particles spawned by the spawner are offset by the emitter's `position`.
+pub struct Emitter {
+    pub spawner: Box<dyn ParticleSpawner>,
+    pub position: Vec2,
+    /// Spawns per second.
+    pub rate: f32,
+    /// How long the emitter lives, `None` means it lives until removed.
+    pub life_time: Option<f32>,
+    pub age: f32,
+    /// Fractional spawns carried over between frames, a spawn happens every time this reaches 1.
+    pub accumulator: f32,
+}
+
+impl Emitter {
+    pub fn new(spawner: impl ParticleSpawner + 'static, rate: f32) -> Self {
+        Self {
+            spawner: Box::new(spawner),
+            position: Vec2::ZERO,
+            rate,
+            life_time: None,
+            age: 0.0,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn with_position(mut self, position: Vec2) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_life_time(mut self, life_time: f32) -> Self {
+        self.life_time = Some(life_time);
+        self
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.life_time.is_none_or(|life_time| self.age < life_time)
+    }
+
+    pub fn update(&mut self, frame_time: f32, particles: &mut Vec<Particle>) {
+        self.age += frame_time;
+        self.accumulator += self.rate * frame_time;
+
+        while self.accumulator >= 1.0 {
+            self.accumulator -= 1.0;
+
+            particles.append(&mut spawn_particles_at(
+                self.spawner.as_ref(),
+                self.position,
+            ));
+        }
+    }
+}
+
 pub struct Particle {
     pub position: Vec2,
     pub velocity: Vec2,
     pub rotation: f32,
     pub angular_velocity: f32,
-    pub texture: Option<Texture2D>,
+    pub texture: Option<Sprite>,
     pub color: Color,
     pub size: f32,
     pub life: f32,
     pub life_time: f32,
+    pub sub_emitter: Option<Rc<dyn ParticleSpawner>>,
 }
 
 impl Particle {
@@ -71,11 +269,10 @@ impl Particle {
     }
 
     pub fn draw(&self) {
-        if let Some(texture) = self.texture {
-            draw_texture_ex(
-                texture,
-                self.position.x - texture.width() / 2.0,
-                self.position.y - texture.height() / 2.0,
+        if let Some(sprite) = self.texture {
+            sprite.draw(
+                self.position.x - sprite.width() / 2.0,
+                self.position.y - sprite.height() / 2.0,
                 self.color,
                 DrawTextureParams {
                     rotation: self.rotation,
@@ -90,11 +287,30 @@ impl Particle {
 
 pub struct ParticleSystem {
     pub particles: Vec<Particle>,
+    pub emitters: Vec<Emitter>,
+    /// Particles of bursts moved by the GPU, `None` when they all stay on the CPU.
+    pub gpu: Option<GpuParticles>,
 }
 
 impl ParticleSystem {
     pub fn new() -> Self {
-        Self { particles: vec![] }
+        Self {
+            particles: vec![],
+            emitters: vec![],
+            gpu: None,
+        }
+    }
+
+    /// A system that hands [`ParticleSystem::burst`] to the GPU.
+    pub fn with_gpu() -> Self {
+        Self {
+            gpu: Some(GpuParticles::default()),
+            ..Self::new()
+        }
+    }
+
+    pub fn add_emitter(&mut self, emitter: Emitter) {
+        self.emitters.push(emitter);
     }
 
     pub fn spawn(&mut self, spawner: &impl ParticleSpawner) {
@@ -103,17 +319,60 @@ impl ParticleSystem {
         self.particles.append(&mut particles);
     }
 
+    /// Like [`ParticleSystem::spawn`], for effects with many particles, spawned on the GPU when
+    /// the system has it and they can run there.
+    pub fn burst(&mut self, spawner: &impl ParticleSpawner) {
+        let mut particles = spawner.spawn_particles();
+
+        if let Some(gpu) = &mut self.gpu {
+            if gpu.push(&particles) {
+                return;
+            }
+        }
+
+        self.particles.append(&mut particles);
+    }
+
     pub fn update(&mut self, frame_time: f32) {
+        if let Some(gpu) = &mut self.gpu {
+            gpu.update(frame_time);
+        }
+
+        for emitter in &mut self.emitters {
+            emitter.update(frame_time, &mut self.particles);
+        }
+
+        self.emitters.retain(|emitter| emitter.is_alive());
+
         for particle in &mut self.particles {
             particle.update(frame_time);
         }
 
-        self.particles.retain(|p| p.is_alive());
+        let mut children = Vec::new();
+
+        self.particles.retain(|p| {
+            if p.is_alive() {
+                return true;
+            }
+
+            if let Some(sub_emitter) = &p.sub_emitter {
+                children.append(&mut spawn_particles_at(sub_emitter.as_ref(), p.position));
+            }
+
+            false
+        });
+
+        self.particles.append(&mut children);
     }
 
-    pub fn draw(&self) {
+    /// Draws the particles, the GPU's through `renderer`.
+    pub fn draw(&self, renderer: Option<&GpuRenderer>) {
         for particle in &self.particles {
             particle.draw();
         }
+
+        if let (Some(gpu), Some(renderer)) = (&self.gpu, renderer) {
+            renderer.draw(gpu);
+        }
     }
 }
@@ -0,0 +1,85 @@
+use crate::settings::Settings;
+use egui::*;
+use macroquad::prelude::*;
+
+pub enum PauseAction {
+    Resume,
+    Restart,
+    /// Start or stop looping the current section.
+    Practice,
+    Quit,
+}
+
+/// Shown over a paused level.
+pub struct PauseMenu;
+
+impl PauseMenu {
+    /// Shows the menu, `song_offset` being the offset of the level in seconds.
+    pub fn update(
+        &self,
+        settings: &Settings,
+        practicing: bool,
+        song_offset: f32,
+    ) -> Option<PauseAction> {
+        // big enough to hit with a finger
+        let button_size = [160.0 * settings.ui_scale, 40.0 * settings.ui_scale];
+
+        let locale = &settings.locale;
+        let mut action = None;
+
+        if is_key_pressed(KeyCode::Escape) {
+            action = Some(PauseAction::Resume);
+        } else if is_key_pressed(settings.restart_key) {
+            action = Some(PauseAction::Restart);
+        }
+
+        set_default_camera();
+
+        egui_macroquad::ui(|ctx| {
+            egui::Window::new(locale.get("pause-title"))
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    if ui
+                        .add_sized(button_size, Button::new(locale.get("pause-resume")))
+                        .clicked()
+                    {
+                        action = Some(PauseAction::Resume);
+                    }
+
+                    if ui
+                        .add_sized(button_size, Button::new(locale.get("pause-restart")))
+                        .clicked()
+                    {
+                        action = Some(PauseAction::Restart);
+                    }
+
+                    let practice = if practicing {
+                        locale.get("pause-stop-practice")
+                    } else {
+                        locale.get("pause-practice")
+                    };
+
+                    if ui.add_sized(button_size, Button::new(practice)).clicked() {
+                        action = Some(PauseAction::Practice);
+                    }
+
+                    if ui
+                        .add_sized(button_size, Button::new(locale.get("pause-quit")))
+                        .clicked()
+                    {
+                        action = Some(PauseAction::Quit);
+                    }
+
+                    let offset = format!("{:+.0}", song_offset * 1000.0);
+
+                    ui.label(locale.format("pause-song-offset", &[("offset", &offset)]));
+                });
+        });
+
+        egui_macroquad::draw();
+
+        action
+    }
+}
@@ -0,0 +1,211 @@
+//! Power-ups dropped by blocked projectiles that end with a `drop=` in the sheet, after any
+//! `jitter=`:
+//!
+//! ```text
+//! // slows the level down
+//! norm L 0;0|2 drop=slow
+//! // widens the timing windows
+//! norm U 0;2|2 jitter=4ms drop=wide
+//! // shields block projectiles from the opposite side too
+//! norm R 0;0|3 drop=double
+//! ```
+//!
+//! Drops drift to the heart of their lane and are collected [`PICKUP_TRAVEL`] seconds after the
+//! block, their effect lasting [`EFFECT_LENGTH`] seconds. Collecting one that's already active
+//! starts it over. Everything runs on the level's clock, so replay verification collects the
+//! same drops at the same times as the game did.
+
+use crate::atlas::Sprite;
+use crate::game::{lane_center, Direction};
+use crate::judgment::TimingWindows;
+use macroquad::prelude::*;
+
+/// Seconds a drop takes to drift from where its projectile was blocked to the heart.
+pub const PICKUP_TRAVEL: f32 = 0.75;
+
+/// Seconds an effect lasts for once collected.
+pub const EFFECT_LENGTH: f32 = 5.0;
+
+/// How fast the level's clock runs under slow motion.
+pub const SLOW_SCALE: f32 = 0.75;
+
+/// How much wider the timing windows are while widened.
+pub const WIDE_WINDOWS: f32 = 1.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PickupKind {
+    /// Slows the level down, the song playing on.
+    Slow,
+    /// Widens the timing windows.
+    Wide,
+    /// Shields block projectiles from the side opposite the one they face too.
+    Double,
+}
+
+impl PickupKind {
+    pub const ALL: [(Self, &'static str); 3] = [
+        (Self::Slow, "slow"),
+        (Self::Wide, "wide"),
+        (Self::Double, "double"),
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|(_, other)| *other == name)
+            .map(|(kind, _)| *kind)
+    }
+
+    pub fn name(self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(kind, _)| *kind == self)
+            .map_or("", |(_, name)| name)
+    }
+
+    /// The locale id of the effect shown on the hud while it's active.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Slow => "hud-pickup-slow",
+            Self::Wide => "hud-pickup-wide",
+            Self::Double => "hud-pickup-double",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Self::Slow => SKYBLUE,
+            Self::Wide => GOLD,
+            Self::Double => VIOLET,
+        }
+    }
+}
+
+/// A drop on its way to the heart.
+#[derive(Clone, Debug)]
+pub struct Drop {
+    pub kind: PickupKind,
+    pub lane: usize,
+    /// Where the projectile it dropped from was blocked, in the arena.
+    pub from: Vec2,
+    /// When the projectile was blocked.
+    pub time: f32,
+}
+
+/// A collected effect.
+#[derive(Clone, Debug)]
+pub struct Effect {
+    pub kind: PickupKind,
+    /// When the effect wears off.
+    pub until: f32,
+}
+
+/// The drops and effects of a run.
+#[derive(Clone, Debug, Default)]
+pub struct Pickups {
+    pub drops: Vec<Drop>,
+    pub effects: Vec<Effect>,
+}
+
+impl Pickups {
+    /// Drops `kind` from a projectile blocked on `lane` at `from` at `time`.
+    pub fn drop(&mut self, kind: PickupKind, lane: usize, from: Vec2, time: f32) {
+        self.drops.push(Drop {
+            kind,
+            lane,
+            from,
+            time,
+        });
+    }
+
+    /// Collects the drops that reached their heart by `time` and wears off expired effects,
+    /// returning what was collected.
+    pub fn update(&mut self, time: f32) -> Vec<PickupKind> {
+        let mut collected = Vec::new();
+
+        self.drops.retain(|drop| {
+            let arrived = drop.time + PICKUP_TRAVEL;
+
+            if arrived > time {
+                return true;
+            }
+
+            collected.push((drop.kind, arrived));
+
+            false
+        });
+
+        for (kind, arrived) in &collected {
+            self.effects.retain(|effect| effect.kind != *kind);
+            self.effects.push(Effect {
+                kind: *kind,
+                until: arrived + EFFECT_LENGTH,
+            });
+        }
+
+        self.effects.retain(|effect| effect.until > time);
+
+        collected.into_iter().map(|(kind, _)| kind).collect()
+    }
+
+    pub fn is_active(&self, kind: PickupKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    /// How fast the level's clock runs for the effects.
+    pub fn time_scale(&self) -> f32 {
+        if self.is_active(PickupKind::Slow) {
+            SLOW_SCALE
+        } else {
+            1.0
+        }
+    }
+
+    /// `windows` as widened by the effects.
+    pub fn windows(&self, windows: &TimingWindows) -> TimingWindows {
+        if self.is_active(PickupKind::Wide) {
+            TimingWindows {
+                perfect: windows.perfect * WIDE_WINDOWS,
+                great: windows.great * WIDE_WINDOWS,
+            }
+        } else {
+            windows.clone()
+        }
+    }
+
+    /// The side a shield facing `shield` blocks projectiles from `direction` on, which is the
+    /// projectile's own when doubled shields cover the opposite side.
+    pub fn shield(&self, shield: &Option<Direction>, direction: &Direction) -> Option<Direction> {
+        let doubled = self.is_active(PickupKind::Double)
+            && shield
+                .as_ref()
+                .is_some_and(|shield| shield.opposite() == *direction);
+
+        if doubled {
+            Some(direction.clone())
+        } else {
+            shield.clone()
+        }
+    }
+
+    /// Draws the drops on their way to the hearts of `lanes` lanes at `time`.
+    pub fn draw(&self, sprite: Sprite, time: f32, lanes: usize) {
+        for drop in &self.drops {
+            // speeding up as it gets closer
+            let progress = ((time - drop.time) / PICKUP_TRAVEL).clamp(0.0, 1.0);
+            let position = drop
+                .from
+                .lerp(lane_center(drop.lane, lanes), progress * progress);
+
+            sprite.draw(
+                position.x - sprite.width() / 2.0,
+                position.y - sprite.height() / 2.0,
+                drop.kind.color(),
+                DrawTextureParams {
+                    rotation: time * 4.0,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! Playlists, levels queued up in the menu to be played back to back as a marathon. The score and
+//! the hearts left carry over from each level to the next, the marathon ending on a death or once
+//! the last level is cleared, with the results of every level played shown together.
+//!
+//! The playlist of a profile is kept in its `playlist.txt`, a level folder per line. The next
+//! level is loaded once every projectile of the one playing has been dealt with, while the end of
+//! its song plays out, so the next one starts right as it's over. It's loaded a step a frame so
+//! the song doesn't stutter, finished all at once if the song ends first.
+//!
+//! Marathons of a [`Course`] are scored as a whole, a run's total kept under the course.
+
+use crate::course::Course;
+use crate::end_menu::{Ending, Summary};
+use crate::game::{AssetLoader, Assets, GameState};
+use crate::log_error;
+use crate::modifiers::Modifiers;
+use crate::profile::Profile;
+use crate::settings::Settings;
+use egui::*;
+use std::path::PathBuf;
+
+/// Levels queued to be played as a marathon.
+#[derive(Clone, Debug, Default)]
+pub struct Playlist {
+    pub levels: Vec<PathBuf>,
+}
+
+impl Playlist {
+    fn path(profile: &Profile) -> PathBuf {
+        profile.dir().join("playlist.txt")
+    }
+
+    pub fn load(profile: &Profile) -> Self {
+        let levels = profile
+            .read(&Self::path(profile))
+            .unwrap_or_default()
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect();
+
+        Self { levels }
+    }
+
+    pub fn save(&self, profile: &Profile) {
+        let contents: String = self
+            .levels
+            .iter()
+            .map(|level| format!("{}\n", level.display()))
+            .collect();
+
+        if let Err(err) = profile.write(&Self::path(profile), &contents) {
+            log_error!("failed to save playlist: {}", err);
+        }
+    }
+
+    /// Moves the level at `index` by `by` places, staying within the playlist.
+    pub fn move_level(&mut self, index: usize, by: isize) {
+        let to = index as isize + by;
+
+        if index < self.levels.len() && (0..self.levels.len() as isize).contains(&to) {
+            self.levels.swap(index, to as usize);
+        }
+    }
+}
+
+/// How a level of a marathon went.
+pub struct LevelResult {
+    /// The level's folder name.
+    pub level: String,
+    /// Points scored on the level alone, with the modifiers' multiplier.
+    pub score: u32,
+    pub best_combo: u32,
+    /// Perfects, greats, lates and misses.
+    pub judgments: [u32; 4],
+}
+
+/// A playlist being played.
+pub struct Marathon {
+    pub levels: Vec<PathBuf>,
+    /// Index of the level playing.
+    pub current: usize,
+    /// Points from the levels before the one playing, without the modifiers' multiplier.
+    carried_score: u32,
+    /// Fraction of the hearts left at the end of the level before.
+    carried_hearts: f32,
+    pub results: Vec<LevelResult>,
+    /// The next level's assets, loaded while the end of the current song plays, or why they
+    /// didn't load.
+    preloaded: Option<Result<Assets, String>>,
+    /// The next level's assets while they're still being loaded.
+    preloading: Option<AssetLoader>,
+    /// How the marathon ended, `None` while it's still going.
+    pub ending: Option<Ending>,
+    /// The course played, `None` for the player's own playlist.
+    pub course: Option<Course>,
+}
+
+impl Marathon {
+    pub fn new(levels: Vec<PathBuf>) -> Self {
+        Self {
+            levels,
+            current: 0,
+            carried_score: 0,
+            carried_hearts: 1.0,
+            results: Vec::new(),
+            preloaded: None,
+            preloading: None,
+            ending: None,
+            course: None,
+        }
+    }
+
+    pub fn of_course(course: Course) -> Self {
+        Self {
+            course: Some(course.clone()),
+            ..Self::new(course.levels)
+        }
+    }
+
+    /// Goes back to the first level, forgetting the levels played.
+    pub fn restart(&mut self) {
+        *self = Self {
+            course: self.course.take(),
+            ..Self::new(std::mem::take(&mut self.levels))
+        };
+    }
+
+    /// The level after the one playing, `None` on the last one.
+    pub fn next_level(&self) -> Option<&PathBuf> {
+        self.levels.get(self.current + 1)
+    }
+
+    /// Takes the next step of loading the next level's assets, until they're loaded.
+    pub async fn preload(&mut self, settings: &Settings) {
+        let next = match self.next_level() {
+            Some(next) if self.preloaded.is_none() => next.clone(),
+            _ => return,
+        };
+        let loader = self
+            .preloading
+            .get_or_insert_with(|| AssetLoader::new(next, None));
+
+        if let Some(loaded) = loader.step(settings).await {
+            self.preloaded = Some(loaded);
+            self.preloading = None;
+        }
+    }
+
+    /// The assets of the level that's up, the ones preloaded for it if there are any, the rest of
+    /// them loaded if they weren't done yet.
+    pub async fn take_assets(&mut self, settings: &Settings) -> Result<Assets, String> {
+        let level = self.levels[self.current].clone();
+
+        match (self.preloaded.take(), self.preloading.take()) {
+            (Some(loaded), _) => loaded,
+            (None, Some(loader)) if loader.song_path == level => loader.finish(settings).await,
+            _ => Assets::load(level, None, settings).await,
+        }
+    }
+
+    /// Starts `state` with the score and hearts the marathon has carried so far.
+    pub fn carry_into(&self, state: &mut GameState) {
+        state.run.score = self.carried_score;
+        state.run.health.hearts = state.run.health.max * self.carried_hearts;
+    }
+
+    /// Keeps how the level playing went, `summary` being its summary, and moves on to the next
+    /// level if it was cleared and there's one left.
+    pub fn finish_level(&mut self, state: &GameState, summary: &Summary) {
+        let multiplier = state.run.modifiers.score_multiplier();
+        let carried = (self.carried_score as f32 * multiplier).round() as u32;
+        let level = self.levels[self.current]
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+
+        self.results.push(LevelResult {
+            level,
+            score: summary.score.saturating_sub(carried),
+            best_combo: summary.best_combo,
+            judgments: summary.judgments,
+        });
+
+        match summary.ending {
+            Ending::Cleared if self.next_level().is_some() => {
+                self.carried_score = state.run.score;
+                self.carried_hearts = state.run.health.fraction();
+                self.current += 1;
+            }
+            Ending::Cleared => self.ending = Some(Ending::Cleared),
+            Ending::Died => self.ending = Some(Ending::Died),
+        }
+    }
+
+    /// The score of every level played together.
+    pub fn total(&self) -> u32 {
+        self.results.iter().map(|result| result.score).sum()
+    }
+
+    /// How a marathon of a course went once it's over, played with `modifiers`, to be kept as
+    /// the course's score. `None` while it's going, for playlists, and for courses with a level
+    /// that doesn't load.
+    pub fn course_summary(&self, modifiers: &Modifiers) -> Option<Summary> {
+        let chart = self.course.as_ref()?.chart?;
+        let mut judgments = [0; 4];
+
+        for result in &self.results {
+            for (total, count) in judgments.iter_mut().zip(result.judgments) {
+                *total += count;
+            }
+        }
+
+        Some(Summary {
+            ending: self.ending?,
+            score: self.total(),
+            best_combo: self
+                .results
+                .iter()
+                .map(|result| result.best_combo)
+                .max()
+                .unwrap_or(0),
+            replay: false,
+            checkpoint: false,
+            chart,
+            modifiers: modifiers.tag(),
+            accuracy: None,
+            full_combo: false,
+            judgments,
+        })
+    }
+}
+
+pub enum MarathonAction {
+    /// Play the playlist again from the first level.
+    Retry,
+    Quit,
+}
+
+/// Shown once a marathon is over, with the results of every level played.
+pub struct MarathonResults;
+
+impl MarathonResults {
+    pub fn update(&self, settings: &Settings, marathon: &Marathon) -> Option<MarathonAction> {
+        let button_size = [200.0 * settings.ui_scale, 40.0 * settings.ui_scale];
+
+        let locale = &settings.locale;
+        let mut action = None;
+
+        let title = match (&marathon.ending, marathon.results.last()) {
+            (Some(Ending::Died), Some(result)) => {
+                locale.format("marathon-died", &[("level", &result.level)])
+            }
+            _ => locale.get("marathon-cleared").to_string(),
+        };
+
+        macroquad::prelude::set_default_camera();
+
+        egui_macroquad::ui(|ctx| {
+            egui::Window::new(title)
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    for result in &marathon.results {
+                        ui.label(locale.format(
+                            "marathon-level",
+                            &[
+                                ("level", &result.level),
+                                ("score", &result.score),
+                                ("streak", &result.best_combo),
+                            ],
+                        ));
+                    }
+
+                    ui.separator();
+                    ui.label(locale.format(
+                        "marathon-total",
+                        &[
+                            ("score", &marathon.total()),
+                            ("played", &marathon.results.len()),
+                            ("count", &marathon.levels.len()),
+                        ],
+                    ));
+
+                    if ui
+                        .add_sized(button_size, Button::new(locale.get("marathon-retry")))
+                        .clicked()
+                    {
+                        action = Some(MarathonAction::Retry);
+                    }
+
+                    if ui
+                        .add_sized(button_size, Button::new(locale.get("results-quit")))
+                        .clicked()
+                    {
+                        action = Some(MarathonAction::Quit);
+                    }
+                });
+        });
+
+        egui_macroquad::draw();
+
+        action
+    }
+}
@@ -0,0 +1,253 @@
+use crate::canvas;
+use crate::game::VERTEX;
+use macroquad::prelude::*;
+
+const FRAGMENT: &str = r#"
+#version 450
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 color_out;
+
+uniform sampler2D scene;
+uniform sampler2D bloom;
+// letterboxed area of the screen the canvas is drawn to, in pixels
+uniform vec4 viewport;
+uniform float bloom_intensity;
+uniform float aberration;
+uniform float vignette;
+uniform float desaturate;
+uniform float crt;
+
+void main() {
+    vec2 pos = (gl_FragCoord.xy - viewport.xy) / viewport.zw * 2.0 - 1.0;
+
+    if (crt > 0.5) {
+        // barrel distortion
+        pos *= 1.0 + dot(pos, pos) * 0.03;
+    }
+
+    vec2 coord = pos * 0.5 + 0.5;
+
+    if (coord.x < 0.0 || coord.x > 1.0 || coord.y < 0.0 || coord.y > 1.0) {
+        color_out = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec2 shift = pos * aberration * 0.01;
+
+    vec3 color = vec3(
+        texture(scene, coord + shift).r,
+        texture(scene, coord).g,
+        texture(scene, coord - shift).b
+    );
+
+    color += texture(bloom, coord).rgb * bloom_intensity;
+
+    float grey = dot(color, vec3(0.299, 0.587, 0.114));
+    color = mix(color, vec3(grey), clamp(desaturate, 0.0, 1.0));
+
+    color *= 1.0 - clamp(vignette, 0.0, 1.0) * smoothstep(0.3, 1.4, length(pos));
+
+    if (crt > 0.5) {
+        color *= 0.8 + 0.2 * sin(gl_FragCoord.y * 3.14159);
+    }
+
+    color_out = vec4(color, 1.0);
+}
+"#;
+
+const THRESHOLD: &str = r#"
+#version 450
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 color_out;
+
+uniform sampler2D scene;
+uniform float threshold;
+
+void main() {
+    vec3 color = texture(scene, uv * 0.5 + 0.5).rgb;
+    float luminance = dot(color, vec3(0.299, 0.587, 0.114));
+
+    color_out = vec4(color * smoothstep(threshold, threshold + 0.2, luminance), 1.0);
+}
+"#;
+
+const BLUR: &str = r#"
+#version 450
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 color_out;
+
+uniform sampler2D source;
+uniform vec2 direction;
+
+void main() {
+    vec2 coord = uv * 0.5 + 0.5;
+
+    float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+    vec3 color = texture(source, coord).rgb * weights[0];
+
+    for (int i = 1; i < 5; i++) {
+        color += texture(source, coord + direction * float(i)).rgb * weights[i];
+        color += texture(source, coord - direction * float(i)).rgb * weights[i];
+    }
+
+    color_out = vec4(color, 1.0);
+}
+"#;
+
+/// Luminance above which the scene starts to bloom.
+const BLOOM_THRESHOLD: f32 = 0.6;
+
+/// Strength of each post effect for a single frame.
+#[derive(Clone, Debug, Default)]
+pub struct PostEffects {
+    pub aberration: f32,
+    pub vignette: f32,
+    pub desaturate: f32,
+    pub crt: bool,
+    /// Strength of the bloom, 0 disables it.
+    pub bloom: f32,
+}
+
+/// Renders the scene to the virtual canvas and draws it to the screen through the post effects.
+pub struct PostProcessing {
+    target: RenderTarget,
+    /// Half resolution targets the bloom is thresholded and blurred in.
+    bloom_targets: [RenderTarget; 2],
+    material: Material,
+    threshold: Material,
+    blur: Material,
+}
+
+impl PostProcessing {
+    pub fn new() -> Self {
+        let (width, height) = (canvas::WIDTH as u32, canvas::HEIGHT as u32);
+
+        let material = load_material(
+            VERTEX,
+            FRAGMENT,
+            MaterialParams {
+                textures: vec!["scene".to_string(), "bloom".to_string()],
+                uniforms: vec![
+                    ("viewport".to_string(), UniformType::Float4),
+                    ("aberration".to_string(), UniformType::Float1),
+                    ("vignette".to_string(), UniformType::Float1),
+                    ("desaturate".to_string(), UniformType::Float1),
+                    ("crt".to_string(), UniformType::Float1),
+                    ("bloom_intensity".to_string(), UniformType::Float1),
+                ],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let threshold = load_material(
+            VERTEX,
+            THRESHOLD,
+            MaterialParams {
+                textures: vec!["scene".to_string()],
+                uniforms: vec![("threshold".to_string(), UniformType::Float1)],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let blur = load_material(
+            VERTEX,
+            BLUR,
+            MaterialParams {
+                textures: vec!["source".to_string()],
+                uniforms: vec![("direction".to_string(), UniformType::Float2)],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let target = render_target(width, height);
+        target.texture.set_filter(FilterMode::Nearest);
+
+        Self {
+            target,
+            bloom_targets: [
+                render_target(width / 2, height / 2),
+                render_target(width / 2, height / 2),
+            ],
+            material,
+            threshold,
+            blur,
+        }
+    }
+
+    /// The render target the scene should be drawn to.
+    pub fn target(&self) -> RenderTarget {
+        self.target
+    }
+
+    /// Runs `material` over the whole of `target`.
+    fn pass(material: Material, target: RenderTarget) {
+        set_camera(&Camera2D {
+            render_target: Some(target),
+            ..Default::default()
+        });
+
+        gl_use_material(material);
+
+        draw_rectangle(0.0, 0.0, 1.0, 1.0, WHITE);
+
+        gl_use_default_material();
+    }
+
+    fn draw_bloom(&self) {
+        let [a, b] = self.bloom_targets;
+        let texel = vec2(1.0 / a.texture.width(), 1.0 / a.texture.height());
+
+        self.threshold.set_texture("scene", self.target.texture);
+        self.threshold.set_uniform("threshold", BLOOM_THRESHOLD);
+        Self::pass(self.threshold, a);
+
+        self.blur.set_texture("source", a.texture);
+        self.blur.set_uniform("direction", vec2(texel.x, 0.0));
+        Self::pass(self.blur, b);
+
+        self.blur.set_texture("source", b.texture);
+        self.blur.set_uniform("direction", vec2(0.0, texel.y));
+        Self::pass(self.blur, a);
+    }
+
+    pub fn draw(&self, effects: &PostEffects) {
+        if effects.bloom > 0.0 {
+            self.draw_bloom();
+        }
+
+        set_default_camera();
+
+        let viewport = canvas::viewport();
+
+        self.material.set_texture("scene", self.target.texture);
+        self.material
+            .set_texture("bloom", self.bloom_targets[0].texture);
+        self.material.set_uniform(
+            "viewport",
+            vec4(viewport.x, viewport.y, viewport.w, viewport.h),
+        );
+        self.material.set_uniform("aberration", effects.aberration);
+        self.material.set_uniform("vignette", effects.vignette);
+        self.material.set_uniform("desaturate", effects.desaturate);
+        self.material
+            .set_uniform("crt", if effects.crt { 1.0f32 } else { 0.0 });
+        self.material
+            .set_uniform("bloom_intensity", effects.bloom.max(0.0));
+
+        gl_use_material(self.material);
+
+        draw_rectangle(0.0, 0.0, 1.0, 1.0, WHITE);
+
+        gl_use_default_material();
+    }
+}
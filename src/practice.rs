@@ -0,0 +1,49 @@
+//! Practice mode, looping a span of a level over and over.
+
+/// Rate the ramp starts at.
+const RAMP_START: f32 = 0.8;
+
+/// How much the rate goes up after each loop without a miss.
+const RAMP_STEP: f32 = 0.05;
+
+pub struct Practice {
+    /// Time the loop goes back to.
+    pub start: f32,
+    /// Time the loop ends at.
+    pub end: f32,
+    /// Rate the current pass plays at.
+    pub rate: f32,
+    /// Rate the ramp stops at, the rate picked for the level.
+    target_rate: f32,
+    /// Speed projectiles had when the loop started, each pass starts with it.
+    pub speed: f32,
+    /// Whether the current pass has had no misses.
+    pub clean: bool,
+    /// Passes finished so far.
+    pub loops: u32,
+}
+
+impl Practice {
+    /// Starts looping from `start` to `end`, ramping up to `rate` if `ramp` is set.
+    pub fn new(start: f32, end: f32, rate: f32, ramp: bool, speed: f32) -> Self {
+        Self {
+            start,
+            end,
+            rate: if ramp { rate.min(RAMP_START) } else { rate },
+            target_rate: rate,
+            speed,
+            clean: true,
+            loops: 0,
+        }
+    }
+
+    /// Moves on to the next pass, speeding up if the last one was clean.
+    pub fn next_pass(&mut self) {
+        if self.clean {
+            self.rate = (self.rate + RAMP_STEP).min(self.target_rate);
+        }
+
+        self.clean = true;
+        self.loops += 1;
+    }
+}
@@ -0,0 +1,108 @@
+//! Local player profiles, each with its own settings and scores in [`PROFILES_DIR`].
+
+use crate::config::{Config, Value};
+use std::path::PathBuf;
+
+pub const PROFILES_DIR: &str = "profiles";
+
+/// Remembers which profile was last played, as `current = "<name>"`.
+pub const PROFILES_PATH: &str = "profiles/profiles.toml";
+
+/// Where settings were kept before there were profiles, read by the default profile until it
+/// saves its own.
+const LEGACY_SETTINGS_PATH: &str = "settings.toml";
+
+const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROFILE)
+    }
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.trim().to_string(),
+        }
+    }
+
+    /// The profile last picked, the default one if none has been.
+    pub fn current() -> Self {
+        std::fs::read_to_string(PROFILES_PATH)
+            .ok()
+            .and_then(|source| Config::parse(&source).ok())
+            .and_then(|config| config.get_str("current").map(Self::new))
+            .filter(Self::is_valid)
+            .unwrap_or_default()
+    }
+
+    /// Makes this the profile the game starts with.
+    pub fn select(&self) -> std::io::Result<()> {
+        let mut config = Config::new();
+        config.set("current", Value::String(self.name.clone()));
+
+        std::fs::create_dir_all(self.dir())?;
+        std::fs::write(PROFILES_PATH, config.to_string())
+    }
+
+    /// Whether the name can be used as a folder name.
+    pub fn is_valid(&self) -> bool {
+        !self.name.is_empty()
+            && !self.name.starts_with('.')
+            && !self.name.contains(['/', '\\', ':', '"'])
+    }
+
+    pub fn dir(&self) -> PathBuf {
+        PathBuf::from(PROFILES_DIR).join(&self.name)
+    }
+
+    pub fn settings_path(&self) -> PathBuf {
+        self.dir().join("settings.toml")
+    }
+
+    pub fn scores_path(&self) -> PathBuf {
+        self.dir().join("scores.toml")
+    }
+
+    /// Reads a file of the profile, falling back to the settings from before profiles for the
+    /// default profile's settings.
+    pub fn read(&self, path: &std::path::Path) -> Option<String> {
+        std::fs::read_to_string(path).ok().or_else(|| {
+            (self.name == DEFAULT_PROFILE && path == self.settings_path())
+                .then(|| std::fs::read_to_string(LEGACY_SETTINGS_PATH).ok())
+                .flatten()
+        })
+    }
+
+    /// Writes a file of the profile, creating the profile's folder if needed.
+    pub fn write(&self, path: &std::path::Path, contents: &str) -> std::io::Result<()> {
+        std::fs::create_dir_all(self.dir())?;
+        std::fs::write(path, contents)
+    }
+}
+
+/// Names of the profiles on disk, always including `current`.
+pub fn profiles(current: &Profile) -> Vec<String> {
+    let mut names: Vec<String> = match std::fs::read_dir(PROFILES_DIR) {
+        Ok(entries) => entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    if !names.contains(&current.name) {
+        names.push(current.name.clone());
+    }
+
+    names.sort();
+
+    names
+}
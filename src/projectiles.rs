@@ -0,0 +1,207 @@
+//! Projectile types, each a [`ProjectileBehavior`] looked up by the name sheets and scripts use
+//! for it.
+//!
+//! A type is written as its name, followed by arguments after a colon for types that take them,
+//! `norm` or `mine:2,0.5`. The built in types can't be replaced, others are added with
+//! [`register`] before the sheets using them are parsed.
+
+use crate::game::*;
+use crate::input::InputBuffer;
+use crate::integrity::Digest;
+use crate::settings::Settings;
+use macroquad::prelude::PINK;
+use std::sync::{Arc, RwLock};
+
+pub type Behavior = Arc<dyn ProjectileBehavior>;
+
+pub trait ProjectileBehavior: Send + Sync {
+    /// The behavior for a projectile written with `args`, an error saying what's wrong with them
+    /// otherwise.
+    fn parse_args(&self, args: &[&str]) -> Result<Behavior, String>;
+
+    /// Checks whether the projectile has been blocked or got through, scoring what happened.
+    fn update(
+        &self,
+        projectile: &Projectile,
+        env: &Env,
+        shield: &Option<Direction>,
+        input: &mut InputBuffer,
+        judge: &mut Judge,
+    ) -> Outcome;
+
+    /// Draws the projectile with its colors faded by `alpha`.
+    fn draw(
+        &self,
+        projectile: &Projectile,
+        env: &Env,
+        assets: &Assets,
+        settings: &Settings,
+        alpha: f32,
+    );
+
+    /// Adds what sets the type apart to a chart's hash, so charts differing only in it don't
+    /// share scores.
+    fn hash(&self, digest: &mut Digest);
+
+    /// Whether the projectile is a pickup, worth nothing when cleared by the bomb meter.
+    fn is_pickup(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Clone)]
+pub struct ProjectileType {
+    /// The type as written in the sheet or script, with its arguments.
+    pub name: String,
+    pub behavior: Behavior,
+}
+
+impl ProjectileType {
+    /// The type written as `source`, `None` if no type has its name.
+    pub fn parse(source: &str) -> Option<Result<Self, String>> {
+        let (name, args) = match source.split_once(':') {
+            Some((name, args)) => (name, args.split(',').collect()),
+            None => (source, Vec::new()),
+        };
+
+        let behavior = builtin(name).or_else(|| {
+            let registered = REGISTERED.read().unwrap();
+
+            registered
+                .iter()
+                .find(|(registered, _)| registered == name)
+                .map(|(_, behavior)| behavior.clone())
+        })?;
+
+        Some(behavior.parse_args(&args).map(|behavior| Self {
+            name: source.to_string(),
+            behavior,
+        }))
+    }
+}
+
+impl std::fmt::Debug for ProjectileType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+static REGISTERED: RwLock<Vec<(String, Behavior)>> = RwLock::new(Vec::new());
+
+fn builtin(name: &str) -> Option<Behavior> {
+    match name {
+        "norm" => Some(Arc::new(Normal)),
+        "heart" => Some(Arc::new(Heart)),
+        _ => None,
+    }
+}
+
+/// Adds a projectile type under `name`, replacing one registered before under it. Built in types
+/// keep their names.
+pub fn register(name: &str, behavior: Behavior) {
+    let mut registered = REGISTERED.write().unwrap();
+
+    registered.retain(|(registered, _)| registered != name);
+    registered.push((name.to_string(), behavior));
+}
+
+fn no_args(args: &[&str], behavior: Behavior) -> Result<Behavior, String> {
+    if args.is_empty() {
+        Ok(behavior)
+    } else {
+        Err(String::from("takes no arguments"))
+    }
+}
+
+/// Blocked for points, costing a heart when it gets through.
+pub struct Normal;
+
+impl ProjectileBehavior for Normal {
+    fn parse_args(&self, args: &[&str]) -> Result<Behavior, String> {
+        no_args(args, Arc::new(Self))
+    }
+
+    fn update(
+        &self,
+        projectile: &Projectile,
+        env: &Env,
+        shield: &Option<Direction>,
+        input: &mut InputBuffer,
+        judge: &mut Judge,
+    ) -> Outcome {
+        match projectile.update(env, shield, input, judge.sheet.bpm) {
+            ProjectileHit::None => Outcome::Flying,
+            ProjectileHit::Blocked(pressed) => judge.block(projectile, pressed),
+            // let go over a hitch, judging it a step later would only delay the miss
+            ProjectileHit::Hit if !judge.judge_misses => Outcome::Passed,
+            ProjectileHit::Hit => judge.miss(),
+        }
+    }
+
+    fn draw(
+        &self,
+        projectile: &Projectile,
+        env: &Env,
+        assets: &Assets,
+        settings: &Settings,
+        alpha: f32,
+    ) {
+        let color = settings.palette.color(&projectile.direction);
+        let angle = projectile.direction.angle();
+
+        projectile.draw_sprite(
+            env,
+            assets,
+            settings,
+            (assets.projectile, color, angle),
+            alpha,
+        );
+    }
+
+    fn hash(&self, digest: &mut Digest) {
+        digest.integer(0);
+    }
+}
+
+/// A pickup giving overheal when blocked, harmless when it isn't.
+pub struct Heart;
+
+impl ProjectileBehavior for Heart {
+    fn parse_args(&self, args: &[&str]) -> Result<Behavior, String> {
+        no_args(args, Arc::new(Self))
+    }
+
+    fn update(
+        &self,
+        projectile: &Projectile,
+        env: &Env,
+        shield: &Option<Direction>,
+        input: &mut InputBuffer,
+        judge: &mut Judge,
+    ) -> Outcome {
+        match projectile.update(env, shield, input, judge.sheet.bpm) {
+            ProjectileHit::None => Outcome::Flying,
+            ProjectileHit::Blocked(_) => judge.pickup(),
+            ProjectileHit::Hit => Outcome::Flying,
+        }
+    }
+
+    fn draw(
+        &self,
+        projectile: &Projectile,
+        env: &Env,
+        assets: &Assets,
+        settings: &Settings,
+        alpha: f32,
+    ) {
+        projectile.draw_sprite(env, assets, settings, (assets.heart, PINK, 0.0), alpha);
+    }
+
+    fn hash(&self, digest: &mut Digest) {
+        digest.integer(1);
+    }
+
+    fn is_pickup(&self) -> bool {
+        true
+    }
+}
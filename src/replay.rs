@@ -0,0 +1,439 @@
+//! Replay files, a whole run with what's needed to play it back, shareable between players.
+//!
+//! A file starts with [`MAGIC`] and the version it was written with, followed by the length of
+//! the fields and the fields deflated. Version 1 files, from before replays were compressed, have
+//! the fields as they are. Each field is a tag, a length and the field's bytes. Fields a reader
+//! doesn't know are skipped, so files from newer versions still load with what they have in
+//! common. Numbers are varints, except for hashes and times which are stored whole so playback
+//! is exact. Presses are stored as the
+//! difference from the press before, which keeps a run to a few bytes a press. The lanes of the
+//! presses are a field of their own, left out for levels with a single lane, and so are the times
+//! the bomb meter was spent at, left out for runs that never spent it, and the hitches, left out
+//! for runs that never hitched.
+
+use crate::config::Config;
+use crate::game::Direction;
+use crate::input::Press;
+use crate::modifiers::Modifiers;
+use crate::profile::Profile;
+use miniz_oxide::inflate::core::{decompress, inflate_flags, DecompressorOxide};
+use miniz_oxide::inflate::TINFLStatus;
+use std::convert::TryInto;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+pub const MAGIC: &[u8; 4] = b"SRGR";
+
+pub const REPLAY_VERSION: u32 = 2;
+
+/// The first version with its fields compressed.
+const COMPRESSED_SINCE: u32 = 2;
+
+/// Most bytes the fields of a replay inflate to, hours of mashing, so a broken or hostile file
+/// can't make the game allocate more.
+const MAX_FIELDS: u64 = 16 * 1024 * 1024;
+
+/// Extension of replay files.
+pub const EXTENSION: &str = "srr";
+
+/// Where replays shared by other players go, to be watched from the level they're of.
+pub const IMPORT_DIR: &str = "replays";
+
+const CHART: u32 = 1;
+const LEVEL: u32 = 2;
+const PLAYER: u32 = 3;
+const MODIFIERS: u32 = 4;
+const SEED: u32 = 5;
+const START: u32 = 6;
+const PRESSES: u32 = 7;
+const SCORE: u32 = 8;
+const LANES: u32 = 9;
+const BOMBS: u32 = 10;
+const HITCHES: u32 = 11;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    NotAReplay,
+    Truncated,
+    InvalidField(u32),
+    /// The fields don't inflate to the length the file says.
+    Corrupt,
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotAReplay => write!(f, "not a replay file"),
+            Self::Truncated => write!(f, "replay file is cut short"),
+            Self::InvalidField(tag) => write!(f, "invalid field {} in replay file", tag),
+            Self::Corrupt => write!(f, "replay file is corrupt"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+#[derive(Clone, Debug)]
+pub struct Replay {
+    /// Version of the game the replay was written with.
+    pub version: u32,
+    /// [`Sheet::level_hash`](crate::sheet::Sheet::level_hash) of the level played.
+    pub chart: u64,
+    /// Name scores of the level are kept under.
+    pub level: String,
+    /// Name of the profile that played.
+    pub player: String,
+    pub modifiers: Modifiers,
+    /// Seed the random modifier picked sides with.
+    pub seed: u32,
+    /// Song time the run started at.
+    pub start: f32,
+    /// Every press of the run in order, with the song time it was made at.
+    pub presses: Vec<Press>,
+    /// Song times the bomb meter was spent at, in order.
+    pub bombs: Vec<f32>,
+    /// Spans of song time the game hitched over, see [`Run::hitches`](crate::run::Run::hitches).
+    pub hitches: Vec<(f32, f32)>,
+    /// The score the run ended with.
+    pub score: u32,
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, ReplayError> {
+    let mut value = 0;
+
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = bytes.split_first().ok_or(ReplayError::Truncated)?;
+        *bytes = rest;
+
+        value |= ((byte & 0x7f) as u64) << shift;
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(ReplayError::Truncated)
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], ReplayError> {
+    if bytes.len() < len {
+        return Err(ReplayError::Truncated);
+    }
+
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+
+    Ok(taken)
+}
+
+/// Maps signed numbers to unsigned ones with small magnitudes staying small.
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+fn write_field(bytes: &mut Vec<u8>, tag: u32, field: &[u8]) {
+    write_varint(bytes, tag as u64);
+    write_varint(bytes, field.len() as u64);
+    bytes.extend_from_slice(field);
+}
+
+/// Inflates `deflated` to the `len` bytes it was deflated from.
+fn inflate(deflated: &[u8], len: u64) -> Result<Vec<u8>, ReplayError> {
+    if len > MAX_FIELDS {
+        return Err(ReplayError::Corrupt);
+    }
+
+    let mut fields = vec![0; len as usize];
+    let mut decompressor = DecompressorOxide::default();
+    let (status, _, written) = decompress(
+        &mut decompressor,
+        deflated,
+        &mut Cursor::new(fields.as_mut_slice()),
+        inflate_flags::TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF,
+    );
+
+    match status {
+        TINFLStatus::Done if written == fields.len() => Ok(fields),
+        _ => Err(ReplayError::Corrupt),
+    }
+}
+
+fn text(field: &[u8], tag: u32) -> Result<String, ReplayError> {
+    String::from_utf8(field.to_vec()).map_err(|_| ReplayError::InvalidField(tag))
+}
+
+impl Replay {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut modifiers = Config::new();
+        self.modifiers.write_config(&mut modifiers);
+
+        write_field(&mut bytes, CHART, &self.chart.to_le_bytes());
+        write_field(&mut bytes, LEVEL, self.level.as_bytes());
+        write_field(&mut bytes, PLAYER, self.player.as_bytes());
+        write_field(&mut bytes, MODIFIERS, modifiers.to_string().as_bytes());
+
+        let mut seed = Vec::new();
+        write_varint(&mut seed, self.seed as u64);
+        write_field(&mut bytes, SEED, &seed);
+
+        write_field(&mut bytes, START, &self.start.to_bits().to_le_bytes());
+
+        // times of presses in order have bits in order too, so the differences stay small
+        let mut presses = Vec::new();
+        let mut last = 0i64;
+
+        write_varint(&mut presses, self.presses.len() as u64);
+
+        for press in &self.presses {
+            let bits = press.time.to_bits() as i32 as i64;
+
+            write_varint(
+                &mut presses,
+                zigzag(bits - last) << 2 | press.direction.clone() as u64,
+            );
+
+            last = bits;
+        }
+
+        write_field(&mut bytes, PRESSES, &presses);
+
+        if self.presses.iter().any(|press| press.lane > 0) {
+            let mut lanes = Vec::new();
+
+            for press in &self.presses {
+                write_varint(&mut lanes, press.lane as u64);
+            }
+
+            write_field(&mut bytes, LANES, &lanes);
+        }
+
+        if !self.bombs.is_empty() {
+            let mut bombs = Vec::new();
+
+            for bomb in &self.bombs {
+                bombs.extend_from_slice(&bomb.to_bits().to_le_bytes());
+            }
+
+            write_field(&mut bytes, BOMBS, &bombs);
+        }
+
+        if !self.hitches.is_empty() {
+            let mut hitches = Vec::new();
+
+            for (from, to) in &self.hitches {
+                hitches.extend_from_slice(&from.to_bits().to_le_bytes());
+                hitches.extend_from_slice(&to.to_bits().to_le_bytes());
+            }
+
+            write_field(&mut bytes, HITCHES, &hitches);
+        }
+
+        let mut score = Vec::new();
+        write_varint(&mut score, self.score as u64);
+        write_field(&mut bytes, SCORE, &score);
+
+        let mut file = MAGIC.to_vec();
+        write_varint(&mut file, REPLAY_VERSION as u64);
+        write_varint(&mut file, bytes.len() as u64);
+        file.extend(miniz_oxide::deflate::compress_to_vec(&bytes, 9));
+
+        file
+    }
+
+    pub fn decode(mut file: &[u8]) -> Result<Self, ReplayError> {
+        let file = &mut file;
+
+        if take(file, MAGIC.len()).ok() != Some(&MAGIC[..]) {
+            return Err(ReplayError::NotAReplay);
+        }
+
+        let version = read_varint(file)? as u32;
+        let fields = if version >= COMPRESSED_SINCE {
+            let len = read_varint(file)?;
+
+            inflate(file, len)?
+        } else {
+            file.to_vec()
+        };
+        let mut bytes = fields.as_slice();
+        let bytes = &mut bytes;
+
+        let mut replay = Self {
+            version,
+            chart: 0,
+            level: String::new(),
+            player: String::new(),
+            modifiers: Modifiers::default(),
+            seed: 0,
+            start: 0.0,
+            presses: Vec::new(),
+            bombs: Vec::new(),
+            hitches: Vec::new(),
+            score: 0,
+        };
+        let mut lanes = Vec::new();
+
+        while !bytes.is_empty() {
+            let tag = read_varint(bytes)? as u32;
+            let len = read_varint(bytes)? as usize;
+            let mut field = take(bytes, len)?;
+            let invalid = || ReplayError::InvalidField(tag);
+
+            match tag {
+                CHART => {
+                    replay.chart = u64::from_le_bytes(field.try_into().map_err(|_| invalid())?)
+                }
+                LEVEL => replay.level = text(field, tag)?,
+                PLAYER => replay.player = text(field, tag)?,
+                MODIFIERS => {
+                    let config = Config::parse(&text(field, tag)?).map_err(|_| invalid())?;
+
+                    replay.modifiers = Modifiers::from_config(&config);
+                }
+                SEED => replay.seed = read_varint(&mut field)? as u32,
+                START => {
+                    let bits = u32::from_le_bytes(field.try_into().map_err(|_| invalid())?);
+
+                    replay.start = f32::from_bits(bits);
+                }
+                PRESSES => {
+                    let count = read_varint(&mut field)?;
+                    let mut last = 0i64;
+
+                    for _ in 0..count {
+                        let press = read_varint(&mut field)?;
+                        let bits = last.checked_add(unzigzag(press >> 2)).ok_or_else(invalid)?;
+                        let direction = Direction::ALL[(press & 3) as usize].clone();
+
+                        replay.presses.push(Press {
+                            lane: 0,
+                            direction,
+                            time: f32::from_bits(bits as i32 as u32),
+                        });
+
+                        last = bits;
+                    }
+                }
+                SCORE => replay.score = read_varint(&mut field)? as u32,
+                LANES => {
+                    while !field.is_empty() {
+                        lanes.push(read_varint(&mut field)? as usize);
+                    }
+                }
+                BOMBS => {
+                    if field.len() % 4 != 0 {
+                        return Err(invalid());
+                    }
+
+                    for bits in field.chunks(4) {
+                        let bits = u32::from_le_bytes(bits.try_into().map_err(|_| invalid())?);
+
+                        replay.bombs.push(f32::from_bits(bits));
+                    }
+                }
+                HITCHES => {
+                    if field.len() % 8 != 0 {
+                        return Err(invalid());
+                    }
+
+                    for bits in field.chunks(8) {
+                        let (from, to) = bits.split_at(4);
+                        let from = u32::from_le_bytes(from.try_into().map_err(|_| invalid())?);
+                        let to = u32::from_le_bytes(to.try_into().map_err(|_| invalid())?);
+
+                        replay
+                            .hitches
+                            .push((f32::from_bits(from), f32::from_bits(to)));
+                    }
+                }
+                // written by a newer version
+                _ => {}
+            }
+        }
+
+        if !lanes.is_empty() {
+            if lanes.len() != replay.presses.len() {
+                return Err(ReplayError::InvalidField(LANES));
+            }
+
+            for (press, lane) in replay.presses.iter_mut().zip(lanes) {
+                press.lane = lane;
+            }
+        }
+
+        Ok(replay)
+    }
+
+    /// Where the run of `level` by `profile` that ended at `date` is kept, for runs that are
+    /// among the best of the level.
+    pub fn path(profile: &Profile, level: &str, date: u64) -> PathBuf {
+        Self::dir(profile, level).join(format!("{}.{}", date, EXTENSION))
+    }
+
+    fn dir(profile: &Profile, level: &str) -> PathBuf {
+        profile.dir().join("replays").join(level)
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::decode(&std::fs::read(path)?)?)
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        std::fs::write(path, self.encode())
+    }
+}
+
+/// Paths of the replay files in `dir`, in order of name.
+fn replay_files(dir: &std::path::Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == EXTENSION)
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    paths.sort();
+
+    paths
+}
+
+/// The replays of `level` that can be watched, the best runs of `profile` best first and then
+/// the imported ones. Files that fail to load are left out.
+pub fn replays_of(profile: &Profile, level: &str) -> Vec<Replay> {
+    let load = |paths: Vec<PathBuf>| -> Vec<Replay> {
+        paths
+            .iter()
+            .filter_map(|path| Replay::load(path).ok())
+            .filter(|replay| replay.level == level)
+            .collect()
+    };
+
+    let mut replays = load(replay_files(&Replay::dir(profile, level)));
+    replays.sort_by_key(|replay| std::cmp::Reverse(replay.score));
+    replays.extend(load(replay_files(IMPORT_DIR.as_ref())));
+
+    replays
+}
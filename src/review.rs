@@ -0,0 +1,62 @@
+//! Replaying a run from its presses, either a slowed down review of the seconds leading up to
+//! a death or a whole cleared run.
+
+use crate::game::Projectile;
+use crate::input::Press;
+
+/// Seconds before the death the review starts at.
+pub const REVIEW_LENGTH: f32 = 5.0;
+
+/// Speed death reviews play at, relative to the run's rate.
+pub const REVIEW_RATE: f32 = 0.4;
+
+pub struct Review {
+    /// Presses of the run being reviewed, in order.
+    presses: Vec<Press>,
+    /// Index of the next press to replay.
+    next: usize,
+    /// The projectile that killed the player, `None` when replaying a cleared run.
+    pub missed: Option<Projectile>,
+    /// Who played the run, when watching someone's replay file.
+    pub player: Option<String>,
+}
+
+impl Review {
+    pub fn new(presses: Vec<Press>, missed: Option<Projectile>) -> Self {
+        Self {
+            presses,
+            next: 0,
+            missed,
+            player: None,
+        }
+    }
+
+    /// Skips the presses before `time`.
+    pub fn skip_to(&mut self, time: f32) {
+        self.next = self.presses.partition_point(|press| press.time < time);
+    }
+
+    /// The presses made up to `time` that haven't been replayed yet.
+    pub fn presses_until(&mut self, time: f32) -> &[Press] {
+        let start = self.next;
+
+        while self
+            .presses
+            .get(self.next)
+            .is_some_and(|press| press.time <= time)
+        {
+            self.next += 1;
+        }
+
+        &self.presses[start..self.next]
+    }
+
+    /// Whether `projectile` is the one that killed the player.
+    pub fn is_missed(&self, projectile: &Projectile) -> bool {
+        self.missed.as_ref().is_some_and(|missed| {
+            projectile.arrival_time == missed.arrival_time
+                && projectile.direction == missed.direction
+                && projectile.lane == missed.lane
+        })
+    }
+}
@@ -0,0 +1,571 @@
+//! A run of a level as far as its score goes: the clock, the projectiles and the shields
+//! judging them, the power-ups, the bomb meter and the script's spawns. Nothing here draws or
+//! plays sounds, [`GameState`](crate::game::GameState) steps a run through the frames of the
+//! game and draws what happens, and [`verify_replay`](crate::verify::verify_replay) steps one
+//! through the presses of a replay, so the two play runs by the same rules.
+
+use crate::aggro::{self, Aggro};
+use crate::boss::{Boss, BOSS_DAMAGE};
+use crate::camera::Camera;
+use crate::events::GameEvent;
+use crate::game::*;
+use crate::health::Health;
+use crate::input::{InputBuffer, Press, BUFFER_WINDOW};
+use crate::judgment::{Judgment, TimingWindows};
+use crate::meter::Meter;
+use crate::modifiers::Modifiers;
+use crate::momentum::Momentum;
+use crate::pickups::Pickups;
+use crate::script::{Action, Event, Script};
+use crate::sheet::Sheet;
+
+/// Most seconds of the clock a single hitch lets projectiles go over, frames longer than that
+/// are only forgiven this much.
+pub const MAX_HITCH: f32 = 0.5;
+
+/// Most seconds of hitches a run forgives altogether, misses count again after.
+pub const MAX_HITCHES: f32 = 2.0;
+
+/// Whether `hitches` are ones a run could have recorded, none longer than [`MAX_HITCH`] and no
+/// more than [`MAX_HITCHES`] of them together. Replays claiming others don't verify.
+pub fn hitches_allowed(hitches: &[(f32, f32)]) -> bool {
+    // the clock adds up in f32, so a hitch recorded at the limit can come out a hair over it
+    const SLACK: f32 = 1e-3;
+
+    let lengths = hitches.iter().map(|(from, to)| to - from);
+
+    lengths
+        .clone()
+        .all(|length| (0.0..=MAX_HITCH + SLACK).contains(&length))
+        && lengths.sum::<f32>() <= MAX_HITCHES + SLACK
+}
+
+pub struct Run {
+    pub env: Env,
+    /// A shield for every lane of the sheet.
+    pub shields: Vec<Shield>,
+    /// Shield input is ignored until this time after a whiffed press, on sheets with stun.
+    stunned_until: f32,
+    pub input: InputBuffer,
+    /// Index of the next projectile in the sheet that isn't on screen yet.
+    pub next_projectile: usize,
+    /// Projectiles the script has spawned since the run started or skipped, numbered after the
+    /// sheet's for the random modifier.
+    spawned: usize,
+    /// Projectiles on screen, from the sheet or spawned by the script.
+    pub active_projectiles: Vec<Projectile>,
+    /// The last beat script hooks ran for.
+    pub beat: i64,
+    /// Index of the next section of the sheet to start.
+    next_section: usize,
+    /// Index of the next phase of the sheet to start.
+    next_phase: usize,
+    /// The boss fought on levels with `#boss`.
+    pub boss: Option<Boss>,
+    /// Power-ups dropped by blocked projectiles and the effects collected.
+    pub pickups: Pickups,
+    /// The bomb meter on levels with `#meter`.
+    pub meter: Meter,
+    /// The multiplier built up with the aggro modifier.
+    pub aggro: Aggro,
+    /// Speed from blocks still easing in, on sheets with `#momentum`.
+    pub momentum: Momentum,
+    /// Song times the meter was spent at, in order.
+    pub bombs: Vec<f32>,
+    /// Index of the next bomb in [`Run::bombs`] to go off.
+    next_bomb: usize,
+    /// Spans of the clock frames took too long over, from and to. Projectiles getting through
+    /// during one are let go rather than missed, the hitch not being the player's fault.
+    pub hitches: Vec<(f32, f32)>,
+    pub camera: Camera,
+    /// Index of the next camera event in the sheet.
+    next_camera_event: usize,
+    /// Points from blocks, without the modifiers' multiplier.
+    pub score: u32,
+    /// Projectiles blocked in a row.
+    pub combo: u32,
+    /// The longest combo of the run.
+    pub best_combo: u32,
+    pub windows: TimingWindows,
+    pub health: Health,
+    /// The projectile that got through with no hearts left, ending the run.
+    pub fatal: Option<Projectile>,
+    pub modifiers: Modifiers,
+    /// Picks the sides projectiles come from with the random modifier.
+    pub seed: u32,
+    /// Whether running out of hearts doesn't end the run, with the no fail modifier or while
+    /// practicing.
+    pub no_fail: bool,
+    /// What happened since the events were last taken.
+    pub events: Vec<GameEvent>,
+    /// The script events run since last taken, for the actions that only change how the level
+    /// looks.
+    pub fired: Vec<Event>,
+}
+
+impl Run {
+    pub fn new(sheet: &Sheet, modifiers: &Modifiers, seed: u32) -> Self {
+        let lanes = sheet.lanes();
+
+        Self {
+            env: Env {
+                lanes,
+                speed: sheet.momentum.min,
+                ..Env::new()
+            },
+            shields: (0..lanes).map(|_| Shield::new()).collect(),
+            stunned_until: 0.0,
+            input: InputBuffer::new(),
+            next_projectile: 0,
+            spawned: 0,
+            active_projectiles: Vec::new(),
+            beat: beat_at(sheet, 0.0),
+            next_section: 0,
+            next_phase: 0,
+            boss: sheet.boss.map(Boss::new),
+            pickups: Pickups::default(),
+            meter: Meter::default(),
+            aggro: Aggro::default(),
+            momentum: Momentum::default(),
+            bombs: Vec::new(),
+            next_bomb: 0,
+            hitches: Vec::new(),
+            camera: Camera::new(),
+            next_camera_event: 0,
+            score: 0,
+            combo: 0,
+            best_combo: 0,
+            windows: sheet.windows.clone(),
+            health: Health::new(sheet.health.unwrap_or(1.0)),
+            fatal: None,
+            modifiers: modifiers.clone(),
+            seed,
+            no_fail: modifiers.no_fail,
+            events: Vec::new(),
+            fired: Vec::new(),
+        }
+    }
+
+    /// Moves the run to `time`, skipping the projectiles before it. The score, health and combo
+    /// are left alone.
+    pub fn skip_to(&mut self, sheet: &Sheet, time: f32) {
+        self.env.time = time;
+        self.next_projectile = sheet
+            .projectiles
+            .partition_point(|projectile| projectile.arrival_time < time);
+        self.spawned = 0;
+        self.active_projectiles.clear();
+        self.beat = beat_at(sheet, time);
+        self.next_section = sheet.sections.partition_point(|start| *start < time);
+        self.next_phase = sheet.phases.partition_point(|phase| phase.time < time);
+        self.boss = sheet.boss.map(Boss::new);
+        self.pickups = Pickups::default();
+        self.meter = Meter::default();
+        self.aggro = Aggro::default();
+        self.momentum = Momentum::default();
+        self.next_bomb = self.bombs.partition_point(|bomb| *bomb < time);
+        self.fatal = None;
+
+        // play the camera moves before `time` so the camera is where it would be
+        self.camera = Camera::new();
+        self.next_camera_event = 0;
+        self.run_camera_events(sheet);
+        self.stunned_until = 0.0;
+        self.env.time_scale = TimeScale::default();
+    }
+
+    /// Whether every projectile of the sheet has been dealt with, only the rest of the song left.
+    pub fn projectiles_done(&self, sheet: &Sheet) -> bool {
+        self.next_projectile >= sheet.projectiles.len() && self.active_projectiles.is_empty()
+    }
+
+    /// Whether shield presses are ignored right now, after a whiff on sheets with stun.
+    pub fn stunned(&self) -> bool {
+        self.env.time < self.stunned_until
+    }
+
+    /// Raises the shields for `presses`, made during the step about to be taken. A press that
+    /// has no projectile to block stuns the player on sheets with stun, all of them are ignored
+    /// while stunned.
+    pub fn press(&mut self, sheet: &Sheet, presses: &[Press]) {
+        self.input.expire(self.env.time);
+
+        if self.stunned() {
+            return;
+        }
+
+        // presses on lanes the sheet doesn't have can't block anything
+        let presses: Vec<Press> = presses
+            .iter()
+            .filter(|press| press.lane < self.shields.len())
+            .cloned()
+            .collect();
+
+        if let Some(stun) = sheet.stun {
+            let whiffed = presses.iter().find(|press| {
+                !self.active_projectiles.iter().any(|projectile| {
+                    projectile.lane == press.lane
+                        && projectile.direction == press.direction
+                        && (projectile.arrival_time - press.time).abs() <= BUFFER_WINDOW
+                })
+            });
+
+            if let Some(press) = whiffed {
+                self.stunned_until = press.time + stun;
+            }
+        }
+
+        self.raise(&presses);
+    }
+
+    /// Buffers `presses` and raises the shield of each lane pressed towards the last direction
+    /// pressed on it.
+    fn raise(&mut self, presses: &[Press]) {
+        let mut raised: Vec<Option<Direction>> = vec![None; self.shields.len()];
+
+        for press in presses.iter().rev() {
+            raised[press.lane].get_or_insert_with(|| press.direction.clone());
+        }
+
+        for press in presses {
+            self.input.push(press.clone());
+        }
+
+        for (lane, direction) in raised.into_iter().enumerate() {
+            if let Some(direction) = direction {
+                if self.shields[lane].raise(direction.clone(), self.env.time) {
+                    self.events
+                        .push(GameEvent::ShieldTurned { lane, direction });
+                }
+            }
+        }
+    }
+
+    /// Forgives the `length` seconds of the clock from the current time, a frame that took too
+    /// long, as far as [`MAX_HITCH`] and what's left of [`MAX_HITCHES`] go.
+    pub fn hitch(&mut self, length: f32) {
+        let forgiven: f32 = self.hitches.iter().map(|(from, to)| to - from).sum();
+        let length = length.min(MAX_HITCH).min(MAX_HITCHES - forgiven);
+
+        if length > 0.0 {
+            self.hitches.push((self.env.time, self.env.time + length));
+        }
+    }
+
+    /// Spends the bomb meter at `time` if it's full and no bomb is waiting to go off.
+    pub fn bomb(&mut self, sheet: &Sheet, time: f32) {
+        let full = sheet
+            .meter
+            .as_ref()
+            .is_some_and(|rules| self.meter.is_full(rules));
+
+        // goes off in the step the press lands in, like the presses do
+        if full && self.next_bomb == self.bombs.len() {
+            self.bombs.push(time);
+        }
+    }
+
+    /// Presses the shields for every projectile arriving after `since`.
+    fn autoplay(&mut self, since: f32) {
+        let presses: Vec<Press> = self
+            .active_projectiles
+            .iter()
+            .filter(|projectile| {
+                projectile.arrival_time > since && projectile.arrival_time <= self.env.time
+            })
+            .map(|projectile| Press {
+                lane: projectile.lane,
+                direction: projectile.direction.clone(),
+                time: projectile.arrival_time,
+            })
+            .collect();
+
+        self.raise(&presses);
+    }
+
+    /// Moves the projectiles that have come on screen or into the sheet's `#prespawn` window from
+    /// the sheet to the active ones.
+    fn activate_projectiles(&mut self, sheet: &Sheet, script: &Script) {
+        let lookahead =
+            activation_lookahead(sheet, &self.camera.state(self.env.time), self.env.speed);
+
+        while let Some(projectile) = sheet.projectiles.get(self.next_projectile) {
+            if projectile.arrival_time > self.env.time + lookahead {
+                break;
+            }
+
+            let mut projectile = projectile.clone();
+            projectile.direction =
+                self.modifiers
+                    .direction(&projectile.direction, self.seed, self.next_projectile);
+            projectile.arrival_time =
+                self.modifiers
+                    .arrival_time(&projectile, self.seed, self.next_projectile);
+
+            self.active_projectiles.push(projectile);
+            self.next_projectile += 1;
+
+            if self.fatal.is_none() {
+                self.run_script(sheet, script, &Event::Spawn);
+            }
+        }
+    }
+
+    /// Starts the sheet's camera moves up to the current time.
+    fn run_camera_events(&mut self, sheet: &Sheet) {
+        while let Some(event) = sheet.camera_events.get(self.next_camera_event) {
+            if event.time > self.env.time {
+                break;
+            }
+
+            self.camera.apply(&event.movement, event.time, sheet.bpm);
+            self.next_camera_event += 1;
+        }
+    }
+
+    /// Runs the actions of the script for `event` that change how the run plays, the rest are
+    /// left to whatever draws it by way of [`Run::fired`].
+    fn run_script(&mut self, sheet: &Sheet, script: &Script, event: &Event) {
+        for action in script.actions(event) {
+            match action {
+                Action::Spawn(ty, lane, direction, offset) => {
+                    let index = sheet.projectiles.len() + self.spawned;
+
+                    self.spawned += 1;
+                    self.active_projectiles.push(Projectile {
+                        arrival_time: self.env.time + offset.time(sheet.bpm),
+                        direction: self.modifiers.direction(direction, self.seed, index),
+                        ty: ty.clone(),
+                        lane: (*lane).min(self.env.lanes - 1),
+                        generated: false,
+                        jitter: 0.0,
+                        drop: None,
+                    });
+                }
+                Action::Camera(movement) => self.camera.apply(movement, self.env.time, sheet.bpm),
+                Action::TimeScale(scale) => self.env.time_scale.script = *scale,
+                Action::Uniform(..) | Action::Shake(_) | Action::Flash(_) => {}
+            }
+        }
+
+        self.fired.push(event.clone());
+    }
+
+    /// Advances the run by `delta` seconds of its clock.
+    pub fn step(&mut self, sheet: &Sheet, script: &Script, delta: f32) {
+        let since = self.env.time;
+
+        self.env.time += delta;
+
+        self.activate_projectiles(sheet, script);
+        self.run_camera_events(sheet);
+
+        if self.fatal.is_some() {
+            return;
+        }
+
+        if self.modifiers.autoplay {
+            self.autoplay(since);
+        }
+
+        while let Some(bomb) = self.bombs.get(self.next_bomb) {
+            if *bomb > self.env.time {
+                break;
+            }
+
+            self.next_bomb += 1;
+
+            let rules = match &sheet.meter {
+                Some(rules) => rules,
+                None => break,
+            };
+            let cleared = self.active_projectiles.len();
+
+            if let Some(points) = self
+                .meter
+                .detonate(rules, sheet, &mut self.active_projectiles)
+            {
+                self.score += points;
+                self.events.push(GameEvent::Bomb {
+                    cleared,
+                    points: (points as f32 * self.modifiers.score_multiplier()).round() as u32,
+                });
+            }
+        }
+
+        let from = self.events.len();
+
+        let time = self.env.time;
+        let hitched = self
+            .hitches
+            .iter()
+            .any(|(from, to)| *from < time && time <= *to);
+
+        self.judge(sheet, !hitched);
+        self.health.update(delta);
+
+        if self.modifiers.aggro {
+            let time = self.env.time;
+            let raised = self.shields.iter().any(|shield| shield.direction.is_some());
+
+            self.aggro.update(raised, delta);
+
+            for shield in &mut self.shields {
+                if aggro::lowered(shield.raised, time) {
+                    shield.direction = None;
+                }
+            }
+        }
+
+        for kind in self.pickups.update(self.env.time) {
+            self.events.push(GameEvent::PickupCollected(kind));
+        }
+
+        while let Some(start) = sheet.sections.get(self.next_section) {
+            if *start > self.env.time {
+                break;
+            }
+
+            self.events.push(GameEvent::SectionStart(self.next_section));
+            self.next_section += 1;
+        }
+
+        while let Some(phase) = sheet.phases.get(self.next_phase) {
+            if phase.time > self.env.time {
+                break;
+            }
+
+            self.events.push(GameEvent::PhaseStart(self.next_phase));
+            self.next_phase += 1;
+        }
+
+        if let Some(boss) = &mut self.boss {
+            boss.update(delta);
+        }
+
+        // the script's hooks for what happened this step, in the order it happened in
+        let hooks: Vec<Event> = self.events[from..]
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::NoteBlocked { .. } => Some(Event::Block),
+                GameEvent::SectionStart(_) => Some(Event::Section),
+                GameEvent::PhaseStart(_) => Some(Event::Phase),
+                _ => None,
+            })
+            .collect();
+
+        for hook in &hooks {
+            self.run_script(sheet, script, hook);
+        }
+
+        let beat = beat_at(sheet, self.env.time);
+
+        while self.beat < beat {
+            self.beat += 1;
+
+            if self.beat >= 0 {
+                self.run_script(sheet, script, &Event::Beat(self.beat as u32));
+            }
+        }
+
+        self.momentum
+            .update(&sheet.momentum, &mut self.env.speed, delta);
+    }
+
+    /// Judges the projectiles on screen against the shields.
+    fn judge(&mut self, sheet: &Sheet, judge_misses: bool) {
+        let env = &self.env;
+        let shields = &self.shields;
+        let input = &mut self.input;
+        let multiplier = self.modifiers.score_multiplier();
+        let fatal = &mut self.fatal;
+        let events = &mut self.events;
+        let boss = &mut self.boss;
+        let pickups = &mut self.pickups;
+        let meter = &mut self.meter;
+        let momentum = &mut self.momentum;
+        let windows = pickups.windows(&self.windows);
+        let mut judge = Judge {
+            sheet,
+            windows: &windows,
+            score: &mut self.score,
+            combo: &mut self.combo,
+            best_combo: &mut self.best_combo,
+            health: &mut self.health,
+            judge_misses,
+            no_fail: self.no_fail,
+            aggro: if self.modifiers.aggro {
+                self.aggro.multiplier()
+            } else {
+                1
+            },
+        };
+
+        self.active_projectiles.retain(|projectile| {
+            let direction = projectile.direction.clone();
+            let shield = pickups.shield(&shields[projectile.lane].direction, &direction);
+
+            match projectile.judge(env, &shield, input, &mut judge) {
+                Outcome::Flying => true,
+                Outcome::Pickup | Outcome::Passed => false,
+                Outcome::Blocked {
+                    offset,
+                    judgment,
+                    points,
+                } => {
+                    let combo = *judge.combo;
+                    let position = projectile.position(env, sheet.bpm);
+
+                    if let Some(kind) = projectile.drop {
+                        pickups.drop(kind, projectile.lane, position, env.time);
+                    }
+
+                    if let Some(rules) = sheet.meter.as_ref() {
+                        if judgment == Judgment::Perfect {
+                            meter.perfect(rules);
+                        }
+                    }
+
+                    momentum.block(&sheet.momentum, judgment);
+
+                    events.push(GameEvent::NoteBlocked {
+                        direction,
+                        position,
+                        offset,
+                        judgment,
+                        points: (points as f32 * multiplier).round() as u32,
+                        combo,
+                    });
+
+                    if boss.as_mut().is_some_and(|boss| boss.damage(BOSS_DAMAGE)) {
+                        events.push(GameEvent::BossDefeated);
+                    }
+
+                    false
+                }
+                Outcome::Missed => {
+                    momentum.miss(&sheet.momentum);
+
+                    events.push(GameEvent::NoteMissed {
+                        direction,
+                        position: projectile.position(env, sheet.bpm),
+                    });
+
+                    false
+                }
+                Outcome::Fatal => {
+                    momentum.miss(&sheet.momentum);
+
+                    events.push(GameEvent::Death {
+                        direction,
+                        position: projectile.position(env, sheet.bpm),
+                    });
+
+                    *fatal = Some(projectile.clone());
+
+                    true
+                }
+            }
+        });
+    }
+}
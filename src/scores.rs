@@ -0,0 +1,334 @@
+//! Best scores and play counts of a profile, kept in its `scores.toml`.
+//!
+//! Each record is signed with the hash of the chart and the modifiers its best score was set
+//! with, records that don't match their signature are left out when loading. The profile's
+//! [`Lifetime`] totals are kept along with them, unsigned.
+
+/// Runs kept for each level, best first.
+pub const TOP_RUNS: usize = 10;
+
+use crate::config::{Config, Value};
+use crate::end_menu::{Ending, Summary};
+use crate::integrity::{checksum, from_hex, to_hex};
+use crate::log_warn;
+use crate::profile::Profile;
+
+#[derive(Clone, Debug, Default)]
+pub struct LevelRecord {
+    pub best_score: u32,
+    pub best_combo: u32,
+    pub plays: u32,
+    pub clears: u32,
+    /// [`Sheet::level_hash`](crate::sheet::Sheet::level_hash) of the level the record was set
+    /// on.
+    pub chart: u64,
+    /// Tag of the modifiers the best score was set with.
+    pub modifiers: String,
+    /// The best [`TOP_RUNS`] runs, best first.
+    pub top: Vec<RunRecord>,
+    /// Whether the chart has been cleared without a miss, shown as a badge.
+    pub full_combo: bool,
+}
+
+/// One of the best runs of a level.
+#[derive(Clone, Debug, Default)]
+pub struct RunRecord {
+    pub score: u32,
+    /// Seconds since the unix epoch the run ended at, also naming its replay.
+    pub date: u64,
+    /// Tag of the modifiers it was played with.
+    pub modifiers: String,
+    /// Fraction of projectiles blocked, `None` if none arrived.
+    pub accuracy: Option<f32>,
+    /// Whether it cleared the level without a miss.
+    pub full_combo: bool,
+}
+
+impl LevelRecord {
+    fn signature(&self, level: &str) -> u64 {
+        let mut signed = format!(
+            "{}|{}|{}|{}|{}|{}|{}",
+            level,
+            self.best_score,
+            self.best_combo,
+            self.plays,
+            self.clears,
+            to_hex(self.chart),
+            self.modifiers,
+        );
+
+        for run in &self.top {
+            signed += &format!(
+                "|{}|{}|{}|{:?}",
+                run.score, run.date, run.modifiers, run.accuracy
+            );
+
+            // only signed when set, so records from before full combos still match
+            if run.full_combo {
+                signed += "|full_combo";
+            }
+        }
+
+        if self.full_combo {
+            signed += "|full_combo";
+        }
+
+        checksum(signed.as_bytes())
+    }
+}
+
+/// Totals over every run added to the scores.
+#[derive(Clone, Debug, Default)]
+pub struct Lifetime {
+    pub runs: u32,
+    /// Perfects, greats, lates and misses.
+    pub judgments: [u32; 4],
+}
+
+/// Names of the judgments in the scores file, in the order of [`Lifetime::judgments`].
+const JUDGMENT_KEYS: [&str; 4] = ["perfect", "great", "late", "miss"];
+
+/// What adding a run to the scores changed.
+pub struct Recorded {
+    /// Whether the run is one of the best of its level, so its replay is worth keeping.
+    pub kept: bool,
+    /// Dates of the runs that aren't among the best anymore.
+    pub dropped: Vec<u64>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Scores {
+    /// Records by the name of the level's folder.
+    levels: Vec<(String, LevelRecord)>,
+    pub lifetime: Lifetime,
+}
+
+impl Scores {
+    /// Loads the scores of `profile`, none if it has no scores file or it's invalid.
+    pub fn load(profile: &Profile) -> Self {
+        let config = profile
+            .read(&profile.scores_path())
+            .and_then(|source| Config::parse(&source).ok())
+            .unwrap_or_default();
+
+        let mut scores = Self::default();
+        let mut signatures: Vec<(String, u64)> = Vec::new();
+
+        for (key, value) in &config.values {
+            if let Some(field) = key.strip_prefix("lifetime.") {
+                let count = value.as_f32().unwrap_or(0.0) as u32;
+
+                match JUDGMENT_KEYS.iter().position(|judgment| *judgment == field) {
+                    Some(index) => scores.lifetime.judgments[index] = count,
+                    None if field == "runs" => scores.lifetime.runs = count,
+                    None => {}
+                }
+
+                continue;
+            }
+
+            // level names can have dots in them, the field never does
+            let (level, field) = match key
+                .strip_prefix("level.")
+                .and_then(|key| key.rsplit_once('.'))
+            {
+                Some(parts) => parts,
+                None => continue,
+            };
+
+            let hash = value.as_str().and_then(from_hex).unwrap_or(0);
+            let count = value.as_f32().unwrap_or(0.0) as u32;
+
+            // runs are under `level.<name>.top.<index>.<field>`
+            if let Some((level, index)) = level.rsplit_once(".top.") {
+                let index: usize = match index.parse() {
+                    Ok(index) if index < TOP_RUNS => index,
+                    _ => continue,
+                };
+
+                let top = &mut scores.entry(level).top;
+
+                if top.len() <= index {
+                    top.resize(index + 1, RunRecord::default());
+                }
+
+                let run = &mut top[index];
+
+                match field {
+                    "score" => run.score = count,
+                    "date" => {
+                        run.date = value
+                            .as_str()
+                            .and_then(|date| date.parse().ok())
+                            .unwrap_or(0)
+                    }
+                    "modifiers" => run.modifiers = value.as_str().unwrap_or("").to_string(),
+                    "accuracy" => run.accuracy = value.as_f32(),
+                    "full_combo" => run.full_combo = value.as_bool().unwrap_or(false),
+                    _ => {}
+                }
+
+                continue;
+            }
+
+            let record = scores.entry(level);
+
+            match field {
+                "best_score" => record.best_score = count,
+                "best_combo" => record.best_combo = count,
+                "plays" => record.plays = count,
+                "clears" => record.clears = count,
+                "chart" => record.chart = hash,
+                "modifiers" => record.modifiers = value.as_str().unwrap_or("").to_string(),
+                "full_combo" => record.full_combo = value.as_bool().unwrap_or(false),
+                "signature" => signatures.push((level.to_string(), hash)),
+                _ => {}
+            }
+        }
+
+        scores.levels.retain(|(level, record)| {
+            let signed = signatures.iter().any(|(signed, signature)| {
+                signed == level && *signature == record.signature(level)
+            });
+
+            if !signed {
+                log_warn!("ignoring the record of '{}', it was edited", level);
+            }
+
+            signed
+        });
+
+        scores
+    }
+
+    pub fn save(&self, profile: &Profile) {
+        let mut config = Config::new();
+
+        config.set("lifetime.runs", Value::Number(self.lifetime.runs as f32));
+
+        for (key, count) in JUDGMENT_KEYS.iter().zip(self.lifetime.judgments) {
+            config.set(format!("lifetime.{}", key), Value::Number(count as f32));
+        }
+
+        for (level, record) in &self.levels {
+            let key = |field: &str| format!("level.{}.{}", level, field);
+
+            config.set(key("best_score"), Value::Number(record.best_score as f32));
+            config.set(key("best_combo"), Value::Number(record.best_combo as f32));
+            config.set(key("plays"), Value::Number(record.plays as f32));
+            config.set(key("clears"), Value::Number(record.clears as f32));
+            config.set(key("chart"), Value::String(to_hex(record.chart)));
+            config.set(key("modifiers"), Value::String(record.modifiers.clone()));
+
+            if record.full_combo {
+                config.set(key("full_combo"), Value::Bool(true));
+            }
+
+            config.set(
+                key("signature"),
+                Value::String(to_hex(record.signature(level))),
+            );
+
+            for (index, run) in record.top.iter().enumerate() {
+                let key = |field: &str| format!("level.{}.top.{}.{}", level, index, field);
+
+                config.set(key("score"), Value::Number(run.score as f32));
+                config.set(key("date"), Value::String(run.date.to_string()));
+                config.set(key("modifiers"), Value::String(run.modifiers.clone()));
+
+                if let Some(accuracy) = run.accuracy {
+                    config.set(key("accuracy"), Value::Number(accuracy));
+                }
+
+                if run.full_combo {
+                    config.set(key("full_combo"), Value::Bool(true));
+                }
+            }
+        }
+
+        // losing a score isn't worth crashing over
+        let _ = profile.write(&profile.scores_path(), &config.to_string());
+    }
+
+    pub fn get(&self, level: &str) -> Option<&LevelRecord> {
+        self.levels
+            .iter()
+            .find(|(name, _)| name == level)
+            .map(|(_, record)| record)
+    }
+
+    fn entry(&mut self, level: &str) -> &mut LevelRecord {
+        let index = match self.levels.iter().position(|(name, _)| name == level) {
+            Some(index) => index,
+            None => {
+                self.levels
+                    .push((level.to_string(), LevelRecord::default()));
+                self.levels.len() - 1
+            }
+        };
+
+        &mut self.levels[index].1
+    }
+
+    /// Adds a run of `level` that ended at `date`. A run of a changed chart starts a new record,
+    /// the old one was set on a different level.
+    pub fn record(&mut self, level: &str, summary: &Summary, date: u64) -> Recorded {
+        self.lifetime.runs += 1;
+
+        for (total, count) in self.lifetime.judgments.iter_mut().zip(summary.judgments) {
+            *total += count;
+        }
+
+        let record = self.entry(level);
+        let mut dropped = Vec::new();
+
+        if record.chart != summary.chart {
+            dropped.extend(record.top.iter().map(|run| run.date));
+
+            *record = LevelRecord {
+                chart: summary.chart,
+                ..LevelRecord::default()
+            };
+        }
+
+        record.plays += 1;
+        record.best_combo = record.best_combo.max(summary.best_combo);
+
+        if summary.score > record.best_score || record.modifiers.is_empty() {
+            record.best_score = summary.score;
+            record.modifiers = summary.modifiers.clone();
+        }
+
+        if let Ending::Cleared = summary.ending {
+            record.clears += 1;
+        }
+
+        record.full_combo |= summary.full_combo;
+
+        // ties go to the earlier run
+        let index = record.top.partition_point(|run| run.score >= summary.score);
+
+        record.top.insert(
+            index,
+            RunRecord {
+                score: summary.score,
+                date,
+                modifiers: summary.modifiers.clone(),
+                accuracy: summary.accuracy,
+                full_combo: summary.full_combo,
+            },
+        );
+
+        dropped.extend(
+            record
+                .top
+                .drain(TOP_RUNS.min(record.top.len())..)
+                .map(|run| run.date),
+        );
+
+        Recorded {
+            kept: index < TOP_RUNS,
+            dropped,
+        }
+    }
+}
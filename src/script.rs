@@ -0,0 +1,284 @@
+//! Level scripts, hooks in a level's `script.txt` that run actions when things happen.
+//!
+//! Each line is an event followed by an action, using the same tokens as sheets:
+//!
+//! ```text
+//! // every 4 beats, send a projectile from the left arriving a bar later
+//! on_beat 4 spawn norm L 0;0|1
+//! // pulse a custom shader uniform whenever a projectile is blocked
+//! on_block uniform iPulse 1
+//! // bump the camera a little on every spawn
+//! on_spawn shake 0.1
+//! // turn the camera a little every bar
+//! on_beat 4 camera rotation=10 over=2
+//! // flash at the start of every section
+//! on_section flash 1
+//! // shake the camera as the first projectile of each boss phase arrives
+//! on_phase shake 0.3
+//! // on stage sheets, send one at the second lane's heart every bar
+//! on_beat 4 spawn norm @2 U 0;0|1
+//! // slow the level down to half speed for the second section, the song plays on as it was
+//! on_section timescale 0.5
+//! ```
+//!
+//! Uniforms set by scripts are declared on the level's background shader, their values written
+//! like those of [`crate::uniforms`], `uniform iTint #ff8800` or `uniform iCenter 0.5,0`.
+
+use crate::camera::CameraMove;
+use crate::game::Direction;
+use crate::integrity::Digest;
+use crate::projectiles::ProjectileType;
+use crate::sheet::{TimeOffset, Token};
+use crate::uniforms::UniformValue;
+
+#[derive(Debug)]
+pub struct ScriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// Every this many beats.
+    Beat(u32),
+    Block,
+    /// A projectile from the sheet coming on screen.
+    Spawn,
+    /// A section of the sheet starting.
+    Section,
+    /// The first projectile of a boss phase of the sheet arriving.
+    Phase,
+}
+
+#[derive(Debug)]
+pub enum Action {
+    /// Spawns a projectile on a lane, arriving the offset after the event. Lanes the sheet
+    /// doesn't have fall back to its last.
+    Spawn(ProjectileType, usize, Direction, TimeOffset),
+    Uniform(String, UniformValue),
+    /// Adds trauma to the camera shake, from 0 to 1.
+    Shake(f32),
+    Flash(f32),
+    Camera(CameraMove),
+    /// Sets how fast the level's clock runs, from [`MIN_TIME_SCALE`] to [`MAX_TIME_SCALE`].
+    TimeScale(f32),
+}
+
+pub const MIN_TIME_SCALE: f32 = 0.1;
+
+pub const MAX_TIME_SCALE: f32 = 2.0;
+
+#[derive(Debug)]
+pub struct Hook {
+    pub event: Event,
+    pub action: Action,
+}
+
+#[derive(Debug, Default)]
+pub struct Script {
+    pub hooks: Vec<Hook>,
+}
+
+impl Script {
+    /// Adds what the hooks do to a level's hash, see [`Sheet::level_hash`]. Offsets are hashed
+    /// as the times they come to at `bpm`, like the sheet's.
+    ///
+    /// [`Sheet::level_hash`]: crate::sheet::Sheet::level_hash
+    pub fn hash(&self, digest: &mut Digest, bpm: f32) {
+        digest.integer(self.hooks.len() as u32);
+
+        for hook in &self.hooks {
+            match &hook.event {
+                Event::Beat(beats) => {
+                    digest.integer(0);
+                    digest.integer(*beats);
+                }
+                Event::Block => digest.integer(1),
+                Event::Spawn => digest.integer(2),
+                Event::Section => digest.integer(3),
+                Event::Phase => digest.integer(4),
+            }
+
+            match &hook.action {
+                Action::Spawn(ty, lane, direction, offset) => {
+                    digest.integer(0);
+                    ty.behavior.hash(digest);
+                    digest.integer(*lane as u32);
+                    digest.integer(direction.clone() as u32);
+                    digest.number(offset.time(bpm));
+                }
+                Action::Uniform(name, value) => {
+                    digest.integer(1);
+                    digest.bytes(name.as_bytes());
+
+                    for component in value.components() {
+                        digest.number(component);
+                    }
+                }
+                Action::Shake(trauma) => {
+                    digest.integer(2);
+                    digest.number(*trauma);
+                }
+                Action::Flash(flash) => {
+                    digest.integer(3);
+                    digest.number(*flash);
+                }
+                Action::Camera(movement) => {
+                    digest.integer(4);
+                    digest.optional(movement.zoom);
+                    digest.optional(movement.rotation);
+                    digest.optional(movement.x);
+                    digest.optional(movement.y);
+                    digest.optional(movement.over.as_ref().map(|over| over.time(bpm)));
+                }
+                Action::TimeScale(scale) => {
+                    digest.integer(5);
+                    digest.number(*scale);
+                }
+            }
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self, ScriptError> {
+        let mut script = Self::default();
+
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+
+            let error = |message: &str| ScriptError {
+                line: index + 1,
+                message: message.to_string(),
+            };
+
+            let mut words = line.split_whitespace();
+
+            let event = match words.next() {
+                Some("on_beat") => Event::Beat(
+                    words
+                        .next()
+                        .and_then(|beats| beats.parse().ok())
+                        .filter(|beats| *beats > 0)
+                        .ok_or_else(|| error("on_beat needs a number of beats"))?,
+                ),
+                Some("on_block") => Event::Block,
+                Some("on_spawn") => Event::Spawn,
+                Some("on_section") => Event::Section,
+                Some("on_phase") => Event::Phase,
+                Some(event) => return Err(error(&format!("unknown event '{}'", event))),
+                None => unreachable!(),
+            };
+
+            let action = match words.next() {
+                Some("spawn") => {
+                    let ty = match words.next().map(Token::parse) {
+                        Some(Ok(Token::Projectile(ty))) => ty,
+                        _ => return Err(error("spawn needs a projectile type")),
+                    };
+
+                    let (lane, direction) = match words.next().map(Token::parse) {
+                        Some(Ok(Token::Lane(lane))) => (lane, words.next().map(Token::parse)),
+                        direction => (0, direction),
+                    };
+
+                    let direction = match direction {
+                        Some(Ok(Token::Direction(direction))) => direction,
+                        _ => return Err(error("spawn needs a direction")),
+                    };
+
+                    let offset = words
+                        .next()
+                        .and_then(|offset| TimeOffset::parse(offset).ok())
+                        .ok_or_else(|| error("spawn needs a time offset"))?;
+
+                    Action::Spawn(ty, lane, direction, offset)
+                }
+                Some("uniform") => {
+                    let name = words
+                        .next()
+                        .ok_or_else(|| error("uniform needs a name"))?
+                        .to_string();
+
+                    Action::Uniform(
+                        name,
+                        words
+                            .next()
+                            .and_then(UniformValue::parse)
+                            .ok_or_else(|| error("uniform needs a value"))?,
+                    )
+                }
+                Some("shake") => Action::Shake(
+                    number(words.next()).ok_or_else(|| error("shake needs an amount"))?,
+                ),
+                Some("flash") => Action::Flash(
+                    number(words.next()).ok_or_else(|| error("flash needs an amount"))?,
+                ),
+                Some("timescale") => Action::TimeScale(
+                    number(words.next())
+                        .filter(|scale| (MIN_TIME_SCALE..=MAX_TIME_SCALE).contains(scale))
+                        .ok_or_else(|| error("timescale needs a scale from 0.1 to 2"))?,
+                ),
+                Some("camera") => Action::Camera(
+                    CameraMove::parse(
+                        words
+                            .by_ref()
+                            .map(|word| word.split_once('=').unwrap_or((word, ""))),
+                    )
+                    .map_err(|pair| error(&format!("invalid camera setting '{}'", pair)))?,
+                ),
+                Some(action) => return Err(error(&format!("unknown action '{}'", action))),
+                None => return Err(error("missing action")),
+            };
+
+            if words.next().is_some() {
+                return Err(error("unexpected text after action"));
+            }
+
+            script.hooks.push(Hook { event, action });
+        }
+
+        Ok(script)
+    }
+
+    /// The uniforms the script sets, each with the first value it's set to.
+    pub fn uniforms(&self) -> Vec<(String, UniformValue)> {
+        let mut uniforms: Vec<(String, UniformValue)> = Vec::new();
+
+        for hook in &self.hooks {
+            if let Action::Uniform(name, value) = &hook.action {
+                if !uniforms.iter().any(|(known, _)| known == name) {
+                    uniforms.push((name.clone(), *value));
+                }
+            }
+        }
+
+        uniforms
+    }
+
+    /// The actions to run when `event` happens.
+    pub fn actions<'a>(&'a self, event: &'a Event) -> impl Iterator<Item = &'a Action> {
+        self.hooks
+            .iter()
+            .filter(move |hook| match (&hook.event, event) {
+                // a beat event carries the beat number, hooks run on multiples of theirs
+                (Event::Beat(every), Event::Beat(beat)) => beat % every == 0,
+                (hook_event, event) => hook_event == event,
+            })
+            .map(|hook| &hook.action)
+    }
+}
+
+fn number(word: Option<&str>) -> Option<f32> {
+    word.and_then(|word| word.parse().ok())
+}
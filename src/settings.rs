@@ -0,0 +1,658 @@
+use crate::asset_source::LEVELS_DIR;
+use crate::config::{Config, Value};
+use crate::input::{key_from_name, key_name, BINDABLE_KEYS};
+use crate::integrity::{from_hex, to_hex};
+use crate::locale::{languages, Locale};
+use crate::modifiers::Modifiers;
+use crate::palette::Palette;
+use crate::profile::Profile;
+use crate::streamer::STREAMER_KEY;
+use macroquad::prelude::*;
+
+pub fn set_fullscreen(fullscreen: bool) {
+    unsafe { get_internal_gl() }
+        .quad_context
+        .set_fullscreen(fullscreen);
+}
+
+/// Frames a second menus are drawn at with [`Settings::battery_saver`] on, egui only changes when
+/// it's used.
+pub const MENU_FRAME_CAP: f32 = 30.0;
+
+pub const DEFAULT_HITSOUND: &str = "assets/kick.wav";
+
+/// Seconds a press of `+` or `-` moves the offset of the level playing by.
+pub const SONG_OFFSET_STEP: f32 = 0.005;
+
+/// Paths of the sounds played for each judgment, empty for none.
+#[derive(Clone, Debug)]
+pub struct HitsoundPaths {
+    pub perfect: String,
+    pub great: String,
+    pub late: String,
+    pub miss: String,
+    pub milestone: String,
+}
+
+impl Default for HitsoundPaths {
+    fn default() -> Self {
+        Self {
+            perfect: DEFAULT_HITSOUND.to_string(),
+            great: DEFAULT_HITSOUND.to_string(),
+            late: DEFAULT_HITSOUND.to_string(),
+            miss: String::new(),
+            milestone: String::new(),
+        }
+    }
+}
+
+impl HitsoundPaths {
+    fn fields(&mut self) -> [(&'static str, &mut String); 5] {
+        [
+            ("perfect", &mut self.perfect),
+            ("great", &mut self.great),
+            ("late", &mut self.late),
+            ("miss", &mut self.miss),
+            ("milestone", &mut self.milestone),
+        ]
+    }
+}
+
+/// Player settings, persisted in the profile's `settings.toml`.
+#[derive(Clone, Debug)]
+pub struct Settings {
+    /// Run the scene through a crt/scanline filter.
+    pub crt: bool,
+    /// Strength of the bloom, 0 disables it.
+    pub bloom: f32,
+    pub fullscreen: bool,
+    /// Scale of menus and hud text.
+    pub ui_scale: f32,
+    /// Multiplier for camera shake, 0 disables it.
+    pub shake: f32,
+    /// Multiplier for screen flashes like the chromatic aberration on hits.
+    pub flashes: f32,
+    /// How much the song's background is darkened, 0 leaves it as it is and 1 blacks it out.
+    pub background_dim: f32,
+    /// Opacity of the projectiles, telegraphs, hearts and shields.
+    pub playfield_opacity: f32,
+    /// Slow time down on death instead of freezing immediately.
+    pub death_warp: bool,
+    /// Draw projectiles with an outline and a glow, styled in the effects file.
+    pub high_contrast: bool,
+    /// Move the particles of big bursts on the GPU, see [`crate::gpu_particles`].
+    pub gpu_particles: bool,
+    /// Most frames drawn a second, 0 for as many as vsync lets through.
+    pub frame_cap: u32,
+    /// Draw menus at [`MENU_FRAME_CAP`] frames a second to save battery.
+    pub battery_saver: bool,
+    /// Tints projectiles and the shield by direction.
+    pub palette: Palette,
+    /// Draws a shape unique to each direction on projectiles.
+    pub direction_glyphs: bool,
+    /// Draws how far the level's clock has drifted from the song on the hud.
+    pub drift_indicator: bool,
+    /// Draws the [streamer overlay](crate::streamer) over levels.
+    pub streamer_mode: bool,
+    /// Keep the last few seconds of gameplay so they can be saved as a clip.
+    pub clip_buffer: bool,
+    /// Seconds of gameplay kept for clips.
+    pub clip_length: f32,
+    /// Frames longer than this many seconds never count a miss.
+    pub hitch_threshold: f32,
+    /// Key held to restart the level.
+    pub restart_key: KeyCode,
+    /// Seconds the restart key has to be held for, 0 restarts on press.
+    pub restart_hold: f32,
+    /// Start practice loops slower and speed up after every loop without a miss.
+    pub practice_ramp: bool,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    /// Volume of the block sound, on top of the sfx volume.
+    pub kick_volume: f32,
+    /// Volume of the death sound, on top of the sfx volume.
+    pub death_volume: f32,
+    /// Seconds the song is heard after it's played, the level's clock runs this far behind it.
+    pub audio_offset: f32,
+    /// Seconds added to the audio offset for single charts, by chart hash, for songs that are off
+    /// on their own.
+    pub song_offsets: Vec<(u64, f32)>,
+    pub hitsounds: HitsoundPaths,
+    /// Whether the tutorial has been started, it opens on the first launch otherwise.
+    pub tutorial_seen: bool,
+    /// The modifiers last picked in the menu.
+    pub modifiers: Modifiers,
+    /// Code of the language text is shown in, like `en`.
+    pub language: String,
+    /// Text in [`Settings::language`], loaded along with the settings.
+    pub locale: Locale,
+    /// The profile the settings belong to and are saved to.
+    pub profile: Profile,
+    /// Where to sync the profile's settings and scores, not synced if empty.
+    pub sync_url: String,
+    /// Folder the levels are read from, picked in the [setup](crate::setup).
+    pub songs_dir: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            crt: false,
+            bloom: 0.5,
+            fullscreen: false,
+            // phones need bigger text to be readable
+            ui_scale: if cfg!(target_os = "android") {
+                1.5
+            } else {
+                1.0
+            },
+            shake: 1.0,
+            flashes: 1.0,
+            background_dim: 0.0,
+            playfield_opacity: 1.0,
+            death_warp: true,
+            high_contrast: false,
+            gpu_particles: true,
+            frame_cap: 0,
+            battery_saver: false,
+            palette: Palette::Off,
+            direction_glyphs: false,
+            drift_indicator: false,
+            streamer_mode: false,
+            hitch_threshold: 0.1,
+            restart_key: KeyCode::R,
+            restart_hold: 0.5,
+            practice_ramp: false,
+            clip_buffer: false,
+            clip_length: 10.0,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            kick_volume: 1.0,
+            death_volume: 1.0,
+            audio_offset: 0.0,
+            song_offsets: Vec::new(),
+            hitsounds: HitsoundPaths::default(),
+            tutorial_seen: false,
+            modifiers: Modifiers::default(),
+            language: "en".to_string(),
+            locale: Locale::load("en"),
+            profile: Profile::default(),
+            sync_url: String::new(),
+            songs_dir: LEVELS_DIR.to_string(),
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the settings of `profile`, falling back to the defaults for anything missing or
+    /// invalid.
+    pub fn load(profile: &Profile) -> Self {
+        let config = profile
+            .read(&profile.settings_path())
+            .and_then(|source| Config::parse(&source).ok())
+            .unwrap_or_default();
+
+        Self {
+            profile: profile.clone(),
+            ..Self::from_config(&config)
+        }
+    }
+
+    pub fn save(&self) {
+        // failing to save settings shouldn't take the game down with it
+        let _ = self
+            .profile
+            .write(&self.profile.settings_path(), &self.to_config().to_string());
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        let default = Self::default();
+
+        let mut hitsounds = default.hitsounds.clone();
+        let language = config.get_str("language").unwrap_or(&default.language);
+
+        for (name, path) in hitsounds.fields() {
+            if let Some(value) = config.get_str(&format!("hitsounds.{}", name)) {
+                *path = value.to_string();
+            }
+        }
+
+        // offsets are under `offsets.<chart hash>`
+        let song_offsets = config
+            .values
+            .iter()
+            .filter_map(|(key, value)| {
+                let chart = key.strip_prefix("offsets.").and_then(from_hex)?;
+
+                Some((chart, value.as_f32()?))
+            })
+            .collect();
+
+        Self {
+            crt: config.get_bool("crt").unwrap_or(default.crt),
+            bloom: config.get_f32("bloom").unwrap_or(default.bloom),
+            fullscreen: config.get_bool("fullscreen").unwrap_or(default.fullscreen),
+            ui_scale: config.get_f32("ui_scale").unwrap_or(default.ui_scale),
+            shake: config.get_f32("shake").unwrap_or(default.shake),
+            flashes: config.get_f32("flashes").unwrap_or(default.flashes),
+            background_dim: config
+                .get_f32("background_dim")
+                .unwrap_or(default.background_dim),
+            playfield_opacity: config
+                .get_f32("playfield_opacity")
+                .unwrap_or(default.playfield_opacity),
+            death_warp: config.get_bool("death_warp").unwrap_or(default.death_warp),
+            high_contrast: config
+                .get_bool("high_contrast")
+                .unwrap_or(default.high_contrast),
+            gpu_particles: config
+                .get_bool("gpu_particles")
+                .unwrap_or(default.gpu_particles),
+            frame_cap: config
+                .get_f32("display.frame_cap")
+                .map_or(default.frame_cap, |cap| cap.max(0.0) as u32),
+            battery_saver: config
+                .get_bool("display.battery_saver")
+                .unwrap_or(default.battery_saver),
+            palette: config
+                .get_str("palette")
+                .and_then(Palette::from_name)
+                .unwrap_or(default.palette),
+            direction_glyphs: config
+                .get_bool("direction_glyphs")
+                .unwrap_or(default.direction_glyphs),
+            drift_indicator: config
+                .get_bool("drift_indicator")
+                .unwrap_or(default.drift_indicator),
+            streamer_mode: config
+                .get_bool("streamer_mode")
+                .unwrap_or(default.streamer_mode),
+            clip_buffer: config
+                .get_bool("capture.clip_buffer")
+                .unwrap_or(default.clip_buffer),
+            clip_length: config
+                .get_f32("capture.clip_length")
+                .unwrap_or(default.clip_length),
+            hitch_threshold: config
+                .get_f32("hitch_threshold")
+                .unwrap_or(default.hitch_threshold),
+            restart_key: config
+                .get_str("controls.restart")
+                .and_then(key_from_name)
+                .unwrap_or(default.restart_key),
+            restart_hold: config
+                .get_f32("controls.restart_hold")
+                .unwrap_or(default.restart_hold),
+            practice_ramp: config
+                .get_bool("practice.rate_ramp")
+                .unwrap_or(default.practice_ramp),
+            music_volume: config
+                .get_f32("audio.music")
+                .unwrap_or(default.music_volume),
+            sfx_volume: config.get_f32("audio.sfx").unwrap_or(default.sfx_volume),
+            kick_volume: config.get_f32("audio.kick").unwrap_or(default.kick_volume),
+            death_volume: config
+                .get_f32("audio.death")
+                .unwrap_or(default.death_volume),
+            audio_offset: config
+                .get_f32("audio.offset")
+                .unwrap_or(default.audio_offset),
+            song_offsets,
+            hitsounds,
+            tutorial_seen: config
+                .get_bool("tutorial_seen")
+                .unwrap_or(default.tutorial_seen),
+            modifiers: Modifiers::from_config(config),
+            locale: Locale::load(language),
+            language: language.to_string(),
+            profile: default.profile.clone(),
+            sync_url: config
+                .get_str("sync.url")
+                .map_or(default.sync_url, str::to_string),
+            songs_dir: config
+                .get_str("songs_dir")
+                .filter(|dir| !dir.is_empty())
+                .map_or(default.songs_dir, str::to_string),
+        }
+    }
+
+    pub fn to_config(&self) -> Config {
+        let mut config = Config::new();
+
+        config.set("crt", Value::Bool(self.crt));
+        config.set("bloom", Value::Number(self.bloom));
+        config.set("fullscreen", Value::Bool(self.fullscreen));
+        config.set("ui_scale", Value::Number(self.ui_scale));
+        config.set("shake", Value::Number(self.shake));
+        config.set("flashes", Value::Number(self.flashes));
+        config.set("background_dim", Value::Number(self.background_dim));
+        config.set("playfield_opacity", Value::Number(self.playfield_opacity));
+        config.set("death_warp", Value::Bool(self.death_warp));
+        config.set("high_contrast", Value::Bool(self.high_contrast));
+        config.set("gpu_particles", Value::Bool(self.gpu_particles));
+        config.set("display.frame_cap", Value::Number(self.frame_cap as f32));
+        config.set("display.battery_saver", Value::Bool(self.battery_saver));
+        config.set("palette", Value::String(self.palette.name().to_string()));
+        config.set("direction_glyphs", Value::Bool(self.direction_glyphs));
+        config.set("drift_indicator", Value::Bool(self.drift_indicator));
+        config.set("streamer_mode", Value::Bool(self.streamer_mode));
+        config.set("hitch_threshold", Value::Number(self.hitch_threshold));
+        config.set("tutorial_seen", Value::Bool(self.tutorial_seen));
+        config.set("language", Value::String(self.language.clone()));
+        config.set(
+            "controls.restart",
+            Value::String(key_name(self.restart_key).to_string()),
+        );
+        config.set("controls.restart_hold", Value::Number(self.restart_hold));
+        config.set("practice.rate_ramp", Value::Bool(self.practice_ramp));
+        config.set("capture.clip_buffer", Value::Bool(self.clip_buffer));
+        config.set("capture.clip_length", Value::Number(self.clip_length));
+        config.set("audio.music", Value::Number(self.music_volume));
+        config.set("audio.sfx", Value::Number(self.sfx_volume));
+        config.set("audio.kick", Value::Number(self.kick_volume));
+        config.set("audio.death", Value::Number(self.death_volume));
+        config.set("audio.offset", Value::Number(self.audio_offset));
+        config.set("sync.url", Value::String(self.sync_url.clone()));
+        config.set("songs_dir", Value::String(self.songs_dir.clone()));
+
+        for (name, path) in self.hitsounds.clone().fields() {
+            config.set(format!("hitsounds.{}", name), Value::String(path.clone()));
+        }
+
+        for (chart, offset) in &self.song_offsets {
+            config.set(
+                format!("offsets.{}", to_hex(*chart)),
+                Value::Number(*offset),
+            );
+        }
+
+        self.modifiers.write_config(&mut config);
+
+        config
+    }
+
+    /// Seconds the level's clock runs behind the song on `chart`, the audio offset and the
+    /// chart's own.
+    pub fn offset(&self, chart: u64) -> f32 {
+        self.audio_offset + self.song_offset(chart)
+    }
+
+    pub fn song_offset(&self, chart: u64) -> f32 {
+        self.song_offsets
+            .iter()
+            .find(|(other, _)| *other == chart)
+            .map_or(0.0, |(_, offset)| *offset)
+    }
+
+    /// Moves the offset of `chart` by `amount` seconds, forgetting it once it's back at 0.
+    pub fn nudge_song_offset(&mut self, chart: u64, amount: f32) {
+        // rounded to whole milliseconds so repeated nudges don't drift
+        let offset = ((self.song_offset(chart) + amount) * 1000.0).round() / 1000.0;
+
+        self.song_offsets.retain(|(other, _)| *other != chart);
+
+        if offset != 0.0 {
+            self.song_offsets.push((chart, offset));
+        }
+    }
+
+    /// Most frames a second to draw, `None` for no cap. Levels being played keep the frame cap
+    /// of the settings, their simulation runs in fixed steps whatever it is.
+    pub fn frames_per_second(&self, in_menu: bool) -> Option<f32> {
+        let cap = Some(self.frame_cap as f32).filter(|cap| *cap > 0.0);
+
+        match (cap, in_menu && self.battery_saver) {
+            (Some(cap), true) => Some(cap.min(MENU_FRAME_CAP)),
+            (None, true) => Some(MENU_FRAME_CAP),
+            (cap, false) => cap,
+        }
+    }
+
+    /// Flips fullscreen on F11 or Alt+Enter, saving the new state.
+    pub fn handle_fullscreen_toggle(&mut self) {
+        let alt = is_key_down(KeyCode::LeftAlt) || is_key_down(KeyCode::RightAlt);
+
+        if is_key_pressed(KeyCode::F11) || (alt && is_key_pressed(KeyCode::Enter)) {
+            self.fullscreen = !self.fullscreen;
+            set_fullscreen(self.fullscreen);
+            self.save();
+        }
+    }
+
+    /// Turns the streamer overlay on and off on [`STREAMER_KEY`], saving the new state.
+    pub fn handle_streamer_toggle(&mut self) {
+        if is_key_pressed(STREAMER_KEY) {
+            self.streamer_mode = !self.streamer_mode;
+            self.save();
+        }
+    }
+
+    /// Draws the settings widgets, returns true if anything changed.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        // taken out of self so the widgets below can borrow the rest of it, put back at the end
+        let mut locale = std::mem::take(&mut self.locale);
+
+        let language = self.language.clone();
+
+        egui::ComboBox::from_label(locale.get("settings-language"))
+            .selected_text(locale.get("language-name"))
+            .show_ui(ui, |ui| {
+                for (code, name) in languages() {
+                    ui.selectable_value(&mut self.language, code, name);
+                }
+            });
+
+        if language != self.language {
+            locale = Locale::load(&self.language);
+            changed = true;
+        }
+
+        ui.separator();
+
+        changed |= ui
+            .checkbox(&mut self.crt, locale.get("settings-crt"))
+            .changed();
+        changed |= ui
+            .add(egui::Slider::new(&mut self.bloom, 0.0..=2.0).text(locale.get("settings-bloom")))
+            .changed();
+
+        if ui
+            .checkbox(&mut self.fullscreen, locale.get("settings-fullscreen"))
+            .changed()
+        {
+            set_fullscreen(self.fullscreen);
+            changed = true;
+        }
+
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.ui_scale, 0.5..=3.0)
+                    .text(locale.get("settings-ui-scale")),
+            )
+            .changed();
+
+        ui.separator();
+
+        changed |= ui
+            .add(egui::Slider::new(&mut self.shake, 0.0..=1.0).text(locale.get("settings-shake")))
+            .changed();
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.flashes, 0.0..=1.0)
+                    .text(locale.get("settings-flashes")),
+            )
+            .changed();
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.background_dim, 0.0..=1.0)
+                    .text(locale.get("settings-background-dim")),
+            )
+            .changed();
+        changed |= ui
+            .add(
+                // never so faint the level can't be played
+                egui::Slider::new(&mut self.playfield_opacity, 0.2..=1.0)
+                    .text(locale.get("settings-playfield-opacity")),
+            )
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.death_warp, locale.get("settings-death-warp"))
+            .changed();
+        changed |= ui
+            .checkbox(
+                &mut self.high_contrast,
+                locale.get("settings-high-contrast"),
+            )
+            .changed();
+        changed |= ui
+            .checkbox(
+                &mut self.gpu_particles,
+                locale.get("settings-gpu-particles"),
+            )
+            .changed();
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.frame_cap, 0..=240)
+                    .text(locale.get("settings-frame-cap")),
+            )
+            .changed();
+        changed |= ui
+            .checkbox(
+                &mut self.battery_saver,
+                locale.get("settings-battery-saver"),
+            )
+            .changed();
+
+        let palette = self.palette;
+
+        egui::ComboBox::from_label(locale.get("settings-palette"))
+            .selected_text(palette.name())
+            .show_ui(ui, |ui| {
+                for option in Palette::ALL.iter() {
+                    ui.selectable_value(&mut self.palette, *option, option.name());
+                }
+            });
+
+        changed |= palette != self.palette;
+        changed |= ui
+            .checkbox(&mut self.direction_glyphs, locale.get("settings-glyphs"))
+            .changed();
+        changed |= ui
+            .checkbox(
+                &mut self.drift_indicator,
+                locale.get("settings-drift-indicator"),
+            )
+            .changed();
+        changed |= ui
+            .checkbox(
+                &mut self.streamer_mode,
+                locale.get("settings-streamer-mode"),
+            )
+            .changed();
+
+        ui.separator();
+
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.hitch_threshold, 0.05..=0.5)
+                    .text(locale.get("settings-hitch")),
+            )
+            .changed();
+        changed |= ui
+            .checkbox(
+                &mut self.practice_ramp,
+                locale.get("settings-practice-ramp"),
+            )
+            .changed();
+
+        let restart_key = self.restart_key;
+
+        egui::ComboBox::from_label(locale.get("settings-restart-key"))
+            .selected_text(key_name(restart_key))
+            .show_ui(ui, |ui| {
+                for (name, key) in BINDABLE_KEYS {
+                    ui.selectable_value(&mut self.restart_key, *key, *name);
+                }
+            });
+
+        changed |= restart_key != self.restart_key;
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.restart_hold, 0.0..=2.0)
+                    .text(locale.get("settings-restart-hold")),
+            )
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.clip_buffer, locale.get("settings-clip-buffer"))
+            .changed();
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.clip_length, 1.0..=30.0)
+                    .text(locale.get("settings-clip-length")),
+            )
+            .changed();
+
+        ui.separator();
+
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.music_volume, 0.0..=1.0)
+                    .text(locale.get("settings-music-volume")),
+            )
+            .changed();
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.sfx_volume, 0.0..=1.0)
+                    .text(locale.get("settings-sfx-volume")),
+            )
+            .changed();
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.kick_volume, 0.0..=1.0)
+                    .text(locale.get("settings-kick-volume")),
+            )
+            .changed();
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut self.death_volume, 0.0..=1.0)
+                    .text(locale.get("settings-death-volume")),
+            )
+            .changed();
+
+        let mut offset = self.audio_offset * 1000.0;
+
+        if ui
+            .add(
+                egui::Slider::new(&mut offset, -300.0..=300.0)
+                    .integer()
+                    .suffix("ms")
+                    .text(locale.get("settings-audio-offset")),
+            )
+            .changed()
+        {
+            self.audio_offset = offset / 1000.0;
+            changed = true;
+        }
+
+        ui.collapsing(locale.get("settings-hitsounds"), |ui| {
+            for (name, path) in self.hitsounds.fields() {
+                ui.horizontal(|ui| {
+                    ui.label(name);
+                    changed |= ui.text_edit_singleline(path).changed();
+                });
+            }
+        });
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label(locale.get("settings-sync-url"));
+            changed |= ui.text_edit_singleline(&mut self.sync_url).changed();
+        });
+
+        self.locale = locale;
+
+        changed
+    }
+}
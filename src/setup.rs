@@ -0,0 +1,327 @@
+//! The setup shown on the first launch, before there's a settings file: where the levels are, how
+//! loud the game is, how late the player hears it, the keys and whether to play the tutorial. It
+//! can be gone through again from the menu.
+
+use crate::asset_source;
+use crate::font;
+use crate::input::{key_name, BINDABLE_KEYS};
+use crate::settings::{Settings, DEFAULT_HITSOUND};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{crash, log_warn};
+use egui::*;
+use macroquad::audio::{play_sound, PlaySoundParams, Sound};
+use macroquad::prelude::*;
+use std::path::Path;
+
+/// Seconds between the ticks of the calibration.
+const TICK_INTERVAL: f64 = 0.6;
+
+/// Taps on the ticks the audio offset is worked out from.
+const CALIBRATION_TAPS: usize = 8;
+
+pub enum SetupAction {
+    /// The player is through, playing the tutorial next if they want to.
+    Done { tutorial: bool },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Songs,
+    Audio,
+    Calibration,
+    Controls,
+    Tutorial,
+}
+
+const STEPS: [Step; 5] = [
+    Step::Songs,
+    Step::Audio,
+    Step::Calibration,
+    Step::Controls,
+    Step::Tutorial,
+];
+
+/// Ticks played on the beat for the player to tap along to, how late the taps are being the
+/// audio offset.
+#[derive(Default)]
+struct Calibration {
+    /// `get_time` the first tick was played at, `None` unless it's running.
+    started: Option<f64>,
+    /// Ticks played so far.
+    ticks: u32,
+    /// Seconds each tap came after the tick it was closest to.
+    taps: Vec<f64>,
+}
+
+impl Calibration {
+    fn start(&mut self) {
+        *self = Self {
+            started: Some(get_time()),
+            ..Self::default()
+        };
+    }
+
+    /// Plays the ticks that are due and takes this frame's tap, returning the offset in seconds
+    /// once there have been enough taps.
+    fn update(&mut self, tick: Sound, volume: f32) -> Option<f32> {
+        let elapsed = get_time() - self.started?;
+
+        if elapsed >= self.ticks as f64 * TICK_INTERVAL {
+            play_sound(
+                tick,
+                PlaySoundParams {
+                    looped: false,
+                    volume,
+                },
+            );
+            self.ticks += 1;
+        }
+
+        if is_key_pressed(KeyCode::Space) {
+            let nearest = (elapsed / TICK_INTERVAL).round();
+
+            // taps before the first tick has been heard aren't on anything
+            if nearest >= 1.0 {
+                self.taps.push(elapsed - nearest * TICK_INTERVAL);
+            }
+        }
+
+        if self.taps.len() < CALIBRATION_TAPS {
+            return None;
+        }
+
+        self.started = None;
+
+        let mean = self.taps.iter().sum::<f64>() / self.taps.len() as f64;
+
+        // whole milliseconds, like the slider in the settings
+        Some((mean * 1000.0).round() as f32 / 1000.0)
+    }
+}
+
+pub struct Setup {
+    step: Step,
+    /// The songs folder the levels were last counted in, and how many there were.
+    counted: Option<(String, usize)>,
+    calibration: Calibration,
+    tick: Option<Sound>,
+    /// The ui scale the egui fonts were last built for.
+    ui_scale: Option<f32>,
+}
+
+impl Setup {
+    /// Whether `settings` have never been saved, so the player hasn't been through the setup.
+    pub fn needed(settings: &Settings) -> bool {
+        let profile = &settings.profile;
+
+        profile.read(&profile.settings_path()).is_none()
+    }
+
+    pub async fn new() -> Self {
+        Self {
+            step: Step::Songs,
+            counted: None,
+            calibration: Calibration::default(),
+            tick: asset_source::load_asset_sound(DEFAULT_HITSOUND).await.ok(),
+            ui_scale: None,
+        }
+    }
+
+    pub async fn update(&mut self, settings: &mut Settings) -> Option<SetupAction> {
+        if self
+            .counted
+            .as_ref()
+            .is_none_or(|(dir, _)| *dir != settings.songs_dir)
+        {
+            let levels = asset_source::levels(Path::new(&settings.songs_dir)).await;
+
+            self.counted = Some((settings.songs_dir.clone(), levels.len()));
+        }
+
+        if let Some(tick) = self.tick {
+            if let Some(offset) = self
+                .calibration
+                .update(tick, settings.sfx_volume * settings.kick_volume)
+            {
+                settings.audio_offset = offset;
+            }
+        }
+
+        let mut action = None;
+        let index = STEPS.iter().position(|step| *step == self.step).unwrap();
+        let mut step = index;
+        // taken out of settings so the widgets below can borrow the rest of them
+        let locale = std::mem::take(&mut settings.locale);
+
+        clear_background(BLACK);
+
+        set_default_camera();
+
+        egui_macroquad::ui(|ctx| {
+            if self.ui_scale != Some(settings.ui_scale) {
+                ctx.set_fonts(font::egui_fonts(settings.ui_scale));
+                self.ui_scale = Some(settings.ui_scale);
+            }
+
+            egui::Window::new(locale.get("setup-title"))
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(locale.format(
+                        "setup-step",
+                        &[("step", &(index + 1)), ("steps", &STEPS.len())],
+                    ));
+                    ui.separator();
+
+                    match self.step {
+                        Step::Songs => {
+                            ui.label(locale.get("setup-songs"));
+
+                            ui.horizontal(|ui| {
+                                ui.label(locale.get("setup-songs-dir"));
+                                ui.text_edit_singleline(&mut settings.songs_dir);
+                            });
+
+                            let found = self.counted.as_ref().map_or(0, |(_, count)| *count);
+
+                            ui.label(locale.format("setup-songs-found", &[("levels", &found)]));
+
+                            #[cfg(not(target_arch = "wasm32"))]
+                            ui.horizontal(|ui| {
+                                let dir = Path::new(&settings.songs_dir);
+
+                                if !dir.is_dir()
+                                    && ui.button(locale.get("setup-songs-create")).clicked()
+                                {
+                                    if let Err(err) = std::fs::create_dir_all(dir) {
+                                        log_warn!("failed to create {}: {}", dir.display(), err);
+                                    }
+                                }
+
+                                if dir.is_dir()
+                                    && ui.button(locale.get("setup-songs-open")).clicked()
+                                {
+                                    if let Err(err) = crash::open(dir) {
+                                        log_warn!("failed to open {}: {}", dir.display(), err);
+                                    }
+                                }
+
+                                if ui.button(locale.get("setup-songs-rescan")).clicked() {
+                                    self.counted = None;
+                                }
+                            });
+                        }
+                        Step::Audio => {
+                            ui.label(locale.get("setup-audio"));
+
+                            ui.add(
+                                egui::Slider::new(&mut settings.music_volume, 0.0..=1.0)
+                                    .text(locale.get("settings-music-volume")),
+                            );
+                            ui.add(
+                                egui::Slider::new(&mut settings.sfx_volume, 0.0..=1.0)
+                                    .text(locale.get("settings-sfx-volume")),
+                            );
+
+                            if let Some(tick) = self.tick {
+                                if ui.button(locale.get("setup-audio-test")).clicked() {
+                                    play_sound(
+                                        tick,
+                                        PlaySoundParams {
+                                            looped: false,
+                                            volume: settings.sfx_volume * settings.kick_volume,
+                                        },
+                                    );
+                                }
+                            }
+                        }
+                        Step::Calibration => {
+                            ui.label(locale.get("setup-calibration"));
+
+                            if self.calibration.started.is_some() {
+                                ui.label(locale.format(
+                                    "setup-calibration-taps",
+                                    &[
+                                        ("taps", &self.calibration.taps.len()),
+                                        ("needed", &CALIBRATION_TAPS),
+                                    ],
+                                ));
+                            } else if self.tick.is_some()
+                                && ui.button(locale.get("setup-calibration-start")).clicked()
+                            {
+                                self.calibration.start();
+                            }
+
+                            let mut offset = settings.audio_offset * 1000.0;
+
+                            if ui
+                                .add(
+                                    egui::Slider::new(&mut offset, -300.0..=300.0)
+                                        .integer()
+                                        .suffix("ms")
+                                        .text(locale.get("settings-audio-offset")),
+                                )
+                                .changed()
+                            {
+                                settings.audio_offset = offset / 1000.0;
+                            }
+                        }
+                        Step::Controls => {
+                            ui.label(locale.get("setup-controls"));
+
+                            egui::ComboBox::from_label(locale.get("settings-restart-key"))
+                                .selected_text(key_name(settings.restart_key))
+                                .show_ui(ui, |ui| {
+                                    for (name, key) in BINDABLE_KEYS {
+                                        ui.selectable_value(&mut settings.restart_key, *key, *name);
+                                    }
+                                });
+                        }
+                        Step::Tutorial => {
+                            ui.label(locale.get("setup-tutorial"));
+
+                            ui.horizontal(|ui| {
+                                if ui.button(locale.get("setup-tutorial-play")).clicked() {
+                                    action = Some(SetupAction::Done { tutorial: true });
+                                }
+
+                                if ui.button(locale.get("setup-tutorial-skip")).clicked() {
+                                    action = Some(SetupAction::Done { tutorial: false });
+                                }
+                            });
+                        }
+                    }
+
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        if index > 0 && ui.button(locale.get("setup-back")).clicked() {
+                            step = index - 1;
+                        }
+
+                        if index + 1 < STEPS.len() && ui.button(locale.get("setup-next")).clicked()
+                        {
+                            step = index + 1;
+                        }
+
+                        if ui.button(locale.get("setup-skip")).clicked() {
+                            action = Some(SetupAction::Done { tutorial: false });
+                        }
+                    });
+                });
+        });
+
+        egui_macroquad::draw();
+
+        settings.locale = locale;
+
+        if step != index {
+            self.step = STEPS[step];
+            // ticks shouldn't carry on over the other steps
+            self.calibration.started = None;
+        }
+
+        action
+    }
+}
@@ -1,10 +1,62 @@
-use crate::*;
+use crate::boss::{Pattern, Phase};
+use crate::camera::{CameraEvent, CameraMove};
+use crate::game::{Direction, Projectile, ProjectileType};
+use crate::generators::{self, Timing};
+use crate::integrity::Digest;
+use crate::json::Json;
+use crate::judgment::{ScoreWeights, TimingWindows};
+use crate::meter::{MeterRules, MAX_PERFECTS};
+use crate::momentum::MomentumRules;
+use crate::pickups::PickupKind;
+use crate::script::Script;
+use crate::uniforms::{UniformEvent, UniformValue};
 
 #[derive(Debug)]
 pub enum ParseError {
     UnrecognizedToken(String),
     UnexpectedToken(Token),
+    /// A projectile type or directive given arguments it doesn't take.
+    InvalidArgs(String, String),
     UnexpectedEof,
+    /// A json sheet that isn't json, or whose values aren't what they should be.
+    InvalidJson(String),
+    /// A `#stage` after projectiles, which were already read as a sheet with a single lane.
+    LateStage,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnrecognizedToken(token) => write!(f, "unrecognized token '{}'", token),
+            Self::UnexpectedToken(token) => write!(f, "unexpected token {:?}", token),
+            Self::InvalidArgs(token, message) => write!(f, "'{}' {}", token, message),
+            Self::UnexpectedEof => write!(f, "unexpected end of file"),
+            Self::InvalidJson(message) => write!(f, "invalid json: {}", message),
+            Self::LateStage => write!(f, "#stage has to come before the first projectile"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Bumped whenever [`Sheet::content_hash`] changes what it hashes, so old and new hashes of the
+/// same chart never match by accident. Things sheets can leave out are added as a [`HashPart`]
+/// instead, which keeps the hashes of sheets without them.
+pub const CHART_HASH_VERSION: u32 = 1;
+
+/// The parts of a sheet [`Sheet::content_hash`] only hashes when the sheet has them, so sheets
+/// from before a part was added keep their hashes and their scores. Each part is hashed after its
+/// tag, so two parts can never hash alike by lining up. New parts go at the end, with the next tag.
+#[derive(Clone, Copy)]
+enum HashPart {
+    Lanes = 1,
+    Jitter,
+    Drops,
+    Swing,
+    Meter,
+    Momentum,
+    Uniforms,
+    Boss,
 }
 
 pub type Result<T> = std::result::Result<T, ParseError>;
@@ -21,6 +73,31 @@ impl<T: Iterator<Item = Token>> TokenStream for T {}
 pub enum Token {
     Bpm,
     Offset,
+    Section,
+    Judge,
+    Health,
+    Camera,
+    Telegraph,
+    Prespawn,
+    Stage,
+    Boss,
+    Phase,
+    Spiral,
+    Wave,
+    /// The ends of a `#wave`, written `L->R`.
+    Sweep(Direction, Direction),
+    /// Which way a `#spiral` turns, `cw` being clockwise and `ccw` counterclockwise.
+    Turn(bool),
+    Swing,
+    Meter,
+    Momentum,
+    Uniform,
+    /// A number written with a `%`.
+    Percent(f32),
+    /// The lane of a projectile on stage sheets, written `@1` for the first.
+    Lane(usize),
+    /// A `key=value` pair.
+    Setting(String, String),
     TimeOffset(TimeOffset),
     Direction(Direction),
     Number(f32),
@@ -37,15 +114,60 @@ impl Token {
             return Ok(Self::Number(number));
         }
 
+        if let Some((key, value)) = source.split_once('=') {
+            return Ok(Self::Setting(key.to_string(), value.to_string()));
+        }
+
+        if let Some(lane) = source.strip_prefix('@') {
+            return match lane.parse::<usize>() {
+                Ok(lane) if lane > 0 => Ok(Self::Lane(lane - 1)),
+                _ => Err(ParseError::UnrecognizedToken(source.to_string())),
+            };
+        }
+
+        if let Some(percent) = source.strip_suffix('%') {
+            return match percent.parse::<f32>() {
+                Ok(percent) => Ok(Self::Percent(percent)),
+                Err(_) => Err(ParseError::UnrecognizedToken(source.to_string())),
+            };
+        }
+
+        if let Some((from, to)) = source.split_once("->") {
+            return match (Self::parse(from), Self::parse(to)) {
+                (Ok(Self::Direction(from)), Ok(Self::Direction(to))) => Ok(Self::Sweep(from, to)),
+                _ => Err(ParseError::UnrecognizedToken(source.to_string())),
+            };
+        }
+
         match source {
             "#bpm" => Ok(Self::Bpm),
             "#offset" => Ok(Self::Offset),
+            "#section" => Ok(Self::Section),
+            "#judge" => Ok(Self::Judge),
+            "#health" => Ok(Self::Health),
+            "#camera" => Ok(Self::Camera),
+            "#telegraph" => Ok(Self::Telegraph),
+            "#prespawn" => Ok(Self::Prespawn),
+            "#stage" => Ok(Self::Stage),
+            "#boss" => Ok(Self::Boss),
+            "#phase" => Ok(Self::Phase),
+            "#spiral" => Ok(Self::Spiral),
+            "#wave" => Ok(Self::Wave),
+            "#swing" => Ok(Self::Swing),
+            "#meter" => Ok(Self::Meter),
+            "#momentum" => Ok(Self::Momentum),
+            "#uniform" => Ok(Self::Uniform),
+            "cw" => Ok(Self::Turn(true)),
+            "ccw" => Ok(Self::Turn(false)),
             "U" => Ok(Self::Direction(Direction::Up)),
             "D" => Ok(Self::Direction(Direction::Down)),
             "L" => Ok(Self::Direction(Direction::Left)),
             "R" => Ok(Self::Direction(Direction::Right)),
-            "norm" => Ok(Self::Projectile(ProjectileType::Normal)),
-            _ => Err(ParseError::UnrecognizedToken(source.to_string())),
+            _ => match ProjectileType::parse(source) {
+                Some(Ok(ty)) => Ok(Self::Projectile(ty)),
+                Some(Err(message)) => Err(ParseError::InvalidArgs(source.to_string(), message)),
+                None => Err(ParseError::UnrecognizedToken(source.to_string())),
+            },
         }
     }
 }
@@ -71,7 +193,7 @@ pub fn parse_tokes(source: &str) -> Result<Vec<Token>> {
     Ok(tokens)
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TimeOffset {
     pub fourths: u32,
     pub beats: u32,
@@ -118,6 +240,83 @@ impl TimeOffset {
 
         self.fourths as f32 * beat / 4.0 + self.beats as f32 * beat + self.bars as f32 * beat * 4.0
     }
+
+    /// The offset `time` seconds is closest to, to a fourth of a beat.
+    pub fn from_time(time: f32, bpm: f32) -> Self {
+        let fourths = (time * bpm / 15.0).round().max(0.0) as u32;
+
+        Self {
+            fourths: fourths % 4,
+            beats: fourths / 4 % 4,
+            bars: fourths / 16,
+        }
+    }
+
+    /// The offset `beats` beats is closest to, to a fourth of a beat.
+    pub fn from_beats(beats: f32) -> Self {
+        let fourths = (beats * 4.0).round().max(0.0) as u32;
+
+        Self {
+            fourths: fourths % 4,
+            beats: fourths / 4 % 4,
+            bars: fourths / 16,
+        }
+    }
+
+    /// Beats the offset is long.
+    pub fn beats(&self) -> f32 {
+        self.fourths as f32 / 4.0 + self.beats as f32 + self.bars as f32 * 4.0
+    }
+}
+
+impl std::fmt::Display for TimeOffset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{};{}|{}", self.fourths, self.beats, self.bars)
+    }
+}
+
+/// The most seconds `jitter=` can move a projectile by, a few milliseconds being the point.
+pub const MAX_JITTER: f32 = 0.05;
+
+/// Lanes of stage sheets, each with its own heart and shield.
+pub const STAGE_LANES: usize = 2;
+
+/// The sheet file of the level at `level`, `<difficulty>.sht` if a difficulty is given and
+/// `sheet.sht` otherwise.
+pub fn sheet_path(level: &std::path::Path, difficulty: Option<&str>) -> std::path::PathBuf {
+    level.join(format!("{}.sht", difficulty.unwrap_or("sheet")))
+}
+
+/// The files the sheet of the level at `level` is looked for in, in order: its
+/// [`sheet_path`], then the same as `.json`.
+pub fn sheet_paths(level: &std::path::Path, difficulty: Option<&str>) -> [std::path::PathBuf; 2] {
+    let path = sheet_path(level, difficulty);
+    let json = path.with_extension("json");
+
+    [path, json]
+}
+
+/// The first of [`sheet_paths`] that exists, the `.sht` one if neither does.
+pub fn find_sheet(level: &std::path::Path, difficulty: Option<&str>) -> std::path::PathBuf {
+    let [path, json] = sheet_paths(level, difficulty);
+
+    if !path.exists() && json.exists() {
+        json
+    } else {
+        path
+    }
+}
+
+/// The numbers [`Sheet::stats`] gathers about a chart, kept so they don't have to be worked out
+/// again every frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChartStats {
+    pub notes: usize,
+    pub duration: f32,
+    pub max_nps: usize,
+    /// Projectiles coming from each side, in the order of [`Direction::ALL`].
+    pub directions: [usize; 4],
+    pub longest_gap: f32,
 }
 
 #[derive(Default)]
@@ -125,65 +324,1721 @@ pub struct Sheet {
     pub bpm: f32,
     pub start_offset: f32,
     pub projectiles: Vec<Projectile>,
+    /// Start times of the sections marked with `#section`, in order.
+    pub sections: Vec<f32>,
+    /// Timing windows, the engine's unless overridden with `#judge`.
+    pub windows: TimingWindows,
+    /// Points per judgment, the engine's unless overridden with `#judge`.
+    pub weights: ScoreWeights,
+    /// Seconds shield input locks up for after a press with nothing to block, set with
+    /// `#judge stun=...`, no stun otherwise.
+    pub stun: Option<f32>,
+    /// Hearts the player has, set with `#health`, 1 otherwise.
+    pub health: Option<f32>,
+    /// Camera moves from `#camera` lines, in order.
+    pub camera_events: Vec<CameraEvent>,
+    /// Seconds before a projectile comes on screen its telegraph shows, set with `#telegraph`,
+    /// a beat otherwise.
+    pub telegraph: Option<f32>,
+    /// Seconds before arrival projectiles become active and their `on_spawn` hooks run, set with
+    /// `#prespawn`, as they come on screen otherwise. Never later than that either way.
+    pub prespawn: Option<f32>,
+    /// Whether the sheet is played on [`STAGE_LANES`] lanes, set with `#stage` before any
+    /// projectiles, phases or generators since their lanes are read by it.
+    pub stage: bool,
+    /// Health of the boss fought, set with `#boss`, no boss otherwise.
+    pub boss: Option<f32>,
+    /// Pattern phases from `#phase` lines, in order, their projectiles already in
+    /// [`Sheet::projectiles`].
+    pub phases: Vec<Phase>,
+    /// How far into each beat its off-beat lands in percent, set with `#swing`, straight at
+    /// half otherwise.
+    pub swing: Option<f32>,
+    /// How the bomb meter fills and what clearing is worth, set with `#meter`, no meter
+    /// otherwise.
+    pub meter: Option<MeterRules>,
+    /// How projectiles speed up, set with `#momentum`, a steady ramp otherwise.
+    pub momentum: MomentumRules,
+    /// Moves of the level shader's uniforms from `#uniform` lines, in order.
+    pub uniform_events: Vec<UniformEvent>,
+    /// [`Sheet::content_hash`] of the sheet as parsed.
+    pub hash: u64,
 }
 
 impl Sheet {
     pub fn parse(source: &str) -> Result<Self> {
         let mut sheet = Self::default();
 
-        let mut tokens = parse_tokes(source)?.into_iter();
+        let mut tokens = parse_tokes(source)?.into_iter().peekable();
 
         sheet.parse_bpm(&mut tokens)?;
         sheet.parse_offset(&mut tokens)?;
 
-        while tokens.len() > 0 {
-            let projectile = Projectile::parse(&mut tokens, sheet.bpm, sheet.start_offset)?;
+        while let Some(token) = tokens.peek() {
+            if let Token::Section = token {
+                tokens.next();
+                sheet.parse_section(&mut tokens)?;
+            } else if let Token::Judge = token {
+                tokens.next();
+                sheet.parse_judge(&mut tokens)?;
+            } else if let Token::Health = token {
+                tokens.next();
+                sheet.parse_health(&mut tokens)?;
+            } else if let Token::Camera = token {
+                tokens.next();
+                sheet.parse_camera(&mut tokens)?;
+            } else if let Token::Telegraph = token {
+                tokens.next();
+                sheet.parse_telegraph(&mut tokens)?;
+            } else if let Token::Prespawn = token {
+                tokens.next();
+                sheet.parse_prespawn(&mut tokens)?;
+            } else if let Token::Stage = token {
+                if !sheet.projectiles.is_empty() || !sheet.phases.is_empty() {
+                    return Err(ParseError::LateStage);
+                }
+
+                tokens.next();
+                sheet.stage = true;
+            } else if let Token::Boss = token {
+                tokens.next();
+                sheet.parse_boss(&mut tokens)?;
+            } else if let Token::Phase = token {
+                tokens.next();
+                sheet.parse_phase(&mut tokens)?;
+            } else if let Token::Swing = token {
+                tokens.next();
+                sheet.parse_swing(&mut tokens)?;
+            } else if let Token::Meter = token {
+                tokens.next();
+                sheet.parse_meter(&mut tokens)?;
+            } else if let Token::Momentum = token {
+                tokens.next();
+                sheet.parse_momentum(&mut tokens)?;
+            } else if let Token::Uniform = token {
+                tokens.next();
+                sheet.parse_uniform(&mut tokens)?;
+            } else if let Token::Spiral = token {
+                tokens.next();
+                sheet.parse_spiral(&mut tokens)?;
+            } else if let Token::Wave = token {
+                tokens.next();
+                sheet.parse_wave(&mut tokens)?;
+            } else {
+                let mut projectile =
+                    Projectile::parse(&mut tokens, sheet.bpm, sheet.start_offset, sheet.lanes())?;
+                projectile.jitter = parse_jitter(&mut tokens)?;
+                projectile.drop = parse_drop(&mut tokens)?;
+
+                sheet.projectiles.push(projectile);
+            }
+        }
+
+        sheet.phases.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        // fired in order of the phases, so ties come out the same however they're written
+        for phase in &sheet.phases {
+            let projectiles = phase.pattern.projectiles(phase.time, sheet.bpm);
+
+            sheet.projectiles.extend(projectiles);
+        }
 
-            sheet.projectiles.push(projectile);
+        if sheet.swing.is_some() {
+            sheet.apply_swing();
         }
 
+        // the game walks the projectiles in order of arrival
+        sheet
+            .projectiles
+            .sort_by(|a, b| a.arrival_time.total_cmp(&b.arrival_time));
+        sheet.sections.sort_by(f32::total_cmp);
+        sheet
+            .camera_events
+            .sort_by(|a, b| a.time.total_cmp(&b.time));
+        sheet
+            .uniform_events
+            .sort_by(|a, b| a.time.total_cmp(&b.time));
+        sheet.hash = sheet.content_hash();
+
         Ok(sheet)
     }
 
-    pub fn parse_bpm(&mut self, tokens: &mut impl TokenStream) -> Result<()> {
-        let bpm = tokens.next_token()?;
+    /// The sheet written back out as source, parsing to the same sheet with times rounded to
+    /// the nearest fourth of a beat. Comments and formatting aren't kept.
+    pub fn to_source(&self) -> String {
+        use std::fmt::Write;
 
-        if let Token::Bpm = bpm {
-            let bpm = tokens.next_token()?;
+        let at =
+            |time: f32| TimeOffset::from_time(self.unswung(time) - self.start_offset, self.bpm);
+        let duration = |time: f32| TimeOffset::from_time(time, self.bpm);
 
-            if let Token::Number(bpm) = bpm {
-                self.bpm = bpm;
+        // numbers are written with `{:?}` so whole ones keep their `.0`, `120` is a time offset
+        let mut source = String::new();
+        let _ = writeln!(source, "#bpm {:?}", self.bpm);
+        let _ = writeln!(source, "#offset {:?} 0;0|0", self.start_offset);
 
-                Ok(())
-            } else {
-                Err(ParseError::UnexpectedToken(bpm))
+        if self.stage {
+            let _ = writeln!(source, "#stage");
+        }
+
+        let (windows, weights) = (TimingWindows::default(), ScoreWeights::default());
+        let mut judge = Vec::new();
+
+        if self.windows.perfect != windows.perfect {
+            judge.push(format!("perfect={}s", self.windows.perfect));
+        }
+
+        if self.windows.great != windows.great {
+            judge.push(format!("great={}s", self.windows.great));
+        }
+
+        if self.weights.perfect != weights.perfect {
+            judge.push(format!("perfect_score={}", self.weights.perfect));
+        }
+
+        if self.weights.great != weights.great {
+            judge.push(format!("great_score={}", self.weights.great));
+        }
+
+        if self.weights.late != weights.late {
+            judge.push(format!("late_score={}", self.weights.late));
+        }
+
+        if let Some(stun) = self.stun {
+            judge.push(format!("stun={}s", stun));
+        }
+
+        if !judge.is_empty() {
+            let _ = writeln!(source, "#judge {}", judge.join(" "));
+        }
+
+        if let Some(health) = self.health {
+            let _ = writeln!(source, "#health {:?}", health);
+        }
+
+        if let Some(swing) = self.swing {
+            let _ = writeln!(source, "#swing {}%", swing);
+        }
+
+        if let Some(meter) = &self.meter {
+            let _ = writeln!(
+                source,
+                "#meter perfects={} score={}%",
+                meter.perfects, meter.score
+            );
+        }
+
+        if self.momentum != MomentumRules::default() {
+            let momentum = &self.momentum;
+            let _ = write!(
+                source,
+                "#momentum ramp={} perfect={} miss={} min={}",
+                momentum.ramp, momentum.perfect, momentum.miss, momentum.min
+            );
+
+            if momentum.max.is_finite() {
+                let _ = write!(source, " max={}", momentum.max);
+            }
+
+            let _ = writeln!(source);
+        }
+
+        if let Some(telegraph) = self.telegraph {
+            let _ = writeln!(source, "#telegraph {}", duration(telegraph));
+        }
+
+        if let Some(prespawn) = self.prespawn {
+            let _ = writeln!(source, "#prespawn {}", duration(prespawn));
+        }
+
+        for section in &self.sections {
+            let _ = writeln!(source, "#section {}", at(*section));
+        }
+
+        for event in &self.camera_events {
+            let movement = &event.movement;
+            let _ = write!(source, "#camera {}", at(event.time));
+
+            let pairs = [
+                ("zoom", movement.zoom),
+                ("rotation", movement.rotation),
+                ("x", movement.x),
+                ("y", movement.y),
+            ];
+
+            for (key, value) in pairs {
+                if let Some(value) = value {
+                    let _ = write!(source, " {}={}", key, value);
+                }
+            }
+
+            if let Some(over) = &movement.over {
+                let _ = write!(source, " over={}", over);
+            }
+
+            let _ = writeln!(source);
+        }
+
+        for event in &self.uniform_events {
+            let _ = write!(source, "#uniform {}", at(event.time));
+
+            for (name, value) in &event.values {
+                let _ = write!(source, " {}={}", name, value);
+            }
+
+            if let Some(over) = &event.over {
+                let _ = write!(source, " over={}", over);
+            }
+
+            let _ = writeln!(source);
+        }
+
+        if let Some(boss) = self.boss {
+            let _ = writeln!(source, "#boss {:?}", boss);
+        }
+
+        for phase in &self.phases {
+            let _ = writeln!(
+                source,
+                "#phase {} {}",
+                at(phase.time),
+                phase.pattern.to_source(self.lanes())
+            );
+        }
+
+        if !self.projectiles.is_empty() {
+            let _ = writeln!(source);
+        }
+
+        // the phases fire theirs again when parsed
+        for projectile in self
+            .projectiles
+            .iter()
+            .filter(|projectile| !projectile.generated)
+        {
+            let direction = direction_name(&projectile.direction);
+
+            let _ = write!(source, "{} ", projectile.ty.name);
+
+            if self.stage {
+                let _ = write!(source, "@{} ", projectile.lane + 1);
+            }
+
+            let _ = write!(source, "{} {}", direction, at(projectile.arrival_time));
+
+            if projectile.jitter > 0.0 {
+                let _ = write!(source, " jitter={}s", projectile.jitter);
+            }
+
+            if let Some(kind) = projectile.drop {
+                let _ = write!(source, " drop={}", kind.name());
             }
+
+            let _ = writeln!(source);
+        }
+
+        source
+    }
+
+    /// Parses `source` as the sheet file at `path`, json if it ends in `.json` and the token
+    /// grammar otherwise.
+    pub fn parse_file(path: &std::path::Path, source: &str) -> Result<Self> {
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            Self::from_json(source)
         } else {
-            Err(ParseError::UnexpectedToken(bpm))
+            Self::parse(source)
         }
     }
 
-    pub fn parse_offset(&mut self, tokens: &mut impl TokenStream) -> Result<()> {
-        let offset = tokens.next_token()?;
+    /// The sheet as json, for tools in other languages that would rather not deal with the token
+    /// grammar. Reads back with [`Sheet::from_json`] the way [`Sheet::to_source`] does.
+    ///
+    /// ```json
+    /// {
+    ///   "bpm": 120,
+    ///   "offset": 0.5,
+    ///   "sections": [0, 16],
+    ///   "camera": [
+    ///     {"beat": 16, "zoom": 1.5, "over": 2}
+    ///   ],
+    ///   "projectiles": [
+    ///     {"type": "norm", "direction": "U", "beat": 4},
+    ///     {"type": "norm", "direction": "L", "beat": 4.5, "drop": "slow"}
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// Times are in beats after the offset and lengths in beats, both to a fourth of a beat
+    /// like in sheet files. The judgment settings are in seconds and points, lanes count from 1
+    /// like `@1`, and directions, projectile types, drops and phase patterns are written as
+    /// they are in sheets. Whatever's at its default is left out.
+    ///
+    /// The other keys are `stage`, `judge` (`perfect`, `great`, `stun`, `perfect_score`,
+    /// `great_score` and `late_score`), `health`, `swing`, `meter` (`perfects` and `score`),
+    /// `momentum` (`ramp`, `perfect`, `miss`, `min` and `max`), `telegraph`, `prespawn`, `boss`,
+    /// `uniforms` (each a `beat`, `values` by name and `over`), `phases` (each a `beat` and
+    /// `pattern`) and, on projectiles, `lane`, `jitter` and `drop`.
+    pub fn to_json(&self) -> String {
+        let at = |time: f32| {
+            let offset = TimeOffset::from_time(self.unswung(time) - self.start_offset, self.bpm);
 
-        if let Token::Offset = offset {
-            let offset = tokens.next_token()?;
+            Json::Number(offset.beats())
+        };
+        let duration = |time: f32| Json::Number(TimeOffset::from_time(time, self.bpm).beats());
+        let number = Json::Number;
+
+        let mut sheet = vec![
+            ("bpm", number(self.bpm)),
+            ("offset", number(self.start_offset)),
+        ];
 
-            if let Token::Number(offset) = offset {
-                let time_offset = tokens.next_token()?;
+        if self.stage {
+            sheet.push(("stage", Json::Bool(true)));
+        }
 
-                if let Token::TimeOffset(time_offset) = time_offset {
-                    self.start_offset = offset + time_offset.time(self.bpm);
+        let (windows, weights) = (TimingWindows::default(), ScoreWeights::default());
+        let mut judge = Vec::new();
 
-                    Ok(())
-                } else {
-                    Err(ParseError::UnexpectedToken(time_offset))
+        if self.windows.perfect != windows.perfect {
+            judge.push(("perfect", number(self.windows.perfect)));
+        }
+
+        if self.windows.great != windows.great {
+            judge.push(("great", number(self.windows.great)));
+        }
+
+        if self.weights.perfect != weights.perfect {
+            judge.push(("perfect_score", number(self.weights.perfect as f32)));
+        }
+
+        if self.weights.great != weights.great {
+            judge.push(("great_score", number(self.weights.great as f32)));
+        }
+
+        if self.weights.late != weights.late {
+            judge.push(("late_score", number(self.weights.late as f32)));
+        }
+
+        if let Some(stun) = self.stun {
+            judge.push(("stun", number(stun)));
+        }
+
+        if !judge.is_empty() {
+            sheet.push(("judge", Json::object(judge)));
+        }
+
+        if let Some(health) = self.health {
+            sheet.push(("health", number(health)));
+        }
+
+        if let Some(swing) = self.swing {
+            sheet.push(("swing", number(swing)));
+        }
+
+        if let Some(meter) = &self.meter {
+            sheet.push((
+                "meter",
+                Json::object([
+                    ("perfects", number(meter.perfects as f32)),
+                    ("score", number(meter.score)),
+                ]),
+            ));
+        }
+
+        if self.momentum != MomentumRules::default() {
+            let momentum = &self.momentum;
+            let mut rules = vec![
+                ("ramp", number(momentum.ramp)),
+                ("perfect", number(momentum.perfect)),
+                ("miss", number(momentum.miss)),
+                ("min", number(momentum.min)),
+            ];
+
+            if momentum.max.is_finite() {
+                rules.push(("max", number(momentum.max)));
+            }
+
+            sheet.push(("momentum", Json::object(rules)));
+        }
+
+        if let Some(telegraph) = self.telegraph {
+            sheet.push(("telegraph", duration(telegraph)));
+        }
+
+        if let Some(prespawn) = self.prespawn {
+            sheet.push(("prespawn", duration(prespawn)));
+        }
+
+        if !self.sections.is_empty() {
+            let sections = self.sections.iter().map(|section| at(*section)).collect();
+
+            sheet.push(("sections", Json::Array(sections)));
+        }
+
+        if !self.camera_events.is_empty() {
+            let events = self.camera_events.iter().map(|event| {
+                let movement = &event.movement;
+                let mut pairs = vec![("beat", at(event.time))];
+
+                let values = [
+                    ("zoom", movement.zoom),
+                    ("rotation", movement.rotation),
+                    ("x", movement.x),
+                    ("y", movement.y),
+                ];
+
+                for (key, value) in values {
+                    if let Some(value) = value {
+                        pairs.push((key, number(value)));
+                    }
                 }
-            } else {
-                Err(ParseError::UnexpectedToken(offset))
+
+                if let Some(over) = &movement.over {
+                    pairs.push(("over", number(over.beats())));
+                }
+
+                Json::object(pairs)
+            });
+
+            sheet.push(("camera", Json::Array(events.collect())));
+        }
+
+        if !self.uniform_events.is_empty() {
+            let events = self.uniform_events.iter().map(|event| {
+                // one component is a number, more of them a list
+                let values = event.values.iter().map(|(name, value)| {
+                    let value = match value.components()[..] {
+                        [x] => number(x),
+                        ref components => {
+                            Json::Array(components.iter().copied().map(number).collect())
+                        }
+                    };
+
+                    (name.as_str(), value)
+                });
+
+                let mut pairs = vec![("beat", at(event.time)), ("values", Json::object(values))];
+
+                if let Some(over) = &event.over {
+                    pairs.push(("over", number(over.beats())));
+                }
+
+                Json::object(pairs)
+            });
+
+            sheet.push(("uniforms", Json::Array(events.collect())));
+        }
+
+        if let Some(boss) = self.boss {
+            sheet.push(("boss", number(boss)));
+        }
+
+        if !self.phases.is_empty() {
+            let phases = self.phases.iter().map(|phase| {
+                Json::object([
+                    ("beat", at(phase.time)),
+                    (
+                        "pattern",
+                        Json::String(phase.pattern.to_source(self.lanes())),
+                    ),
+                ])
+            });
+
+            sheet.push(("phases", Json::Array(phases.collect())));
+        }
+
+        // the phases fire theirs again when read
+        let projectiles = self
+            .projectiles
+            .iter()
+            .filter(|projectile| !projectile.generated)
+            .map(|projectile| {
+                let mut pairs = vec![
+                    ("type", Json::String(projectile.ty.name.clone())),
+                    (
+                        "direction",
+                        Json::String(direction_name(&projectile.direction).to_string()),
+                    ),
+                    ("beat", at(projectile.arrival_time)),
+                ];
+
+                if self.stage {
+                    pairs.push(("lane", number((projectile.lane + 1) as f32)));
+                }
+
+                if projectile.jitter > 0.0 {
+                    pairs.push(("jitter", number(projectile.jitter)));
+                }
+
+                if let Some(kind) = projectile.drop {
+                    pairs.push(("drop", Json::String(kind.name().to_string())));
+                }
+
+                Json::object(pairs)
+            });
+
+        sheet.push(("projectiles", Json::Array(projectiles.collect())));
+
+        format!("{}\n", Json::object(sheet))
+    }
+
+    /// Parses a sheet written as json like [`Sheet::to_json`] writes it. `bpm` is the only key
+    /// that has to be there, the rest are left at their defaults if they're missing.
+    pub fn from_json(source: &str) -> Result<Self> {
+        let json = Json::parse(source).map_err(|err| ParseError::InvalidJson(err.to_string()))?;
+
+        Self::parse(&json_to_source(&json)?)
+    }
+
+    /// Hash of what the sheet plays like, the same however it's formatted or commented. Scores
+    /// and replays are kept under it, so they follow a chart but not edits to it.
+    pub fn content_hash(&self) -> u64 {
+        let mut digest = Digest::new();
+
+        digest.integer(CHART_HASH_VERSION);
+        digest.number(self.bpm);
+        digest.number(self.start_offset);
+
+        digest.integer(self.projectiles.len() as u32);
+
+        for projectile in &self.projectiles {
+            digest.number(projectile.arrival_time);
+            digest.integer(projectile.direction.clone() as u32);
+            projectile.ty.behavior.hash(&mut digest);
+        }
+
+        digest.integer(self.sections.len() as u32);
+
+        for section in &self.sections {
+            digest.number(*section);
+        }
+
+        digest.number(self.windows.perfect);
+        digest.number(self.windows.great);
+        digest.integer(self.weights.perfect);
+        digest.integer(self.weights.great);
+        digest.integer(self.weights.late);
+        digest.optional(self.stun);
+        digest.optional(self.health);
+
+        digest.integer(self.camera_events.len() as u32);
+
+        for event in &self.camera_events {
+            let movement = &event.movement;
+
+            digest.number(event.time);
+            digest.optional(movement.zoom);
+            digest.optional(movement.rotation);
+            digest.optional(movement.x);
+            digest.optional(movement.y);
+            digest.optional(movement.over.as_ref().map(|over| over.time(self.bpm)));
+        }
+
+        digest.optional(self.telegraph);
+        digest.optional(self.prespawn);
+
+        // the rest only when the sheet has it, see `HashPart`
+        if self.stage {
+            digest.integer(HashPart::Lanes as u32);
+
+            for projectile in &self.projectiles {
+                digest.integer(projectile.lane as u32);
+            }
+        }
+
+        if self
+            .projectiles
+            .iter()
+            .any(|projectile| projectile.jitter > 0.0)
+        {
+            digest.integer(HashPart::Jitter as u32);
+
+            for projectile in &self.projectiles {
+                digest.number(projectile.jitter);
+            }
+        }
+
+        if self
+            .projectiles
+            .iter()
+            .any(|projectile| projectile.drop.is_some())
+        {
+            digest.integer(HashPart::Drops as u32);
+
+            for projectile in &self.projectiles {
+                digest.integer(projectile.drop.map_or(0, |kind| kind as u32 + 1));
+            }
+        }
+
+        // the swung times are hashed already, this only keeps a swing of exactly half apart
+        if let Some(swing) = self.swing {
+            digest.integer(HashPart::Swing as u32);
+            digest.number(swing);
+        }
+
+        if let Some(meter) = &self.meter {
+            digest.integer(HashPart::Meter as u32);
+            digest.integer(meter.perfects);
+            digest.number(meter.score);
+        }
+
+        if self.momentum != MomentumRules::default() {
+            let momentum = &self.momentum;
+
+            digest.integer(HashPart::Momentum as u32);
+
+            for value in [
+                momentum.ramp,
+                momentum.perfect,
+                momentum.miss,
+                momentum.min,
+                momentum.max,
+            ] {
+                digest.number(value);
+            }
+        }
+
+        if !self.uniform_events.is_empty() {
+            digest.integer(HashPart::Uniforms as u32);
+            digest.integer(self.uniform_events.len() as u32);
+
+            for event in &self.uniform_events {
+                digest.number(event.time);
+                digest.integer(event.values.len() as u32);
+
+                for (name, value) in &event.values {
+                    digest.bytes(name.as_bytes());
+
+                    for component in value.components() {
+                        digest.number(component);
+                    }
+                }
+
+                digest.optional(event.over.as_ref().map(|over| over.time(self.bpm)));
+            }
+        }
+
+        // the projectiles of phases are hashed with the rest, what's left is what's drawn
+        if self.boss.is_some() || !self.phases.is_empty() {
+            digest.integer(HashPart::Boss as u32);
+            digest.optional(self.boss);
+            digest.integer(self.phases.len() as u32);
+
+            for phase in &self.phases {
+                digest.number(phase.time);
+                digest.integer(phase.pattern.kind as u32);
+                digest.integer(phase.pattern.side.clone() as u32);
             }
-        } else {
-            Err(ParseError::UnexpectedToken(offset))
         }
+
+        digest.finish()
     }
+
+    /// Hash of what the level plays like with `script`, its `script.txt`. Replays are kept under
+    /// it since scripts can spawn projectiles and slow the clock. Levels without a script keep
+    /// [`Sheet::hash`].
+    pub fn level_hash(&self, script: &Script) -> u64 {
+        if script.hooks.is_empty() {
+            return self.hash;
+        }
+
+        let mut digest = Digest::new();
+
+        digest.bytes(&self.hash.to_le_bytes());
+        script.hash(&mut digest, self.bpm);
+
+        digest.finish()
+    }
+
+    /// `time` pushed onto the sheet's swing, the part of each beat before the off-beat stretched
+    /// to fill the swing's share of it and the rest squeezed into what's left.
+    pub fn swung(&self, time: f32) -> f32 {
+        self.map_beats(time, |fraction, swing| {
+            if fraction < 0.5 {
+                fraction * 2.0 * swing
+            } else {
+                swing + (fraction - 0.5) * 2.0 * (1.0 - swing)
+            }
+        })
+    }
+
+    /// `time` taken back off the sheet's swing, undoing [`Sheet::swung`].
+    pub fn unswung(&self, time: f32) -> f32 {
+        self.map_beats(time, |fraction, swing| {
+            if fraction < swing {
+                fraction / swing * 0.5
+            } else {
+                0.5 + (fraction - swing) / (1.0 - swing) * 0.5
+            }
+        })
+    }
+
+    /// `time` with how far it is into its beat moved by `map`, given the sheet's swing.
+    fn map_beats(&self, time: f32, map: impl Fn(f32, f32) -> f32) -> f32 {
+        let swing = match self.swing {
+            Some(swing) => swing / 100.0,
+            None => return time,
+        };
+
+        let beat = 60.0 / self.bpm;
+        let beats = (time - self.start_offset) / beat;
+        let whole = beats.floor();
+
+        self.start_offset + (whole + map(beats - whole, swing)) * beat
+    }
+
+    /// Moves every time in the sheet onto its swing.
+    fn apply_swing(&mut self) {
+        // taken out while moved since moving needs the rest of the sheet
+        let mut projectiles = std::mem::take(&mut self.projectiles);
+        let mut sections = std::mem::take(&mut self.sections);
+        let mut camera_events = std::mem::take(&mut self.camera_events);
+        let mut uniform_events = std::mem::take(&mut self.uniform_events);
+        let mut phases = std::mem::take(&mut self.phases);
+
+        for projectile in &mut projectiles {
+            projectile.arrival_time = self.swung(projectile.arrival_time);
+        }
+
+        for section in &mut sections {
+            *section = self.swung(*section);
+        }
+
+        for event in &mut camera_events {
+            event.time = self.swung(event.time);
+        }
+
+        for event in &mut uniform_events {
+            event.time = self.swung(event.time);
+        }
+
+        for phase in &mut phases {
+            phase.time = self.swung(phase.time);
+        }
+
+        self.projectiles = projectiles;
+        self.sections = sections;
+        self.camera_events = camera_events;
+        self.uniform_events = uniform_events;
+        self.phases = phases;
+    }
+
+    /// Lanes the sheet is played on, each with a heart and a shield.
+    pub fn lanes(&self) -> usize {
+        if self.stage {
+            STAGE_LANES
+        } else {
+            1
+        }
+    }
+
+    /// Seconds before a projectile comes on screen its telegraph shows, 0 if disabled.
+    pub fn telegraph_time(&self) -> f32 {
+        self.telegraph.unwrap_or(60.0 / self.bpm)
+    }
+
+    /// Time a bar after the last projectile arrives.
+    pub fn end_time(&self) -> f32 {
+        let last = self
+            .projectiles
+            .last()
+            .map_or(self.start_offset, |projectile| projectile.arrival_time);
+
+        last + 240.0 / self.bpm
+    }
+
+    /// Projectiles the sheet sends, the generated ones included.
+    pub fn note_count(&self) -> usize {
+        self.projectiles.len()
+    }
+
+    /// Seconds from the first projectile arriving to the last, 0 with fewer than two.
+    pub fn duration(&self) -> f32 {
+        match (self.projectiles.first(), self.projectiles.last()) {
+            (Some(first), Some(last)) => last.arrival_time - first.arrival_time,
+            _ => 0.0,
+        }
+    }
+
+    /// Most projectiles arriving within any one second of the sheet.
+    pub fn max_nps(&self) -> usize {
+        let projectiles = &self.projectiles;
+        let mut start = 0;
+        let mut most = 0;
+
+        for (end, projectile) in projectiles.iter().enumerate() {
+            while projectiles[start].arrival_time <= projectile.arrival_time - 1.0 {
+                start += 1;
+            }
+
+            most = most.max(end + 1 - start);
+        }
+
+        most
+    }
+
+    /// Projectiles coming from each side, in the order of [`Direction::ALL`].
+    pub fn direction_counts(&self) -> [usize; 4] {
+        let mut counts = [0; 4];
+
+        for projectile in &self.projectiles {
+            let side = Direction::ALL
+                .iter()
+                .position(|direction| *direction == projectile.direction)
+                .unwrap();
+
+            counts[side] += 1;
+        }
+
+        counts
+    }
+
+    /// Longest seconds between two projectiles arriving one after the other, 0 with fewer than
+    /// two.
+    pub fn longest_gap(&self) -> f32 {
+        self.projectiles
+            .windows(2)
+            .map(|pair| pair[1].arrival_time - pair[0].arrival_time)
+            .fold(0.0, f32::max)
+    }
+
+    pub fn stats(&self) -> ChartStats {
+        ChartStats {
+            notes: self.note_count(),
+            duration: self.duration(),
+            max_nps: self.max_nps(),
+            directions: self.direction_counts(),
+            longest_gap: self.longest_gap(),
+        }
+    }
+
+    /// The latest checkpoint at or before `time`, the starts of sections if there are any and
+    /// every `bars` bars otherwise.
+    pub fn checkpoint_at(&self, time: f32, bars: u32) -> f32 {
+        if !self.sections.is_empty() {
+            return self.section_at(time).0;
+        }
+
+        let bar = ((time - self.start_offset) / (240.0 / self.bpm)).max(0.0) as u32;
+
+        self.bar_time(bar - bar % bars)
+    }
+
+    /// Start and end of the section playing at `time`, the whole sheet without sections.
+    pub fn section_at(&self, time: f32) -> (f32, f32) {
+        let index = self.sections.partition_point(|start| *start <= time);
+
+        let start = index
+            .checked_sub(1)
+            .map_or(self.start_offset, |index| self.sections[index]);
+        let end = self
+            .sections
+            .get(index)
+            .copied()
+            .unwrap_or_else(|| self.end_time());
+
+        (start, end)
+    }
+
+    /// Time at the start of `bar`, counted from the start offset.
+    pub fn bar_time(&self, bar: u32) -> f32 {
+        let offset = TimeOffset {
+            fourths: 0,
+            beats: 0,
+            bars: bar,
+        };
+
+        self.start_offset + offset.time(self.bpm)
+    }
+
+    pub fn parse_bpm(&mut self, tokens: &mut impl TokenStream) -> Result<()> {
+        let bpm = tokens.next_token()?;
+
+        if let Token::Bpm = bpm {
+            let bpm = tokens.next_token()?;
+
+            // anything else would stall the game or put every projectile at infinity
+            match bpm {
+                Token::Number(bpm) if bpm.is_finite() && bpm > 0.0 => {
+                    self.bpm = bpm;
+
+                    Ok(())
+                }
+                _ => Err(ParseError::UnexpectedToken(bpm)),
+            }
+        } else {
+            Err(ParseError::UnexpectedToken(bpm))
+        }
+    }
+
+    /// Parses the `key=value` pairs after a `#judge`, like `perfect=25ms great=60ms
+    /// perfect_score=3 stun=200ms`. Times are in seconds unless suffixed with `ms`. The windows
+    /// have to be over 0 seconds, and the perfect window no wider than the great one.
+    pub fn parse_judge(
+        &mut self,
+        tokens: &mut std::iter::Peekable<impl Iterator<Item = Token>>,
+    ) -> Result<()> {
+        let mut settings = Vec::new();
+
+        while let Some(Token::Setting(..)) = tokens.peek() {
+            match tokens.next() {
+                Some(Token::Setting(key, value)) => settings.push((key, value)),
+                _ => unreachable!(),
+            }
+        }
+
+        let line = settings
+            .iter()
+            .fold(String::from("#judge"), |line, (key, value)| {
+                format!("{} {}={}", line, key, value)
+            });
+        let bad_windows =
+            |message: &str| ParseError::InvalidArgs(line.clone(), message.to_string());
+
+        for (key, value) in &settings {
+            let invalid = || ParseError::UnrecognizedToken(format!("{}={}", key, value));
+
+            let seconds = || parse_seconds(value);
+
+            let window = || match seconds() {
+                Some(window) if window.is_finite() && window > 0.0 => Ok(window),
+                Some(_) => Err(bad_windows("has a timing window that isn't over 0 seconds")),
+                None => Err(invalid()),
+            };
+
+            let points = || value.parse::<u32>().ok();
+
+            match key.as_str() {
+                "perfect" => self.windows.perfect = window()?,
+                "great" => self.windows.great = window()?,
+                "stun" => self.stun = Some(seconds().ok_or_else(invalid)?),
+                "perfect_score" => self.weights.perfect = points().ok_or_else(invalid)?,
+                "great_score" => self.weights.great = points().ok_or_else(invalid)?,
+                "late_score" => self.weights.late = points().ok_or_else(invalid)?,
+                _ => return Err(invalid()),
+            }
+        }
+
+        if self.windows.perfect > self.windows.great {
+            return Err(bad_windows(
+                "has a perfect window wider than its great window",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Parses the time offset and `key=value` pairs after a `#camera`.
+    pub fn parse_camera(
+        &mut self,
+        tokens: &mut std::iter::Peekable<impl Iterator<Item = Token>>,
+    ) -> Result<()> {
+        let time_offset = match tokens.next() {
+            Some(Token::TimeOffset(time_offset)) => time_offset,
+            Some(token) => return Err(ParseError::UnexpectedToken(token)),
+            None => return Err(ParseError::UnexpectedEof),
+        };
+
+        let mut pairs = Vec::new();
+
+        while let Some(Token::Setting(..)) = tokens.peek() {
+            if let Some(Token::Setting(key, value)) = tokens.next() {
+                pairs.push((key, value));
+            }
+        }
+
+        let movement = CameraMove::parse(
+            pairs
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+        )
+        .map_err(ParseError::UnrecognizedToken)?;
+
+        self.camera_events.push(CameraEvent {
+            time: self.start_offset + time_offset.time(self.bpm),
+            movement,
+        });
+
+        Ok(())
+    }
+
+    /// Parses the time offset and `name=value` pairs after a `#uniform`, `over=` setting how long
+    /// the move takes.
+    pub fn parse_uniform(
+        &mut self,
+        tokens: &mut std::iter::Peekable<impl Iterator<Item = Token>>,
+    ) -> Result<()> {
+        let time_offset = match tokens.next() {
+            Some(Token::TimeOffset(time_offset)) => time_offset,
+            Some(token) => return Err(ParseError::UnexpectedToken(token)),
+            None => return Err(ParseError::UnexpectedEof),
+        };
+
+        let mut event = UniformEvent {
+            time: self.start_offset + time_offset.time(self.bpm),
+            values: Vec::new(),
+            over: None,
+        };
+
+        while let Some(Token::Setting(..)) = tokens.peek() {
+            let (key, value) = match tokens.next() {
+                Some(Token::Setting(key, value)) => (key, value),
+                _ => unreachable!(),
+            };
+
+            let invalid = || ParseError::UnrecognizedToken(format!("{}={}", key, value));
+
+            if key == "over" {
+                event.over = Some(TimeOffset::parse(&value).map_err(|_| invalid())?);
+            } else if key.is_empty() {
+                return Err(invalid());
+            } else {
+                let uniform = UniformValue::parse(&value).ok_or_else(invalid)?;
+
+                event.values.push((key, uniform));
+            }
+        }
+
+        self.uniform_events.push(event);
+
+        Ok(())
+    }
+
+    /// Parses the time offset after a `#telegraph`, `#telegraph 0` disabling telegraphs.
+    pub fn parse_telegraph(&mut self, tokens: &mut impl TokenStream) -> Result<()> {
+        let time_offset = tokens.next_token()?;
+
+        if let Token::TimeOffset(time_offset) = time_offset {
+            self.telegraph = Some(time_offset.time(self.bpm));
+
+            Ok(())
+        } else {
+            Err(ParseError::UnexpectedToken(time_offset))
+        }
+    }
+
+    /// Parses the time offset after a `#prespawn`.
+    pub fn parse_prespawn(&mut self, tokens: &mut impl TokenStream) -> Result<()> {
+        let time_offset = tokens.next_token()?;
+
+        if let Token::TimeOffset(time_offset) = time_offset {
+            self.prespawn = Some(time_offset.time(self.bpm));
+
+            Ok(())
+        } else {
+            Err(ParseError::UnexpectedToken(time_offset))
+        }
+    }
+
+    /// Parses the number of hearts after a `#health`.
+    pub fn parse_health(&mut self, tokens: &mut impl TokenStream) -> Result<()> {
+        let health = tokens.next_token()?;
+
+        match health {
+            Token::Number(health) if health > 0.0 => {
+                self.health = Some(health);
+
+                Ok(())
+            }
+            _ => Err(ParseError::UnexpectedToken(health)),
+        }
+    }
+
+    /// Parses the ratio after a `#swing`, `#swing 62%` landing off-beats 62% of the way through
+    /// their beat. Offsets within a beat are swung, durations like `every=` and `over=` aren't.
+    pub fn parse_swing(&mut self, tokens: &mut impl TokenStream) -> Result<()> {
+        let swing = tokens.next_token()?;
+
+        match swing {
+            Token::Percent(swing) if swing > 0.0 && swing < 100.0 => {
+                self.swing = Some(swing);
+
+                Ok(())
+            }
+            _ => Err(ParseError::UnexpectedToken(swing)),
+        }
+    }
+
+    /// Parses the `key=value` pairs after a `#meter`, like `perfects=30 score=50%`.
+    pub fn parse_meter(
+        &mut self,
+        tokens: &mut std::iter::Peekable<impl Iterator<Item = Token>>,
+    ) -> Result<()> {
+        let mut meter = MeterRules::default();
+
+        while let Some(Token::Setting(..)) = tokens.peek() {
+            let (key, value) = match tokens.next() {
+                Some(Token::Setting(key, value)) => (key, value),
+                _ => unreachable!(),
+            };
+
+            let invalid = || ParseError::UnrecognizedToken(format!("{}={}", key, value));
+
+            match key.as_str() {
+                "perfects" => {
+                    meter.perfects = value
+                        .parse()
+                        .ok()
+                        .filter(|perfects| (1..=MAX_PERFECTS).contains(perfects))
+                        .ok_or_else(invalid)?
+                }
+                "score" => {
+                    meter.score = value
+                        .strip_suffix('%')
+                        .and_then(|score| score.parse().ok())
+                        .filter(|score| (0.0..=100.0).contains(score))
+                        .ok_or_else(invalid)?
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        self.meter = Some(meter);
+
+        Ok(())
+    }
+
+    /// Parses the `key=value` pairs after a `#momentum`, like `perfect=0.5 miss=24 max=320`.
+    pub fn parse_momentum(
+        &mut self,
+        tokens: &mut std::iter::Peekable<impl Iterator<Item = Token>>,
+    ) -> Result<()> {
+        let mut momentum = MomentumRules::default();
+
+        while let Some(Token::Setting(..)) = tokens.peek() {
+            let (key, value) = match tokens.next() {
+                Some(Token::Setting(key, value)) => (key, value),
+                _ => unreachable!(),
+            };
+
+            let invalid = || ParseError::UnrecognizedToken(format!("{}={}", key, value));
+            let number = value
+                .parse::<f32>()
+                .ok()
+                .filter(|number| number.is_finite() && *number >= 0.0)
+                .ok_or_else(invalid)?;
+
+            match key.as_str() {
+                "ramp" => momentum.ramp = number,
+                "perfect" => momentum.perfect = number,
+                "miss" => momentum.miss = number,
+                "min" if number > 0.0 => momentum.min = number,
+                "max" if number > 0.0 => momentum.max = number,
+                _ => return Err(invalid()),
+            }
+        }
+
+        if momentum.max < momentum.min {
+            return Err(ParseError::InvalidArgs(
+                String::from("#momentum"),
+                String::from("needs a max= of at least its min="),
+            ));
+        }
+
+        self.momentum = momentum;
+
+        Ok(())
+    }
+
+    /// Parses the boss health after a `#boss`.
+    pub fn parse_boss(&mut self, tokens: &mut impl TokenStream) -> Result<()> {
+        let health = tokens.next_token()?;
+
+        match health {
+            Token::Number(health) if health.is_finite() && health > 0.0 => {
+                self.boss = Some(health);
+
+                Ok(())
+            }
+            _ => Err(ParseError::UnexpectedToken(health)),
+        }
+    }
+
+    /// Parses the time offset and `key=value` pairs after a `#phase`.
+    pub fn parse_phase(
+        &mut self,
+        tokens: &mut std::iter::Peekable<impl Iterator<Item = Token>>,
+    ) -> Result<()> {
+        let time_offset = match tokens.next() {
+            Some(Token::TimeOffset(time_offset)) => time_offset,
+            Some(token) => return Err(ParseError::UnexpectedToken(token)),
+            None => return Err(ParseError::UnexpectedEof),
+        };
+
+        let mut pairs = Vec::new();
+
+        while let Some(Token::Setting(..)) = tokens.peek() {
+            if let Some(Token::Setting(key, value)) = tokens.next() {
+                pairs.push((key, value));
+            }
+        }
+
+        if !pairs.iter().any(|(key, _)| key == "pattern") {
+            return Err(ParseError::InvalidArgs(
+                String::from("#phase"),
+                String::from("needs a pattern="),
+            ));
+        }
+
+        let pattern = Pattern::parse(
+            pairs
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+            self.lanes(),
+        )
+        .map_err(ParseError::UnrecognizedToken)?;
+
+        self.phases.push(Phase {
+            time: self.start_offset + time_offset.time(self.bpm),
+            pattern,
+        });
+
+        Ok(())
+    }
+
+    /// Parses the count, spacing and turn after a `#spiral`, then its optional starting side
+    /// and lane and its start, `#spiral 16 0;1|0 cw L @2 0;0|2`. Spirals start from the top
+    /// without a side.
+    pub fn parse_spiral(
+        &mut self,
+        tokens: &mut std::iter::Peekable<impl Iterator<Item = Token>>,
+    ) -> Result<()> {
+        let count = parse_count(tokens)?;
+        let every = parse_time_offset(tokens)?;
+
+        let clockwise = match tokens.next_token()? {
+            Token::Turn(clockwise) => clockwise,
+            token => return Err(ParseError::UnexpectedToken(token)),
+        };
+
+        let side = match tokens.next_if(|token| matches!(token, Token::Direction(_))) {
+            Some(Token::Direction(side)) => side,
+            _ => Direction::Up,
+        };
+
+        let timing = self.parse_timing(tokens, count, every)?;
+
+        self.projectiles
+            .extend(generators::spiral(&timing, &side, clockwise));
+
+        Ok(())
+    }
+
+    /// Parses the sides swept between and count after a `#wave`, then its optional spacing and
+    /// lane and its start, `#wave L->R 8 1;0|0 @2 0;0|6`. Waves are a beat apart without a
+    /// spacing.
+    pub fn parse_wave(
+        &mut self,
+        tokens: &mut std::iter::Peekable<impl Iterator<Item = Token>>,
+    ) -> Result<()> {
+        let (from, to) = match tokens.next_token()? {
+            Token::Sweep(from, to) => (from, to),
+            token => return Err(ParseError::UnexpectedToken(token)),
+        };
+
+        let count = parse_count(tokens)?;
+
+        let first = tokens.next_if(|token| matches!(token, Token::TimeOffset(_)));
+        let lane = tokens.next_if(|token| matches!(token, Token::Lane(_)));
+        let second = tokens.next_if(|token| matches!(token, Token::TimeOffset(_)));
+
+        let beat = TimeOffset {
+            fourths: 0,
+            beats: 1,
+            bars: 0,
+        };
+
+        // the start is the last time offset, the spacing the one before it if there are two
+        let (every, start) = match (first, second) {
+            (Some(Token::TimeOffset(every)), Some(Token::TimeOffset(start))) => (every, start),
+            (None, Some(Token::TimeOffset(start))) => (beat, start),
+            (Some(Token::TimeOffset(start)), None) if lane.is_none() => (beat, start),
+            (_, Some(token)) | (Some(token), None) => {
+                return Err(ParseError::UnexpectedToken(token))
+            }
+            (None, None) => return Err(ParseError::UnexpectedToken(tokens.next_token()?)),
+        };
+
+        let lane = match lane {
+            Some(Token::Lane(lane)) if lane < self.lanes() => lane,
+            Some(token) => return Err(ParseError::UnexpectedToken(token)),
+            None => 0,
+        };
+
+        let timing = Timing {
+            start: self.start_offset + start.time(self.bpm),
+            every: every.time(self.bpm),
+            count,
+            lane,
+            jitter: parse_jitter(tokens)?,
+        };
+
+        self.projectiles
+            .extend(generators::wave(&timing, &from, &to));
+
+        Ok(())
+    }
+
+    /// Parses the optional lane, the start and the optional jitter ending a generator.
+    fn parse_timing(
+        &self,
+        tokens: &mut std::iter::Peekable<impl Iterator<Item = Token>>,
+        count: u32,
+        every: TimeOffset,
+    ) -> Result<Timing> {
+        let lane = match tokens.next_if(|token| matches!(token, Token::Lane(_))) {
+            Some(Token::Lane(lane)) if lane < self.lanes() => lane,
+            Some(token) => return Err(ParseError::UnexpectedToken(token)),
+            None => 0,
+        };
+
+        let start = parse_time_offset(tokens)?;
+
+        Ok(Timing {
+            start: self.start_offset + start.time(self.bpm),
+            every: every.time(self.bpm),
+            count,
+            lane,
+            jitter: parse_jitter(tokens)?,
+        })
+    }
+
+    /// Parses the time offset after a `#section`.
+    pub fn parse_section(&mut self, tokens: &mut impl TokenStream) -> Result<()> {
+        let time_offset = tokens.next_token()?;
+
+        if let Token::TimeOffset(time_offset) = time_offset {
+            self.sections
+                .push(self.start_offset + time_offset.time(self.bpm));
+
+            Ok(())
+        } else {
+            Err(ParseError::UnexpectedToken(time_offset))
+        }
+    }
+
+    pub fn parse_offset(&mut self, tokens: &mut impl TokenStream) -> Result<()> {
+        let offset = tokens.next_token()?;
+
+        if let Token::Offset = offset {
+            let offset = match tokens.next_token()? {
+                Token::Number(offset) if offset.is_finite() => offset,
+                token => return Err(ParseError::UnexpectedToken(token)),
+            };
+
+            let time_offset = tokens.next_token()?;
+
+            if let Token::TimeOffset(time_offset) = time_offset {
+                self.start_offset = offset + time_offset.time(self.bpm);
+
+                Ok(())
+            } else {
+                Err(ParseError::UnexpectedToken(time_offset))
+            }
+        } else {
+            Err(ParseError::UnexpectedToken(offset))
+        }
+    }
+}
+
+/// Parses the count of a generator, a whole number of projectiles.
+fn parse_count(tokens: &mut impl TokenStream) -> Result<u32> {
+    match tokens.next_token()? {
+        Token::TimeOffset(offset) => match generators::count(&offset) {
+            Some(count) => Ok(count),
+            None => Err(ParseError::UnexpectedToken(Token::TimeOffset(offset))),
+        },
+        token => Err(ParseError::UnexpectedToken(token)),
+    }
+}
+
+fn parse_time_offset(tokens: &mut impl TokenStream) -> Result<TimeOffset> {
+    match tokens.next_token()? {
+        Token::TimeOffset(offset) => Ok(offset),
+        token => Err(ParseError::UnexpectedToken(token)),
+    }
+}
+
+/// Parses a time in seconds, or in milliseconds when suffixed with `ms`, `25ms` or `0.025s`.
+pub fn parse_seconds(source: &str) -> Option<f32> {
+    match source.strip_suffix("ms") {
+        Some(ms) => ms.parse::<f32>().ok().map(|ms| ms / 1000.0),
+        None => source.strip_suffix('s').unwrap_or(source).parse().ok(),
+    }
+}
+
+/// Parses the optional `jitter=...` after a projectile or generator, the most seconds the
+/// humanize modifier moves its projectiles by, 0 without one.
+fn parse_jitter(tokens: &mut std::iter::Peekable<impl Iterator<Item = Token>>) -> Result<f32> {
+    match tokens.next_if(|token| matches!(token, Token::Setting(key, _) if key == "jitter")) {
+        Some(Token::Setting(key, value)) => parse_jitter_value(&value)
+            .ok_or_else(|| ParseError::UnrecognizedToken(format!("{}={}", key, value))),
+        _ => Ok(0.0),
+    }
+}
+
+/// Parses the optional `drop=...` ending a projectile, the power-up it drops when blocked.
+fn parse_drop(
+    tokens: &mut std::iter::Peekable<impl Iterator<Item = Token>>,
+) -> Result<Option<PickupKind>> {
+    match tokens.next_if(|token| matches!(token, Token::Setting(key, _) if key == "drop")) {
+        Some(Token::Setting(key, value)) => PickupKind::from_name(&value)
+            .map(Some)
+            .ok_or_else(|| ParseError::UnrecognizedToken(format!("{}={}", key, value))),
+        _ => Ok(None),
+    }
+}
+
+/// How `direction` is written in sheets.
+fn direction_name(direction: &Direction) -> &'static str {
+    match direction {
+        Direction::Up => "U",
+        Direction::Down => "D",
+        Direction::Left => "L",
+        Direction::Right => "R",
+    }
+}
+
+/// The value under `key` of `object`, `None` if it's missing or null.
+fn json_field<'a>(object: &'a Json, key: &str) -> Option<&'a Json> {
+    object.get(key).filter(|value| **value != Json::Null)
+}
+
+fn json_number(object: &Json, key: &str) -> Result<Option<f32>> {
+    json_field(object, key)
+        .map(|value| {
+            value
+                .as_f32()
+                .ok_or_else(|| ParseError::InvalidJson(format!("'{}' isn't a number", key)))
+        })
+        .transpose()
+}
+
+fn json_string<'a>(object: &'a Json, key: &str) -> Result<Option<&'a str>> {
+    json_field(object, key)
+        .map(|value| {
+            value
+                .as_str()
+                .ok_or_else(|| ParseError::InvalidJson(format!("'{}' isn't a string", key)))
+        })
+        .transpose()
+}
+
+/// The string under `key` of `object` if it's one token of a sheet, which is how projectile
+/// types and the like are written.
+fn json_token<'a>(object: &'a Json, key: &str) -> Result<Option<&'a str>> {
+    match json_string(object, key)? {
+        Some(token) if token.is_empty() || token.contains(char::is_whitespace) => Err(
+            ParseError::InvalidJson(format!("'{}' isn't a single word", key)),
+        ),
+        token => Ok(token),
+    }
+}
+
+/// The list under `key` of `object`, empty if it's missing.
+fn json_list<'a>(object: &'a Json, key: &str) -> Result<&'a [Json]> {
+    json_field(object, key).map_or(Ok(&[]), |value| {
+        value
+            .as_array()
+            .ok_or_else(|| ParseError::InvalidJson(format!("'{}' isn't a list", key)))
+    })
+}
+
+fn json_required<T>(value: Option<T>, key: &str) -> Result<T> {
+    value.ok_or_else(|| ParseError::InvalidJson(format!("'{}' is missing", key)))
+}
+
+/// The sheet source of a json sheet, for [`Sheet::parse`] to check and read like any other.
+fn json_to_source(json: &Json) -> Result<String> {
+    use std::fmt::Write;
+
+    if !matches!(json, Json::Object(_)) {
+        return Err(ParseError::InvalidJson(
+            "the sheet isn't an object".to_string(),
+        ));
+    }
+
+    let beat = |object: &Json| -> Result<TimeOffset> {
+        let beat = json_required(json_number(object, "beat")?, "beat")?;
+
+        Ok(TimeOffset::from_beats(beat))
+    };
+
+    // numbers are written with `{:?}` so whole ones keep their `.0`, `120` is a time offset
+    let mut source = String::new();
+    let bpm = json_required(json_number(json, "bpm")?, "bpm")?;
+    let offset = json_number(json, "offset")?.unwrap_or(0.0);
+    let _ = writeln!(source, "#bpm {:?}", bpm);
+    let _ = writeln!(source, "#offset {:?} 0;0|0", offset);
+
+    if json_field(json, "stage").and_then(Json::as_bool) == Some(true) {
+        let _ = writeln!(source, "#stage");
+    }
+
+    if let Some(judge) = json_field(json, "judge") {
+        let mut pairs = Vec::new();
+
+        for key in ["perfect", "great", "stun"] {
+            if let Some(seconds) = json_number(judge, key)? {
+                pairs.push(format!("{}={}s", key, seconds));
+            }
+        }
+
+        for key in ["perfect_score", "great_score", "late_score"] {
+            if let Some(points) = json_number(judge, key)? {
+                pairs.push(format!("{}={}", key, points));
+            }
+        }
+
+        if !pairs.is_empty() {
+            let _ = writeln!(source, "#judge {}", pairs.join(" "));
+        }
+    }
+
+    if let Some(health) = json_number(json, "health")? {
+        let _ = writeln!(source, "#health {:?}", health);
+    }
+
+    if let Some(swing) = json_number(json, "swing")? {
+        let _ = writeln!(source, "#swing {}%", swing);
+    }
+
+    if let Some(meter) = json_field(json, "meter") {
+        let _ = write!(source, "#meter");
+
+        if let Some(perfects) = json_number(meter, "perfects")? {
+            let _ = write!(source, " perfects={}", perfects);
+        }
+
+        if let Some(score) = json_number(meter, "score")? {
+            let _ = write!(source, " score={}%", score);
+        }
+
+        let _ = writeln!(source);
+    }
+
+    if let Some(momentum) = json_field(json, "momentum") {
+        let _ = write!(source, "#momentum");
+
+        for key in ["ramp", "perfect", "miss", "min", "max"] {
+            if let Some(value) = json_number(momentum, key)? {
+                let _ = write!(source, " {}={}", key, value);
+            }
+        }
+
+        let _ = writeln!(source);
+    }
+
+    if let Some(telegraph) = json_number(json, "telegraph")? {
+        let _ = writeln!(source, "#telegraph {}", TimeOffset::from_beats(telegraph));
+    }
+
+    if let Some(prespawn) = json_number(json, "prespawn")? {
+        let _ = writeln!(source, "#prespawn {}", TimeOffset::from_beats(prespawn));
+    }
+
+    for section in json_list(json, "sections")? {
+        let section = section
+            .as_f32()
+            .ok_or_else(|| ParseError::InvalidJson("'sections' isn't a list of numbers".into()))?;
+
+        let _ = writeln!(source, "#section {}", TimeOffset::from_beats(section));
+    }
+
+    for event in json_list(json, "camera")? {
+        let _ = write!(source, "#camera {}", beat(event)?);
+
+        for key in ["zoom", "rotation", "x", "y"] {
+            if let Some(value) = json_number(event, key)? {
+                let _ = write!(source, " {}={}", key, value);
+            }
+        }
+
+        if let Some(over) = json_number(event, "over")? {
+            let _ = write!(source, " over={}", TimeOffset::from_beats(over));
+        }
+
+        let _ = writeln!(source);
+    }
+
+    for event in json_list(json, "uniforms")? {
+        let _ = write!(source, "#uniform {}", beat(event)?);
+
+        if let Some(Json::Object(values)) = json_field(event, "values") {
+            for (name, value) in values {
+                let invalid = || {
+                    ParseError::InvalidJson(format!("uniform '{}' isn't a number or list", name))
+                };
+
+                if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '=') {
+                    return Err(ParseError::InvalidJson(format!(
+                        "uniform '{}' isn't a single word",
+                        name
+                    )));
+                }
+
+                let components = match value {
+                    Json::Number(x) => vec![*x],
+                    Json::Array(components) => components
+                        .iter()
+                        .map(Json::as_f32)
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(invalid)?,
+                    _ => return Err(invalid()),
+                };
+                let components: Vec<_> = components.iter().map(f32::to_string).collect();
+
+                let _ = write!(source, " {}={}", name, components.join(","));
+            }
+        }
+
+        if let Some(over) = json_number(event, "over")? {
+            let _ = write!(source, " over={}", TimeOffset::from_beats(over));
+        }
+
+        let _ = writeln!(source);
+    }
+
+    if let Some(boss) = json_number(json, "boss")? {
+        let _ = writeln!(source, "#boss {:?}", boss);
+    }
+
+    for phase in json_list(json, "phases")? {
+        let pattern = json_required(json_string(phase, "pattern")?, "pattern")?;
+
+        let _ = writeln!(source, "#phase {} {}", beat(phase)?, pattern);
+    }
+
+    for projectile in json_list(json, "projectiles")? {
+        let ty = json_required(json_token(projectile, "type")?, "type")?;
+        let direction = json_required(json_token(projectile, "direction")?, "direction")?;
+
+        let _ = write!(source, "{} ", ty);
+
+        if let Some(lane) = json_number(projectile, "lane")? {
+            let _ = write!(source, "@{} ", lane);
+        }
+
+        let _ = write!(source, "{} {}", direction, beat(projectile)?);
+
+        if let Some(jitter) = json_number(projectile, "jitter")? {
+            let _ = write!(source, " jitter={}s", jitter);
+        }
+
+        if let Some(drop) = json_token(projectile, "drop")? {
+            let _ = write!(source, " drop={}", drop);
+        }
+
+        let _ = writeln!(source);
+    }
+
+    Ok(source)
+}
+
+/// The seconds of a `jitter=` value, if it's within [`MAX_JITTER`].
+pub fn parse_jitter_value(source: &str) -> Option<f32> {
+    parse_seconds(source).filter(|jitter| (0.0..=MAX_JITTER).contains(jitter))
 }
@@ -0,0 +1,149 @@
+//! The background of levels without a shader or a video: the song's spectrum as bars around the
+//! heart over rings pulsing on the beat, so a folder with only a song and a sheet still looks
+//! like something.
+//!
+//! The spectrum is worked out from the song's samples as it plays, which only desktop builds can
+//! get at. Elsewhere the bars stay down and only the rings move.
+
+use crate::audio_engine::Song;
+use macroquad::prelude::*;
+use std::cell::RefCell;
+
+/// Bars the spectrum is split into, from the low notes to the high ones.
+pub const BANDS: usize = 32;
+
+/// Samples each spectrum is worked out from, a power of two.
+const WINDOW: usize = 512;
+
+/// Samples a second the song is mixed down to, enough for everything up to the high notes.
+const SAMPLE_RATE: u32 = 11025;
+
+/// Frequencies the lowest and highest bands start and end at, in hertz.
+const FREQUENCIES: (f32, f32) = (40.0, 5000.0);
+
+/// Fraction of a bar kept every frame it's higher than the song, so bars fall instead of
+/// flickering.
+const FALL: f32 = 0.85;
+
+/// Shader of levels with neither a shader nor a video, built into the game like the other core
+/// assets. `iBeat`, given to every level shader, is how far into the beat the song is, from 0
+/// right on it to 1 just before the next.
+pub const SPECTRUM_SHADER_PATH: &str = "assets/shader.glsl";
+
+/// The song's spectrum, a [`BANDS`] wide texture with the height of each bar in red.
+pub struct Spectrum {
+    /// The song mixed down to mono and the samples a second it's at, `None` where they can't be
+    /// had.
+    samples: Option<(Vec<f32>, u32)>,
+    pub texture: Texture2D,
+    // drawing only has the assets borrowed
+    bars: RefCell<[f32; BANDS]>,
+}
+
+impl Spectrum {
+    pub fn new(song: &Song) -> Self {
+        let texture = Texture2D::from_rgba8(BANDS as u16, 1, &[0; BANDS * 4]);
+        texture.set_filter(FilterMode::Linear);
+
+        Self {
+            samples: song.mono_samples(SAMPLE_RATE),
+            texture,
+            bars: RefCell::new([0.0; BANDS]),
+        }
+    }
+
+    /// Works out the spectrum `position` seconds into the song and puts it in the texture.
+    pub fn update(&self, position: f32) {
+        let (samples, rate) = match &self.samples {
+            Some(samples) => samples,
+            None => return,
+        };
+
+        let start = ((position * *rate as f32) as isize - WINDOW as isize / 2).max(0) as usize;
+        let mut bins: Vec<(f32, f32)> = (0..WINDOW)
+            .map(|index| {
+                let sample = samples.get(start + index).copied().unwrap_or(0.0);
+                // hann window, so the edges of the window don't show up as noise
+                let window =
+                    0.5 - 0.5 * (2.0 * std::f32::consts::PI * index as f32 / WINDOW as f32).cos();
+
+                (sample * window, 0.0)
+            })
+            .collect();
+
+        fft(&mut bins);
+
+        let hertz_per_bin = *rate as f32 / WINDOW as f32;
+        let (low, high) = FREQUENCIES;
+        let mut bars = self.bars.borrow_mut();
+
+        for (band, bar) in bars.iter_mut().enumerate() {
+            // spaced evenly in pitch rather than frequency, like notes are
+            let from = low * (high / low).powf(band as f32 / BANDS as f32) / hertz_per_bin;
+            let to = low * (high / low).powf((band + 1) as f32 / BANDS as f32) / hertz_per_bin;
+            let range = from as usize..(to as usize).max(from as usize + 1).min(WINDOW / 2);
+
+            let loudest = bins[range]
+                .iter()
+                .map(|(re, im)| (re * re + im * im).sqrt())
+                .fold(0.0, f32::max);
+            let level =
+                ((loudest / WINDOW as f32 * 8.0).max(1e-4).log10() / 4.0 + 1.0).clamp(0.0, 1.0);
+
+            *bar = level.max(*bar * FALL);
+        }
+
+        let bytes: Vec<u8> = bars
+            .iter()
+            .flat_map(|bar| [(bar * 255.0) as u8, 0, 0, 255])
+            .collect();
+
+        self.texture.update(&Image {
+            bytes,
+            width: BANDS as u16,
+            height: 1,
+        });
+    }
+}
+
+/// Transforms `values`, a power of two long, to their frequencies in place.
+fn fft(values: &mut [(f32, f32)]) {
+    let length = values.len();
+    let mut j = 0;
+
+    // bits reversed order
+    for i in 1..length {
+        let mut bit = length >> 1;
+
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+
+        j |= bit;
+
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+
+    let mut size = 2;
+
+    while size <= length {
+        let angle = -2.0 * std::f32::consts::PI / size as f32;
+
+        for start in (0..length).step_by(size) {
+            for k in 0..size / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (re, im) = values[start + k + size / 2];
+                let odd = (re * cos - im * sin, re * sin + im * cos);
+                let even = values[start + k];
+
+                values[start + k] = (even.0 + odd.0, even.1 + odd.1);
+                values[start + k + size / 2] = (even.0 - odd.0, even.1 - odd.1);
+            }
+        }
+
+        size *= 2;
+    }
+}
@@ -0,0 +1,137 @@
+//! Statistics collected over a run, split up by the direction projectiles come from.
+
+use crate::canvas;
+use crate::game::Direction;
+use crate::judgment::Judgment;
+use crate::settings::Settings;
+use macroquad::prelude::*;
+
+#[derive(Clone, Debug, Default)]
+pub struct DirectionStats {
+    pub blocked: u32,
+    pub missed: u32,
+    /// Sum of the offsets of every block, in seconds.
+    offset_total: f32,
+}
+
+impl DirectionStats {
+    /// Fraction of projectiles blocked, `None` before any have arrived.
+    pub fn hit_rate(&self) -> Option<f32> {
+        let total = self.blocked + self.missed;
+
+        (total > 0).then(|| self.blocked as f32 / total as f32)
+    }
+
+    /// Average seconds blocks were off by, negative when early.
+    pub fn average_offset(&self) -> Option<f32> {
+        (self.blocked > 0).then(|| self.offset_total / self.blocked as f32)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RunStats {
+    /// Up, down, left and right.
+    directions: [DirectionStats; 4],
+    /// Perfect, great, late and miss.
+    judgments: [u32; 4],
+}
+
+impl RunStats {
+    pub fn get(&self, direction: &Direction) -> &DirectionStats {
+        &self.directions[index(direction)]
+    }
+
+    /// Times `judgment` was given.
+    pub fn judgments(&self, judgment: Judgment) -> u32 {
+        self.judgments[judgment as usize]
+    }
+
+    /// Times each judgment was given, perfects, greats, lates and misses.
+    pub fn judgment_counts(&self) -> [u32; 4] {
+        self.judgments
+    }
+
+    /// Fraction of all projectiles blocked, `None` before any have arrived.
+    pub fn hit_rate(&self) -> Option<f32> {
+        let total = self
+            .directions
+            .iter()
+            .fold(DirectionStats::default(), |total, stats| DirectionStats {
+                blocked: total.blocked + stats.blocked,
+                missed: total.missed + stats.missed,
+                offset_total: 0.0,
+            });
+
+        total.hit_rate()
+    }
+
+    /// Records a block `offset` seconds after the projectile reached the shield.
+    pub fn block(&mut self, direction: &Direction, offset: f32, judgment: Judgment) {
+        let stats = &mut self.directions[index(direction)];
+
+        stats.blocked += 1;
+        stats.offset_total += offset;
+        self.judgments[judgment as usize] += 1;
+    }
+
+    pub fn miss(&mut self, direction: &Direction) {
+        self.directions[index(direction)].missed += 1;
+        self.judgments[Judgment::Miss as usize] += 1;
+    }
+
+    /// Draws each direction's hit rate and average offset on its side of the canvas, tinted from
+    /// red to green by the hit rate.
+    pub fn draw_overlay(&self, settings: &Settings) {
+        let viewport = canvas::viewport();
+        let scale = canvas::scale() * settings.ui_scale;
+        let font_size = 14.0 * scale;
+        let center = vec2(viewport.x + viewport.w / 2.0, viewport.y + viewport.h / 2.0);
+
+        for direction in Direction::ALL {
+            let stats = self.get(&direction);
+            let angle = direction.angle();
+            let position = center + vec2(angle.cos(), angle.sin()) * viewport.h * 0.3;
+
+            let text = match (stats.hit_rate(), stats.average_offset()) {
+                (Some(rate), Some(offset)) => {
+                    format!("{:.0}% {:+.0}ms", rate * 100.0, offset * 1000.0)
+                }
+                (Some(rate), None) => format!("{:.0}%", rate * 100.0),
+                _ => "-".to_string(),
+            };
+
+            let size = measure_text(&text, None, font_size as u16, 1.0);
+            let padding = 4.0 * scale;
+
+            let heat = match stats.hit_rate() {
+                Some(rate) => Color::new(1.0 - rate, rate, 0.0, 0.5),
+                None => Color::new(0.5, 0.5, 0.5, 0.5),
+            };
+
+            draw_rectangle(
+                position.x - size.width / 2.0 - padding,
+                position.y - size.height / 2.0 - padding,
+                size.width + padding * 2.0,
+                size.height + padding * 2.0,
+                heat,
+            );
+
+            draw_text(
+                &text,
+                position.x - size.width / 2.0,
+                position.y + size.height / 2.0,
+                font_size,
+                WHITE,
+            );
+        }
+    }
+}
+
+fn index(direction: &Direction) -> usize {
+    match direction {
+        Direction::Up => 0,
+        Direction::Down => 1,
+        Direction::Left => 2,
+        Direction::Right => 3,
+    }
+}
@@ -0,0 +1,165 @@
+//! The streamer overlay, toggled with [`STREAMER_KEY`] and kept in the settings: the last
+//! judgment in big letters, which direction keys are down, and the combo and accuracy together
+//! in a corner in place of their widgets. It's placed by the `streamer_*` sections of the hud
+//! layout on the 16:9 canvas, so a capture of the canvas has all of it.
+
+use crate::canvas;
+use crate::effects::HitEffects;
+use crate::font::draw_outlined_text;
+use crate::game::Direction;
+use crate::hud::Hud;
+use crate::judgment::Judgment;
+use crate::locale::Locale;
+use macroquad::prelude::*;
+
+/// Key turning the overlay on and off.
+pub const STREAMER_KEY: KeyCode = KeyCode::F7;
+
+/// Seconds the last judgment stays up for.
+const JUDGMENT_SHOWN: f32 = 0.6;
+
+/// Font size of the judgment in canvas pixels.
+const JUDGMENT_SIZE: f32 = 40.0;
+
+/// Font size of the combo in canvas pixels, the accuracy is half as big.
+const COMBO_SIZE: f32 = 28.0;
+
+/// Size of each key of the key display in canvas pixels, and the gap between them.
+const KEY_SIZE: f32 = 16.0;
+const KEY_GAP: f32 = 2.0;
+
+/// Where each key of the key display goes, in keys from its top left, like arrow keys.
+const KEY_LAYOUT: [(Direction, (f32, f32)); 4] = [
+    (Direction::Up, (1.0, 0.0)),
+    (Direction::Down, (1.0, 1.0)),
+    (Direction::Left, (0.0, 1.0)),
+    (Direction::Right, (2.0, 1.0)),
+];
+
+#[derive(Default)]
+pub struct StreamerOverlay {
+    /// The last judgment and seconds since it.
+    last: Option<(Judgment, f32)>,
+}
+
+impl StreamerOverlay {
+    pub fn judge(&mut self, judgment: Judgment) {
+        self.last = Some((judgment, 0.0));
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        if let Some((_, age)) = &mut self.last {
+            *age += delta;
+        }
+    }
+
+    /// Draws the overlay, `held` being whether each direction of [`Direction::ALL`] is held
+    /// down. The judgment is colored like the particles `effects` give it.
+    pub fn draw(
+        &self,
+        hud: &Hud,
+        effects: &HitEffects,
+        (held, combo, accuracy): ([bool; 4], u32, Option<f32>),
+        locale: &Locale,
+        ui_scale: f32,
+    ) {
+        let scale = canvas::scale() * ui_scale;
+        let text = |text: &str, position: Vec2, size: f32, color: Color| {
+            draw_outlined_text(
+                text,
+                position.x,
+                position.y,
+                TextParams {
+                    font: hud.font,
+                    font_size: size as u16,
+                    color,
+                    ..Default::default()
+                },
+            );
+        };
+
+        if let Some((judgment, age)) = self.last.filter(|(_, age)| *age < JUDGMENT_SHOWN) {
+            let label = locale.get(match judgment {
+                Judgment::Perfect => "streamer-perfect",
+                Judgment::Great => "streamer-great",
+                Judgment::Late => "streamer-late",
+                Judgment::Miss => "streamer-miss",
+            });
+            // pops in a little bigger and fades out over the second half
+            let size = JUDGMENT_SIZE * scale * (1.0 + (1.0 - age / 0.1).max(0.0) * 0.2);
+            let fade = (2.0 - age / JUDGMENT_SHOWN * 2.0).min(1.0);
+            let color = effects.get(judgment).color;
+            let position = hud
+                .streamer_judgment
+                .place_text(label, hud.font, size, ui_scale);
+
+            text(
+                label,
+                position,
+                size,
+                Color::new(color.r, color.g, color.b, color.a * fade),
+            );
+        }
+
+        let key = KEY_SIZE * scale;
+        let gap = KEY_GAP * scale;
+        let corner = hud
+            .streamer_keys
+            .place(vec2(key * 3.0 + gap * 2.0, key * 2.0 + gap), ui_scale);
+
+        for (direction, (x, y)) in KEY_LAYOUT {
+            let index = Direction::ALL.iter().position(|d| *d == direction).unwrap();
+            let position = corner + vec2(x, y) * (key + gap);
+            let center = position + vec2(key, key) / 2.0;
+            let angle = direction.angle();
+            let forward = vec2(angle.cos(), angle.sin()) * key * 0.3;
+            let side = forward.perp();
+            let (fill, arrow) = if held[index] {
+                (WHITE, BLACK)
+            } else {
+                (Color::new(0.0, 0.0, 0.0, 0.5), WHITE)
+            };
+
+            draw_rectangle(position.x, position.y, key, key, fill);
+            draw_rectangle_lines(position.x, position.y, key, key, 1.0, WHITE);
+            draw_triangle(
+                center + forward,
+                center - forward + side,
+                center - forward - side,
+                arrow,
+            );
+        }
+
+        let combo_text = locale.format("streamer-combo", &[("combo", &combo)]);
+        let accuracy_text = match accuracy {
+            Some(accuracy) => format!("{:.2}%", accuracy * 100.0),
+            None => "-".to_string(),
+        };
+        let combo_size = COMBO_SIZE * scale;
+        let accuracy_size = combo_size / 2.0;
+        let width =
+            |line: &str, size: f32| measure_text(line, Some(hud.font), size as u16, 1.0).width;
+        let block = vec2(
+            width(&combo_text, combo_size).max(width(&accuracy_text, accuracy_size)),
+            combo_size + accuracy_size * 1.2,
+        );
+        let corner = hud.streamer_stats.place(block, ui_scale);
+        // lines line up with the side the block is anchored to
+        let align = |line: &str, size: f32| {
+            (block.x - width(line, size)) * hud.streamer_stats.anchor.factors().x
+        };
+
+        text(
+            &combo_text,
+            corner + vec2(align(&combo_text, combo_size), combo_size),
+            combo_size,
+            WHITE,
+        );
+        text(
+            &accuracy_text,
+            corner + vec2(align(&accuracy_text, accuracy_size), block.y),
+            accuracy_size,
+            Color::new(1.0, 1.0, 1.0, 0.75),
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! The tutorial level, built into the binary so it's always there for new players.
+//!
+//! The level is a click track with a short sheet. Prompts are tied to bars of the sheet, and
+//! when one comes up the level waits for the player to do what it asks before going on.
+
+use crate::audio_engine::Song;
+use crate::game::{Assets, Direction};
+use crate::script::Script;
+use crate::settings::Settings;
+use crate::sheet::Sheet;
+
+const SHEET: &str = include_str!("../assets/tutorial/sheet.sht");
+pub(crate) const SHADER: &str = include_str!("../assets/tutorial/shader.glsl");
+
+/// Bars of click track, enough to cover the sheet.
+const SONG_BARS: u32 = 18;
+
+enum Goal {
+    Press(Direction),
+    /// Space, enter or a tap.
+    Continue,
+}
+
+struct Step {
+    /// Bar of the sheet the prompt comes up at.
+    bar: u32,
+    /// Id of the prompt's text in the locale.
+    prompt: &'static str,
+    goal: Goal,
+}
+
+const STEPS: &[Step] = &[
+    Step {
+        bar: 0,
+        prompt: "tutorial-up",
+        goal: Goal::Press(Direction::Up),
+    },
+    Step {
+        bar: 0,
+        prompt: "tutorial-down",
+        goal: Goal::Press(Direction::Down),
+    },
+    Step {
+        bar: 0,
+        prompt: "tutorial-left",
+        goal: Goal::Press(Direction::Left),
+    },
+    Step {
+        bar: 0,
+        prompt: "tutorial-right",
+        goal: Goal::Press(Direction::Right),
+    },
+    Step {
+        bar: 0,
+        prompt: "tutorial-start",
+        goal: Goal::Continue,
+    },
+    Step {
+        bar: 6,
+        prompt: "tutorial-judgments",
+        goal: Goal::Continue,
+    },
+    Step {
+        bar: 11,
+        prompt: "tutorial-buffer",
+        goal: Goal::Continue,
+    },
+    Step {
+        bar: 16,
+        prompt: "tutorial-done",
+        goal: Goal::Continue,
+    },
+];
+
+pub struct Tutorial {
+    step: usize,
+    /// Whether the level is currently held for a prompt.
+    pub holding: bool,
+}
+
+impl Tutorial {
+    pub fn new() -> Self {
+        Self {
+            step: 0,
+            holding: false,
+        }
+    }
+
+    fn current(&self, sheet: &Sheet, time: f32) -> Option<&'static Step> {
+        STEPS
+            .get(self.step)
+            .filter(|step| time >= sheet.bar_time(step.bar))
+    }
+
+    /// Whether the level should wait for the player at `time`.
+    pub fn waiting(&self, sheet: &Sheet, time: f32) -> bool {
+        self.current(sheet, time).is_some()
+    }
+
+    /// Locale id of the prompt shown at `time`, if any.
+    pub fn prompt(&self, sheet: &Sheet, time: f32) -> Option<&'static str> {
+        self.current(sheet, time).map(|step| step.prompt)
+    }
+
+    /// Moves on to the next step if the player did what the current one asks.
+    pub fn update(&mut self, sheet: &Sheet, time: f32, pressed: &[Direction], continued: bool) {
+        let done = match self.current(sheet, time).map(|step| &step.goal) {
+            Some(Goal::Press(direction)) => pressed.contains(direction),
+            Some(Goal::Continue) => continued,
+            None => false,
+        };
+
+        if done {
+            self.step += 1;
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.step >= STEPS.len()
+    }
+}
+
+pub async fn load_assets(settings: &Settings) -> Assets {
+    let sheet = Sheet::parse(SHEET).unwrap();
+    let seconds = sheet.bar_time(SONG_BARS);
+    let song = Song::from_bytes(click_track(sheet.bpm, seconds)).await;
+
+    Assets::from_parts(
+        song,
+        SHADER,
+        sheet,
+        Script::default(),
+        Vec::new(),
+        None,
+        None,
+        settings,
+    )
+    .await
+    .unwrap()
+}
+
+/// A wav of a click on every beat, higher on the first beat of each bar.
+pub(crate) fn click_track(bpm: f32, seconds: f32) -> Vec<u8> {
+    const SAMPLE_RATE: u32 = 22050;
+
+    let samples = (seconds * SAMPLE_RATE as f32) as u32;
+    let beat_length = 60.0 / bpm;
+
+    let mut wav = Vec::with_capacity(44 + samples as usize * 2);
+
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + samples * 2).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // pcm
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(samples * 2).to_le_bytes());
+
+    for sample in 0..samples {
+        let time = sample as f32 / SAMPLE_RATE as f32;
+        let beat = (time / beat_length) as u32;
+        let since_beat = time - beat as f32 * beat_length;
+
+        let pitch = if beat.is_multiple_of(4) {
+            1500.0
+        } else {
+            1000.0
+        };
+        let envelope = (-since_beat * 60.0).exp();
+        let value = (since_beat * pitch * std::f32::consts::TAU).sin() * envelope * 0.5;
+
+        wav.extend_from_slice(&((value * i16::MAX as f32) as i16).to_le_bytes());
+    }
+
+    wav
+}
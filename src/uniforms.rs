@@ -0,0 +1,239 @@
+//! Custom uniforms of level shaders, declared in the `[uniforms]` section of the level's
+//! `level.toml`, their type going by the value they start at:
+//!
+//! ```toml
+//! [uniforms]
+//! # a float
+//! iGlow = 0.5
+//! # a vec2, or a vec3 or vec4 with three or four numbers
+//! iCenter = [0.0, 0.25]
+//! # a color, as a vec4
+//! iTint = "#ff8800"
+//! ```
+//!
+//! Sheets animate them with `#uniform` and scripts set them with `uniform`, values written as
+//! numbers split by commas or as a color:
+//!
+//! ```text
+//! #uniform 4;0|0 iTint=#ffffff iCenter=0.5,0 over=0;1|0
+//! ```
+//!
+//! Uniforms only set by sheets and scripts start at 0, with as many numbers as they're first set
+//! to. A value with a different number of them than the uniform has is ignored.
+
+use crate::asset_source::load_text;
+use crate::config::{Config, Value};
+use crate::hud::parse_color;
+use crate::log_warn;
+use crate::sheet::TimeOffset;
+use macroquad::prelude::*;
+use std::path::Path;
+
+/// The value of a uniform, of the type declared in the shader.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UniformValue {
+    Float(f32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4(Vec4),
+}
+
+impl UniformValue {
+    /// The value with `components`, `None` unless there are from one to four of them.
+    pub fn from_components(components: &[f32]) -> Option<Self> {
+        match *components {
+            [x] => Some(Self::Float(x)),
+            [x, y] => Some(Self::Vec2(vec2(x, y))),
+            [x, y, z] => Some(Self::Vec3(vec3(x, y, z))),
+            [x, y, z, w] => Some(Self::Vec4(vec4(x, y, z, w))),
+            _ => None,
+        }
+    }
+
+    pub fn components(&self) -> Vec<f32> {
+        match *self {
+            Self::Float(x) => vec![x],
+            Self::Vec2(value) => vec![value.x, value.y],
+            Self::Vec3(value) => vec![value.x, value.y, value.z],
+            Self::Vec4(value) => vec![value.x, value.y, value.z, value.w],
+        }
+    }
+
+    /// Parses numbers split by commas, `1,0.5`, or a `#rrggbb` or `#rrggbbaa` color.
+    pub fn parse(source: &str) -> Option<Self> {
+        if source.starts_with('#') {
+            let color = parse_color(source)?;
+
+            return Some(Self::Vec4(vec4(color.r, color.g, color.b, color.a)));
+        }
+
+        let components = source
+            .split(',')
+            .map(|component| component.parse::<f32>().ok().filter(|x| x.is_finite()))
+            .collect::<Option<Vec<_>>>()?;
+
+        Self::from_components(&components)
+    }
+
+    /// Reads a value from `level.toml`, a number, an array of numbers or a color.
+    fn from_config(value: &Value) -> Option<Self> {
+        match value {
+            Value::Number(x) => Some(Self::Float(*x)),
+            Value::String(color) => Self::parse(color),
+            Value::Array(values) => {
+                let components = values
+                    .iter()
+                    .map(Value::as_f32)
+                    .collect::<Option<Vec<_>>>()?;
+
+                Self::from_components(&components)
+            }
+            Value::Bool(_) => None,
+        }
+    }
+
+    /// The value of the same type with every component 0.
+    pub fn zero(&self) -> Self {
+        Self::from_components(&vec![0.0; self.components().len()]).unwrap()
+    }
+
+    pub fn uniform_type(&self) -> UniformType {
+        match self {
+            Self::Float(_) => UniformType::Float1,
+            Self::Vec2(_) => UniformType::Float2,
+            Self::Vec3(_) => UniformType::Float3,
+            Self::Vec4(_) => UniformType::Float4,
+        }
+    }
+
+    pub fn same_type(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    /// The value `t` of the way from `self` to `to`, `to` if they're of different types.
+    pub fn lerp(&self, to: &Self, t: f32) -> Self {
+        if !self.same_type(to) {
+            return *to;
+        }
+
+        let components: Vec<f32> = self
+            .components()
+            .iter()
+            .zip(to.components())
+            .map(|(from, to)| from + (to - from) * t)
+            .collect();
+
+        Self::from_components(&components).unwrap()
+    }
+
+    pub fn set_on(&self, material: Material, name: &str) {
+        match *self {
+            Self::Float(x) => material.set_uniform(name, x),
+            Self::Vec2(value) => material.set_uniform(name, value),
+            Self::Vec3(value) => material.set_uniform(name, value),
+            Self::Vec4(value) => material.set_uniform(name, value),
+        }
+    }
+}
+
+impl std::fmt::Display for UniformValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let components: Vec<String> = self
+            .components()
+            .iter()
+            .map(|component| component.to_string())
+            .collect();
+
+        write!(f, "{}", components.join(","))
+    }
+}
+
+/// Reads the uniforms declared in the `level.toml` of the level in `song_path`, with the values
+/// they start at. None if the file is missing, invalid ones are left out.
+pub async fn load_declared(song_path: &Path) -> Vec<(String, UniformValue)> {
+    let path = song_path.join("level.toml");
+
+    let config = match load_text(&path).await {
+        Ok(source) => match Config::parse(&source) {
+            Ok(config) => config,
+            Err(err) => {
+                log_warn!("{}: {}", path.display(), err);
+
+                return Vec::new();
+            }
+        },
+        Err(_) => return Vec::new(),
+    };
+
+    let mut declared = Vec::new();
+
+    for (key, value) in &config.values {
+        let name = match key.strip_prefix("uniforms.") {
+            Some(name) => name,
+            None => continue,
+        };
+
+        match UniformValue::from_config(value) {
+            Some(value) => declared.push((name.to_string(), value)),
+            None => log_warn!("{}: invalid value for uniform {}", path.display(), name),
+        }
+    }
+
+    declared
+}
+
+/// Uniforms moved by a `#uniform` line of a sheet at `time`.
+#[derive(Debug)]
+pub struct UniformEvent {
+    pub time: f32,
+    pub values: Vec<(String, UniformValue)>,
+    /// How long the move takes, the uniforms jump straight there without it.
+    pub over: Option<TimeOffset>,
+}
+
+/// A uniform as it's animated through a level.
+#[derive(Clone, Debug)]
+pub struct ShaderUniform {
+    pub name: String,
+    from: UniformValue,
+    to: UniformValue,
+    /// Level time the move from `from` to `to` started at.
+    start: f32,
+    /// Seconds the move takes, 0 to jump straight there.
+    duration: f32,
+}
+
+impl ShaderUniform {
+    pub fn new(name: &str, value: UniformValue) -> Self {
+        Self {
+            name: name.to_string(),
+            from: value,
+            to: value,
+            start: 0.0,
+            duration: 0.0,
+        }
+    }
+
+    /// The value at level time `time`.
+    pub fn value(&self, time: f32) -> UniformValue {
+        if self.duration <= 0.0 {
+            return self.to;
+        }
+
+        let t = ((time - self.start) / self.duration).clamp(0.0, 1.0);
+
+        self.from.lerp(&self.to, t)
+    }
+
+    /// Moves to `value` over `duration` seconds from `time`, ignored if it's the wrong type.
+    pub fn move_to(&mut self, value: UniformValue, time: f32, duration: f32) {
+        if !value.same_type(&self.to) {
+            return;
+        }
+
+        self.from = self.value(time);
+        self.to = value;
+        self.start = time;
+        self.duration = duration;
+    }
+}
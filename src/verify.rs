@@ -0,0 +1,104 @@
+//! Checking the score a replay claims by playing its presses back without a window or audio,
+//! for leaderboard servers and for the game before it submits a run.
+//!
+//! The play back steps the same [`Run`] the game plays, but in steps of [`FIXED_STEP`] while the
+//! game steps through its frames, so on rare occasions a press right on the edge of a timing
+//! window is judged differently. What [`verify_replay`] finds is what the presses are worth,
+//! leaderboards should rank by it rather than by the claimed score. Runs retried from a
+//! checkpoint don't verify, replays don't hold where the retry happened.
+
+use crate::end_menu::Ending;
+use crate::events::GameEvent;
+use crate::game::FIXED_STEP;
+use crate::judgment::Judgment;
+use crate::replay::Replay;
+use crate::run::{hitches_allowed, Run};
+use crate::script::Script;
+use crate::sheet::Sheet;
+
+/// Seconds past the end of the sheet play back gives up at, in case scripted projectiles keep
+/// coming.
+const OVERTIME: f32 = 60.0;
+
+#[derive(Debug)]
+pub struct RunResult {
+    /// The score the presses get, with the modifiers' multiplier.
+    pub score: u32,
+    pub best_combo: u32,
+    /// Perfect, great, late and miss counts.
+    pub judgments: [u32; 4],
+    /// How the run ended, `None` if it didn't by [`OVERTIME`] past the end of the sheet.
+    pub ending: Option<Ending>,
+    /// Whether the replay is of the sheet, is [ranked](crate::modifiers::Modifiers::ranked),
+    /// claims the score it gets and only hitches as much as a run can, see
+    /// [`hitches_allowed`].
+    pub verified: bool,
+}
+
+/// Plays the presses of `replay` back on `sheet`, with `script` being the level's script since
+/// it can spawn projectiles, and checks them against the score the replay claims.
+pub fn verify_replay(sheet: &Sheet, script: &Script, replay: &Replay) -> RunResult {
+    let modifiers = &replay.modifiers;
+    let mut run = Run::new(sheet, modifiers, replay.seed);
+
+    run.bombs = replay.bombs.clone();
+    run.hitches = replay.hitches.clone();
+    run.skip_to(sheet, replay.start);
+
+    let mut next_press = replay
+        .presses
+        .partition_point(|press| press.time < replay.start);
+    let mut judgments = [0; 4];
+    let mut ending = None;
+
+    while run.env.time < sheet.end_time() + OVERTIME {
+        if run.projectiles_done(sheet) && run.env.time >= sheet.end_time() {
+            ending = Some(Ending::Cleared);
+            break;
+        }
+
+        // the game takes the presses of a frame before stepping through it
+        let time = run.env.time + FIXED_STEP;
+        let from = next_press;
+
+        while replay
+            .presses
+            .get(next_press)
+            .is_some_and(|press| press.time <= time)
+        {
+            next_press += 1;
+        }
+
+        run.press(sheet, &replay.presses[from..next_press]);
+        run.step(sheet, script, FIXED_STEP);
+        run.fired.clear();
+
+        for event in run.events.drain(..) {
+            match event {
+                GameEvent::NoteBlocked { judgment, .. } => judgments[judgment as usize] += 1,
+                GameEvent::NoteMissed { .. } | GameEvent::Death { .. } => {
+                    judgments[Judgment::Miss as usize] += 1
+                }
+                _ => {}
+            }
+        }
+
+        if run.fatal.is_some() {
+            ending = Some(Ending::Died);
+            break;
+        }
+    }
+
+    let score = (run.score as f32 * modifiers.score_multiplier()).round() as u32;
+
+    RunResult {
+        verified: modifiers.ranked()
+            && hitches_allowed(&replay.hitches)
+            && replay.chart == sheet.level_hash(script)
+            && replay.score == score,
+        score,
+        best_combo: run.best_combo,
+        judgments,
+        ending,
+    }
+}
@@ -0,0 +1,244 @@
+//! Video backgrounds, a `background.mp4` or `background.webm` in the level's folder played along
+//! with the song. Level shaders get the frame showing as the `iVideo` texture to composite it
+//! with whatever else they draw, and levels with a video but no shader just show the video.
+//!
+//! Videos are decoded by `ffmpeg`, which has to be installed, in builds with the `video` feature.
+//! Without it, or on the web and android, levels play without their video.
+
+use std::path::{Path, PathBuf};
+
+/// File names a level's video can have, in the order they're looked for.
+pub const VIDEO_FILES: [&str; 2] = ["background.mp4", "background.webm"];
+
+/// Frames a second videos are decoded at, whatever they were made at.
+pub const VIDEO_FPS: f32 = 30.0;
+
+/// Shader of levels with a video but no shader of their own.
+pub const VIDEO_SHADER: &str = r#"
+#version 450
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 color_out;
+
+uniform sampler2D iVideo;
+
+void main() {
+    color_out = vec4(texture(iVideo, vec2(uv.x, -uv.y) * 0.5 + 0.5).rgb, 1.0);
+}
+"#;
+
+/// The video in the level folder `song_path`, if there's one.
+pub fn find_video(song_path: &Path) -> Option<PathBuf> {
+    VIDEO_FILES
+        .iter()
+        .map(|name| song_path.join(name))
+        .find(|path| path.exists())
+}
+
+#[cfg(all(
+    feature = "video",
+    not(any(target_arch = "wasm32", target_os = "android"))
+))]
+pub use ffmpeg::Video;
+
+#[cfg(all(
+    feature = "video",
+    not(any(target_arch = "wasm32", target_os = "android"))
+))]
+mod ffmpeg {
+    use crate::canvas;
+    use crate::log_error;
+    use macroquad::prelude::*;
+    use std::cell::RefCell;
+    use std::io::Read;
+    use std::path::{Path, PathBuf};
+    use std::process::{Child, Command, Stdio};
+    use std::sync::mpsc::{sync_channel, Receiver, TryRecvError};
+
+    /// Frames decoded ahead of the one showing.
+    const BUFFERED_FRAMES: usize = 4;
+
+    /// Frames behind the song past which decoding starts over from where the song is, rather than
+    /// catching up frame by frame.
+    const SEEK_FRAMES: usize = 15;
+
+    const WIDTH: u16 = canvas::WIDTH as u16;
+    const HEIGHT: u16 = canvas::HEIGHT as u16;
+
+    /// An ffmpeg decoding the video from `start`.
+    struct Decoder {
+        child: Child,
+        frames: Receiver<Vec<u8>>,
+        /// Index of the frame decoding started at.
+        start: usize,
+        /// Frames taken so far.
+        taken: usize,
+        /// Whether every frame to the end of the video has been taken.
+        ended: bool,
+    }
+
+    impl Decoder {
+        fn spawn(path: &Path, start: usize) -> Option<Self> {
+            let mut child = Command::new("ffmpeg")
+                .args(["-loglevel", "error", "-ss"])
+                .arg(format!("{:.3}", start as f32 / super::VIDEO_FPS))
+                .arg("-i")
+                .arg(path)
+                .args(["-f", "rawvideo", "-pix_fmt", "rgba", "-vf"])
+                .arg(format!(
+                    "fps={},scale={}:{}",
+                    super::VIDEO_FPS,
+                    WIDTH,
+                    HEIGHT
+                ))
+                .arg("pipe:1")
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .map_err(|err| log_error!("failed to start ffmpeg: {}", err))
+                .ok()?;
+
+            let mut stdout = child.stdout.take()?;
+            let (sender, frames) = sync_channel(BUFFERED_FRAMES);
+
+            // blocks on the channel once it's full, so decoding never gets far ahead
+            std::thread::spawn(move || loop {
+                let mut frame = vec![0; WIDTH as usize * HEIGHT as usize * 4];
+
+                if stdout.read_exact(&mut frame).is_err() || sender.send(frame).is_err() {
+                    break;
+                }
+            });
+
+            Some(Self {
+                child,
+                frames,
+                start,
+                taken: 0,
+                ended: false,
+            })
+        }
+
+        /// Index of the next frame to be taken.
+        fn next(&self) -> usize {
+            self.start + self.taken
+        }
+    }
+
+    impl Drop for Decoder {
+        fn drop(&mut self) {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+
+    struct Playback {
+        decoder: Option<Decoder>,
+        /// Index of the frame in the texture, `None` before the first one.
+        showing: Option<usize>,
+    }
+
+    /// A level's video, decoded as the song plays.
+    pub struct Video {
+        path: PathBuf,
+        pub texture: Texture2D,
+        // drawing only has the assets borrowed, so the frames move on behind a shared reference
+        playback: RefCell<Playback>,
+    }
+
+    impl Video {
+        pub fn open(path: PathBuf) -> Option<Self> {
+            let decoder = Decoder::spawn(&path, 0)?;
+            let texture = Texture2D::from_rgba8(
+                WIDTH,
+                HEIGHT,
+                &vec![0; WIDTH as usize * HEIGHT as usize * 4],
+            );
+
+            Some(Self {
+                path,
+                texture,
+                playback: RefCell::new(Playback {
+                    decoder: Some(decoder),
+                    showing: None,
+                }),
+            })
+        }
+
+        /// Puts the frame at `time` seconds into the song in the texture, as far as it's been
+        /// decoded.
+        pub fn update(&self, time: f32) {
+            let frame = (time.max(0.0) * super::VIDEO_FPS) as usize;
+            let mut playback = self.playback.borrow_mut();
+
+            if playback.showing == Some(frame) {
+                return;
+            }
+
+            // past the end of the video the last frame stays up
+            let seek = playback.decoder.as_ref().is_some_and(|decoder| {
+                frame < decoder.next() || (!decoder.ended && frame > decoder.next() + SEEK_FRAMES)
+            });
+
+            if seek {
+                playback.decoder = Decoder::spawn(&self.path, frame);
+            }
+
+            let mut latest = None;
+
+            if let Some(decoder) = &mut playback.decoder {
+                while decoder.next() <= frame {
+                    match decoder.frames.try_recv() {
+                        Ok(bytes) => {
+                            latest = Some((decoder.next(), bytes));
+                            decoder.taken += 1;
+                        }
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => {
+                            decoder.ended = true;
+
+                            break;
+                        }
+                    }
+                }
+            }
+
+            if let Some((index, bytes)) = latest {
+                self.texture.update(&Image {
+                    bytes,
+                    width: WIDTH,
+                    height: HEIGHT,
+                });
+                playback.showing = Some(index);
+            }
+        }
+    }
+}
+
+/// Stands in for the video on builds that can't decode it.
+#[cfg(not(all(
+    feature = "video",
+    not(any(target_arch = "wasm32", target_os = "android"))
+)))]
+pub struct Video {
+    pub texture: macroquad::texture::Texture2D,
+}
+
+#[cfg(not(all(
+    feature = "video",
+    not(any(target_arch = "wasm32", target_os = "android"))
+)))]
+impl Video {
+    pub fn open(path: PathBuf) -> Option<Self> {
+        crate::log_warn!(
+            "{} not played, this build can't decode video",
+            path.display()
+        );
+
+        None
+    }
+
+    pub fn update(&self, _time: f32) {}
+}
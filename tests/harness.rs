@@ -0,0 +1,422 @@
+//! Golden tests for the sheet parser and scripted runs through the windowless simulation.
+//!
+//! Fixtures live in `tests/fixtures`:
+//!
+//! - `sheets/valid/*.sht` are parsed and summarized, the summary compared to the `.out` next to
+//!   each sheet. The bundled levels are checked the same way against `sheets/bundled`.
+//! - `sheets/invalid/*.sht` must fail to parse with the error in the `.err` next to each sheet.
+//! - `sheets/lint/*.sht` are linted, the warnings compared to the `.warn` next to each sheet.
+//! - `runs/<name>/` holds a `sheet.sht`, an optional `script.txt` and `inputs.txt`, a press per
+//!   line as the song time in seconds and a side, `1.250 L`, or `1.250 @2 L` on a lane of a stage
+//!   sheet. `1.250 bomb` spends the bomb meter instead, and `1.250 hitch 0.3` has the game hitch
+//!   for 0.3 seconds from then on. An optional `modifiers.toml` sets the
+//!   modifiers like the settings file does. The run's result is compared to `expected.txt`.
+//!
+//! Run with `UPDATE_GOLDEN=1` to write the expected files from what the game does now, then
+//! check the diff.
+
+use srg::audio_engine::Song;
+use srg::config::Config;
+use srg::input::Press;
+use srg::integrity::to_hex;
+use srg::lint::lint;
+use srg::modifiers::Modifiers;
+use srg::replay::{Replay, REPLAY_VERSION};
+use srg::script::Script;
+use srg::sheet::{Sheet, Token};
+use srg::verify::verify_replay;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// Files in `dir` with `extension`, in order of name.
+fn files(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("{}: {}", dir.display(), err))
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .collect();
+
+    files.sort();
+
+    files
+}
+
+fn updating() -> bool {
+    std::env::var_os("UPDATE_GOLDEN").is_some()
+}
+
+/// Compares `actual` to the contents of `expected`, or writes them when updating. Returns a
+/// description of the mismatch, for tests to report every fixture that fails at once.
+fn golden(expected: &Path, actual: &str) -> Option<String> {
+    if updating() {
+        std::fs::write(expected, actual).unwrap();
+
+        return None;
+    }
+
+    match std::fs::read_to_string(expected) {
+        Ok(contents) if contents == actual => None,
+        Ok(contents) => Some(format!(
+            "{} differs\n--- expected\n{}--- actual\n{}",
+            expected.display(),
+            contents,
+            actual
+        )),
+        Err(err) => Some(format!("{}: {}", expected.display(), err)),
+    }
+}
+
+fn check(failures: Vec<String>) {
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+/// What a parsed sheet holds, in a form that's easy to read in a diff.
+fn summary(sheet: &Sheet) -> String {
+    let mut summary = String::new();
+
+    let _ = writeln!(summary, "bpm {}", sheet.bpm);
+    let _ = writeln!(summary, "offset {:.4}", sheet.start_offset);
+    let _ = writeln!(summary, "hash {}", to_hex(sheet.hash));
+    let _ = writeln!(
+        summary,
+        "windows {:.4} {:.4}",
+        sheet.windows.perfect, sheet.windows.great
+    );
+    let _ = writeln!(
+        summary,
+        "weights {} {} {}",
+        sheet.weights.perfect, sheet.weights.great, sheet.weights.late
+    );
+    let _ = writeln!(summary, "stun {:?}", sheet.stun);
+    let _ = writeln!(summary, "health {:?}", sheet.health);
+    let _ = writeln!(summary, "swing {:?}", sheet.swing);
+    let _ = writeln!(summary, "meter {:?}", sheet.meter);
+    let _ = writeln!(summary, "momentum {:?}", sheet.momentum);
+    let _ = writeln!(summary, "telegraph {:?}", sheet.telegraph);
+    let _ = writeln!(summary, "prespawn {:?}", sheet.prespawn);
+    let _ = writeln!(summary, "sections {:?}", sheet.sections);
+    let _ = writeln!(summary, "camera events {}", sheet.camera_events.len());
+    let _ = writeln!(summary, "uniform events {:?}", sheet.uniform_events);
+    let _ = writeln!(summary, "lanes {}", sheet.lanes());
+    let _ = writeln!(summary, "boss {:?}", sheet.boss);
+    let _ = writeln!(summary, "phases {}", sheet.phases.len());
+
+    for phase in &sheet.phases {
+        let _ = writeln!(
+            summary,
+            "  {:.4} {}",
+            phase.time,
+            phase.pattern.to_source(sheet.lanes())
+        );
+    }
+
+    let _ = writeln!(summary, "projectiles {}", sheet.projectiles.len());
+
+    for projectile in &sheet.projectiles {
+        let _ = write!(
+            summary,
+            "  {:.4} {:?} {:?} lane {}",
+            projectile.arrival_time, projectile.direction, projectile.ty, projectile.lane
+        );
+
+        if projectile.jitter > 0.0 {
+            let _ = write!(summary, " jitter {:.4}", projectile.jitter);
+        }
+
+        if let Some(kind) = projectile.drop {
+            let _ = write!(summary, " drop {}", kind.name());
+        }
+
+        let _ = writeln!(summary);
+    }
+
+    summary
+}
+
+#[test]
+fn valid_sheets_match_their_summaries() {
+    let mut failures = Vec::new();
+
+    for path in files(&fixtures().join("sheets/valid"), "sht") {
+        let source = std::fs::read_to_string(&path).unwrap();
+
+        match Sheet::parse(&source) {
+            Ok(sheet) => failures.extend(golden(&path.with_extension("out"), &summary(&sheet))),
+            Err(err) => failures.push(format!("{}: {}", path.display(), err)),
+        }
+    }
+
+    check(failures);
+}
+
+#[test]
+fn bundled_sheets_match_their_summaries() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let bundled = [
+        ("undyne", root.join("songs/Undyne/sheet.sht")),
+        ("tutorial", root.join("assets/tutorial/sheet.sht")),
+    ];
+    let mut failures = Vec::new();
+
+    for (name, path) in bundled {
+        let source = std::fs::read_to_string(&path).unwrap();
+        let sheet = Sheet::parse(&source).unwrap_or_else(|err| panic!("{}: {}", name, err));
+        let expected = fixtures()
+            .join("sheets/bundled")
+            .join(format!("{}.out", name));
+
+        failures.extend(golden(&expected, &summary(&sheet)));
+    }
+
+    check(failures);
+}
+
+#[test]
+fn invalid_sheets_fail_with_their_errors() {
+    let mut failures = Vec::new();
+
+    for path in files(&fixtures().join("sheets/invalid"), "sht") {
+        let source = std::fs::read_to_string(&path).unwrap();
+
+        match Sheet::parse(&source) {
+            Ok(_) => failures.push(format!("{} parsed", path.display())),
+            Err(err) => failures.extend(golden(&path.with_extension("err"), &format!("{}\n", err))),
+        }
+    }
+
+    check(failures);
+}
+
+#[test]
+fn linted_sheets_match_their_warnings() {
+    let mut failures = Vec::new();
+
+    for path in files(&fixtures().join("sheets/lint"), "sht") {
+        let source = std::fs::read_to_string(&path).unwrap();
+
+        match Sheet::parse(&source) {
+            Ok(sheet) => {
+                let warnings: String = lint(&sheet)
+                    .iter()
+                    .map(|warning| format!("{}\n", warning))
+                    .collect();
+
+                failures.extend(golden(&path.with_extension("warn"), &warnings));
+            }
+            Err(err) => failures.push(format!("{}: {}", path.display(), err)),
+        }
+    }
+
+    check(failures);
+}
+
+/// Parses an inputs file into presses, bombs and hitches in order of time.
+fn parse_inputs(source: &str) -> (Vec<Press>, Vec<f32>, Vec<(f32, f32)>) {
+    let mut presses = Vec::new();
+    let mut bombs = Vec::new();
+    let mut hitches = Vec::new();
+
+    for line in source.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+
+        let mut words = line.split_whitespace();
+        let time: f32 = words
+            .next()
+            .and_then(|time| time.parse().ok())
+            .unwrap_or_else(|| panic!("bad time in '{}'", line));
+
+        if line.ends_with(" bomb") {
+            bombs.push(time);
+
+            continue;
+        }
+
+        if let Some(length) = line.split_once(" hitch ").map(|(_, length)| length) {
+            let length: f32 = length
+                .trim()
+                .parse()
+                .unwrap_or_else(|_| panic!("bad hitch in '{}'", line));
+
+            hitches.push((time, time + length));
+
+            continue;
+        }
+
+        let (lane, direction) = match words.next().map(Token::parse) {
+            Some(Ok(Token::Lane(lane))) => (lane, words.next().map(Token::parse)),
+            direction => (0, direction),
+        };
+        let direction = match direction {
+            Some(Ok(Token::Direction(direction))) => direction,
+            _ => panic!("bad side in '{}'", line),
+        };
+
+        presses.push(Press {
+            lane,
+            direction,
+            time,
+        });
+    }
+
+    presses.sort_by(|a, b| a.time.total_cmp(&b.time));
+    bombs.sort_by(f32::total_cmp);
+    hitches.sort_by(|a: &(f32, f32), b| a.0.total_cmp(&b.0));
+
+    (presses, bombs, hitches)
+}
+
+/// The level in `dir`, and `inputs` as a replay of it claiming no score.
+fn load_run(dir: &Path) -> (Sheet, Script, Replay) {
+    let read = |name: &str| std::fs::read_to_string(dir.join(name));
+
+    let sheet = Sheet::parse(&read("sheet.sht").unwrap())
+        .unwrap_or_else(|err| panic!("{}: {}", dir.display(), err));
+    let script = match read("script.txt") {
+        Ok(source) => Script::parse(&source).unwrap(),
+        Err(_) => Script::default(),
+    };
+
+    let modifiers = match read("modifiers.toml") {
+        Ok(source) => Modifiers::from_config(&Config::parse(&source).unwrap()),
+        Err(_) => Modifiers::default(),
+    };
+
+    let (presses, bombs, hitches) = parse_inputs(&read("inputs.txt").unwrap());
+    let replay = Replay {
+        version: REPLAY_VERSION,
+        chart: sheet.level_hash(&script),
+        level: String::new(),
+        player: String::new(),
+        modifiers,
+        seed: 0,
+        start: 0.0,
+        presses,
+        bombs,
+        hitches,
+        score: 0,
+    };
+
+    (sheet, script, replay)
+}
+
+/// Plays `inputs` on the level in `dir`, describing the result like `expected.txt` does.
+fn play(dir: &Path) -> String {
+    let (sheet, script, replay) = load_run(dir);
+    let result = verify_replay(&sheet, &script, &replay);
+    let [perfect, great, late, miss] = result.judgments;
+
+    format!(
+        "score {}\nbest combo {}\nperfect {}\ngreat {}\nlate {}\nmiss {}\nending {:?}\n",
+        result.score, result.best_combo, perfect, great, late, miss, result.ending
+    )
+}
+
+#[test]
+fn scripted_runs_match_their_results() {
+    let mut runs: Vec<PathBuf> = std::fs::read_dir(fixtures().join("runs"))
+        .unwrap()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    runs.sort();
+
+    assert!(!runs.is_empty());
+
+    let mut failures = Vec::new();
+
+    for dir in runs {
+        failures.extend(golden(&dir.join("expected.txt"), &play(&dir)));
+    }
+
+    check(failures);
+}
+
+/// Whether the replay of the run in `dir` verifies, claiming the score it gets with `hitches`.
+fn verifies_with_hitches(dir: &str, hitches: Option<Vec<(f32, f32)>>) -> bool {
+    let (sheet, script, mut replay) = load_run(&fixtures().join("runs").join(dir));
+
+    if let Some(hitches) = hitches {
+        replay.hitches = hitches;
+    }
+
+    replay.score = verify_replay(&sheet, &script, &replay).score;
+
+    verify_replay(&sheet, &script, &replay).verified
+}
+
+#[test]
+fn forged_hitches_dont_verify() {
+    assert!(verifies_with_hitches("hitch_forgives", None));
+    // one hitch over the whole song would let every projectile through without a miss
+    assert!(!verifies_with_hitches(
+        "no_presses_dies",
+        Some(vec![(0.0, 1000.0)])
+    ));
+    // and so would enough short ones
+    assert!(!verifies_with_hitches(
+        "no_presses_dies",
+        Some(
+            (0..40)
+                .map(|i| (i as f32 * 0.5, i as f32 * 0.5 + 0.5))
+                .collect()
+        )
+    ));
+}
+
+#[test]
+fn replays_survive_encoding() {
+    let (_, _, mut replay) = load_run(&fixtures().join("runs").join("hitch_forgives"));
+    replay.level = String::from("hitch_forgives");
+    replay.score = 1234;
+
+    let bytes = replay.encode();
+    let decoded = Replay::decode(&bytes).unwrap();
+    assert_eq!(decoded.chart, replay.chart);
+    assert_eq!(decoded.level, replay.level);
+    assert_eq!(decoded.presses, replay.presses);
+    assert_eq!(decoded.bombs, replay.bombs);
+    assert_eq!(decoded.hitches, replay.hitches);
+    assert_eq!(decoded.score, replay.score);
+
+    // every cut short or damaged file is an error, never a panic
+    for len in 0..bytes.len() {
+        assert!(Replay::decode(&bytes[..len]).is_err());
+    }
+    let mut damaged = bytes.clone();
+    let last = damaged.len() - 1;
+    damaged[last] ^= 0xff;
+    assert!(Replay::decode(&damaged).is_err());
+}
+
+#[test]
+fn press_deltas_cant_overflow() {
+    // an uncompressed version 1 file whose presses each step back by nearly 2^61 bits
+    let step = [0xfc, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+    let mut field = vec![8];
+    for _ in 0..8 {
+        field.extend_from_slice(&step);
+    }
+
+    let mut bytes = b"SRGR".to_vec();
+    bytes.extend_from_slice(&[1, 7, field.len() as u8]);
+    bytes.extend(field);
+
+    assert!(Replay::decode(&bytes).is_err());
+}
+
+#[test]
+fn unreadable_songs_dont_load() {
+    let level = fixtures().join("levels/unreadable_song");
+    Sheet::parse(&std::fs::read_to_string(level.join("sheet.sht")).unwrap()).unwrap();
+
+    assert!(Song::open(&level.join("song.wav")).is_err());
+    assert!(Song::open(&level.join("missing.wav")).is_err());
+}
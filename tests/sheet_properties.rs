@@ -0,0 +1,555 @@
+//! Property tests for the sheet parser: random bytes and token streams must only ever fail with
+//! errors, and generated sheets must survive being written back out.
+//!
+//! Every case is generated from its own seed, printed when it fails. Set `SRG_PROPERTY_SEED` to
+//! run different cases than the fixed ones, and `SRG_PROPERTY_CASES` to run more of them.
+
+use srg::density::Density;
+use srg::fuzz;
+use srg::sheet::Sheet;
+
+/// Xorshift, enough to spread cases around without a dependency.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // xorshift never leaves zero
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, one_in: usize) -> bool {
+        self.below(one_in) == 0
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    /// A float between `min` and `max`, in hundredths.
+    fn number(&mut self, min: f32, max: f32) -> f32 {
+        let steps = ((max - min) * 100.0) as usize + 1;
+
+        min + self.below(steps) as f32 / 100.0
+    }
+}
+
+fn env_number(name: &str) -> Option<u64> {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+}
+
+/// Checks `check` holds for `cases` inputs made by `generate`, each from its own seed.
+fn for_cases<T: std::fmt::Debug + std::panic::RefUnwindSafe>(
+    cases: u64,
+    generate: impl Fn(&mut Rng) -> T,
+    check: impl Fn(&T) + std::panic::RefUnwindSafe,
+) {
+    let seed = env_number("SRG_PROPERTY_SEED").unwrap_or(0);
+    let cases = env_number("SRG_PROPERTY_CASES").unwrap_or(cases);
+
+    for case in seed..seed + cases {
+        let input = generate(&mut Rng::new(case));
+
+        if std::panic::catch_unwind(|| check(&input)).is_err() {
+            panic!(
+                "case {} failed, rerun with SRG_PROPERTY_SEED={} SRG_PROPERTY_CASES=1\n{:?}",
+                case, case, input
+            );
+        }
+    }
+}
+
+fn time_offset(rng: &mut Rng) -> String {
+    format!("{};{}|{}", rng.below(8), rng.below(8), rng.below(64))
+}
+
+/// A token the parser might see, valid or close to it.
+fn token(rng: &mut Rng) -> String {
+    const WORDS: &[&str] = &[
+        "#bpm",
+        "#offset",
+        "#section",
+        "#judge",
+        "#health",
+        "#camera",
+        "#telegraph",
+        "#prespawn",
+        "#stage",
+        "#boss",
+        "#phase",
+        "#spiral",
+        "#wave",
+        "#swing",
+        "#meter",
+        "#momentum",
+        "#uniform",
+        "iTint=#ff8800",
+        "62%",
+        "0%",
+        "150%",
+        "%",
+        "cw",
+        "ccw",
+        "L->R",
+        "U->U",
+        "D->L",
+        "->",
+        "L->",
+        "300",
+        "#unknown",
+        "@1",
+        "@2",
+        "@3",
+        "@0",
+        "@",
+        "U",
+        "D",
+        "L",
+        "R",
+        "X",
+        "norm",
+        "heart",
+        "norm:1",
+        "heart:a,b",
+        "spike",
+        ":",
+        "nan",
+        "inf",
+        "-inf",
+        "-0.0",
+        "1e40",
+        "0",
+        "|",
+        ";",
+        ";|",
+        "4294967296;0|0",
+        "0;0|4294967295",
+        "=",
+        "perfect=",
+        "miss=24",
+        "max=0",
+        "stun=ms",
+        "zoom=0",
+        "over=1;2",
+        "pattern=spiral",
+        "pattern=wall",
+        "pattern=feint",
+        "pattern=",
+        "count=0",
+        "count=100000",
+        "side=X",
+        "turn=up",
+        "lane=0",
+        "lane=3",
+        "jitter=4ms",
+        "jitter=1s",
+        "jitter=-1ms",
+        "jitter=nan",
+        "drop=slow",
+        "drop=wide",
+        "drop=double",
+        "drop=bomb",
+        "drop=",
+        "perfects=30",
+        "perfects=0",
+        "perfects=-1",
+        "score=50%",
+        "score=150%",
+        "score=50",
+        "//",
+    ];
+    const KEYS: &[&str] = &[
+        "perfect",
+        "great",
+        "stun",
+        "perfect_score",
+        "great_score",
+        "late_score",
+        "zoom",
+        "rotation",
+        "x",
+        "y",
+        "over",
+        "count",
+        "every",
+        "side",
+        "turn",
+        "lane",
+    ];
+
+    match rng.below(5) {
+        0 => time_offset(rng),
+        1 => format!("{:?}", rng.number(-500.0, 500.0)),
+        2 => {
+            let value = match rng.below(3) {
+                0 => format!("{}ms", rng.below(1000)),
+                1 => time_offset(rng),
+                _ => format!("{}", rng.number(-10.0, 10.0)),
+            };
+
+            format!("{}={}", rng.pick(KEYS), value)
+        }
+        _ => rng.pick(WORDS).to_string(),
+    }
+}
+
+#[test]
+fn random_bytes_never_panic() {
+    let generate = |rng: &mut Rng| {
+        const BYTES: &[u8] = b"#bpmoffset 0123456789.;|=:,-\nULDRnormheart/";
+
+        let length = rng.below(256);
+        (0..length)
+            .map(|_| {
+                if rng.chance(8) {
+                    rng.next() as u8
+                } else {
+                    *rng.pick(BYTES)
+                }
+            })
+            .collect::<Vec<u8>>()
+    };
+
+    for_cases(2000, generate, |data: &Vec<u8>| fuzz::sheet(data));
+}
+
+#[test]
+fn random_token_streams_never_panic() {
+    let generate = |rng: &mut Rng| {
+        // most streams start like a sheet, so parsing gets past the header
+        let mut source = if rng.chance(4) {
+            String::new()
+        } else {
+            format!("#bpm {:?}\n#offset 0.0 0;0|0\n", rng.number(1.0, 300.0))
+        };
+
+        for _ in 0..rng.below(64) {
+            source.push_str(&token(rng));
+            source.push(if rng.chance(4) { '\n' } else { ' ' });
+        }
+
+        source
+    };
+
+    for_cases(2000, generate, |source| fuzz::sheet(source.as_bytes()));
+}
+
+/// A valid sheet using every directive now and then.
+fn sheet_source(rng: &mut Rng) -> String {
+    let mut source = format!(
+        "#bpm {:?}\n#offset {:?} {}\n",
+        rng.number(40.0, 300.0),
+        rng.number(-1.0, 2.0),
+        time_offset(rng)
+    );
+
+    let stage = rng.chance(3);
+
+    if stage {
+        source.push_str("#stage\n");
+    }
+
+    if rng.chance(3) {
+        source.push_str(&format!(
+            "#judge perfect={}ms great={}ms perfect_score={} late_score={} stun={}ms\n",
+            rng.below(60) + 10,
+            rng.below(100) + 70,
+            rng.below(5),
+            rng.below(5),
+            rng.below(500)
+        ));
+    }
+
+    if rng.chance(3) {
+        source.push_str(&format!("#health {:?}\n", rng.number(0.5, 10.0)));
+    }
+
+    if rng.chance(3) {
+        source.push_str(&format!("#telegraph {}\n", time_offset(rng)));
+    }
+
+    if rng.chance(3) {
+        source.push_str(&format!("#prespawn {}\n", time_offset(rng)));
+    }
+
+    for _ in 0..rng.below(4) {
+        source.push_str(&format!("#section {}\n", time_offset(rng)));
+    }
+
+    for _ in 0..rng.below(4) {
+        source.push_str(&format!(
+            "#camera {} zoom={} rotation={} over={}\n",
+            time_offset(rng),
+            rng.number(0.1, 4.0),
+            rng.number(-180.0, 180.0),
+            time_offset(rng)
+        ));
+    }
+
+    for _ in 0..rng.below(3) {
+        source.push_str(&format!(
+            "#uniform {} iGlow={:?} iCenter={:?},{:?} over={}\n",
+            time_offset(rng),
+            rng.number(0.0, 1.0),
+            rng.number(-1.0, 1.0),
+            rng.number(-1.0, 1.0),
+            time_offset(rng)
+        ));
+    }
+
+    if rng.chance(3) {
+        source.push_str(&format!("#swing {:?}%\n", rng.number(30.0, 75.0)));
+    }
+
+    if rng.chance(3) {
+        source.push_str(&format!(
+            "#meter perfects={} score={:?}%\n",
+            rng.below(100) + 1,
+            rng.number(0.0, 100.0)
+        ));
+    }
+
+    if rng.chance(3) {
+        source.push_str(&format!(
+            "#momentum ramp={:?} perfect={:?} miss={:?} min={:?} max={:?}\n",
+            rng.number(0.0, 4.0),
+            rng.number(0.0, 2.0),
+            rng.number(0.0, 32.0),
+            rng.number(64.0, 128.0),
+            rng.number(128.0, 512.0)
+        ));
+    }
+
+    if rng.chance(3) {
+        source.push_str(&format!("#boss {:?}\n", rng.number(1.0, 100.0)));
+    }
+
+    for _ in 0..rng.below(4) {
+        let lane = if stage {
+            rng.pick(&["", " lane=1", " lane=2"])
+        } else {
+            ""
+        };
+
+        source.push_str(&format!(
+            "#phase {} pattern={} count={} every={} side={} turn={}{}\n",
+            time_offset(rng),
+            rng.pick(&["spiral", "wall", "feint"]),
+            rng.below(16) + 1,
+            time_offset(rng),
+            rng.pick(&["U", "D", "L", "R"]),
+            rng.pick(&["cw", "ccw"]),
+            lane
+        ));
+    }
+
+    for _ in 0..rng.below(3) {
+        let lane = if stage {
+            rng.pick(&["", "@1 ", "@2 "])
+        } else {
+            ""
+        };
+
+        let sides = ["U", "D", "L", "R"];
+
+        let jitter = if rng.chance(3) {
+            format!(" jitter={}ms", rng.below(50))
+        } else {
+            String::new()
+        };
+
+        if rng.chance(2) {
+            source.push_str(&format!(
+                "#spiral {} {} {} {} {}{}{}\n",
+                rng.below(16) + 1,
+                time_offset(rng),
+                rng.pick(&["cw", "ccw"]),
+                rng.pick(&sides),
+                lane,
+                time_offset(rng),
+                jitter
+            ));
+        } else {
+            source.push_str(&format!(
+                "#wave {}->{} {} {} {}{}{}\n",
+                rng.pick(&sides),
+                rng.pick(&sides),
+                rng.below(16) + 1,
+                time_offset(rng),
+                lane,
+                time_offset(rng),
+                jitter
+            ));
+        }
+    }
+
+    for _ in 0..rng.below(48) {
+        let lane = if stage {
+            rng.pick(&["", "@1 ", "@2 "])
+        } else {
+            ""
+        };
+
+        source.push_str(&format!(
+            "{} {}{} {}{}{}\n",
+            rng.pick(&["norm", "heart"]),
+            lane,
+            rng.pick(&["U", "D", "L", "R"]),
+            time_offset(rng),
+            rng.pick(&["", "", "", " jitter=3ms", " jitter=0.01s"]),
+            rng.pick(&["", "", "", "", " drop=slow", " drop=wide", " drop=double"])
+        ));
+    }
+
+    source
+}
+
+#[test]
+fn generated_sheets_round_trip() {
+    for_cases(1000, sheet_source, |source| {
+        let sheet = Sheet::parse(source)
+            .unwrap_or_else(|err| panic!("generated sheet doesn't parse: {}\n{}", err, source));
+
+        let parsed = fuzz::round_trip(&sheet);
+
+        for (parsed, projectile) in parsed.projectiles.iter().zip(&sheet.projectiles) {
+            assert!(
+                (parsed.arrival_time - projectile.arrival_time).abs() < 0.001,
+                "{}",
+                source
+            );
+        }
+
+        // writing out what was read back changes nothing more
+        assert_eq!(parsed.to_source(), sheet.to_source(), "{}", source);
+    });
+}
+
+#[test]
+fn generated_sheets_round_trip_through_json() {
+    for_cases(1000, sheet_source, |source| {
+        let sheet = Sheet::parse(source)
+            .unwrap_or_else(|err| panic!("generated sheet doesn't parse: {}\n{}", err, source));
+        let json = sheet.to_json();
+
+        let parsed = Sheet::from_json(&json)
+            .unwrap_or_else(|err| panic!("written json doesn't parse: {}\n{}", err, json));
+        let written = Sheet::parse(&sheet.to_source()).unwrap();
+
+        // json rounds times the way sheet files do, so it reads back as the written sheet
+        assert_eq!(written.to_json(), json, "{}", source);
+        assert_eq!(parsed.to_json(), json, "{}", source);
+    });
+}
+
+#[test]
+fn damaged_json_sheets_never_panic() {
+    let generate = |rng: &mut Rng| {
+        let sheet = Sheet::parse(&sheet_source(rng)).unwrap();
+        let mut json = sheet.to_json().into_bytes();
+
+        for _ in 0..1 + rng.below(4) {
+            let index = rng.below(json.len());
+
+            if rng.chance(2) {
+                json.remove(index);
+            } else {
+                json[index] = *rng.pick(b"{}[]\",:\"-0.5eUnull");
+            }
+        }
+
+        json
+    };
+
+    for_cases(1000, generate, |data: &Vec<u8>| fuzz::json_sheet(data));
+
+    // deep enough to run out of stack if the nesting wasn't limited
+    for open in [&b"["[..], b"{\"a\":"] {
+        fuzz::json_sheet(&open.repeat(200_000));
+    }
+}
+
+#[test]
+fn broken_judge_windows_dont_parse() {
+    let broken = [
+        "perfect=-20ms",
+        "great=0ms",
+        "perfect=NaN",
+        "great=inf",
+        "perfect=90ms great=60ms",
+        "perfect=1s",
+    ];
+
+    for_cases(
+        100,
+        |rng| {
+            let line = format!("#judge {}", rng.pick(&broken));
+
+            (format!("{}{}\n", sheet_source(rng), line), line)
+        },
+        |(source, line)| match Sheet::parse(source) {
+            Ok(_) => panic!("broken judge windows parse\n{}", source),
+            Err(err) => assert!(err.to_string().contains(line.as_str()), "{}", err),
+        },
+    );
+}
+
+#[test]
+fn densities_count_every_projectile() {
+    for_cases(200, sheet_source, |source| {
+        let sheet = Sheet::parse(source)
+            .unwrap_or_else(|err| panic!("generated sheet doesn't parse: {}\n{}", err, source));
+        let density = Density::of(&sheet);
+
+        let length = match (sheet.projectiles.first(), sheet.projectiles.last()) {
+            (Some(first), Some(last)) => (last.arrival_time - first.arrival_time).max(1.0),
+            _ => 1.0,
+        };
+        let counted: f32 = density.bars.iter().sum::<f32>() * length / density.bars.len() as f32;
+
+        assert_eq!(density.bars.len(), srg::density::BARS, "{}", source);
+        assert!(
+            (counted - sheet.projectiles.len() as f32).abs() < 0.01 * counted.max(1.0),
+            "{}",
+            source
+        );
+        assert!(
+            density.bars.iter().all(|bar| *bar <= density.peak),
+            "{}",
+            source
+        );
+    });
+}
+
+#[test]
+fn stats_agree_with_the_projectiles() {
+    for_cases(200, sheet_source, |source| {
+        let sheet = Sheet::parse(source)
+            .unwrap_or_else(|err| panic!("generated sheet doesn't parse: {}\n{}", err, source));
+        let stats = sheet.stats();
+
+        assert_eq!(stats.notes, sheet.projectiles.len(), "{}", source);
+        assert_eq!(
+            stats.directions.iter().sum::<usize>(),
+            stats.notes,
+            "{}",
+            source
+        );
+        assert!(stats.max_nps <= stats.notes, "{}", source);
+        assert_eq!(stats.max_nps == 0, stats.notes == 0, "{}", source);
+        assert!(stats.longest_gap <= stats.duration, "{}", source);
+        assert!(stats.duration >= 0.0, "{}", source);
+    });
+}